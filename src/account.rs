@@ -0,0 +1,416 @@
+use uuid::Uuid;
+use std::collections::HashMap;
+use crate::error::{checked_add, checked_sub, EngineResult, ErrorType};
+use crate::market::AssetId;
+use crate::activity::Activity;
+use crate::order::{Order, OrderId, OrderMode, OrderSide};
+use crate::pricing::{to_coins, to_signed, PriceMode};
+use crate::settlement::SettlementHold;
+
+pub type AccountId = Uuid;
+pub type PortfolioId = Uuid;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Portfolio {
+    pub id: Uuid,
+    pub coins: Account,
+    pub assets: HashMap<AssetId, Account>,
+    pub reservations: HashMap<OrderId, Reservation>,
+    #[serde(default)]
+    pub bankrupt_since: Option<u64>,
+    #[serde(default)]
+    pub cost_basis: HashMap<AssetId, CostBasis>,
+    // proceeds of sales minus the average cost of what was sold, fees not included
+    #[serde(default)]
+    pub realized_pnl: i64,
+    #[serde(default)]
+    pub activity: Activity,
+    // proceeds of trades still waiting to be settled, locked alongside the reservations
+    #[serde(default)]
+    pub settlement_holds: Vec<SettlementHold>,
+}
+
+impl Portfolio {
+
+    pub fn with_id(id: PortfolioId, initial_coins: usize) -> Portfolio {
+        Portfolio {
+            id,
+            coins: Account::new(initial_coins),
+            assets: HashMap::new(),
+            reservations: HashMap::new(),
+            bankrupt_since: None,
+            cost_basis: HashMap::new(),
+            realized_pnl: 0,
+            activity: Activity::default(),
+            settlement_holds: vec![],
+        }
+    }
+
+    // same balances, cost basis and PnL under a new id, nothing reserved and no orders
+    pub fn fork(&self, id: PortfolioId) -> Portfolio {
+        Portfolio {
+            id,
+            coins: Account::new(self.coins.get_total_amount()),
+            assets: self.assets.iter()
+                .map(|(asset, account)| (*asset, Account::new(account.get_total_amount())))
+                .collect(),
+            reservations: HashMap::new(),
+            bankrupt_since: self.bankrupt_since,
+            cost_basis: self.cost_basis.clone(),
+            realized_pnl: self.realized_pnl,
+            activity: Activity::default(),
+            settlement_holds: vec![],
+        }
+    }
+
+    pub fn get_asset_account_mut(&mut self, asset_id: Uuid) -> EngineResult<&mut Account> {
+        self.assets.get_mut(&asset_id).ok_or(ErrorType::AssetNotFound(asset_id))
+    }
+
+    // Portfolios that never held an asset get an empty account on first use. Whether the
+    // asset exists is up to the market to check.
+    pub fn ensure_asset_account(&mut self, asset_id: AssetId) -> &mut Account {
+        self.assets.entry(asset_id).or_insert_with(|| Account::new(0))
+    }
+
+    fn get_reserved_account_mut(&mut self, account: ReservedAccount) -> EngineResult<&mut Account> {
+        match account {
+            ReservedAccount::Coins => Ok(&mut self.coins),
+            ReservedAccount::Asset(asset_id) => Ok(self.ensure_asset_account(asset_id)),
+        }
+    }
+
+    pub(crate) fn reserve(&mut self, reservation: Reservation) -> EngineResult<()> {
+        // both or neither are locked
+        if self.coins.get_free_amount() < reservation.owed {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        self.get_reserved_account_mut(reservation.account)?
+            .lock_amount(reservation.amount)?;
+        self.coins.lock_amount(reservation.owed)?;
+        self.reservations.insert(reservation.order_id, reservation);
+        Ok(())
+    }
+
+    pub(crate) fn spend_reservation(&mut self, order_id: OrderId, amount: usize) -> EngineResult<()> {
+        let reservation = *self.reservations.get(&order_id)
+            .ok_or(ErrorType::ReservationNotFound(order_id))?;
+        if reservation.amount < amount {
+            return Err(ErrorType::InsufficientLockedAmount);
+        }
+        self.get_reserved_account_mut(reservation.account)?
+            .spend_from_locked_amount(amount)?;
+        self.reservations.get_mut(&order_id).unwrap().amount -= amount;
+        Ok(())
+    }
+
+    // what a sell below zero pays the buyer, from the coins its order locked for it
+    pub(crate) fn spend_owed(&mut self, order_id: OrderId, amount: usize) -> EngineResult<()> {
+        let reservation = self.reservations.get_mut(&order_id)
+            .ok_or(ErrorType::ReservationNotFound(order_id))?;
+        if reservation.owed < amount {
+            return Err(ErrorType::InsufficientLockedAmount);
+        }
+        self.coins.spend_from_locked_amount(amount)?;
+        reservation.owed -= amount;
+        Ok(())
+    }
+
+    pub(crate) fn release_reservation(&mut self, order_id: OrderId) -> EngineResult<()> {
+        let reservation = self.reservations.remove(&order_id)
+            .ok_or(ErrorType::ReservationNotFound(order_id))?;
+        self.coins.unlock_amount(reservation.owed)?;
+        self.get_reserved_account_mut(reservation.account)?
+            .unlock_amount(reservation.amount)
+    }
+
+    // Locks what the trade brought in until it's settled, as much of it as is still free.
+    pub(crate) fn hold(&mut self, trade: u64, account: ReservedAccount, amount: usize) -> EngineResult<()> {
+        let held = self.get_reserved_account_mut(account)?;
+        let amount = amount.min(held.get_free_amount());
+        if amount > 0 {
+            held.lock_amount(amount)?;
+            self.settlement_holds.push(SettlementHold { trade, account, amount });
+        }
+        Ok(())
+    }
+
+    pub(crate) fn release_holds(&mut self, trade: u64) -> EngineResult<()> {
+        let (released, kept): (Vec<SettlementHold>, Vec<SettlementHold>) = std::mem::take(&mut self.settlement_holds)
+            .into_iter()
+            .partition(|hold| hold.trade == trade);
+        self.settlement_holds = kept;
+        for hold in released {
+            self.get_reserved_account_mut(hold.account)?.unlock_amount(hold.amount)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_held_for(&self, account: ReservedAccount) -> usize {
+        self.settlement_holds.iter().filter(|hold| hold.account == account).map(|hold| hold.amount).sum()
+    }
+
+    // `cost` is what was paid for the lot, negative when the asset came with coins
+    pub(crate) fn record_buy(&mut self, asset_id: AssetId, quantity: usize, cost: i128) -> EngineResult<()> {
+        let basis = self.cost_basis.entry(asset_id).or_default();
+        basis.total_cost = to_signed(basis.total_cost as i128 + cost)?;
+        basis.quantity = checked_add(basis.quantity, quantity)?;
+        Ok(())
+    }
+
+    // holdings without a basis, like the ones handed out by the admin, count as bought for nothing
+    pub(crate) fn record_sell(&mut self, asset_id: AssetId, quantity: usize, proceeds: i128) -> EngineResult<()> {
+        let basis = self.cost_basis.entry(asset_id).or_default();
+        let sold = quantity.min(basis.quantity);
+        let cost = match basis.quantity {
+            0 => 0,
+            held => basis.total_cost as i128 * sold as i128 / held as i128,
+        };
+        basis.total_cost = to_signed(basis.total_cost as i128 - cost)?;
+        basis.quantity -= sold;
+        self.realized_pnl = to_signed(self.realized_pnl as i128 + proceeds - cost)?;
+        Ok(())
+    }
+
+    pub fn get_reservations_for(&self, account: ReservedAccount) -> Vec<Reservation> {
+        self.reservations.values()
+            .filter(|r| r.account == account)
+            .cloned()
+            .collect()
+    }
+
+    // what the reservations lock on the account, the coins owed by sells below zero included
+    pub fn get_reserved_for(&self, account: ReservedAccount) -> usize {
+        self.reservations.values()
+            .map(|r| match (r.account == account, account) {
+                (true, _) => r.amount,
+                (false, ReservedAccount::Coins) => r.owed,
+                (false, ReservedAccount::Asset(_)) => 0,
+            })
+            .sum()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReservedAccount {
+    Coins,
+    Asset(AssetId),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReservationPurpose {
+    BuyOrder,
+    SellOrder,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Reservation {
+    pub order_id: OrderId,
+    pub account: ReservedAccount,
+    pub purpose: ReservationPurpose,
+    pub amount: usize,
+    // coins a sell below zero pays the buyers, locked on the coins next to the asset
+    #[serde(default)]
+    pub owed: usize,
+}
+
+impl Reservation {
+    // a buy below zero in signed mode gets paid, there's nothing to lock for it
+    pub fn for_order(order: &Order, price_mode: PriceMode) -> EngineResult<Reservation> {
+        let (account, purpose, amount, owed) = match (order.side, order.mode) {
+            (OrderSide::Sell, OrderMode::Limit(limit)) => (
+                ReservedAccount::Asset(order.asset),
+                ReservationPurpose::SellOrder,
+                order.quantity,
+                to_coins((-price_mode.notional(limit, order.quantity)?).max(0))?,
+            ),
+            (OrderSide::Buy, OrderMode::Limit(limit)) =>
+                (ReservedAccount::Coins, ReservationPurpose::BuyOrder, to_coins(price_mode.notional(limit, order.quantity)?.max(0))?, 0),
+            _ => Err(ErrorType::CantLockAmountForBestOrder)?,
+        };
+        Ok(Reservation {
+            order_id: order.id,
+            account,
+            purpose,
+            amount,
+            owed,
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CostBasis {
+    pub quantity: usize,
+    pub total_cost: i64,
+}
+
+impl CostBasis {
+    pub fn average_cost(&self) -> f64 {
+        if self.quantity == 0 {
+            return 0.0;
+        }
+        self.total_cost as f64 / self.quantity as f64
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Account {
+    total_amount: usize,
+    locked_amount: usize,
+}
+
+impl Account {
+
+    pub fn new(initial_amount: usize) -> Account {
+        Account {
+            total_amount: initial_amount,
+            locked_amount: 0,
+        }
+    }
+
+    fn lock_amount(&mut self, amount_to_lock: usize) -> EngineResult<()> {
+        if self.free_amount()? < amount_to_lock {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        self.locked_amount = checked_add(self.locked_amount, amount_to_lock)?;
+        Ok(())
+    }
+
+    fn spend_from_locked_amount(&mut self, amount_to_spend: usize) -> EngineResult<()> {
+        if self.locked_amount < amount_to_spend {
+            return Err(ErrorType::InsufficientLockedAmount);
+        }
+        let total_amount = checked_sub(self.total_amount, amount_to_spend)?;
+        self.locked_amount = checked_sub(self.locked_amount, amount_to_spend)?;
+        self.total_amount = total_amount;
+        Ok(())
+    }
+
+    pub(crate) fn spend_from_free_amount(&mut self, amount_to_spend: usize) -> EngineResult<()> {
+        if self.free_amount()? < amount_to_spend {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        self.total_amount = checked_sub(self.total_amount, amount_to_spend)?;
+        Ok(())
+    }
+
+    fn unlock_amount(&mut self, amount_to_unlock: usize) -> EngineResult<()>{
+        if self.locked_amount < amount_to_unlock {
+            return Err(ErrorType::InsufficientLockedAmount);
+        }
+        self.locked_amount = checked_sub(self.locked_amount, amount_to_unlock)?;
+        Ok(())
+    }
+
+    pub fn add(&mut self, amount: usize) -> EngineResult<()> {
+        self.total_amount = checked_add(self.total_amount, amount)?;
+        Ok(())
+    }
+
+    // fails if more is locked than there is, which would mean the books are broken
+    fn free_amount(&self) -> EngineResult<usize> {
+        checked_sub(self.total_amount, self.locked_amount)
+    }
+
+    pub fn get_free_amount(&self) -> usize {
+        self.total_amount.saturating_sub(self.locked_amount)
+    }
+
+    pub fn get_locked_amount(&self) -> usize {
+        self.locked_amount
+    }
+
+    pub fn get_total_amount(&self) -> usize {
+        self.total_amount
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn portfolio() -> Portfolio {
+        let mut portfolio = Portfolio::with_id(Uuid::nil(), 100);
+        portfolio.ensure_asset_account(asset_id()).add(10).unwrap();
+        portfolio
+    }
+
+    fn asset_id() -> AssetId {
+        Uuid::nil()
+    }
+
+    fn order_id() -> OrderId {
+        Uuid::nil()
+    }
+
+    fn coins(amount: usize) -> Reservation {
+        Reservation { order_id: order_id(), account: ReservedAccount::Coins, purpose: ReservationPurpose::BuyOrder, amount, owed: 0 }
+    }
+
+    fn assets(amount: usize, owed: usize) -> Reservation {
+        Reservation { order_id: order_id(), account: ReservedAccount::Asset(asset_id()), purpose: ReservationPurpose::SellOrder, amount, owed }
+    }
+
+    // (total, locked) of the coins and the asset
+    fn balances(portfolio: &Portfolio) -> ((usize, usize), (usize, usize)) {
+        let asset = &portfolio.assets[&asset_id()];
+        ((portfolio.coins.get_total_amount(), portfolio.coins.get_locked_amount()),
+         (asset.get_total_amount(), asset.get_locked_amount()))
+    }
+
+    #[test]
+    fn spending_takes_from_the_reservation_and_releasing_unlocks_the_rest() {
+        let mut portfolio = portfolio();
+        portfolio.reserve(coins(60)).unwrap();
+        assert_eq!(balances(&portfolio), ((100, 60), (10, 0)));
+        portfolio.spend_reservation(order_id(), 20).unwrap();
+        assert_eq!(portfolio.reservations[&order_id()].amount, 40);
+        assert_eq!(balances(&portfolio), ((80, 40), (10, 0)));
+        portfolio.release_reservation(order_id()).unwrap();
+        assert!(portfolio.reservations.is_empty());
+        assert_eq!(balances(&portfolio), ((80, 0), (10, 0)));
+    }
+
+    #[test]
+    fn reserving_more_than_is_free_locks_nothing() {
+        let mut portfolio = portfolio();
+        assert!(matches!(portfolio.reserve(coins(101)), Err(ErrorType::InsufficientFreeAmount)));
+        // both or neither, the asset isn't locked when the coins owed aren't there
+        assert!(matches!(portfolio.reserve(assets(5, 101)), Err(ErrorType::InsufficientFreeAmount)));
+        assert!(portfolio.reservations.is_empty());
+        assert_eq!(balances(&portfolio), ((100, 0), (10, 0)));
+    }
+
+    #[test]
+    fn spending_more_than_is_reserved_fails() {
+        let mut portfolio = portfolio();
+        portfolio.reserve(coins(30)).unwrap();
+        assert!(matches!(portfolio.spend_reservation(order_id(), 31), Err(ErrorType::InsufficientLockedAmount)));
+        assert_eq!(portfolio.reservations[&order_id()].amount, 30);
+        assert_eq!(balances(&portfolio), ((100, 30), (10, 0)));
+    }
+
+    #[test]
+    fn releasing_twice_fails() {
+        let mut portfolio = portfolio();
+        portfolio.reserve(coins(30)).unwrap();
+        portfolio.release_reservation(order_id()).unwrap();
+        assert!(matches!(portfolio.release_reservation(order_id()), Err(ErrorType::ReservationNotFound(_))));
+        assert!(matches!(portfolio.spend_reservation(order_id(), 1), Err(ErrorType::ReservationNotFound(_))));
+        assert_eq!(balances(&portfolio), ((100, 0), (10, 0)));
+    }
+
+    #[test]
+    fn owed_coins_are_paid_from_their_lock() {
+        let mut portfolio = portfolio();
+        portfolio.reserve(assets(10, 50)).unwrap();
+        assert_eq!(balances(&portfolio), ((100, 50), (10, 10)));
+        assert!(matches!(portfolio.spend_owed(order_id(), 51), Err(ErrorType::InsufficientLockedAmount)));
+        portfolio.spend_owed(order_id(), 20).unwrap();
+        assert_eq!(portfolio.reservations[&order_id()].owed, 30);
+        assert_eq!(balances(&portfolio), ((80, 30), (10, 10)));
+        portfolio.release_reservation(order_id()).unwrap();
+        assert_eq!(balances(&portfolio), ((80, 0), (10, 0)));
+    }
+}
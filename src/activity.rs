@@ -0,0 +1,68 @@
+use crate::account::PortfolioId;
+use crate::fees::FeeSchedule;
+
+// What a portfolio sent and how it traded. Kept on the portfolio, so a rolled back event leaves
+// no trace here either.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Activity {
+    pub orders: usize,
+    pub ordered_quantity: usize,
+    pub cancels: usize,
+    // trade legs, one per resting order crossed
+    pub fills: usize,
+    pub maker_quantity: usize,
+    pub maker_notional: usize,
+    pub taker_quantity: usize,
+    pub taker_notional: usize,
+}
+
+impl Activity {
+    // what volume tiers are counted in, made and taken alike
+    pub fn volume(&self) -> usize {
+        self.maker_notional.saturating_add(self.taker_notional)
+    }
+
+    pub(crate) fn record_fill(&mut self, taker: bool, quantity: usize, notional: usize) {
+        self.fills += 1;
+        if taker {
+            self.taker_quantity = self.taker_quantity.saturating_add(quantity);
+            self.taker_notional = self.taker_notional.saturating_add(notional);
+        } else {
+            self.maker_quantity = self.maker_quantity.saturating_add(quantity);
+            self.maker_notional = self.maker_notional.saturating_add(notional);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ActivityStats {
+    pub portfolio: PortfolioId,
+    #[serde(flatten)]
+    pub activity: Activity,
+    // of the traded quantity, the part that took liquidity
+    pub taker_ratio: Option<f64>,
+    // traded over ordered quantity
+    pub fill_ratio: Option<f64>,
+    // orders sent per fill
+    pub order_to_trade_ratio: Option<f64>,
+    // the rates of the volume tier the portfolio is in
+    pub taker_fee_basis_points: usize,
+    pub maker_rebate_basis_points: usize,
+}
+
+impl ActivityStats {
+    pub fn new(portfolio: PortfolioId, activity: &Activity, fees: &FeeSchedule) -> ActivityStats {
+        let ratio = |a: usize, b: usize| if b == 0 { None } else { Some(a as f64 / b as f64) };
+        let traded = activity.maker_quantity + activity.taker_quantity;
+        let (taker_fee_basis_points, maker_rebate_basis_points) = fees.rates(activity.volume());
+        ActivityStats {
+            portfolio,
+            activity: activity.clone(),
+            taker_ratio: ratio(activity.taker_quantity, traded),
+            fill_ratio: ratio(traded, activity.ordered_quantity),
+            order_to_trade_ratio: ratio(activity.orders, activity.fills),
+            taker_fee_basis_points,
+            maker_rebate_basis_points,
+        }
+    }
+}
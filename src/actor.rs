@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::error::{EngineResult, ErrorType};
+
+// FNV-1a, journals keep fingerprints so they can't depend on the Rust version's hasher
+pub fn fingerprint(token: &str) -> String {
+    let hash = token.bytes().fold(0xcbf2_9ce4_8422_2325u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    });
+    format!("{:016x}", hash)
+}
+
+// Who submitted an event, as opposed to the portfolio it acts for.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Actor {
+    // nothing identified the caller
+    #[default]
+    Anonymous,
+    // a player's API token by fingerprint, the journal never holds the token itself
+    Token(String),
+    Admin,
+    // the engine's own runners: market makers, schedules, Dutch auctions, bankruptcy rules, heartbeats
+    Bot(String),
+    // the game clock
+    System,
+}
+
+impl Actor {
+    pub fn token(token: &str) -> Actor {
+        Actor::Token(fingerprint(token))
+    }
+
+    pub fn bot(name: &str) -> Actor {
+        Actor::Bot(name.to_string())
+    }
+
+    // someone playing, rather than an admin or the game itself
+    pub fn is_player(&self) -> bool {
+        matches!(self, Actor::Anonymous | Actor::Token(_))
+    }
+
+    // `anonymous`, `admin`, `system`, `token:<fingerprint>` or `bot:<name>`, as admin queries take it
+    pub fn label(&self) -> String {
+        match self {
+            Actor::Anonymous => "anonymous".to_string(),
+            Actor::Token(fingerprint) => format!("token:{}", fingerprint),
+            Actor::Admin => "admin".to_string(),
+            Actor::Bot(name) => format!("bot:{}", name),
+            Actor::System => "system".to_string(),
+        }
+    }
+}
+
+// Answers the admin who asked for a token, the only place the token itself shows up.
+#[derive(Clone, Debug, Serialize)]
+pub struct IssuedToken {
+    pub portfolio: PortfolioId,
+    pub token: String,
+    pub fingerprint: String,
+}
+
+// The API tokens the server issued, by fingerprint, with the portfolio each one acts for.
+#[derive(Clone, Debug, Default)]
+pub struct Tokens {
+    tokens: HashMap<String, PortfolioId>,
+}
+
+impl Tokens {
+    pub fn new() -> Tokens {
+        Tokens::default()
+    }
+
+    pub fn issue(&mut self, fingerprint: String, portfolio: PortfolioId) {
+        self.tokens.insert(fingerprint, portfolio);
+    }
+
+    // false if it wasn't issued
+    pub fn revoke(&mut self, fingerprint: &str) -> bool {
+        self.tokens.remove(fingerprint).is_some()
+    }
+
+    pub fn is_issued(&self, fingerprint: &str) -> bool {
+        self.tokens.contains_key(fingerprint)
+    }
+
+    // A portfolio with issued tokens only takes player events sent with one of them.
+    pub fn check(&self, portfolio_id: PortfolioId, actor: &Actor) -> EngineResult<()> {
+        if !actor.is_player() || !self.tokens.values().any(|p| *p == portfolio_id) {
+            return Ok(());
+        }
+        self.check_owner(portfolio_id, actor)
+    }
+
+    // Only one of the portfolio's own tokens, even while it holds none.
+    pub fn check_owner(&self, portfolio_id: PortfolioId, actor: &Actor) -> EngineResult<()> {
+        match actor {
+            Actor::Token(fingerprint) if self.tokens.get(fingerprint) == Some(&portfolio_id) => Ok(()),
+            _ => Err(ErrorType::TokenRequired),
+        }
+    }
+
+    // (fingerprint, portfolio) sorted by fingerprint
+    pub fn list(&self) -> Vec<(String, PortfolioId)> {
+        let mut tokens: Vec<(String, PortfolioId)> = self.tokens.iter()
+            .map(|(fingerprint, portfolio)| (fingerprint.clone(), *portfolio))
+            .collect();
+        tokens.sort();
+        tokens
+    }
+}
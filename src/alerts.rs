@@ -0,0 +1,157 @@
+use crate::account::PortfolioId;
+use crate::market::{AssetId, Market, Notification};
+use std::collections::{HashMap, VecDeque};
+use uuid::Uuid;
+
+const MAX_FIRED_ALERTS: usize = 100;
+const MAX_PRICE_POINTS: usize = 1000;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum AlertRule {
+    PriceMove { asset: AssetId, percent: usize, ticks: u64 },
+    PortfolioValueBelow { value: usize },
+    OrderRejections { count: usize },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Alert {
+    pub id: Uuid,
+    pub rule: AlertRule,
+    // set while the condition holds, so an alert fires once per crossing
+    #[serde(skip)]
+    active: bool,
+    #[serde(skip)]
+    rejections: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FiredAlert {
+    pub alert: Uuid,
+    pub tick: u64,
+    pub message: String,
+}
+
+#[derive(Default)]
+pub struct Alerts {
+    rules: HashMap<PortfolioId, Vec<Alert>>,
+    fired: HashMap<PortfolioId, VecDeque<FiredAlert>>,
+    prices: HashMap<AssetId, VecDeque<(u64, usize)>>,
+}
+
+impl Alerts {
+    pub fn new() -> Alerts {
+        Alerts::default()
+    }
+
+    pub fn add_rule(&mut self, portfolio: PortfolioId, id: Uuid, rule: AlertRule) {
+        self.rules.entry(portfolio).or_default().push(Alert {
+            id,
+            rule,
+            active: false,
+            rejections: 0,
+        });
+    }
+
+    pub fn remove_rule(&mut self, portfolio: PortfolioId, id: Uuid) -> bool {
+        match self.rules.get_mut(&portfolio) {
+            Some(rules) => {
+                let before = rules.len();
+                rules.retain(|a| a.id != id);
+                rules.len() != before
+            },
+            None => false,
+        }
+    }
+
+    pub fn get_rules(&self, portfolio: PortfolioId) -> Vec<Alert> {
+        self.rules.get(&portfolio).cloned().unwrap_or_default()
+    }
+
+    pub fn get_fired(&self, portfolio: PortfolioId) -> Vec<FiredAlert> {
+        self.fired.get(&portfolio)
+            .map(|fired| fired.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn record_price(&mut self, asset: AssetId, tick: u64, price: usize) {
+        let prices = self.prices.entry(asset).or_default();
+        if prices.back().is_some_and(|(_, last)| *last == price) {
+            return;
+        }
+        prices.push_back((tick, price));
+        while prices.len() > MAX_PRICE_POINTS {
+            prices.pop_front();
+        }
+    }
+
+    pub fn record_rejection(&mut self, portfolio: PortfolioId) {
+        if let Some(rules) = self.rules.get_mut(&portfolio) {
+            rules.iter_mut()
+                .filter(|a| matches!(a.rule, AlertRule::OrderRejections { .. }))
+                .for_each(|a| a.rejections += 1);
+        }
+    }
+
+    // percent change of the price over the last `ticks` ticks
+    fn price_move(&self, asset: AssetId, tick: u64, ticks: u64) -> Option<usize> {
+        let prices = self.prices.get(&asset)?;
+        let (_, current) = *prices.back()?;
+        let since = tick.saturating_sub(ticks);
+        let (_, reference) = *prices.iter().rev()
+            .find(|(t, _)| *t < since)
+            .or_else(|| prices.front())?;
+        if reference == 0 {
+            return None;
+        }
+        Some(current.abs_diff(reference) * 100 / reference)
+    }
+
+    pub fn evaluate(&mut self, market: &Market, tick: u64) -> Vec<(PortfolioId, Notification)> {
+        let mut triggered: Vec<(PortfolioId, Uuid, String)> = vec![];
+        let portfolios: Vec<PortfolioId> = self.rules.keys().cloned().collect();
+        for portfolio in portfolios {
+            let mut rules = self.rules.remove(&portfolio).unwrap_or_default();
+            for alert in rules.iter_mut() {
+                let message = match alert.rule {
+                    AlertRule::PriceMove { asset, percent, ticks } => {
+                        self.price_move(asset, tick, ticks)
+                            .filter(|moved| *moved > percent)
+                            .map(|moved| format!("price of {} moved {}% within {} ticks", asset, moved, ticks))
+                    },
+                    AlertRule::PortfolioValueBelow { value } => {
+                        market.portfolio_value(portfolio).ok()
+                            .filter(|current| *current < value)
+                            .map(|current| format!("portfolio value {} is below {}", current, value))
+                    },
+                    AlertRule::OrderRejections { count } => {
+                        if alert.rejections >= count {
+                            alert.rejections = 0;
+                            alert.active = false;
+                            Some(format!("{} orders were rejected", count))
+                        } else {
+                            None
+                        }
+                    },
+                };
+                match message {
+                    Some(message) if !alert.active => {
+                        alert.active = true;
+                        triggered.push((portfolio, alert.id, message));
+                    },
+                    Some(_) => {},
+                    None => alert.active = false,
+                }
+            }
+            self.rules.insert(portfolio, rules);
+        }
+
+        triggered.into_iter().map(|(portfolio, alert, message)| {
+            let fired = self.fired.entry(portfolio).or_default();
+            fired.push_back(FiredAlert { alert, tick, message: message.clone() });
+            while fired.len() > MAX_FIRED_ALERTS {
+                fired.pop_front();
+            }
+            (portfolio, Notification::Alert { alert, message })
+        }).collect()
+    }
+}
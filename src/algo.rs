@@ -0,0 +1,287 @@
+use uuid::Uuid;
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::engine::Engine;
+use crate::error::{EngineResult, ErrorType};
+use crate::lifecycle::{OrderRecord, OrderRegistry};
+use crate::market::{AssetId, Market};
+use crate::order::{Event, Order, OrderId, OrderMode, OrderSide};
+use crate::trades::Trade;
+
+const ORDER_LIFETIME_MILLIS: u64 = 24*60*60*1000;
+
+// How a parent order is worked into the book.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum AlgoStrategy {
+    // `slices` children of about the same size, evenly spread over `duration_ticks`
+    Twap { duration_ticks: u64, slices: u64 },
+    // every tick, keeps what was placed at `percent` of what the others traded in the asset since the
+    // parent started. No child is more than `max_percent` of what they traded since the tick before
+    Pov { percent: u64, max_percent: u64 },
+}
+
+impl AlgoStrategy {
+    pub fn check(&self) -> EngineResult<()> {
+        match self {
+            AlgoStrategy::Twap { duration_ticks, slices } => {
+                if *slices == 0 || *slices > *duration_ticks {
+                    return Err(ErrorType::InvalidAlgo);
+                }
+            },
+            AlgoStrategy::Pov { percent, max_percent } => {
+                if *percent == 0 || *percent > *max_percent || *max_percent > 100 {
+                    return Err(ErrorType::InvalidAlgo);
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AlgoTerms {
+    pub side: OrderSide,
+    pub quantity: usize,
+    // of every child
+    pub mode: OrderMode,
+    pub strategy: AlgoStrategy,
+}
+
+impl AlgoTerms {
+    pub fn check(&self) -> EngineResult<()> {
+        if self.quantity == 0 {
+            return Err(ErrorType::QuantityCantBeZero);
+        }
+        self.strategy.check()
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum AlgoState {
+    Working,
+    Filled,
+    // placed all it was going to, what the children didn't fill is left over
+    Finished,
+    Cancelled,
+}
+
+// An order the engine slices into child orders over time on the portfolio's behalf.
+#[derive(Clone, Debug, Serialize)]
+pub struct ParentOrder {
+    pub id: Uuid,
+    pub portfolio: PortfolioId,
+    pub asset: AssetId,
+    #[serde(flatten)]
+    pub terms: AlgoTerms,
+    pub started_tick: u64,
+    pub next_tick: u64,
+    // slices gone by, placed or turned down
+    pub slices_done: u64,
+    // what the children were given so far
+    pub placed: usize,
+    pub filled: usize,
+    // what the others traded in the asset since the parent started, as far as it looked
+    pub market_volume: usize,
+    // of it, what they traded since the look before
+    pub latest_volume: usize,
+    #[serde(skip)]
+    tape_cursor: usize,
+    pub children: Vec<OrderId>,
+    pub state: AlgoState,
+}
+
+// A parent with where its children stand.
+#[derive(Clone, Debug, Serialize)]
+pub struct AlgoStatus {
+    #[serde(flatten)]
+    pub parent: ParentOrder,
+    pub child_orders: Vec<OrderRecord>,
+}
+
+impl ParentOrder {
+    pub fn new(id: Uuid, portfolio: PortfolioId, asset: AssetId, terms: AlgoTerms, market: &Market) -> EngineResult<ParentOrder> {
+        terms.check()?;
+        Ok(ParentOrder {
+            id,
+            portfolio,
+            asset,
+            terms,
+            started_tick: market.tick,
+            next_tick: market.tick,
+            slices_done: 0,
+            placed: 0,
+            filled: 0,
+            market_volume: 0,
+            latest_volume: 0,
+            tape_cursor: market.trades.len(),
+            children: vec![],
+            state: AlgoState::Working,
+        })
+    }
+
+    fn is_over(&self) -> bool {
+        self.state != AlgoState::Working
+    }
+
+    fn slicing(&self) -> bool {
+        match self.terms.strategy {
+            AlgoStrategy::Twap { slices, .. } => self.slices_done < slices,
+            AlgoStrategy::Pov { .. } => self.placed < self.terms.quantity,
+        }
+    }
+
+    // A slice that was turned down is made up for by the next one.
+    fn slice_quantity(&self) -> usize {
+        match self.terms.strategy {
+            AlgoStrategy::Twap { slices, .. } => {
+                let target = (self.terms.quantity as u128 * (self.slices_done + 1) as u128 / slices as u128) as usize;
+                target.saturating_sub(self.placed)
+            },
+            AlgoStrategy::Pov { percent, max_percent } => {
+                let target = (self.market_volume as u128 * percent as u128 / 100).min(self.terms.quantity as u128) as usize;
+                let cap = (self.latest_volume as u128 * max_percent as u128 / 100) as usize;
+                target.saturating_sub(self.placed).min(cap)
+            },
+        }
+    }
+
+    // the tick the slice after the current one is due
+    fn following_tick(&self, tick: u64) -> u64 {
+        match self.terms.strategy {
+            AlgoStrategy::Twap { duration_ticks, slices } => self.started_tick + self.slices_done * (duration_ticks / slices),
+            AlgoStrategy::Pov { .. } => tick + 1,
+        }
+    }
+
+    // the children's own trades aren't the market's volume, busted ones never happened
+    fn watch_tape(&mut self, trades: &[Trade]) {
+        let children = &self.children;
+        let new_trades = trades.get(self.tape_cursor..).unwrap_or(&[]);
+        self.latest_volume = new_trades.iter()
+            .filter(|t| t.asset == self.asset && !t.busted)
+            .filter(|t| !children.contains(&t.buy_order) && !children.contains(&t.sell_order))
+            .map(|t| t.quantity)
+            .sum();
+        self.market_volume += self.latest_volume;
+        self.tape_cursor = trades.len();
+    }
+
+    // The child of the next slice, none when it has nothing to place. Moves on to the slice after it
+    // either way.
+    pub fn next_child(&mut self, engine: &Engine) -> Option<Order> {
+        self.watch_tape(&engine.market.trades);
+        let quantity = self.slice_quantity();
+        self.slices_done += 1;
+        self.next_tick = self.following_tick(engine.market.tick);
+        if quantity == 0 {
+            return None;
+        }
+        let stamp = engine.stamp();
+        let expires = stamp.created_at + ORDER_LIFETIME_MILLIS;
+        Order::new(stamp, self.portfolio, self.asset, self.terms.side, quantity, self.terms.mode, expires).ok()
+    }
+
+    pub fn record_child(&mut self, child: &Order) {
+        self.placed += child.quantity;
+        self.children.push(child.get_id());
+    }
+
+    // counts the children's fills and tells when the parent is done
+    fn refresh(&mut self, orders: &OrderRegistry) {
+        self.filled = self.children.iter().filter_map(|id| orders.get(*id).ok()).map(|r| r.filled).sum();
+        if self.state == AlgoState::Cancelled {
+            return;
+        }
+        let resting = self.open_children(orders).next().is_some();
+        self.state = if self.filled >= self.terms.quantity {
+            AlgoState::Filled
+        } else if !self.slicing() && !resting {
+            AlgoState::Finished
+        } else {
+            AlgoState::Working
+        };
+    }
+
+    fn open_children<'a>(&'a self, orders: &'a OrderRegistry) -> impl Iterator<Item = OrderId> + 'a {
+        self.children.iter().cloned().filter(move |id| orders.get(*id).is_ok_and(|r| !r.state.is_final()))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Algos {
+    parents: HashMap<Uuid, ParentOrder>,
+}
+
+impl Algos {
+    pub fn new() -> Algos {
+        Algos {
+            parents: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, parent: ParentOrder) {
+        self.parents.insert(parent.id, parent);
+    }
+
+    pub fn remove(&mut self, id: Uuid) {
+        self.parents.remove(&id);
+    }
+
+    pub fn get(&self, portfolio_id: PortfolioId, id: Uuid, orders: &OrderRegistry) -> EngineResult<AlgoStatus> {
+        let mut parent = match self.parents.get(&id) {
+            Some(parent) if parent.portfolio == portfolio_id => parent.clone(),
+            _ => return Err(ErrorType::AlgoNotFound(id)),
+        };
+        parent.refresh(orders);
+        let child_orders = parent.children.iter().filter_map(|id| orders.get(*id).ok()).cloned().collect();
+        Ok(AlgoStatus { parent, child_orders })
+    }
+
+    pub fn get_for(&self, portfolio_id: PortfolioId, orders: &OrderRegistry) -> Vec<AlgoStatus> {
+        let mut algos: Vec<AlgoStatus> = self.parents.values()
+            .filter(|p| p.portfolio == portfolio_id)
+            .filter_map(|p| self.get(portfolio_id, p.id, orders).ok())
+            .collect();
+        algos.sort_by_key(|a| (a.parent.started_tick, a.parent.id));
+        algos
+    }
+
+    // Stops slicing, the children still in the book are returned to be cancelled.
+    pub fn cancel(&mut self, portfolio_id: PortfolioId, id: Uuid, orders: &OrderRegistry) -> EngineResult<Vec<(PortfolioId, OrderId, AssetId)>> {
+        let parent = match self.parents.get_mut(&id) {
+            Some(parent) if parent.portfolio == portfolio_id => parent,
+            _ => return Err(ErrorType::AlgoNotFound(id)),
+        };
+        parent.refresh(orders);
+        if parent.is_over() {
+            return Err(ErrorType::InvalidAlgo);
+        }
+        parent.state = AlgoState::Cancelled;
+        Ok(parent.open_children(orders).map(|order| (parent.portfolio, order, parent.asset)).collect())
+    }
+
+    fn due(&self, tick: u64) -> Vec<Uuid> {
+        let mut due: Vec<Uuid> = self.parents.values()
+            .filter(|p| p.state == AlgoState::Working && p.slicing() && p.next_tick <= tick)
+            .map(|p| p.id)
+            .collect();
+        due.sort();
+        due
+    }
+}
+
+// Places the children that are due. They run through `Engine::process`, so they're journaled like
+// any player's orders.
+pub fn run_algos(engine: &mut Engine) {
+    let tick = engine.market.tick;
+    for id in engine.algos.due(tick) {
+        let mut parent = engine.algos.parents[&id].clone();
+        if let Some(child) = parent.next_child(engine) {
+            if engine.process(Event::Order(child)).is_ok() {
+                parent.record_child(&child);
+            }
+        }
+        engine.algos.add(parent);
+    }
+}
@@ -0,0 +1,84 @@
+use std::cmp::Reverse;
+use crate::book::{Book, DarkPriority};
+use crate::order::{Order, OrderMode, OrderSide};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum MarketStructure {
+    // every order is matched as it arrives
+    #[default]
+    Continuous,
+    // orders collect in the book during a tick and cross at one price when it ends
+    BatchAuction,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct Clearing {
+    pub price: usize,
+    pub volume: usize,
+    // buy minus sell quantity at the price, either way
+    pub imbalance: usize,
+}
+
+fn limits<'a>(orders: impl Iterator<Item = &'a Order>) -> Vec<(usize, usize)> {
+    orders.filter_map(|o| match o.mode {
+        OrderMode::Limit(limit) => Some((limit, o.quantity)),
+        OrderMode::Best => None,
+    }).collect()
+}
+
+// The one price that crosses the most quantity. Ties go to the smaller imbalance, then to the price
+// closest to the last trade, then to the lower price. None while nothing crosses.
+pub fn clearing(book: &Book, last_price: Option<usize>) -> Option<Clearing> {
+    let buys = limits(book.queue(OrderSide::Buy, DarkPriority::Time));
+    let sells = limits(book.queue(OrderSide::Sell, DarkPriority::Time));
+    let mut prices: Vec<usize> = buys.iter().chain(sells.iter()).map(|(price, _)| *price).collect();
+    prices.sort();
+    prices.dedup();
+    prices.into_iter()
+        .map(|price| {
+            let demand: usize = buys.iter().filter(|(limit, _)| *limit >= price).map(|(_, q)| *q).sum();
+            let supply: usize = sells.iter().filter(|(limit, _)| *limit <= price).map(|(_, q)| *q).sum();
+            Clearing {
+                price,
+                volume: demand.min(supply),
+                imbalance: demand.max(supply) - demand.min(supply),
+            }
+        })
+        .filter(|c| c.volume > 0)
+        .min_by_key(|c| {
+            let distance = last_price.map_or(0, |last| c.price.abs_diff(last));
+            (Reverse(c.volume), c.imbalance, distance, c.price)
+        })
+}
+
+// Pairs the orders that cross at the clearing price into (buy, sell, quantity) legs. Both sides go
+// in book priority, best price first and then as `priority` orders hidden and lit ones.
+pub fn allocate(book: &Book, clearing: &Clearing, priority: DarkPriority) -> Vec<(Order, Order, usize)> {
+    let crosses = |o: &&Order, buying: bool| match o.mode {
+        OrderMode::Limit(limit) if buying => limit >= clearing.price,
+        OrderMode::Limit(limit) => limit <= clearing.price,
+        OrderMode::Best => false,
+    };
+    let mut buys: Vec<(Order, usize)> = book.queue(OrderSide::Buy, priority)
+        .filter(|o| crosses(o, true)).map(|o| (*o, o.quantity)).collect();
+    let mut sells: Vec<(Order, usize)> = book.queue(OrderSide::Sell, priority)
+        .filter(|o| crosses(o, false)).map(|o| (*o, o.quantity)).collect();
+
+    let mut legs = vec![];
+    let mut left = clearing.volume;
+    let (mut b, mut s) = (0, 0);
+    while left > 0 && b < buys.len() && s < sells.len() {
+        let quantity = buys[b].1.min(sells[s].1).min(left);
+        legs.push((buys[b].0, sells[s].0, quantity));
+        buys[b].1 -= quantity;
+        sells[s].1 -= quantity;
+        left -= quantity;
+        if buys[b].1 == 0 {
+            b += 1;
+        }
+        if sells[s].1 == 0 {
+            s += 1;
+        }
+    }
+    legs
+}
@@ -0,0 +1,175 @@
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use crate::account::{Account, PortfolioId};
+use crate::actor;
+use crate::error::{EngineResult, ErrorType};
+use crate::journal::JournalRecord;
+use crate::market::{AssetId, Market};
+use crate::order::{Order, OrderId};
+
+// wall clock time isn't journaled, a replay can't reproduce it, nor the game time derived from it
+const UNJOURNALED_FIELDS: [&str; 4] = ["now", "timestamp", "game_time", "expiries"];
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ReplayDivergence {
+    // the first entry whose replayed state doesn't match the hash it was journaled with, none if every
+    // hash matched and only the end differs from the snapshot
+    pub sequence: Option<u64>,
+    pub record: Option<JournalRecord>,
+    // where the end of the replay differs from the snapshot, empty when it stopped at an entry
+    pub paths: Vec<String>,
+}
+
+pub fn to_value(market: &Market) -> EngineResult<Value> {
+    serde_json::to_value(market).map_err(|_| ErrorType::InvalidState)
+}
+
+// paths like `/portfolios/<id>/coins/total_amount` of every value that differs
+pub fn diff(left: &Value, right: &Value) -> Vec<String> {
+    let mut paths = vec![];
+    diff_at("", left, right, &mut paths);
+    paths
+}
+
+fn diff_at(path: &str, left: &Value, right: &Value, paths: &mut Vec<String>) {
+    match (left, right) {
+        (Value::Object(l), Value::Object(r)) => {
+            let mut keys: Vec<&String> = l.keys().chain(r.keys()).collect();
+            keys.sort();
+            keys.dedup();
+            for key in keys {
+                if UNJOURNALED_FIELDS.contains(&key.as_str()) {
+                    continue;
+                }
+                let child = format!("{}/{}", path, key);
+                diff_at(&child, l.get(key).unwrap_or(&Value::Null), r.get(key).unwrap_or(&Value::Null), paths);
+            }
+        },
+        (Value::Array(l), Value::Array(r)) => {
+            for i in 0..l.len().max(r.len()) {
+                let child = format!("{}/{}", path, i);
+                diff_at(&child, l.get(i).unwrap_or(&Value::Null), r.get(i).unwrap_or(&Value::Null), paths);
+            }
+        },
+        (l, r) if l != r => paths.push(path.to_string()),
+        _ => (),
+    }
+}
+
+// of everything a replay reproduces, in the same form whichever hasher the maps use
+pub fn state_hash(market: &Market) -> EngineResult<String> {
+    let mut value = to_value(market)?;
+    strip_unjournaled(&mut value);
+    Ok(actor::fingerprint(&value.to_string()))
+}
+
+fn strip_unjournaled(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for field in UNJOURNALED_FIELDS.iter() {
+                fields.remove(*field);
+            }
+            fields.values_mut().for_each(strip_unjournaled);
+        },
+        Value::Array(items) => items.iter_mut().for_each(strip_unjournaled),
+        _ => (),
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct Balance {
+    pub total: usize,
+    pub locked: usize,
+}
+
+impl Balance {
+    fn of(account: &Account) -> Balance {
+        Balance { total: account.get_total_amount(), locked: account.get_locked_amount() }
+    }
+}
+
+// no asset for coins, no balance on a side where the account doesn't exist
+#[derive(Clone, Debug, Serialize)]
+pub struct BalanceChange {
+    pub portfolio: PortfolioId,
+    pub asset: Option<AssetId>,
+    pub before: Option<Balance>,
+    pub after: Option<Balance>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OrderChange {
+    pub before: Order,
+    pub after: Order,
+}
+
+// what changed from snapshot `a` to snapshot `b`, sorted by id
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct SnapshotDiff {
+    pub portfolios_added: Vec<PortfolioId>,
+    pub portfolios_removed: Vec<PortfolioId>,
+    pub balances: Vec<BalanceChange>,
+    pub orders_added: Vec<Order>,
+    pub orders_removed: Vec<Order>,
+    pub orders_changed: Vec<OrderChange>,
+}
+
+impl SnapshotDiff {
+    pub fn is_empty(&self) -> bool {
+        self.portfolios_added.is_empty() && self.portfolios_removed.is_empty() && self.balances.is_empty()
+            && self.orders_added.is_empty() && self.orders_removed.is_empty() && self.orders_changed.is_empty()
+    }
+}
+
+fn balances(market: &Market) -> HashMap<(PortfolioId, Option<AssetId>), Balance> {
+    let mut balances = HashMap::new();
+    for portfolio in market.portfolios.values() {
+        balances.insert((portfolio.id, None), Balance::of(&portfolio.coins));
+        for (asset, account) in &portfolio.assets {
+            balances.insert((portfolio.id, Some(*asset)), Balance::of(account));
+        }
+    }
+    balances
+}
+
+fn open_orders(market: &Market) -> HashMap<OrderId, Order> {
+    market.books.values()
+        .flat_map(|book| book.all_orders())
+        .map(|order| (order.get_id(), *order))
+        .collect()
+}
+
+pub fn diff_snapshots(a: &Market, b: &Market) -> SnapshotDiff {
+    let mut diff = SnapshotDiff::default();
+    let portfolios: BTreeSet<&PortfolioId> = a.portfolios.keys().chain(b.portfolios.keys()).collect();
+    for id in portfolios {
+        match (a.portfolios.contains_key(id), b.portfolios.contains_key(id)) {
+            (false, true) => diff.portfolios_added.push(*id),
+            (true, false) => diff.portfolios_removed.push(*id),
+            _ => (),
+        }
+    }
+
+    let (before, after) = (balances(a), balances(b));
+    let keys: BTreeSet<&(PortfolioId, Option<AssetId>)> = before.keys().chain(after.keys()).collect();
+    for key in keys {
+        let (old, new) = (before.get(key).cloned(), after.get(key).cloned());
+        if old != new {
+            diff.balances.push(BalanceChange { portfolio: key.0, asset: key.1, before: old, after: new });
+        }
+    }
+
+    let (before, after) = (open_orders(a), open_orders(b));
+    let ids: BTreeSet<&OrderId> = before.keys().chain(after.keys()).collect();
+    for id in ids {
+        match (before.get(id), after.get(id)) {
+            (None, Some(order)) => diff.orders_added.push(*order),
+            (Some(order), None) => diff.orders_removed.push(*order),
+            (Some(old), Some(new)) if old.quantity != new.quantity => {
+                diff.orders_changed.push(OrderChange { before: *old, after: *new })
+            },
+            _ => (),
+        }
+    }
+    diff
+}
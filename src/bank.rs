@@ -0,0 +1,43 @@
+use crate::ledger::{InterestPolicy, LedgerKind};
+use crate::account::PortfolioId;
+use crate::market::Market;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct MonetaryStats {
+    pub tick: u64,
+    pub bank_account: usize,
+    pub bank_portfolio: Option<PortfolioId>,
+    pub bank_portfolio_coins: usize,
+    // coins held by the bank and all portfolios, locked ones included
+    pub money_supply: usize,
+    pub minted: usize,
+    pub burned: usize,
+    pub interest_paid: usize,
+    pub interest_policy: Option<InterestPolicy>,
+    pub faucet_paid: usize,
+}
+
+impl MonetaryStats {
+    pub fn from_market(market: &Market) -> MonetaryStats {
+        let bank_portfolio_coins = market.bank_portfolio
+            .and_then(|id| market.portfolios.get(&id))
+            .map_or(0, |p| p.coins.get_total_amount());
+        MonetaryStats {
+            tick: market.tick,
+            bank_account: market.bank_account,
+            bank_portfolio: market.bank_portfolio,
+            bank_portfolio_coins,
+            money_supply: market.bank_account + market.portfolios.values()
+                .map(|p| p.coins.get_total_amount())
+                .sum::<usize>(),
+            minted: market.minted,
+            burned: market.burned,
+            interest_paid: market.ledger.iter()
+                .filter(|e| e.kind == LedgerKind::Interest)
+                .map(|e| e.amount)
+                .sum(),
+            interest_policy: market.interest_policy,
+            faucet_paid: market.faucet.paid,
+        }
+    }
+}
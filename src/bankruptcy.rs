@@ -0,0 +1,88 @@
+use crate::journal::JournalRecord;
+use crate::order::{Event, Order, OrderMode, OrderSide};
+use crate::account::PortfolioId;
+use crate::engine::Engine;
+
+const LIQUIDATION_LIFETIME_MILLIS: u64 = 60*1000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BankruptcyMeasure {
+    NetWorth,
+    FreeCoins,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct BankruptcyRule {
+    pub measure: BankruptcyMeasure,
+    pub threshold: usize,
+    pub liquidate: bool,
+    // fresh balance handed out once the cooldown is over, bankrupt portfolios stay out of the game if unset
+    pub restart_coins: Option<usize>,
+    pub cooldown_ticks: u64,
+}
+
+// Declares portfolios below the threshold bankrupt and restarts those whose cooldown is over.
+// Runs outside of `Engine::apply` and goes through journaled records and events only, so
+// replays don't run it a second time.
+pub fn run_bankruptcy_rules(engine: &mut Engine) {
+    let rule = match engine.bankruptcy_rule {
+        Some(rule) => rule,
+        None => return,
+    };
+    let mut portfolio_ids: Vec<PortfolioId> = engine.market.portfolios.keys().cloned().collect();
+    portfolio_ids.sort();
+    for id in portfolio_ids {
+        if !engine.is_player(id) {
+            continue;
+        }
+        let portfolio = match engine.market.get_portfolio(id) {
+            Ok(portfolio) => portfolio,
+            Err(_) => continue,
+        };
+        match portfolio.bankrupt_since {
+            Some(since) => {
+                if let Some(coins) = rule.restart_coins {
+                    if engine.market.tick >= since + rule.cooldown_ticks {
+                        // the holdings go back to the bank portfolio
+                        let holds_assets = portfolio.assets.values().any(|a| a.get_total_amount() > 0);
+                        if holds_assets && engine.bank_portfolio().is_err() {
+                            continue;
+                        }
+                        let _ = engine.apply(JournalRecord::RestartPortfolio { portfolio: id, coins });
+                    }
+                }
+            },
+            None => {
+                let measured = match rule.measure {
+                    BankruptcyMeasure::NetWorth => engine.market.portfolio_value(id).unwrap_or(0),
+                    BankruptcyMeasure::FreeCoins => portfolio.coins.get_free_amount(),
+                };
+                if measured < rule.threshold {
+                    declare_bankrupt(engine, id, rule.liquidate);
+                }
+            },
+        }
+    }
+}
+
+fn declare_bankrupt(engine: &mut Engine, portfolio_id: PortfolioId, liquidate: bool) {
+    engine.cancel_all_orders_of(portfolio_id);
+    if liquidate {
+        let holdings: Vec<_> = engine.market.get_portfolio(portfolio_id)
+            .map(|p| p.assets.iter()
+                .map(|(asset, account)| (*asset, account.get_free_amount()))
+                .filter(|(_, amount)| *amount > 0)
+                .collect())
+            .unwrap_or_default();
+        for (asset, amount) in holdings {
+            let stamp = engine.stamp();
+            let expires = stamp.created_at + LIQUIDATION_LIFETIME_MILLIS;
+            if let Ok(order) = Order::new(stamp, portfolio_id, asset, OrderSide::Sell, amount, OrderMode::Best, expires) {
+                let _ = engine.process(Event::Order(order));
+            }
+        }
+        // whatever didn't fill must not stay in the books
+        engine.cancel_all_orders_of(portfolio_id);
+    }
+    let _ = engine.apply(JournalRecord::DeclareBankrupt { portfolio: portfolio_id });
+}
@@ -0,0 +1,277 @@
+// Runs simulated bot clients against a game and reports throughput and latencies. Each client owns
+// a portfolio and keeps placing limit and best orders around a price, cancelling some of the ones it
+// placed. In process the clients go through the event queue like the server does, over HTTP they
+// use the portfolios listed on the leaderboard.
+//
+// usage: market_game_loadtest [--target in-process|<host:port>] [--clients 8] [--duration-secs 10]
+//                             [--cancel-percent 20] [--best-percent 10] [--assets 1] [--api-token <token>]
+
+use market_game::game::Game;
+use market_game::order::{Event, OrderMode, OrderSide};
+use serde_json::Value;
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::env;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::thread;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const COINS: usize = 1_000_000_000;
+const HOLDINGS: usize = 1_000_000;
+const PRICE: usize = 100;
+// orders a client remembers to cancel later
+const MAX_OPEN: usize = 50;
+
+#[derive(Clone)]
+struct Config {
+    target: String,
+    clients: usize,
+    duration: Duration,
+    cancel_percent: u64,
+    best_percent: u64,
+    assets: usize,
+    api_token: Option<String>,
+}
+
+impl Config {
+    fn from_args(args: &[String]) -> Config {
+        let value = |name: &str| args.iter()
+            .position(|a| a == name)
+            .and_then(|i| args.get(i + 1))
+            .cloned();
+        let number = |name: &str, default: u64| value(name).map_or(default, |v| v.parse().expect(name));
+        Config {
+            target: value("--target").unwrap_or_else(|| "in-process".to_string()),
+            clients: number("--clients", 8) as usize,
+            duration: Duration::from_secs(number("--duration-secs", 10)),
+            cancel_percent: number("--cancel-percent", 20),
+            best_percent: number("--best-percent", 10),
+            assets: number("--assets", 1) as usize,
+            api_token: value("--api-token"),
+        }
+    }
+}
+
+// xorshift, good enough to vary the orders and keeps the binary free of dependencies
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+#[derive(Clone)]
+enum Target {
+    InProcess(Game),
+    Http { address: String, api_token: Option<String> },
+}
+
+impl Target {
+    // the id of the placed order
+    fn place(&self, portfolio: Uuid, asset: Uuid, side: OrderSide, quantity: usize, mode: OrderMode) -> Result<Uuid, String> {
+        match self {
+            Target::InProcess(game) => {
+                let order = game.new_order(portfolio, asset, side, quantity, mode, Duration::from_secs(60))
+                    .map_err(|e| error_name(&format!("{:?}", e)))?;
+                game.submit(Event::Order(order)).map_err(|e| error_name(&format!("{:?}", e)))?;
+                Ok(order.get_id())
+            },
+            Target::Http { .. } => {
+                let side = if side == OrderSide::Buy { "buy" } else { "sell" };
+                let body = serde_json::json!({ "quantity": quantity, "mode": mode }).to_string();
+                let record = self.request("POST", &format!("/v1/portfolio/{}/asset/{}/{}", portfolio, asset, side), Some(&body))?;
+                record["order_id"].as_str()
+                    .and_then(|id| Uuid::parse_str(id).ok())
+                    .ok_or_else(|| "UnexpectedResponse".to_string())
+            },
+        }
+    }
+
+    fn cancel(&self, portfolio: Uuid, asset: Uuid, order: Uuid) -> Result<(), String> {
+        match self {
+            Target::InProcess(game) => game.submit(Event::CancelOrder(portfolio, order, asset))
+                .map_err(|e| error_name(&format!("{:?}", e))),
+            Target::Http { .. } => self.request("DELETE", &format!("/v1/portfolio/{}/asset/{}/order/{}", portfolio, asset, order), None)
+                .map(|_| ()),
+        }
+    }
+
+    // one connection per request, errors are named by their status and the engine's error
+    fn request(&self, method: &str, path: &str, body: Option<&str>) -> Result<Value, String> {
+        let (address, api_token) = match self {
+            Target::Http { address, api_token } => (address, api_token),
+            Target::InProcess(_) => return Err("NotHttp".to_string()),
+        };
+        let mut stream = TcpStream::connect(address).map_err(|_| "ConnectionFailed".to_string())?;
+        let body = body.unwrap_or("");
+        let mut request = format!("{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n",
+                                  method, path, address, body.len());
+        if let Some(token) = api_token {
+            request.push_str(&format!("X-Api-Token: {}\r\n", token));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream.write_all(request.as_bytes()).map_err(|_| "ConnectionFailed".to_string())?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).map_err(|_| "ConnectionFailed".to_string())?;
+        let status = response.split(' ').nth(1).unwrap_or("").to_string();
+        let body = response.split_once("\r\n\r\n").map_or("", |(_, body)| body);
+        if !status.starts_with('2') {
+            return Err(format!("{} {}", status, error_name(body)));
+        }
+        Ok(serde_json::from_str(body).unwrap_or(Value::Null))
+    }
+}
+
+// `PriceOutOfBounds { .. }` and `OrderNotFound(..)` are counted by their name
+fn error_name(debug: &str) -> String {
+    debug.split([' ', '(']).next().unwrap_or("").to_string()
+}
+
+#[derive(Default)]
+struct Stats {
+    orders: Vec<Duration>,
+    cancels: Vec<Duration>,
+    errors: HashMap<String, usize>,
+}
+
+impl Stats {
+    fn merge(&mut self, other: Stats) {
+        self.orders.extend(other.orders);
+        self.cancels.extend(other.cancels);
+        for (name, count) in other.errors {
+            *self.errors.entry(name).or_insert(0) += count;
+        }
+    }
+}
+
+fn run_client(target: Target, portfolio: Uuid, assets: Vec<Uuid>, config: &Config, seed: u64) -> Stats {
+    let mut rng = Rng(seed | 1);
+    let mut stats = Stats::default();
+    let mut open: Vec<(Uuid, Uuid)> = vec![];
+    let started = Instant::now();
+    while started.elapsed() < config.duration {
+        let cancelling = !open.is_empty() && rng.below(100) < config.cancel_percent;
+        let sent = Instant::now();
+        let result = if cancelling {
+            let (asset, order) = open.swap_remove(rng.below(open.len() as u64) as usize);
+            let result = target.cancel(portfolio, asset, order);
+            stats.cancels.push(sent.elapsed());
+            result
+        } else {
+            let asset = assets[rng.below(assets.len() as u64) as usize];
+            let side = if rng.below(2) == 0 { OrderSide::Buy } else { OrderSide::Sell };
+            let quantity = 1 + rng.below(5) as usize;
+            let mode = if rng.below(100) < config.best_percent {
+                OrderMode::Best
+            } else {
+                OrderMode::Limit(PRICE - 5 + rng.below(11) as usize)
+            };
+            let result = target.place(portfolio, asset, side, quantity, mode);
+            stats.orders.push(sent.elapsed());
+            result.map(|order| {
+                if open.len() == MAX_OPEN {
+                    open.remove(0);
+                }
+                open.push((asset, order));
+            })
+        };
+        if let Err(name) = result {
+            *stats.errors.entry(name).or_insert(0) += 1;
+        }
+    }
+    stats
+}
+
+// a fresh game with one funded portfolio per client, the worker drains the queue
+fn in_process(config: &Config) -> (Target, Vec<Uuid>, Vec<Uuid>) {
+    let mut game = Game::new();
+    let assets: Vec<Uuid> = (0..config.assets.max(1))
+        .map(|i| game.create_asset(format!("asset_{}", i)).unwrap())
+        .collect();
+    let portfolios: Vec<Uuid> = (0..config.clients)
+        .map(|_| {
+            let portfolio = game.create_portfolio(COINS);
+            for asset in &assets {
+                game.set_asset_amount(portfolio, *asset, HOLDINGS);
+            }
+            portfolio
+        })
+        .collect();
+    game.start_worker();
+    (Target::InProcess(game), portfolios, assets)
+}
+
+// the assets and portfolios the server already has, clients share portfolios when there are fewer
+fn over_http(config: &Config) -> (Target, Vec<Uuid>, Vec<Uuid>) {
+    let target = Target::Http { address: config.target.clone(), api_token: config.api_token.clone() };
+    let ids = |path: &str, list: &str, field: &str| -> Vec<Uuid> {
+        let response = target.request("GET", path, None).unwrap_or_else(|e| panic!("GET {} failed: {}", path, e));
+        response[list].as_array().map(|items| items.iter()
+            .filter_map(|item| item[field].as_str().and_then(|id| Uuid::parse_str(id).ok()))
+            .collect())
+            .unwrap_or_default()
+    };
+    let assets = ids("/v1/asset", "assets", "id");
+    let portfolios = ids("/v1/leaderboard", "entries", "portfolio");
+    assert!(!assets.is_empty() && !portfolios.is_empty(), "the server has no assets or portfolios to trade with");
+    let portfolios = (0..config.clients).map(|i| portfolios[i % portfolios.len()]).collect();
+    let assets = assets.into_iter().take(config.assets.max(1)).collect();
+    (target, portfolios, assets)
+}
+
+fn percentiles(latencies: &mut [Duration]) -> String {
+    if latencies.is_empty() {
+        return "-".to_string();
+    }
+    latencies.sort();
+    let at = |p: usize| latencies[((latencies.len() - 1) * p + 50) / 100].as_micros();
+    format!("p50 {}µs, p90 {}µs, p99 {}µs, max {}µs", at(50), at(90), at(99), latencies[latencies.len() - 1].as_micros())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let config = Config::from_args(&args);
+    let (target, portfolios, assets) = if config.target == "in-process" {
+        in_process(&config)
+    } else {
+        over_http(&config)
+    };
+
+    let started = Instant::now();
+    let clients: Vec<_> = portfolios.into_iter().enumerate()
+        .map(|(i, portfolio)| {
+            let target = target.clone();
+            let assets = assets.clone();
+            let config = config.clone();
+            thread::spawn(move || run_client(target, portfolio, assets, &config, 0x9e3779b97f4a7c15 ^ i as u64))
+        })
+        .collect();
+    let mut stats = Stats::default();
+    for client in clients {
+        stats.merge(client.join().expect("client panicked"));
+    }
+    let elapsed = started.elapsed().as_secs_f64();
+
+    let sent = stats.orders.len() + stats.cancels.len();
+    let failed: usize = stats.errors.values().sum();
+    println!("target: {}, clients: {}, {:.1}s", config.target, config.clients, elapsed);
+    println!("events: {} sent, {} rejected, {:.1} per second", sent, failed, sent as f64 / elapsed);
+    println!("orders: {} ({})", stats.orders.len(), percentiles(&mut stats.orders));
+    println!("cancels: {} ({})", stats.cancels.len(), percentiles(&mut stats.cancels));
+    let mut errors: Vec<(String, usize)> = stats.errors.into_iter().collect();
+    errors.sort_by_key(|e| Reverse(e.1));
+    for (name, count) in errors {
+        println!("  {}: {}", name, count);
+    }
+}
@@ -0,0 +1,500 @@
+use uuid::Uuid;
+use std::collections::{HashMap, VecDeque};
+use std::cmp::Ordering;
+use crate::error::{EngineResult, ErrorType};
+use crate::order::{Order, OrderId, OrderMode, OrderSide};
+use crate::market::AssetId;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct OrderKey(u32);
+
+// Slab of orders; freed slots are reused so keys stay small and stable while an order rests.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct OrderArena {
+    slots: Vec<Option<Order>>,
+    free: Vec<u32>,
+}
+
+impl OrderArena {
+    pub fn insert(&mut self, order: Order) -> OrderKey {
+        match self.free.pop() {
+            Some(slot) => {
+                self.slots[slot as usize] = Some(order);
+                OrderKey(slot)
+            },
+            None => {
+                self.slots.push(Some(order));
+                OrderKey((self.slots.len() - 1) as u32)
+            },
+        }
+    }
+
+    pub fn remove(&mut self, key: OrderKey) -> Option<Order> {
+        let order = self.slots.get_mut(key.0 as usize).and_then(|slot| slot.take());
+        if order.is_some() {
+            self.free.push(key.0);
+        }
+        order
+    }
+
+    pub fn get(&self, key: OrderKey) -> Option<&Order> {
+        self.slots.get(key.0 as usize).and_then(|slot| slot.as_ref())
+    }
+
+    pub fn get_mut(&mut self, key: OrderKey) -> Option<&mut Order> {
+        self.slots.get_mut(key.0 as usize).and_then(|slot| slot.as_mut())
+    }
+}
+
+// A trade leg against a resting order.
+#[derive(Copy, Clone, Debug)]
+pub struct Fill {
+    pub key: OrderKey,
+    pub quantity: usize,
+}
+
+// how many changes a book remembers for clients syncing incrementally
+const DELTA_BUFFER_SIZE: usize = 1000;
+
+#[derive(Copy, Clone, Debug, Serialize)]
+pub enum BookChange {
+    Added { id: OrderId, side: OrderSide, price: usize, quantity: usize },
+    Amended { id: OrderId, quantity: usize },
+    Removed { id: OrderId },
+}
+
+// the change that took the book to `version`
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct BookDelta {
+    pub version: u64,
+    pub change: BookChange,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BookIndex {
+    pub indexed: usize,
+    pub slots: usize,
+    pub free_slots: usize,
+    pub sell_keys: usize,
+    pub buy_keys: usize,
+    pub hidden_sell_keys: usize,
+    pub hidden_buy_keys: usize,
+    // empty for a healthy book
+    pub problems: Vec<String>,
+}
+
+// What rests in the lit book, kept up to date as orders come and go so it never needs a scan of the book.
+// Hidden orders aren't counted, they'd show through otherwise.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct OpenInterest {
+    pub buy_quantity: usize,
+    // sum of limit price times quantity
+    pub buy_notional: usize,
+    pub sell_quantity: usize,
+    pub sell_notional: usize,
+}
+
+impl OpenInterest {
+    // the open interest with the order added, it's left as it is when the sums don't fit
+    fn with_added(&self, side: OrderSide, price: usize, quantity: usize) -> EngineResult<OpenInterest> {
+        let mut added = *self;
+        let (total, notional) = match side {
+            OrderSide::Buy => (&mut added.buy_quantity, &mut added.buy_notional),
+            OrderSide::Sell => (&mut added.sell_quantity, &mut added.sell_notional),
+        };
+        *total = total.checked_add(quantity).ok_or(ErrorType::ArithmeticOverflow)?;
+        *notional = price.checked_mul(quantity)
+            .and_then(|n| notional.checked_add(n))
+            .ok_or(ErrorType::ArithmeticOverflow)?;
+        Ok(added)
+    }
+
+    // only ever takes away what was added, so it can't overflow
+    fn remove(&mut self, side: OrderSide, price: usize, quantity: usize) {
+        let (total, notional) = match side {
+            OrderSide::Buy => (&mut self.buy_quantity, &mut self.buy_notional),
+            OrderSide::Sell => (&mut self.sell_quantity, &mut self.sell_notional),
+        };
+        *total = total.saturating_sub(quantity);
+        *notional = notional.saturating_sub(price.saturating_mul(quantity));
+    }
+
+    pub fn merge(&mut self, other: &OpenInterest) -> EngineResult<()> {
+        let add = |a: usize, b: usize| a.checked_add(b).ok_or(ErrorType::ArithmeticOverflow);
+        *self = OpenInterest {
+            buy_quantity: add(self.buy_quantity, other.buy_quantity)?,
+            buy_notional: add(self.buy_notional, other.buy_notional)?,
+            sell_quantity: add(self.sell_quantity, other.sell_quantity)?,
+            sell_notional: add(self.sell_notional, other.sell_notional)?,
+        };
+        Ok(())
+    }
+
+    // -100 when only sells rest, 100 when only buys do
+    pub fn imbalance_percent(&self) -> i64 {
+        let total = self.buy_quantity as i128 + self.sell_quantity as i128;
+        if total == 0 {
+            return 0;
+        }
+        ((self.buy_quantity as i128 - self.sell_quantity as i128) * 100 / total) as i64
+    }
+}
+
+// Where a resting order stands at its price. Other hidden orders aren't counted, they're nobody's business.
+#[derive(Clone, Debug, Serialize)]
+pub struct QueuePosition {
+    pub order: OrderId,
+    pub side: OrderSide,
+    pub price: usize,
+    // 1 fills next at this price
+    pub position: usize,
+    pub orders_ahead: usize,
+    pub quantity_ahead: usize,
+    pub quantity: usize,
+    pub orders_at_price: usize,
+    pub quantity_at_price: usize,
+    // price levels that fill before this one
+    pub levels_ahead: usize,
+}
+
+// Where hidden orders queue at a price they share with lit ones.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum DarkPriority {
+    // displaying an order earns priority, hidden ones fill after every lit one at their price
+    #[default]
+    BehindLit,
+    // hidden and lit orders queue by arrival alike
+    Time,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Book {
+    pub asset_id: AssetId,
+    orders: OrderArena,
+    index: HashMap<OrderId, OrderKey>,
+    sell_keys: Vec<OrderKey>,
+    buy_keys: Vec<OrderKey>,
+    // dark orders, matched like the others but never shown and never in the deltas
+    #[serde(default)]
+    hidden_sell_keys: Vec<OrderKey>,
+    #[serde(default)]
+    hidden_buy_keys: Vec<OrderKey>,
+    // bumped on every change to the orders, the ETag of the book
+    #[serde(default)]
+    version: u64,
+    #[serde(default)]
+    open_interest: OpenInterest,
+    #[serde(skip)]
+    deltas: VecDeque<BookDelta>,
+}
+
+impl Book {
+
+    pub fn new(asset_id: AssetId) -> Book {
+        Book {
+            asset_id,
+            orders: OrderArena::default(),
+            index: HashMap::new(),
+            sell_keys: vec![],
+            buy_keys: vec![],
+            hidden_sell_keys: vec![],
+            hidden_buy_keys: vec![],
+            version: 0,
+            open_interest: OpenInterest::default(),
+            deltas: VecDeque::new(),
+        }
+    }
+
+    pub fn sell_orders(&self) -> impl Iterator<Item = &Order> {
+        self.sell_keys.iter().filter_map(move |key| self.orders.get(*key))
+    }
+
+    pub fn buy_orders(&self) -> impl Iterator<Item = &Order> {
+        self.buy_keys.iter().filter_map(move |key| self.orders.get(*key))
+    }
+
+    pub fn hidden_orders(&self) -> impl Iterator<Item = &Order> {
+        self.hidden_sell_keys.iter().chain(self.hidden_buy_keys.iter()).filter_map(move |key| self.orders.get(*key))
+    }
+
+    // lit and hidden, for everything but the public views
+    pub fn all_orders(&self) -> impl Iterator<Item = &Order> {
+        self.sell_orders().chain(self.buy_orders()).chain(self.hidden_orders())
+    }
+
+    // The resting orders of one side in the order they fill, best price first. Both lanes are kept
+    // sorted already, so they're merged as they're walked.
+    pub fn queue(&self, side: OrderSide, priority: DarkPriority) -> impl Iterator<Item = &Order> {
+        let (lit, hidden, revert_price_order) = match side {
+            OrderSide::Sell => (&self.sell_keys, &self.hidden_sell_keys, false),
+            OrderSide::Buy => (&self.buy_keys, &self.hidden_buy_keys, true),
+        };
+        let mut lit = lit.iter().filter_map(move |key| self.orders.get(*key)).peekable();
+        let mut hidden = hidden.iter().filter_map(move |key| self.orders.get(*key)).peekable();
+        std::iter::from_fn(move || {
+            let hidden_first = match (lit.peek(), hidden.peek()) {
+                (Some(a), Some(b)) => {
+                    let by_price = Book::cmp_prices(a, b, revert_price_order);
+                    match by_price {
+                        Ordering::Equal if priority == DarkPriority::BehindLit => false,
+                        Ordering::Equal => a.created_at > b.created_at,
+                        _ => by_price == Ordering::Greater,
+                    }
+                },
+                (Some(_), None) => false,
+                (None, _) => true,
+            };
+            match hidden_first {
+                true => hidden.next(),
+                false => lit.next(),
+            }
+        })
+    }
+
+    pub fn queue_position(&self, id: OrderId, priority: DarkPriority) -> EngineResult<QueuePosition> {
+        let order = self.get_order(id)?;
+        let price = order.mode.get_limit()?;
+        let visible = |o: &&Order| !o.hidden || o.id == id;
+        let queue: Vec<&Order> = self.queue(order.side, priority).filter(visible).collect();
+        let index = queue.iter().position(|o| o.id == id).ok_or(ErrorType::OrderNotFound(id))?;
+        let at_price = |o: &&&Order| o.mode == order.mode;
+        let ahead = &queue[..index];
+        let mut levels: Vec<usize> = ahead.iter().filter_map(|o| o.mode.get_limit().ok()).filter(|p| *p != price).collect();
+        levels.dedup();
+        let orders_ahead = ahead.iter().filter(at_price).count();
+        Ok(QueuePosition {
+            order: id,
+            side: order.side,
+            price,
+            position: orders_ahead + 1,
+            orders_ahead,
+            quantity_ahead: ahead.iter().filter(at_price).map(|o| o.quantity).sum(),
+            quantity: order.quantity,
+            orders_at_price: queue.iter().filter(at_price).count(),
+            quantity_at_price: queue.iter().filter(at_price).map(|o| o.quantity).sum(),
+            levels_ahead: levels.len(),
+        })
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn open_interest(&self) -> OpenInterest {
+        self.open_interest
+    }
+
+    pub fn key_of(&self, id: OrderId) -> Option<OrderKey> {
+        self.index.get(&id).cloned()
+    }
+
+    // The sizes of the book's structures and everything that doesn't line up between them.
+    pub fn index_report(&self) -> BookIndex {
+        let mut problems = vec![];
+        let lists = [
+            (&self.sell_keys, OrderSide::Sell, false),
+            (&self.buy_keys, OrderSide::Buy, false),
+            (&self.hidden_sell_keys, OrderSide::Sell, true),
+            (&self.hidden_buy_keys, OrderSide::Buy, true),
+        ];
+        let mut queued: Vec<OrderKey> = vec![];
+        for (keys, side, hidden) in lists.iter() {
+            for key in keys.iter() {
+                if queued.contains(key) {
+                    problems.push(format!("key {} is queued more than once", key.0));
+                }
+                queued.push(*key);
+                let order = match self.orders.get(*key) {
+                    Some(order) => order,
+                    None => {
+                        problems.push(format!("key {} is queued but its slot is empty", key.0));
+                        continue;
+                    },
+                };
+                if order.side != *side || order.hidden != *hidden {
+                    problems.push(format!("order {} is queued on the wrong side or visibility", order.id));
+                }
+                match self.index.get(&order.id) {
+                    Some(indexed) if indexed == key => (),
+                    Some(indexed) => problems.push(format!("order {} is queued under key {} but indexed under {}", order.id, key.0, indexed.0)),
+                    None => problems.push(format!("order {} is queued but not indexed", order.id)),
+                }
+            }
+        }
+        let mut unqueued: Vec<String> = self.index.iter()
+            .filter(|(_, key)| !queued.contains(key))
+            .map(|(id, key)| format!("order {} is indexed under key {} but not queued", id, key.0))
+            .collect();
+        unqueued.sort();
+        problems.extend(unqueued);
+        let occupied = self.orders.slots.iter().filter(|slot| slot.is_some()).count();
+        if occupied != self.index.len() {
+            problems.push(format!("{} slots hold orders but {} orders are indexed", occupied, self.index.len()));
+        }
+        let counted = self.sell_orders().chain(self.buy_orders())
+            .try_fold(OpenInterest::default(), |counted, order| {
+                counted.with_added(order.side, order.mode.get_limit().unwrap_or(0), order.quantity)
+            });
+        match counted {
+            Ok(counted) if counted == self.open_interest => (),
+            Ok(counted) => problems.push(format!("open interest is {:?} but the orders add up to {:?}", self.open_interest, counted)),
+            Err(_) => problems.push("the orders' open interest doesn't fit in a number".to_string()),
+        }
+        BookIndex {
+            indexed: self.index.len(),
+            slots: self.orders.slots.len(),
+            free_slots: self.orders.free.len(),
+            sell_keys: self.sell_keys.len(),
+            buy_keys: self.buy_keys.len(),
+            hidden_sell_keys: self.hidden_sell_keys.len(),
+            hidden_buy_keys: self.hidden_buy_keys.len(),
+            problems,
+        }
+    }
+
+    fn record(&mut self, change: BookChange) {
+        self.version += 1;
+        if self.deltas.len() == DELTA_BUFFER_SIZE {
+            self.deltas.pop_front();
+        }
+        self.deltas.push_back(BookDelta { version: self.version, change });
+    }
+
+    // Fails once the buffer no longer reaches back to the version, the client has to fetch the whole book then.
+    pub fn deltas_since(&self, version: u64) -> EngineResult<Vec<BookDelta>> {
+        if version == self.version {
+            return Ok(vec![]);
+        }
+        let oldest = self.deltas.front().map_or(self.version, |d| d.version - 1);
+        if version > self.version || version < oldest {
+            return Err(ErrorType::BookDeltaUnavailable { since_version: version, oldest_version: oldest });
+        }
+        Ok(self.deltas.iter().filter(|d| d.version > version).cloned().collect())
+    }
+
+    pub fn get_order_by_key(&self, key: OrderKey) -> Option<&Order> {
+        self.orders.get(key)
+    }
+
+    pub(crate) fn add_order(&mut self, order: Order) -> EngineResult<()> {
+        if order.mode == OrderMode::Best {
+            return Err(ErrorType::NotEnoughMatchingOrdersToImmediatelyFillBestOrder);
+        }
+        // checked before anything changes, an order too big to count is turned down
+        let open_interest = match order.hidden {
+            true => self.open_interest,
+            false => self.open_interest.with_added(order.side, order.mode.get_limit()?, order.quantity)?,
+        };
+        let (keys, revert_price_order) = match (order.side, order.hidden) {
+            (OrderSide::Sell, false) => (&mut self.sell_keys, false),
+            (OrderSide::Buy, false) => (&mut self.buy_keys, true),
+            (OrderSide::Sell, true) => (&mut self.hidden_sell_keys, false),
+            (OrderSide::Buy, true) => (&mut self.hidden_buy_keys, true),
+        };
+        let orders = &self.orders;
+        let position = keys.iter()
+            .position(|key| Book::cmp_orders(orders.get(*key).unwrap(), &order, revert_price_order) == Ordering::Greater)
+            .unwrap_or(keys.len());
+        let key = self.orders.insert(order);
+        keys.insert(position, key);
+        self.index.insert(order.id, key);
+        if order.hidden {
+            return Ok(());
+        }
+        let price = order.mode.get_limit()?;
+        self.open_interest = open_interest;
+        self.record(BookChange::Added {
+            id: order.id,
+            side: order.side,
+            price,
+            quantity: order.quantity,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn remove_order(&mut self, id: Uuid) {
+        if let Some(key) = self.index.remove(&id) {
+            self.sell_keys.retain(|k| *k != key);
+            self.buy_keys.retain(|k| *k != key);
+            self.hidden_sell_keys.retain(|k| *k != key);
+            self.hidden_buy_keys.retain(|k| *k != key);
+            if let Some(order) = self.orders.remove(key).filter(|o| !o.hidden) {
+                self.open_interest.remove(order.side, order.mode.get_limit().unwrap_or(0), order.quantity);
+                self.record(BookChange::Removed { id });
+            }
+        }
+    }
+
+    // Partial fills keep the resting order (and with it its lock and queue position) in place.
+    pub(crate) fn reduce_order(&mut self, id: Uuid, quantity: usize) -> EngineResult<usize> {
+        let key = *self.index.get(&id).ok_or(ErrorType::OrderNotFound(id))?;
+        let order = self.orders.get_mut(key).ok_or(ErrorType::OrderNotFound(id))?;
+        if quantity > order.quantity {
+            return Err(ErrorType::InvalidState);
+        }
+        order.quantity -= quantity;
+        let remaining = order.quantity;
+        if !order.hidden {
+            let (side, price) = (order.side, order.mode.get_limit().unwrap_or(0));
+            self.open_interest.remove(side, price, quantity);
+            self.record(BookChange::Amended { id, quantity: remaining });
+        }
+        if remaining == 0 {
+            self.remove_order(id);
+        }
+        Ok(remaining)
+    }
+
+    pub(crate) fn find_fills(&self, order: &Order, priority: DarkPriority) -> Vec<Fill> {
+        let other_side = match order.side {
+            OrderSide::Sell => OrderSide::Buy,
+            OrderSide::Buy => OrderSide::Sell,
+        };
+
+        let mut fills: Vec<Fill> = vec![];
+        let mut remaining = order.quantity;
+        for other in self.queue(other_side, priority) {
+            if !order.matches(other) {
+                break;
+            }
+            let key = match self.index.get(&other.id) {
+                Some(key) => *key,
+                None => break,
+            };
+            let quantity = other.quantity.min(remaining);
+            fills.push(Fill { key, quantity });
+            remaining -= quantity;
+            if remaining == 0 {
+                break;
+            }
+        }
+        fills
+    }
+
+    pub(crate) fn get_order(&self, id: Uuid) -> EngineResult<&Order> {
+        self.index.get(&id)
+            .and_then(|key| self.orders.get(*key))
+            .ok_or(ErrorType::OrderNotFound(id))
+    }
+    fn cmp_prices(a: &Order, b: &Order, revert_price_order: bool) -> Ordering {
+        let order = match (a.mode, b.mode) {
+            (OrderMode::Best, OrderMode::Best) => Ordering::Equal,
+            (OrderMode::Best, OrderMode::Limit(_)) => Ordering::Less,
+            (OrderMode::Limit(_), OrderMode::Best) => Ordering::Greater,
+            (OrderMode::Limit(a), OrderMode::Limit(b)) => a.cmp(&b)
+        };
+        if revert_price_order {
+            order.reverse()
+        } else {
+            order
+        }
+    }
+
+    fn cmp_orders(a: &Order, b: & Order, revert_price_order: bool) -> Ordering {
+        let order = Book::cmp_prices(a, b, revert_price_order);
+        if order == Ordering::Equal {
+            return a.created_at.cmp(&b.created_at);
+        }
+        order
+    }
+}
@@ -0,0 +1,134 @@
+use std::cmp::Reverse;
+use crate::account::PortfolioId;
+use crate::error::{checked_add, EngineResult, ErrorType};
+use crate::market::AssetId;
+use crate::pricing::{to_coins, PriceMode};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Distribution {
+    // the same quantity for everyone, what doesn't divide goes to the first portfolios one by one
+    EqualSplit,
+    // random shares that add up to the supply, the same seed gives the same shares
+    Random { seed: u64 },
+    // Sold by the bank at one price: the highest bids are filled until the supply runs out and
+    // everyone pays the lowest filled bid. What nobody bid for stays unallocated.
+    Auction { bids: Vec<AllocationBid> },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AllocationBid {
+    pub portfolio: PortfolioId,
+    pub price: usize,
+    pub quantity: usize,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AssetSupply {
+    pub asset: AssetId,
+    pub supply: usize,
+    pub distribution: Distribution,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BootstrapPlan {
+    // every player's portfolio if left empty
+    #[serde(default)]
+    pub portfolios: Vec<PortfolioId>,
+    pub assets: Vec<AssetSupply>,
+}
+
+// `cost` is paid to the bank, it's only set by auctions
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Allocation {
+    pub portfolio: PortfolioId,
+    pub asset: AssetId,
+    pub quantity: usize,
+    pub cost: usize,
+}
+
+fn equal_split(supply: usize, portfolios: &[PortfolioId]) -> Vec<(PortfolioId, usize)> {
+    let share = supply / portfolios.len();
+    let rest = supply % portfolios.len();
+    portfolios.iter().enumerate()
+        .map(|(i, p)| (*p, share + if i < rest { 1 } else { 0 }))
+        .collect()
+}
+
+fn random_split(supply: usize, portfolios: &[PortfolioId], seed: u64) -> Vec<(PortfolioId, usize)> {
+    // xorshift, a zero state would stay zero
+    let mut state = seed | 1;
+    let weights: Vec<u128> = portfolios.iter()
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % 1000 + 1) as u128
+        })
+        .collect();
+    let total: u128 = weights.iter().sum();
+    let mut shares: Vec<usize> = weights.iter().map(|w| (supply as u128 * w / total) as usize).collect();
+    let rest = supply - shares.iter().sum::<usize>();
+    shares.iter_mut().take(rest).for_each(|share| *share += 1);
+    portfolios.iter().cloned().zip(shares).collect()
+}
+
+// (portfolio, quantity) of the filled bids and the price they pay
+fn auction(supply: usize, bids: &[AllocationBid]) -> (Vec<(PortfolioId, usize)>, Option<usize>) {
+    let mut bids: Vec<&AllocationBid> = bids.iter().filter(|b| b.quantity > 0).collect();
+    // stable, so bids at the same price fill in the order they were listed
+    bids.sort_by_key(|b| Reverse(b.price));
+    let mut left = supply;
+    let mut filled = vec![];
+    let mut price = None;
+    for bid in bids {
+        if left == 0 {
+            break;
+        }
+        let quantity = bid.quantity.min(left);
+        left -= quantity;
+        filled.push((bid.portfolio, quantity));
+        price = Some(bid.price);
+    }
+    (filled, price)
+}
+
+// What every portfolio gets, in the order of the plan. `players` stands in for an empty portfolio list.
+pub fn plan_allocations(plan: &BootstrapPlan, players: Vec<PortfolioId>, price_mode: PriceMode) -> EngineResult<Vec<Allocation>> {
+    let portfolios = if plan.portfolios.is_empty() { players } else { plan.portfolios.clone() };
+    if portfolios.is_empty() {
+        return Err(ErrorType::InvalidBootstrapPlan);
+    }
+    let mut allocations = vec![];
+    for supply in &plan.assets {
+        let (quantities, price) = match &supply.distribution {
+            Distribution::EqualSplit => (equal_split(supply.supply, &portfolios), None),
+            Distribution::Random { seed } => (random_split(supply.supply, &portfolios, *seed), None),
+            Distribution::Auction { bids } => {
+                if bids.iter().any(|b| !portfolios.contains(&b.portfolio)) {
+                    return Err(ErrorType::InvalidBootstrapPlan);
+                }
+                auction(supply.supply, bids)
+            },
+        };
+        for (portfolio, quantity) in quantities.into_iter().filter(|(_, q)| *q > 0) {
+            let cost = match price {
+                Some(price) => to_coins(price_mode.notional(price, quantity)?.max(0))?,
+                None => 0,
+            };
+            allocations.push(Allocation { portfolio, asset: supply.asset, quantity, cost });
+        }
+    }
+    Ok(allocations)
+}
+
+// coins each portfolio pays in total
+pub fn costs(allocations: &[Allocation]) -> EngineResult<Vec<(PortfolioId, usize)>> {
+    let mut costs: Vec<(PortfolioId, usize)> = vec![];
+    for allocation in allocations.iter().filter(|a| a.cost > 0) {
+        match costs.iter_mut().find(|(p, _)| *p == allocation.portfolio) {
+            Some((_, cost)) => *cost = checked_add(*cost, allocation.cost)?,
+            None => costs.push((allocation.portfolio, allocation.cost)),
+        }
+    }
+    Ok(costs)
+}
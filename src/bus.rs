@@ -0,0 +1,157 @@
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use crate::account::PortfolioId;
+use crate::error::{EngineResult, ErrorType};
+use crate::ledger::LedgerKind;
+use crate::market::AssetId;
+use crate::order::{OrderId, OrderSide};
+use crate::stream::GameEvent;
+use crate::trades::Trade;
+
+// What the game tells the transports about. The engine only collects these, see
+// `Engine::take_domain_events`, the game publishes them once it let go of the engine.
+#[derive(Clone, Debug, Serialize)]
+pub enum DomainEvent {
+    OrderAccepted {
+        order: OrderId,
+        portfolio: PortfolioId,
+        asset: AssetId,
+        side: OrderSide,
+        quantity: usize,
+    },
+    TradeExecuted {
+        trade: Trade,
+        // in coins, as the market's price mode counts them
+        notional: usize,
+    },
+    OrderCancelled {
+        order: OrderId,
+        portfolio: PortfolioId,
+        asset: AssetId,
+    },
+    // fees and taker fees paid to the bank
+    FeesCharged {
+        portfolio: PortfolioId,
+        kind: LedgerKind,
+        amount: usize,
+    },
+    // an event the engine turned down
+    EventRejected {
+        portfolio: PortfolioId,
+        assets: Vec<AssetId>,
+        error: String,
+    },
+    // game wide news that doesn't come out of the engine's records
+    Game(GameEvent),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+pub enum DomainEventKind {
+    OrderAccepted,
+    TradeExecuted,
+    OrderCancelled,
+    FeesCharged,
+    EventRejected,
+    Game,
+}
+
+impl DomainEvent {
+    pub fn kind(&self) -> DomainEventKind {
+        match self {
+            DomainEvent::OrderAccepted { .. } => DomainEventKind::OrderAccepted,
+            DomainEvent::TradeExecuted { .. } => DomainEventKind::TradeExecuted,
+            DomainEvent::OrderCancelled { .. } => DomainEventKind::OrderCancelled,
+            DomainEvent::FeesCharged { .. } => DomainEventKind::FeesCharged,
+            DomainEvent::EventRejected { .. } => DomainEventKind::EventRejected,
+            DomainEvent::Game(_) => DomainEventKind::Game,
+        }
+    }
+}
+
+// Called on the publishing thread, anything slow belongs on a thread of its own.
+pub trait BusConsumer: Send + Sync {
+    fn wants(&self, kind: DomainEventKind) -> bool;
+    fn consume(&self, event: &DomainEvent);
+}
+
+impl<C: BusConsumer> BusConsumer for Arc<C> {
+    fn wants(&self, kind: DomainEventKind) -> bool {
+        C::wants(self, kind)
+    }
+
+    fn consume(&self, event: &DomainEvent) {
+        C::consume(self, event)
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus {
+    consumers: RwLock<Vec<(usize, Box<dyn BusConsumer>)>>,
+    next_id: AtomicUsize,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        EventBus::default()
+    }
+
+    // the id to unsubscribe with
+    pub fn subscribe(&self, consumer: Box<dyn BusConsumer>) -> EngineResult<usize> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        self.consumers.write().map_err(|_| ErrorType::InvalidState)?.push((id, consumer));
+        Ok(id)
+    }
+
+    pub fn unsubscribe(&self, id: usize) -> EngineResult<()> {
+        self.consumers.write().map_err(|_| ErrorType::InvalidState)?.retain(|(c, _)| *c != id);
+        Ok(())
+    }
+
+    // lets publishers skip work nobody would see
+    pub fn has_consumers(&self, kind: DomainEventKind) -> bool {
+        self.consumers.read().is_ok_and(|consumers| consumers.iter().any(|(_, c)| c.wants(kind)))
+    }
+
+    pub fn publish(&self, events: Vec<DomainEvent>) {
+        let consumers = match self.consumers.read() {
+            Ok(consumers) => consumers,
+            Err(_) => return,
+        };
+        for event in &events {
+            let kind = event.kind();
+            consumers.iter()
+                .filter(|(_, c)| c.wants(kind))
+                .for_each(|(_, c)| c.consume(event));
+        }
+    }
+}
+
+// How many of each event the engine put on the bus.
+#[derive(Default)]
+pub struct EventCounts {
+    counts: Mutex<BTreeMap<DomainEventKind, u64>>,
+}
+
+impl EventCounts {
+    pub fn new() -> EventCounts {
+        EventCounts::default()
+    }
+
+    pub fn get_all(&self) -> EngineResult<BTreeMap<DomainEventKind, u64>> {
+        Ok(self.counts.lock().map_err(|_| ErrorType::InvalidState)?.clone())
+    }
+}
+
+impl BusConsumer for EventCounts {
+    // the game's own news aren't the engine's
+    fn wants(&self, kind: DomainEventKind) -> bool {
+        kind != DomainEventKind::Game
+    }
+
+    fn consume(&self, event: &DomainEvent) {
+        if let Ok(mut counts) = self.counts.lock() {
+            *counts.entry(event.kind()).or_insert(0) += 1;
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use crate::error::{EngineResult, ErrorType};
+use crate::market::AssetId;
+use crate::marketdata::Level;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum CachedView {
+    // the whole public book, as `GET /book/<asset>` without a query
+    Book,
+    MarketData(Level),
+}
+
+// What the JSON was made from. The book version moves with every change to the book, the decimals
+// are in the asset the book comes with.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CacheStamp {
+    pub version: u64,
+    pub decimals: u32,
+}
+
+struct Entry {
+    stamp: CacheStamp,
+    json: Arc<String>,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    // entries replaced because their book moved on
+    pub invalidations: u64,
+    pub entries: usize,
+    // none before the first lookup
+    pub hit_ratio: Option<f64>,
+}
+
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<(AssetId, CachedView), Entry>,
+    metrics: CacheMetrics,
+}
+
+// Public books and market data serialized once per book version, so dashboards polling them don't
+// build and serialize the whole book under the engine's read lock every time.
+#[derive(Default)]
+pub struct BookCache {
+    entries: Mutex<Entries>,
+}
+
+impl BookCache {
+    pub fn new() -> BookCache {
+        BookCache::default()
+    }
+
+    // counts a hit or a miss, a miss is expected to be followed by `insert`
+    pub fn get(&self, asset: AssetId, view: CachedView, stamp: CacheStamp) -> EngineResult<Option<Arc<String>>> {
+        let mut entries = self.entries.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        let json = entries.entries.get(&(asset, view)).filter(|entry| entry.stamp == stamp).map(|entry| entry.json.clone());
+        match json {
+            Some(_) => entries.metrics.hits += 1,
+            None => entries.metrics.misses += 1,
+        }
+        Ok(json)
+    }
+
+    // A reader that was slower than one with a newer stamp doesn't put its older JSON back.
+    pub fn insert(&self, asset: AssetId, view: CachedView, stamp: CacheStamp, json: Arc<String>) -> EngineResult<()> {
+        let mut entries = self.entries.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        let previous = entries.entries.get(&(asset, view)).map(|entry| entry.stamp);
+        match previous {
+            Some(previous) if previous.version > stamp.version => return Ok(()),
+            Some(previous) if previous != stamp => entries.metrics.invalidations += 1,
+            _ => {},
+        }
+        entries.entries.insert((asset, view), Entry { stamp, json });
+        entries.metrics.entries = entries.entries.len();
+        Ok(())
+    }
+
+    // after the engine was rebuilt, book versions may repeat with other orders
+    pub fn clear(&self) -> EngineResult<()> {
+        let mut entries = self.entries.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        entries.entries.clear();
+        entries.metrics.entries = 0;
+        Ok(())
+    }
+
+    pub fn get_metrics(&self) -> EngineResult<CacheMetrics> {
+        let metrics = self.entries.lock().map_err(|_| ErrorType::EngineWasTooBusy)?.metrics.clone();
+        let hit_ratio = match metrics.hits + metrics.misses {
+            0 => None,
+            lookups => Some(metrics.hits as f64 / lookups as f64),
+        };
+        Ok(CacheMetrics { hit_ratio, ..metrics })
+    }
+}
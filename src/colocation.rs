@@ -0,0 +1,67 @@
+use std::collections::{HashMap, HashSet};
+use crate::account::PortfolioId;
+use crate::error::{EngineResult, ErrorType};
+
+// what portfolios in none of the classes are in, dequeued last
+pub const STANDARD_CLASS: &str = "Standard";
+
+// A class of portfolios whose events are taken off the queue ahead of the classes after it. One with
+// a price can be bought by players, see `Market::buy_priority_class`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PriorityClass {
+    pub name: String,
+    #[serde(default)]
+    pub price: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Colocation {
+    // best first
+    pub classes: Vec<PriorityClass>,
+    pub members: HashMap<PortfolioId, String>,
+}
+
+impl Colocation {
+    // Replaces the classes, members of a class that's gone are standard again.
+    pub fn set_classes(&mut self, classes: Vec<PriorityClass>) -> EngineResult<()> {
+        let mut names = HashSet::new();
+        if classes.iter().any(|c| c.name.is_empty() || c.name == STANDARD_CLASS || !names.insert(c.name.as_str())) {
+            return Err(ErrorType::InvalidPriorityClasses);
+        }
+        self.members.retain(|_, class| names.contains(class.as_str()));
+        self.classes = classes;
+        Ok(())
+    }
+
+    pub fn class(&self, name: &str) -> EngineResult<&PriorityClass> {
+        self.classes.iter().find(|c| c.name == name).ok_or_else(|| ErrorType::UnknownPriorityClass(name.to_string()))
+    }
+
+    // none makes the portfolio standard
+    pub fn assign(&mut self, portfolio: PortfolioId, class: Option<String>) -> EngineResult<()> {
+        match class {
+            Some(class) => {
+                self.class(&class)?;
+                self.members.insert(portfolio, class);
+            },
+            None => {
+                self.members.remove(&portfolio);
+            },
+        }
+        Ok(())
+    }
+
+    pub fn class_of(&self, portfolio: PortfolioId) -> &str {
+        self.members.get(&portfolio).map_or(STANDARD_CLASS, |class| class.as_str())
+    }
+
+    // What the queue orders by: the names best first, standard last, and every member's rank in them.
+    pub fn ranks(&self) -> (Vec<String>, HashMap<PortfolioId, usize>) {
+        let mut names: Vec<String> = self.classes.iter().map(|c| c.name.clone()).collect();
+        let ranks = self.members.iter()
+            .filter_map(|(portfolio, class)| names.iter().position(|name| name == class).map(|rank| (*portfolio, rank)))
+            .collect();
+        names.push(STANDARD_CLASS.to_string());
+        (names, ranks)
+    }
+}
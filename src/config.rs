@@ -0,0 +1,109 @@
+use crate::error::EngineResult;
+use crate::fees::FeeSchedule;
+use crate::ledger::InterestPolicy;
+use crate::market::Market;
+use crate::order::MinimumRestingTime;
+use crate::rules::SessionTimes;
+use serde::{Deserialize, Deserializer};
+#[cfg(feature = "server")]
+use crate::game::Game;
+#[cfg(feature = "server")]
+use std::path::PathBuf;
+#[cfg(feature = "server")]
+use std::time::{Duration, SystemTime};
+
+// Tells a field left out, None, from one set to null, Some(None).
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where D: Deserializer<'de>, T: Deserialize<'de> {
+    Option::deserialize(deserializer).map(Some)
+}
+
+// The parameters that can change while the game runs. Fields left out keep their current values,
+// those that can be switched off are switched off with null.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GameConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee_schedule: Option<FeeSchedule>,
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub price_band_percent: Option<Option<usize>>,
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub minimum_resting_time: Option<Option<MinimumRestingTime>>,
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub interest_policy: Option<Option<InterestPolicy>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub margin_warning_threshold: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starting_coins: Option<usize>,
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub session_times: Option<Option<SessionTimes>>,
+    // not part of the market, the queue in front of the engine applies it
+    #[serde(default, deserialize_with = "present", skip_serializing_if = "Option::is_none")]
+    pub queue_max_depth: Option<Option<usize>>,
+}
+
+impl GameConfig {
+    // every field, as it is now
+    pub fn of(market: &Market, queue_max_depth: Option<usize>) -> GameConfig {
+        GameConfig {
+            fee_schedule: Some(market.fee_schedule.clone()),
+            price_band_percent: Some(market.price_band_percent),
+            minimum_resting_time: Some(market.minimum_resting_time),
+            interest_policy: Some(market.interest_policy),
+            margin_warning_threshold: Some(market.margin_warning_threshold),
+            starting_coins: Some(market.starting_coins),
+            session_times: Some(market.session_times),
+            queue_max_depth: Some(queue_max_depth),
+        }
+    }
+
+    // all of it or nothing
+    pub fn apply_to(&self, market: &mut Market) -> EngineResult<()> {
+        if let Some(Some(times)) = &self.session_times {
+            times.validate()?;
+        }
+        if let Some(fee_schedule) = &self.fee_schedule {
+            market.fee_schedule = fee_schedule.clone();
+        }
+        if let Some(price_band_percent) = self.price_band_percent {
+            market.price_band_percent = price_band_percent;
+        }
+        if let Some(minimum_resting_time) = self.minimum_resting_time {
+            market.minimum_resting_time = minimum_resting_time;
+        }
+        if let Some(interest_policy) = self.interest_policy {
+            market.interest_policy = interest_policy;
+        }
+        if let Some(margin_warning_threshold) = self.margin_warning_threshold {
+            market.margin_warning_threshold = margin_warning_threshold;
+        }
+        if let Some(starting_coins) = self.starting_coins {
+            market.starting_coins = starting_coins;
+        }
+        if let Some(session_times) = self.session_times {
+            market.session_times = session_times;
+        }
+        Ok(())
+    }
+}
+
+// Polls the file and applies it whenever it was modified, the first time right away.
+// A file that doesn't parse is reported and the running config stays.
+#[cfg(feature = "server")]
+pub fn watch(game: Game, path: PathBuf, interval: Duration) {
+    let mut last_modified: Option<SystemTime> = None;
+    loop {
+        let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+        if modified.is_some() && modified != last_modified {
+            last_modified = modified;
+            let config = std::fs::read_to_string(&path).ok()
+                .and_then(|content| serde_json::from_str::<GameConfig>(&content).ok());
+            match config {
+                Some(config) => if let Err(e) = game.set_config(config) {
+                    println!("config {:?} not applied: {:?}", path, e);
+                },
+                None => println!("config {:?} can't be read", path),
+            }
+        }
+        std::thread::sleep(interval);
+    }
+}
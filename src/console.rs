@@ -0,0 +1,172 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use crate::account::PortfolioId;
+use crate::bus::{BusConsumer, DomainEvent, DomainEventKind};
+use crate::error::{EngineResult, ErrorType};
+use crate::game::Game;
+use crate::market::AssetId;
+use crate::news::NewsImpact;
+use crate::queue::QueueMetrics;
+use crate::stream::{StreamHub, Topic};
+use crate::trades::Trade;
+
+// of the rejected events and the big trades, the overview keeps no more
+pub const RECENT_ENTRIES: usize = 20;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RejectedEvent {
+    pub portfolio: PortfolioId,
+    pub assets: Vec<AssetId>,
+    pub error: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BigTrade {
+    pub trade: Trade,
+    pub notional: usize,
+}
+
+// What the game master sees when the console connects, and again every second. The newest entries
+// come last.
+#[derive(Clone, Debug, Serialize)]
+pub struct Overview {
+    pub tick: u64,
+    pub paused: bool,
+    pub queue: QueueMetrics,
+    pub halted: Vec<AssetId>,
+    pub recent_errors: Vec<RejectedEvent>,
+    pub big_trades: Vec<BigTrade>,
+}
+
+// What the console sends, one JSON message each. They run as the admin.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub enum ConsoleCommand {
+    // right away, instead of waiting for the next one
+    Overview,
+    HaltAsset { asset: AssetId },
+    ResumeAsset { asset: AssetId },
+    InjectNews { headline: String, impacts: Vec<NewsImpact> },
+    PayDividend { asset: AssetId, per_unit: usize },
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub enum ConsoleMessage {
+    Overview(Overview),
+    // as they happen
+    Rejected(RejectedEvent),
+    BigTrade(BigTrade),
+    // `error` is none when the command was carried out
+    Reply { command: ConsoleCommand, error: Option<String> },
+    // a message that isn't a command
+    Unreadable { problem: String },
+}
+
+impl ConsoleCommand {
+    pub fn run(self, game: &Game) -> ConsoleMessage {
+        let result = match &self {
+            ConsoleCommand::Overview => game.get_console_overview().map(ConsoleMessage::Overview),
+            ConsoleCommand::HaltAsset { asset } => game.set_asset_halted(*asset, true).map(|_| self.done()),
+            ConsoleCommand::ResumeAsset { asset } => game.set_asset_halted(*asset, false).map(|_| self.done()),
+            ConsoleCommand::InjectNews { headline, impacts } => game.post_news(headline.clone(), impacts.clone()).map(|_| self.done()),
+            ConsoleCommand::PayDividend { asset, per_unit } => game.pay_dividend(*asset, *per_unit).map(|_| self.done()),
+        };
+        result.unwrap_or_else(|e| ConsoleMessage::Reply { command: self, error: Some(format!("{:?}", e)) })
+    }
+
+    fn done(&self) -> ConsoleMessage {
+        ConsoleMessage::Reply { command: self.clone(), error: None }
+    }
+}
+
+#[derive(Default)]
+struct Recent {
+    errors: VecDeque<RejectedEvent>,
+    big_trades: VecDeque<BigTrade>,
+}
+
+// Keeps what the overview shows of the bus and passes it on to the connected consoles as it happens.
+pub struct ConsoleFeed {
+    hub: Arc<StreamHub>,
+    // none shows no trades
+    big_trade_notional: Mutex<Option<usize>>,
+    recent: Mutex<Recent>,
+}
+
+impl ConsoleFeed {
+    pub fn new(hub: Arc<StreamHub>) -> ConsoleFeed {
+        ConsoleFeed {
+            hub,
+            big_trade_notional: Mutex::new(None),
+            recent: Mutex::new(Recent::default()),
+        }
+    }
+
+    pub fn set_big_trade_notional(&self, notional: Option<usize>) -> EngineResult<()> {
+        *self.big_trade_notional.lock().map_err(|_| ErrorType::InvalidState)? = notional;
+        Ok(())
+    }
+
+    pub fn overview(&self, tick: u64, paused: bool, queue: QueueMetrics, halted: Vec<AssetId>) -> EngineResult<Overview> {
+        let recent = self.recent.lock().map_err(|_| ErrorType::InvalidState)?;
+        Ok(Overview {
+            tick,
+            paused,
+            queue,
+            halted,
+            recent_errors: recent.errors.iter().cloned().collect(),
+            big_trades: recent.big_trades.iter().cloned().collect(),
+        })
+    }
+
+    fn big_trade_notional(&self) -> Option<usize> {
+        self.big_trade_notional.lock().ok().and_then(|notional| *notional)
+    }
+
+    fn publish(&self, message: &ConsoleMessage) {
+        if !self.hub.has_subscribers(Topic::Console) {
+            return;
+        }
+        if let Ok(message) = serde_json::to_string(message) {
+            self.hub.publish(Topic::Console, message);
+        }
+    }
+}
+
+fn keep<T>(entries: &mut VecDeque<T>, entry: T) {
+    if entries.len() == RECENT_ENTRIES {
+        entries.pop_front();
+    }
+    entries.push_back(entry);
+}
+
+impl BusConsumer for ConsoleFeed {
+    fn wants(&self, kind: DomainEventKind) -> bool {
+        match kind {
+            DomainEventKind::EventRejected => true,
+            DomainEventKind::TradeExecuted => self.big_trade_notional().is_some(),
+            _ => false,
+        }
+    }
+
+    fn consume(&self, event: &DomainEvent) {
+        let message = match event {
+            DomainEvent::EventRejected { portfolio, assets, error } => ConsoleMessage::Rejected(RejectedEvent {
+                portfolio: *portfolio,
+                assets: assets.clone(),
+                error: error.clone(),
+            }),
+            DomainEvent::TradeExecuted { trade, notional } if self.big_trade_notional().is_some_and(|min| *notional >= min) =>
+                ConsoleMessage::BigTrade(BigTrade { trade: trade.clone(), notional: *notional }),
+            _ => return,
+        };
+        if let Ok(mut recent) = self.recent.lock() {
+            match &message {
+                ConsoleMessage::Rejected(rejected) => keep(&mut recent.errors, rejected.clone()),
+                ConsoleMessage::BigTrade(trade) => keep(&mut recent.big_trades, trade.clone()),
+                _ => {},
+            }
+        }
+        self.publish(&message);
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashSet;
+use crate::account::{Account, Portfolio, PortfolioId, Reservation, ReservedAccount};
+use crate::book::{BookIndex, OrderKey};
+use crate::market::{AssetId, Market};
+use crate::order::{Order, OrderId, OrderSide};
+
+// bumped whenever the shape of the dump changes, tools reading it can tell what they got
+pub const DEBUG_STATE_VERSION: u32 = 1;
+
+// Everything the engine knows about the books and the accounts, with the links between them
+// spelled out and checked. Meant for diagnosing accounting bugs, not for players.
+#[derive(Clone, Debug, Serialize)]
+pub struct DebugState {
+    pub version: u32,
+    pub sequence: u64,
+    pub tick: u64,
+    pub bank_account: usize,
+    pub books: Vec<BookState>,
+    pub portfolios: Vec<PortfolioState>,
+    // empty when everything adds up
+    pub issues: Vec<Inconsistency>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BookState {
+    pub asset: AssetId,
+    pub version: u64,
+    pub index: BookIndex,
+    pub orders: Vec<RestingOrder>,
+}
+
+// a resting order with the lock backing it
+#[derive(Clone, Debug, Serialize)]
+pub struct RestingOrder {
+    pub key: Option<OrderKey>,
+    pub order: Order,
+    pub reservation: Option<Reservation>,
+    pub oco_group: Option<OrderId>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PortfolioState {
+    pub id: PortfolioId,
+    pub bankrupt_since: Option<u64>,
+    pub coins: AccountState,
+    pub assets: Vec<(AssetId, AccountState)>,
+}
+
+// what's locked and which orders it's locked for
+#[derive(Clone, Debug, Serialize)]
+pub struct AccountState {
+    pub total: usize,
+    pub locked: usize,
+    pub free: usize,
+    pub reservations: Vec<(OrderId, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum Inconsistency {
+    // the locked amount isn't what the reservations on the account add up to
+    LockMismatch { portfolio: PortfolioId, account: ReservedAccount, locked: usize, reserved: usize },
+    OverLocked { portfolio: PortfolioId, account: ReservedAccount, locked: usize, total: usize },
+    UnknownPortfolio { asset: AssetId, order: OrderId, portfolio: PortfolioId },
+    OrderWithoutReservation { asset: AssetId, order: OrderId },
+    ReservationWithoutOrder { portfolio: PortfolioId, order: OrderId },
+    // locked on the wrong account, or a sell that doesn't lock exactly what's left to sell
+    ReservationMismatch { asset: AssetId, order: OrderId },
+    BrokenIndex { asset: AssetId, problem: String },
+    SequenceRegression(SequenceRegression),
+}
+
+// A book's sequence went backwards between two journal entries, see `Engine::check_sequences`.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub struct SequenceRegression {
+    pub asset: AssetId,
+    pub from: u64,
+    pub to: u64,
+    // of the journal entry after which it was noticed
+    pub journal_sequence: u64,
+}
+
+pub fn dump(market: &Market, sequence: u64, regressions: &[SequenceRegression]) -> DebugState {
+    let mut books: Vec<BookState> = market.books.values()
+        .map(|book| {
+            let mut orders: Vec<RestingOrder> = book.all_orders()
+                .map(|order| RestingOrder {
+                    key: book.key_of(order.id),
+                    order: *order,
+                    reservation: market.portfolios.get(&order.portfolio)
+                        .and_then(|p| p.reservations.get(&order.id))
+                        .cloned(),
+                    oco_group: market.get_oco_group_of(order.id).map(|group| group.id),
+                })
+                .collect();
+            orders.sort_by_key(|o| (o.order.created_at, o.order.id));
+            BookState {
+                asset: book.asset_id,
+                version: book.version(),
+                index: book.index_report(),
+                orders,
+            }
+        })
+        .collect();
+    books.sort_by_key(|b| b.asset);
+    let mut portfolios: Vec<PortfolioState> = market.portfolios.values().map(portfolio_state).collect();
+    portfolios.sort_by_key(|p| p.id);
+    let mut issues = check(market, &books);
+    issues.extend(regressions.iter().map(|r| Inconsistency::SequenceRegression(*r)));
+    DebugState {
+        version: DEBUG_STATE_VERSION,
+        sequence,
+        tick: market.tick,
+        bank_account: market.bank_account,
+        books,
+        portfolios,
+        issues,
+    }
+}
+
+fn portfolio_state(portfolio: &Portfolio) -> PortfolioState {
+    let mut assets: Vec<(AssetId, AccountState)> = portfolio.assets.iter()
+        .map(|(asset, account)| (*asset, account_state(portfolio, account, ReservedAccount::Asset(*asset))))
+        .collect();
+    assets.sort_by_key(|(asset, _)| *asset);
+    PortfolioState {
+        id: portfolio.id,
+        bankrupt_since: portfolio.bankrupt_since,
+        coins: account_state(portfolio, &portfolio.coins, ReservedAccount::Coins),
+        assets,
+    }
+}
+
+fn account_state(portfolio: &Portfolio, account: &Account, reserved: ReservedAccount) -> AccountState {
+    let mut reservations: Vec<(OrderId, usize)> = portfolio.reservations.values()
+        .filter_map(|r| match (r.account == reserved, reserved) {
+            (true, _) => Some((r.order_id, r.amount)),
+            (false, ReservedAccount::Coins) if r.owed > 0 => Some((r.order_id, r.owed)),
+            _ => None,
+        })
+        .collect();
+    reservations.sort();
+    AccountState {
+        total: account.get_total_amount(),
+        locked: account.get_locked_amount(),
+        free: account.get_free_amount(),
+        reservations,
+    }
+}
+
+fn check(market: &Market, books: &[BookState]) -> Vec<Inconsistency> {
+    let mut issues = vec![];
+    let mut resting: HashSet<OrderId> = HashSet::new();
+    for book in books {
+        issues.extend(book.index.problems.iter()
+            .map(|problem| Inconsistency::BrokenIndex { asset: book.asset, problem: problem.clone() }));
+        for resting_order in &book.orders {
+            let order = &resting_order.order;
+            resting.insert(order.id);
+            if !market.portfolios.contains_key(&order.portfolio) {
+                issues.push(Inconsistency::UnknownPortfolio { asset: book.asset, order: order.id, portfolio: order.portfolio });
+                continue;
+            }
+            match &resting_order.reservation {
+                None => issues.push(Inconsistency::OrderWithoutReservation { asset: book.asset, order: order.id }),
+                Some(reservation) if !backs(reservation, order) =>
+                    issues.push(Inconsistency::ReservationMismatch { asset: book.asset, order: order.id }),
+                Some(_) => (),
+            }
+        }
+    }
+    let mut portfolios: Vec<&Portfolio> = market.portfolios.values().collect();
+    portfolios.sort_by_key(|p| p.id);
+    for portfolio in portfolios {
+        let mut orphans: Vec<OrderId> = portfolio.reservations.keys()
+            .filter(|id| !resting.contains(id))
+            .cloned()
+            .collect();
+        orphans.sort();
+        issues.extend(orphans.into_iter()
+            .map(|order| Inconsistency::ReservationWithoutOrder { portfolio: portfolio.id, order }));
+        let mut accounts: Vec<(ReservedAccount, &Account)> = portfolio.assets.iter()
+            .map(|(asset, account)| (ReservedAccount::Asset(*asset), account))
+            .collect();
+        accounts.sort_by_key(|(account, _)| match account {
+            ReservedAccount::Asset(asset) => *asset,
+            ReservedAccount::Coins => AssetId::nil(),
+        });
+        accounts.insert(0, (ReservedAccount::Coins, &portfolio.coins));
+        for (reserved_account, account) in accounts {
+            let locked = account.get_locked_amount();
+            let reserved = portfolio.get_reserved_for(reserved_account) + portfolio.get_held_for(reserved_account);
+            if locked != reserved {
+                issues.push(Inconsistency::LockMismatch { portfolio: portfolio.id, account: reserved_account, locked, reserved });
+            }
+            if locked > account.get_total_amount() {
+                issues.push(Inconsistency::OverLocked { portfolio: portfolio.id, account: reserved_account, locked, total: account.get_total_amount() });
+            }
+        }
+    }
+    issues
+}
+
+// Sells lock exactly the quantity left. What's left of a buy's lock depends on the prices it filled at.
+fn backs(reservation: &Reservation, order: &Order) -> bool {
+    match order.side {
+        OrderSide::Sell => reservation.account == ReservedAccount::Asset(order.asset) && reservation.amount == order.quantity,
+        OrderSide::Buy => reservation.account == ReservedAccount::Coins,
+    }
+}
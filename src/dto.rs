@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use crate::account::{Account, CostBasis, Portfolio, PortfolioId, Reservation, ReservationPurpose, ReservedAccount};
+use crate::actor::Actor;
+use crate::book::Book;
+use crate::lifecycle::{OrderRecord, OrderState};
+use crate::market::{Asset, AssetId, Position};
+use crate::order::{Order, OrderId, OrderMode, OrderSide};
+use crate::providers::Timestamp;
+use crate::trades::{ExecutionLeg, ExecutionReport};
+
+// The JSON the API answers with, spelled out field by field and mapped from the engine's types. The
+// engine can change how it keeps orders and amounts without clients noticing, a change to anything
+// in here is a change of the wire format and bumps the version.
+pub const DTO_VERSION: u32 = 1;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SideDto {
+    Buy,
+    Sell,
+}
+
+impl From<OrderSide> for SideDto {
+    fn from(side: OrderSide) -> SideDto {
+        match side {
+            OrderSide::Buy => SideDto::Buy,
+            OrderSide::Sell => SideDto::Sell,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ModeDto {
+    Best,
+    Limit(usize),
+}
+
+impl From<OrderMode> for ModeDto {
+    fn from(mode: OrderMode) -> ModeDto {
+        match mode {
+            OrderMode::Best => ModeDto::Best,
+            OrderMode::Limit(price) => ModeDto::Limit(price),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ActorDto {
+    Anonymous,
+    Token(String),
+    Admin,
+    Bot(String),
+    System,
+}
+
+impl From<&Actor> for ActorDto {
+    fn from(actor: &Actor) -> ActorDto {
+        match actor {
+            Actor::Anonymous => ActorDto::Anonymous,
+            Actor::Token(fingerprint) => ActorDto::Token(fingerprint.clone()),
+            Actor::Admin => ActorDto::Admin,
+            Actor::Bot(name) => ActorDto::Bot(name.clone()),
+            Actor::System => ActorDto::System,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OrderStateDto {
+    New,
+    Resting,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Expired,
+    Rejected,
+}
+
+impl From<OrderState> for OrderStateDto {
+    fn from(state: OrderState) -> OrderStateDto {
+        match state {
+            OrderState::New => OrderStateDto::New,
+            OrderState::Resting => OrderStateDto::Resting,
+            OrderState::PartiallyFilled => OrderStateDto::PartiallyFilled,
+            OrderState::Filled => OrderStateDto::Filled,
+            OrderState::Cancelled => OrderStateDto::Cancelled,
+            OrderState::Expired { .. } => OrderStateDto::Expired,
+            OrderState::Rejected => OrderStateDto::Rejected,
+        }
+    }
+}
+
+// an order and where it stands
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrderDto {
+    pub order_id: OrderId,
+    pub asset: AssetId,
+    pub portfolio: PortfolioId,
+    pub actor: ActorDto,
+    pub side: SideDto,
+    pub quantity: usize,
+    pub filled: usize,
+    pub remaining: usize,
+    pub state: OrderStateDto,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+    // why the engine turned the order down, or why it expired
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl From<&OrderRecord> for OrderDto {
+    fn from(record: &OrderRecord) -> OrderDto {
+        OrderDto {
+            order_id: record.order_id,
+            asset: record.asset,
+            portfolio: record.portfolio,
+            actor: ActorDto::from(&record.actor),
+            side: SideDto::from(record.side),
+            quantity: record.quantity,
+            filled: record.filled,
+            remaining: record.remaining,
+            state: OrderStateDto::from(record.state),
+            created_at: record.created_at,
+            updated_at: record.updated_at,
+            reason: match record.state {
+                OrderState::Expired { reason } => Some(format!("{:?}", reason)),
+                _ => record.reason.clone(),
+            },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionLegDto {
+    pub trade_id: u64,
+    pub price: usize,
+    pub quantity: usize,
+}
+
+impl From<&ExecutionLeg> for ExecutionLegDto {
+    fn from(leg: &ExecutionLeg) -> ExecutionLegDto {
+        ExecutionLegDto {
+            trade_id: leg.trade_id,
+            price: leg.price,
+            quantity: leg.quantity,
+        }
+    }
+}
+
+// what an incoming order traded, summed up over the resting orders it crossed
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionDto {
+    pub quantity: usize,
+    pub average_price: usize,
+    pub legs: Vec<ExecutionLegDto>,
+}
+
+impl From<&ExecutionReport> for ExecutionDto {
+    fn from(report: &ExecutionReport) -> ExecutionDto {
+        ExecutionDto {
+            quantity: report.quantity,
+            average_price: report.average_price,
+            legs: report.legs.iter().map(ExecutionLegDto::from).collect(),
+        }
+    }
+}
+
+// a freshly placed order, with its execution if it traded on arrival
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlacementDto {
+    #[serde(flatten)]
+    pub order: OrderDto,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution: Option<ExecutionDto>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AccountDto {
+    pub total_amount: usize,
+    pub locked_amount: usize,
+}
+
+impl From<&Account> for AccountDto {
+    fn from(account: &Account) -> AccountDto {
+        AccountDto {
+            total_amount: account.get_total_amount(),
+            locked_amount: account.get_locked_amount(),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReservedAccountDto {
+    Coins,
+    Asset(AssetId),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PurposeDto {
+    BuyOrder,
+    SellOrder,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ReservationDto {
+    pub order_id: OrderId,
+    pub account: ReservedAccountDto,
+    pub purpose: PurposeDto,
+    pub amount: usize,
+    #[serde(default)]
+    pub owed: usize,
+}
+
+impl From<&Reservation> for ReservationDto {
+    fn from(reservation: &Reservation) -> ReservationDto {
+        ReservationDto {
+            order_id: reservation.order_id,
+            account: match reservation.account {
+                ReservedAccount::Coins => ReservedAccountDto::Coins,
+                ReservedAccount::Asset(asset) => ReservedAccountDto::Asset(asset),
+            },
+            purpose: match reservation.purpose {
+                ReservationPurpose::BuyOrder => PurposeDto::BuyOrder,
+                ReservationPurpose::SellOrder => PurposeDto::SellOrder,
+            },
+            amount: reservation.amount,
+            owed: reservation.owed,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CostBasisDto {
+    pub quantity: usize,
+    pub total_cost: i64,
+}
+
+impl From<&CostBasis> for CostBasisDto {
+    fn from(basis: &CostBasis) -> CostBasisDto {
+        CostBasisDto {
+            quantity: basis.quantity,
+            total_cost: basis.total_cost,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PortfolioDto {
+    pub id: PortfolioId,
+    pub coins: AccountDto,
+    pub assets: HashMap<AssetId, AccountDto>,
+    pub reservations: HashMap<OrderId, ReservationDto>,
+    pub bankrupt_since: Option<u64>,
+    pub cost_basis: HashMap<AssetId, CostBasisDto>,
+    pub realized_pnl: i64,
+    pub unrealized_pnl: i64,
+}
+
+impl PortfolioDto {
+    pub fn new(portfolio: &Portfolio, unrealized_pnl: i64) -> PortfolioDto {
+        PortfolioDto {
+            id: portfolio.id,
+            coins: AccountDto::from(&portfolio.coins),
+            assets: portfolio.assets.iter().map(|(id, account)| (*id, AccountDto::from(account))).collect(),
+            reservations: portfolio.reservations.iter().map(|(id, r)| (*id, ReservationDto::from(r))).collect(),
+            bankrupt_since: portfolio.bankrupt_since,
+            cost_basis: portfolio.cost_basis.iter().map(|(id, basis)| (*id, CostBasisDto::from(basis))).collect(),
+            realized_pnl: portfolio.realized_pnl,
+            unrealized_pnl,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PositionDto {
+    pub asset: AssetId,
+    pub name: String,
+    pub quantity: usize,
+    pub locked: usize,
+    pub average_cost: Option<f64>,
+    pub mark_price: Option<usize>,
+    pub market_value: Option<i64>,
+    pub unrealized_pnl: Option<i64>,
+}
+
+impl From<&Position> for PositionDto {
+    fn from(position: &Position) -> PositionDto {
+        PositionDto {
+            asset: position.asset,
+            name: position.name.clone(),
+            quantity: position.quantity,
+            locked: position.locked,
+            average_cost: position.average_cost,
+            mark_price: position.mark_price,
+            market_value: position.market_value,
+            unrealized_pnl: position.unrealized_pnl,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AssetDto {
+    pub id: AssetId,
+    pub name: String,
+    #[serde(default)]
+    pub decimals: u32,
+}
+
+impl From<&Asset> for AssetDto {
+    fn from(asset: &Asset) -> AssetDto {
+        AssetDto {
+            id: asset.id,
+            name: asset.name.clone(),
+            decimals: asset.decimals,
+        }
+    }
+}
+
+// a resting order as everyone sees it, without its owner
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BookOrderDto {
+    pub asset: AssetId,
+    pub mode: ModeDto,
+    pub side: SideDto,
+    pub quantity: usize,
+}
+
+impl From<&Order> for BookOrderDto {
+    fn from(order: &Order) -> BookOrderDto {
+        BookOrderDto {
+            asset: order.asset,
+            mode: ModeDto::from(order.mode),
+            side: SideDto::from(order.side),
+            quantity: order.quantity,
+        }
+    }
+}
+
+// the lit orders of a book, hidden ones never show up here
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BookDto {
+    pub asset: AssetDto,
+    pub sell: Vec<BookOrderDto>,
+    pub buy: Vec<BookOrderDto>,
+    pub version: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+}
+
+impl BookDto {
+    pub fn from_book(asset: &Asset, book: &Book) -> BookDto {
+        BookDto {
+            asset: AssetDto::from(asset),
+            sell: book.sell_orders().map(BookOrderDto::from).collect(),
+            buy: book.buy_orders().map(BookOrderDto::from).collect(),
+            version: book.version(),
+            next_cursor: None,
+        }
+    }
+
+    // a page of orders, sells and buys in book order
+    pub fn from_orders(asset: &Asset, version: u64, (orders, next_cursor): (Vec<&Order>, Option<String>)) -> BookDto {
+        BookDto {
+            asset: AssetDto::from(asset),
+            sell: orders.iter().filter(|o| o.side == OrderSide::Sell).map(|o| BookOrderDto::from(*o)).collect(),
+            buy: orders.iter().filter(|o| o.side == OrderSide::Buy).map(|o| BookOrderDto::from(*o)).collect(),
+            version,
+            next_cursor,
+        }
+    }
+}
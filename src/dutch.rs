@@ -0,0 +1,211 @@
+use uuid::Uuid;
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::engine::Engine;
+use crate::error::{EngineResult, ErrorType};
+use crate::lifecycle::OrderRegistry;
+use crate::market::AssetId;
+use crate::order::{Event, Order, OrderId, OrderMode, OrderSide};
+
+const ORDER_LIFETIME_MILLIS: u64 = 24*60*60*1000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum DutchAuctionState {
+    // the ask comes down every tick
+    Running,
+    // the ask rests at the floor until it's taken or the auction is cancelled
+    AtFloor,
+    Sold,
+    Cancelled,
+}
+
+// Sells `quantity` through one resting ask that starts at `start_price` and drops by `decay_per_tick`
+// every tick until it reaches `floor`.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct DutchAuctionTerms {
+    pub quantity: usize,
+    pub start_price: usize,
+    pub floor: usize,
+    pub decay_per_tick: usize,
+}
+
+impl DutchAuctionTerms {
+    pub fn check(&self) -> EngineResult<()> {
+        if self.quantity == 0 || self.decay_per_tick == 0 || self.floor > self.start_price {
+            return Err(ErrorType::InvalidDutchAuction);
+        }
+        Ok(())
+    }
+
+    // how often the ask gets replaced on its way down to the floor
+    pub fn steps(&self) -> usize {
+        (self.start_price - self.floor).div_ceil(self.decay_per_tick)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct DutchAuction {
+    pub id: Uuid,
+    pub portfolio: PortfolioId,
+    pub asset: AssetId,
+    #[serde(flatten)]
+    pub terms: DutchAuctionTerms,
+    pub started_tick: u64,
+    // the price of the current ask
+    pub price: usize,
+    pub sold: usize,
+    pub order: Option<OrderId>,
+    pub state: DutchAuctionState,
+    // sold by the asks before the current one
+    #[serde(skip)]
+    settled: usize,
+}
+
+impl DutchAuction {
+    pub fn new(id: Uuid, portfolio: PortfolioId, asset: AssetId, terms: DutchAuctionTerms, started_tick: u64) -> EngineResult<DutchAuction> {
+        terms.check()?;
+        Ok(DutchAuction {
+            id,
+            portfolio,
+            asset,
+            terms,
+            started_tick,
+            price: terms.start_price,
+            sold: 0,
+            order: None,
+            state: if terms.floor == terms.start_price { DutchAuctionState::AtFloor } else { DutchAuctionState::Running },
+            settled: 0,
+        })
+    }
+
+    pub fn price_at(&self, tick: u64) -> usize {
+        let decay = (tick.saturating_sub(self.started_tick) as usize).saturating_mul(self.terms.decay_per_tick);
+        self.terms.start_price.saturating_sub(decay).max(self.terms.floor)
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.state == DutchAuctionState::Sold || self.state == DutchAuctionState::Cancelled
+    }
+
+    // the ask, if it may still be in the book
+    fn open_order(&self, orders: &OrderRegistry) -> Option<OrderId> {
+        self.order.filter(|id| orders.get(*id).is_ok_and(|r| !r.state.is_final()))
+    }
+
+    // counts what the current ask sold so far
+    fn refresh(&mut self, orders: &OrderRegistry) {
+        if let Some(record) = self.order.and_then(|id| orders.get(id).ok()) {
+            self.sold = self.settled + record.filled;
+        }
+        if self.sold >= self.terms.quantity {
+            self.state = DutchAuctionState::Sold;
+        }
+    }
+
+    // the current ask is done with, whatever it sold stays sold
+    fn settle(&mut self) {
+        self.settled = self.sold;
+        self.order = None;
+    }
+
+    pub fn ask(&self, engine: &Engine) -> EngineResult<Order> {
+        let stamp = engine.stamp();
+        let expires = stamp.created_at + ORDER_LIFETIME_MILLIS;
+        Order::new(stamp, self.portfolio, self.asset, OrderSide::Sell, self.terms.quantity - self.sold, OrderMode::Limit(self.price), expires)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct DutchAuctions {
+    auctions: HashMap<Uuid, DutchAuction>,
+}
+
+impl DutchAuctions {
+    pub fn new() -> DutchAuctions {
+        DutchAuctions {
+            auctions: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, auction: DutchAuction) {
+        self.auctions.insert(auction.id, auction);
+    }
+
+    pub fn remove(&mut self, id: Uuid) {
+        self.auctions.remove(&id);
+    }
+
+    // `sold` brought up to date, it's only counted when the runner comes by otherwise
+    pub fn get(&self, id: Uuid, orders: &OrderRegistry) -> EngineResult<DutchAuction> {
+        let mut auction = *self.auctions.get(&id).ok_or(ErrorType::DutchAuctionNotFound(id))?;
+        if !auction.is_over() {
+            auction.refresh(orders);
+        }
+        Ok(auction)
+    }
+
+    pub fn get_all(&self, orders: &OrderRegistry) -> Vec<DutchAuction> {
+        let mut auctions: Vec<DutchAuction> = self.auctions.keys()
+            .filter_map(|id| self.get(*id, orders).ok())
+            .collect();
+        auctions.sort_by_key(|a| (a.started_tick, a.id));
+        auctions
+    }
+
+    // the ask to cancel, if it's still in the book
+    pub fn cancel(&mut self, id: Uuid, orders: &OrderRegistry) -> EngineResult<Option<(PortfolioId, OrderId, AssetId)>> {
+        let auction = self.auctions.get_mut(&id).ok_or(ErrorType::DutchAuctionNotFound(id))?;
+        if auction.is_over() {
+            return Err(ErrorType::InvalidDutchAuction);
+        }
+        auction.refresh(orders);
+        let open = auction.open_order(orders).map(|order| (auction.portfolio, order, auction.asset));
+        auction.state = DutchAuctionState::Cancelled;
+        Ok(open)
+    }
+
+    fn running(&self) -> Vec<Uuid> {
+        let mut running: Vec<Uuid> = self.auctions.values()
+            .filter(|a| !a.is_over())
+            .map(|a| a.id)
+            .collect();
+        running.sort();
+        running
+    }
+}
+
+// Moves every running auction's ask down to the current price. Replacing the ask runs through
+// `Engine::process`, so it's journaled like any player's cancel and order. An ask that was turned
+// down is tried again on the next tick.
+pub fn run_dutch_auctions(engine: &mut Engine) {
+    let tick = engine.market.tick;
+    for id in engine.dutch_auctions.running() {
+        let mut auction = engine.dutch_auctions.auctions[&id];
+        auction.refresh(&engine.orders);
+        let price = auction.price_at(tick);
+        let open = auction.open_order(&engine.orders);
+        if auction.is_over() || (open.is_some() && price == auction.price) {
+            engine.dutch_auctions.add(auction);
+            continue;
+        }
+        if let Some(order) = open {
+            if engine.process(Event::CancelOrder(auction.portfolio, order, auction.asset)).is_err() {
+                engine.dutch_auctions.add(auction);
+                continue;
+            }
+            auction.refresh(&engine.orders);
+        }
+        auction.settle();
+        auction.price = price;
+        if let Ok(order) = auction.ask(engine) {
+            if engine.process(Event::Order(order)).is_ok() {
+                auction.order = Some(order.get_id());
+                auction.refresh(&engine.orders);
+            }
+        }
+        if !auction.is_over() {
+            auction.state = if price == auction.terms.floor { DutchAuctionState::AtFloor } else { DutchAuctionState::Running };
+        }
+        engine.dutch_auctions.add(auction);
+    }
+}
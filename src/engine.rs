@@ -0,0 +1,976 @@
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+use crate::error::{checked_mul, EngineResult, ErrorType};
+use crate::order::{Event, Order, OrderId, Stamp};
+use crate::market::{Asset, AssetId, AssetScope, Market, Notification};
+use crate::account::{Portfolio, PortfolioId};
+use crate::history::BookHistory;
+use crate::alerts::Alerts;
+use crate::news::NewsItem;
+use crate::npc::MarketMaker;
+use crate::leaderboard::{PortfolioHistory, ScoringMetric};
+use crate::bankruptcy::BankruptcyRule;
+use crate::heartbeat::Heartbeats;
+use crate::dutch::DutchAuctions;
+use crate::algo::Algos;
+use crate::format::MAX_DECIMALS;
+use crate::debug::SequenceRegression;
+use crate::watchlist::Watchlists;
+use crate::lifecycle::{ExpiryReason, OrderRegistry, OrderState};
+use crate::sandbox::Sandboxes;
+use crate::auction::MarketStructure;
+use crate::hooks::EngineHook;
+use crate::actor::{Actor, Tokens};
+use crate::suspension::{Suspension, Suspensions};
+use crate::penalty::{self, Penalties};
+use crate::scenario::{Recording, ScenarioPlayer};
+use crate::report::GameReport;
+use crate::profile::{Phase, PhaseTimer, Profiler};
+use crate::trades::{ExecutionReport, Trade};
+use crate::audit::{self, ReplayDivergence};
+use crate::journal::{JournalEntry, JournalRecord, ReplayFile, REPLAY_FORMAT_VERSION};
+use crate::providers::{default_ids, Clock, IdProvider, SystemClock, Timestamp};
+use crate::bus::DomainEvent;
+use crate::ledger::LedgerKind;
+
+// the newest are kept, one is already a bug
+const MAX_SEQUENCE_REGRESSIONS: usize = 100;
+
+pub struct Engine {
+    pub market: Market,
+    pub clock: Box<dyn Clock>,
+    pub ids: Box<dyn IdProvider>,
+    pub book_history: BookHistory,
+    pub initial_market: Market,
+    pub journal: Vec<JournalEntry>,
+    pub sequence: u64,
+    // the journal entry being applied, left behind by a panic so recovering knows which one to drop
+    applying: Option<u64>,
+    // every book's sequence after the last journal entry, they may only ever go up
+    book_sequences: HashMap<AssetId, u64>,
+    pub sequence_regressions: Vec<SequenceRegression>,
+    pub alerts: Alerts,
+    pub news: Vec<NewsItem>,
+    pub market_makers: Vec<MarketMaker>,
+    pub portfolio_history: PortfolioHistory,
+    pub scoring_metric: ScoringMetric,
+    pub bankruptcy_rule: Option<BankruptcyRule>,
+    pub heartbeats: Heartbeats,
+    pub dutch_auctions: DutchAuctions,
+    pub algos: Algos,
+    pub watchlists: Watchlists,
+    pub orders: OrderRegistry,
+    // paper trading, never journaled
+    pub sandboxes: Sandboxes,
+    pub suspensions: Suspensions,
+    pub tokens: Tokens,
+    pub penalties: Penalties,
+    // what the admins and players do from the start on, never journaled
+    pub recording: Option<Recording>,
+    // the scenario being played back, see `run_scenario`
+    pub scenario: Option<ScenarioPlayer>,
+    // built when the game ended, see `Game::end_game`
+    pub report: Option<GameReport>,
+    // per-phase timings of every event while set
+    pub profiler: Option<Profiler>,
+    // who the records journaled from now on are attributed to
+    pub actor: Actor,
+    // the clock's time when the game time was last brought up to date, none until the engine first looks
+    clock_seen: Option<Timestamp>,
+    hooks: Vec<Box<dyn EngineHook>>,
+    // accepted orders and cancels not yet taken, see `take_domain_events`
+    domain_events: Vec<DomainEvent>,
+    // how far the trades and the ledger were taken
+    published_trades: usize,
+    published_ledger: usize,
+}
+
+impl Default for Engine {
+    fn default() -> Engine {
+        Engine::new()
+    }
+}
+
+impl Engine {
+
+    pub fn new() -> Engine {
+        Engine::from_market(Market::new())
+    }
+
+    pub fn from_market(market: Market) -> Engine {
+        Engine::with_providers(market, Box::new(SystemClock), default_ids())
+    }
+
+    pub fn with_providers(market: Market, clock: Box<dyn Clock>, ids: Box<dyn IdProvider>) -> Engine {
+        // what the market comes with happened before anyone listened
+        let (published_trades, published_ledger) = (market.trades.len(), market.ledger.len());
+        Engine {
+            initial_market: market.clone(),
+            market,
+            clock,
+            ids,
+            book_history: BookHistory::new(Duration::from_secs(1), 3600),
+            journal: vec![],
+            sequence: 0,
+            applying: None,
+            book_sequences: HashMap::new(),
+            sequence_regressions: vec![],
+            alerts: Alerts::new(),
+            news: vec![],
+            market_makers: vec![],
+            portfolio_history: PortfolioHistory::new(),
+            scoring_metric: ScoringMetric::NetWorth,
+            bankruptcy_rule: None,
+            heartbeats: Heartbeats::new(),
+            dutch_auctions: DutchAuctions::new(),
+            algos: Algos::new(),
+            watchlists: Watchlists::new(),
+            orders: OrderRegistry::new(),
+            sandboxes: Sandboxes::new(),
+            suspensions: Suspensions::new(),
+            tokens: Tokens::new(),
+            penalties: Penalties::new(),
+            recording: None,
+            scenario: None,
+            report: None,
+            profiler: None,
+            actor: Actor::Anonymous,
+            clock_seen: None,
+            hooks: vec![],
+            domain_events: vec![],
+            published_trades,
+            published_ledger,
+        }
+    }
+
+    pub fn replay(file: ReplayFile, until_sequence: Option<u64>) -> EngineResult<Engine> {
+        if file.version != REPLAY_FORMAT_VERSION {
+            return Err(ErrorType::UnsupportedReplayVersion(file.version));
+        }
+        let mut engine = Engine::from_market(file.initial);
+        for entry in file.entries {
+            if until_sequence.is_some_and(|until| entry.sequence > until) {
+                break;
+            }
+            // failed events were journaled as well, replaying them reproduces their side effects
+            let record = entry.record;
+            let _ = engine.acting_as(entry.actor, |engine| engine.apply(record));
+        }
+        engine.take_notifications();
+        engine.take_domain_events();
+        Ok(engine)
+    }
+
+    // Replays the journal on a fresh engine, comparing the state after each entry with the hash it was
+    // journaled with, and stops at the first that differs. If they all match the end is compared with
+    // the snapshot.
+    pub fn verify_replay(file: ReplayFile, snapshot: &Market) -> EngineResult<Option<ReplayDivergence>> {
+        if file.version != REPLAY_FORMAT_VERSION {
+            return Err(ErrorType::UnsupportedReplayVersion(file.version));
+        }
+        let mut engine = Engine::from_market(file.initial);
+        for entry in file.entries {
+            let record = entry.record.clone();
+            let _ = engine.acting_as(entry.actor, |engine| engine.apply(record));
+            if let Some(hash) = entry.state_hash {
+                if hash != audit::state_hash(&engine.market)? {
+                    return Ok(Some(ReplayDivergence {
+                        sequence: Some(entry.sequence),
+                        record: Some(entry.record),
+                        paths: vec![],
+                    }));
+                }
+            }
+        }
+        let paths = audit::diff(&audit::to_value(&engine.market)?, &audit::to_value(snapshot)?);
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(ReplayDivergence { sequence: None, record: None, paths }))
+    }
+
+    pub fn stamp(&self) -> Stamp {
+        Stamp {
+            id: self.ids.next_id(),
+            created_at: self.clock.now(),
+            tick: self.market.tick,
+        }
+    }
+
+    pub fn export_replay(&self) -> ReplayFile {
+        ReplayFile {
+            version: REPLAY_FORMAT_VERSION,
+            initial: self.initial_market.clone(),
+            entries: self.journal.clone(),
+        }
+    }
+
+    // Rebuilds everything the journal holds by replaying it, after a panic left the engine in a state
+    // that doesn't add up. If replaying the last entry panics again, it's the one that broke the engine
+    // and is left out, its sequence is returned. The clock, the game time and whatever else isn't
+    // journaled carry over.
+    pub fn restore_from_journal(&mut self) -> EngineResult<Option<u64>> {
+        let replay = |until: Option<u64>| panic::catch_unwind(AssertUnwindSafe(|| Engine::replay(self.export_replay(), until)))
+            .map_err(|_| ErrorType::InvalidState)
+            .and_then(|rebuilt| rebuilt);
+        // the entry that panicked goes, and whatever it journaled itself while it was applied
+        let (mut rebuilt, dropped) = match self.applying {
+            Some(sequence) => (replay(Some(sequence.saturating_sub(1)))?, Some(sequence)),
+            None => match replay(None) {
+                Ok(rebuilt) => (rebuilt, None),
+                Err(_) => (replay(Some(self.sequence.saturating_sub(1)))?, Some(self.sequence)),
+            },
+        };
+        rebuilt.market.now = self.market.now;
+        rebuilt.market.game_time = self.market.game_time;
+        rebuilt.market.expiries = std::mem::take(&mut self.market.expiries);
+        self.market = rebuilt.market;
+        self.orders = rebuilt.orders;
+        // replaying doesn't tell which orders are whose quotes, they're replaced on the next tick
+        let mut market_makers = rebuilt.market_makers;
+        for mm in market_makers.iter_mut() {
+            if let Some(before) = self.market_makers.iter().find(|b| b.portfolio == mm.portfolio && b.asset == mm.asset) {
+                mm.quotes = before.quotes.clone();
+            }
+        }
+        self.market_makers = market_makers;
+        self.news = rebuilt.news;
+        self.portfolio_history = rebuilt.portfolio_history;
+        self.scoring_metric = rebuilt.scoring_metric;
+        self.bankruptcy_rule = rebuilt.bankruptcy_rule;
+        self.suspensions = rebuilt.suspensions;
+        self.tokens = rebuilt.tokens;
+        self.alerts = rebuilt.alerts;
+        self.penalties = rebuilt.penalties;
+        self.book_sequences = rebuilt.book_sequences;
+        self.sequence_regressions = rebuilt.sequence_regressions;
+        self.journal = rebuilt.journal;
+        self.sequence = rebuilt.sequence;
+        self.applying = None;
+        Ok(dropped)
+    }
+
+    pub fn apply(&mut self, record: JournalRecord) -> EngineResult<()> {
+        if let JournalRecord::Event(event) = record {
+            return self.process(event);
+        }
+        self.record(record.clone());
+        let outer = self.applying.replace(self.sequence);
+        let seen = self.market.notifications.len();
+        let trade_count = self.market.trades.len();
+        // holding the trades it made may still fail, the record's changes go with them
+        let backup = self.market.settlement.as_ref().map(|_| self.market.clone());
+        let result = self.apply_record(record);
+        let result = match (result, backup) {
+            (Ok(_), Some(backup)) => self.market.hold_settlements(trade_count).inspect_err(|_| self.market = backup),
+            (result, _) => result,
+        };
+        self.track_orders(&[], &result, seen);
+        self.check_sequences();
+        self.seal(outer);
+        self.applying = outer;
+        result
+    }
+
+    fn apply_record(&mut self, record: JournalRecord) -> EngineResult<()> {
+        match record {
+            JournalRecord::Event(_) => unreachable!(),
+            JournalRecord::CreatePortfolio { id, initial_coins } => {
+                self.market.add_portfolio(Portfolio::with_id(id, initial_coins));
+                self.portfolio_history.record_one(&self.market, id);
+                Ok(())
+            },
+            JournalRecord::SetPaused(paused) => {
+                // the time up to now still counts when pausing, none of the paused time when resuming
+                self.sync_clock();
+                self.market.paused = paused;
+                Ok(())
+            },
+            JournalRecord::HaltAsset { asset, halted } => self.market.set_halted(asset, halted),
+            JournalRecord::PayDividend { asset, per_unit } => {
+                let backup = self.market.clone();
+                let result = self.market.pay_dividend(asset, per_unit);
+                if result.is_err() {
+                    self.market = backup;
+                }
+                result
+            },
+            JournalRecord::ExpireOrder { asset, order } => {
+                self.market.expire_order(asset, order, ExpiryReason::GoodTillDate)?;
+                self.market.expiries.remove(&order);
+                Ok(())
+            },
+            JournalRecord::EndGame => {
+                // the time up to now still counts
+                self.sync_clock();
+                let mut assets: Vec<AssetId> = self.market.books.keys().cloned().collect();
+                assets.sort();
+                for asset in assets {
+                    self.market.expire_all_orders(asset, ExpiryReason::GameEnded)?;
+                }
+                self.market.expiries.clear();
+                self.market.ended = true;
+                Ok(())
+            },
+            JournalRecord::ProvisionPortfolios { coins, entries } => {
+                for entry in entries {
+                    self.market.add_portfolio(Portfolio::with_id(entry.portfolio, coins));
+                    self.portfolio_history.record_one(&self.market, entry.portfolio);
+                    self.tokens.issue(entry.fingerprint.clone(), entry.portfolio);
+                    self.market.roster.push(entry);
+                }
+                Ok(())
+            },
+            JournalRecord::ClonePortfolio { source, id } => {
+                let portfolio = self.market.get_portfolio(source)?.fork(id);
+                self.market.add_portfolio(portfolio);
+                self.portfolio_history.record_one(&self.market, id);
+                Ok(())
+            },
+            JournalRecord::CreateAsset { id, name } => {
+                self.market.add_asset(Asset::with_id(id, name)).map(|_| ())
+            },
+            JournalRecord::SetAssetAmount { portfolio, asset, amount } => {
+                self.market.get_asset(&asset)?;
+                self.market.get_portfolio_mut(portfolio)?
+                    .ensure_asset_account(asset).add(amount)?;
+                Ok(())
+            },
+            JournalRecord::SetReferencePricePolicy(policy) => {
+                self.market.reference_price_policy = policy;
+                Ok(())
+            },
+            JournalRecord::SetFairValue { asset, price } => {
+                self.market.get_asset(&asset)?;
+                self.market.fair_values.insert(asset, price);
+                Ok(())
+            },
+            JournalRecord::OraclePrice { asset, price } => {
+                self.market.get_asset(&asset)?;
+                self.market.fair_values.insert(asset, price);
+                for mm in self.market_makers.iter_mut().filter(|mm| mm.asset == asset) {
+                    mm.fair_value = price;
+                }
+                Ok(())
+            },
+            JournalRecord::SetAssetDecimals { asset, decimals } => {
+                if decimals > MAX_DECIMALS {
+                    return Err(ErrorType::InvalidDecimals { max: MAX_DECIMALS });
+                }
+                self.market.assets.get_mut(&asset).ok_or(ErrorType::AssetNotFound(asset))?.decimals = decimals;
+                Ok(())
+            },
+            JournalRecord::Tick => {
+                // the orders of the ending tick cross before it's over
+                if self.market.market_structure == MarketStructure::BatchAuction {
+                    let seen = self.market.notifications.len();
+                    self.market.run_auctions();
+                    self.market.resolve_oco_groups(seen);
+                }
+                self.market.tick += 1;
+                self.market.pay_interest();
+                self.portfolio_history.record(&self.market);
+                self.evaluate_alerts();
+                Ok(())
+            },
+            JournalRecord::PostNews(item) => {
+                for impact in &item.impacts {
+                    self.market.get_asset(&impact.asset)?;
+                }
+                for impact in &item.impacts {
+                    self.market_makers.iter_mut().for_each(|mm| mm.apply_impact(impact));
+                }
+                self.news.push(item);
+                Ok(())
+            },
+            JournalRecord::SetScoringMetric(metric) => {
+                self.scoring_metric = metric;
+                Ok(())
+            },
+            JournalRecord::ForceCancelOrder { asset, order } => {
+                self.market.force_cancel_order(asset, order)
+            },
+            JournalRecord::CancelAllOrders { asset } => {
+                self.market.cancel_all_orders(asset).map(|_| ())
+            },
+            JournalRecord::BustTrade { trade } => self.market.reverse_trade(trade),
+            JournalRecord::SetBankruptcyRule(rule) => {
+                self.bankruptcy_rule = rule;
+                Ok(())
+            },
+            JournalRecord::DeclareBankrupt { portfolio } => self.market.declare_bankrupt(portfolio),
+            JournalRecord::RestartPortfolio { portfolio, coins } => self.market.restart_portfolio(portfolio, coins),
+            JournalRecord::SetInterestPolicy(policy) => {
+                self.market.interest_policy = policy;
+                Ok(())
+            },
+            JournalRecord::Mint { amount } => self.market.mint(amount),
+            JournalRecord::Burn { amount } => self.market.burn(amount),
+            JournalRecord::CreateBankPortfolio { id } => self.market.create_bank_portfolio(id),
+            JournalRecord::FundBankPortfolio { amount } => self.market.fund_bank_portfolio(amount),
+            JournalRecord::SetFeeSchedule(schedule) => {
+                self.market.fee_schedule = schedule;
+                Ok(())
+            },
+            JournalRecord::SetPriceBand(percent) => {
+                self.market.price_band_percent = percent;
+                Ok(())
+            },
+            JournalRecord::SetMinimumRestingTime(minimum) => {
+                self.market.minimum_resting_time = minimum;
+                Ok(())
+            },
+            JournalRecord::SetLatency(latency) => {
+                self.market.latency = latency;
+                Ok(())
+            },
+            // the queue depth isn't the engine's, replaying ignores it
+            JournalRecord::SetConfig(config) => config.apply_to(&mut self.market),
+            JournalRecord::SetMarketStructure(structure) => {
+                // what collected since the last tick crosses before continuous matching takes over
+                if self.market.market_structure == MarketStructure::BatchAuction && structure == MarketStructure::Continuous {
+                    let seen = self.market.notifications.len();
+                    self.market.run_auctions();
+                    self.market.resolve_oco_groups(seen);
+                }
+                self.market.market_structure = structure;
+                Ok(())
+            },
+            JournalRecord::SetDarkPriority(priority) => {
+                self.market.dark_priority = priority;
+                Ok(())
+            },
+            JournalRecord::SetFaucetPolicy(policy) => {
+                self.market.faucet.policy = policy;
+                Ok(())
+            },
+            JournalRecord::ClaimFaucet { portfolio } => {
+                // the bank and the market makers are funded otherwise
+                if !self.is_player(portfolio) {
+                    return Err(ErrorType::InvalidState);
+                }
+                self.check_access(portfolio)?;
+                self.market.claim_faucet(portfolio).map(|_| ())
+            },
+            JournalRecord::SetSettlementPolicy(policy) => self.market.set_settlement_policy(policy),
+            JournalRecord::ConfirmSettlement { trade } => self.market.confirm_settlement(trade),
+            JournalRecord::RejectSettlement { trade } => self.market.reject_settlement(trade),
+            JournalRecord::SetPriorityClasses(classes) => self.market.colocation.set_classes(classes),
+            JournalRecord::AssignPriorityClass { portfolio, class } => {
+                self.market.get_portfolio(portfolio)?;
+                self.market.colocation.assign(portfolio, class)
+            },
+            JournalRecord::BuyPriorityClass { portfolio, class } => {
+                if !self.is_player(portfolio) {
+                    return Err(ErrorType::InvalidState);
+                }
+                self.check_access(portfolio)?;
+                self.market.buy_priority_class(portfolio, &class).map(|_| ())
+            },
+            JournalRecord::Suspend(suspension) => {
+                // cancelled as part of this record, so replaying it cancels the same orders
+                let orders = match &suspension {
+                    Suspension::Portfolio(id) => self.market.get_orders_of(*id),
+                    Suspension::Token(fingerprint) => self.orders.open_by(&Actor::Token(fingerprint.clone())),
+                    Suspension::Ip(_) => Vec::new(),
+                };
+                self.suspensions.suspend(suspension);
+                for (asset, order) in orders {
+                    let _ = self.market.force_cancel_order(asset, order);
+                }
+                Ok(())
+            },
+            JournalRecord::Allocate(allocations) => {
+                if !self.market.trades.is_empty() {
+                    return Err(ErrorType::GameAlreadyStarted);
+                }
+                self.market.allocate(&allocations)
+            },
+            JournalRecord::Reinstate(suspension) => {
+                if !self.suspensions.reinstate(&suspension) {
+                    return Err(ErrorType::InvalidState);
+                }
+                Ok(())
+            },
+            JournalRecord::ScheduleOrder(order) => {
+                self.market.get_portfolio(order.portfolio)?;
+                self.market.get_asset(&order.asset)?;
+                self.market.schedules.add(order)
+            },
+            JournalRecord::CancelSchedule { portfolio, id } => self.market.schedules.cancel(portfolio, id),
+            JournalRecord::ScheduleFired { id, tick, placed } => self.market.schedules.fired(id, tick, placed),
+            JournalRecord::AddMarketMaker { portfolio, asset, fair_value, spread_percent, quantity } => {
+                self.market.get_asset(&asset)?;
+                self.market.get_portfolio(portfolio)?;
+                self.market_makers.push(MarketMaker::new(portfolio, asset, fair_value, spread_percent, quantity));
+                Ok(())
+            },
+            JournalRecord::AddAlert { portfolio, id, rule } => {
+                self.market.get_portfolio(portfolio)?;
+                self.alerts.add_rule(portfolio, id, rule);
+                Ok(())
+            },
+            JournalRecord::RemoveAlert { portfolio, id } => {
+                if !self.alerts.remove_rule(portfolio, id) {
+                    return Err(ErrorType::AlertNotFound(id));
+                }
+                Ok(())
+            },
+            JournalRecord::IssueToken { fingerprint, portfolio } => {
+                self.market.get_portfolio(portfolio)?;
+                self.tokens.issue(fingerprint, portfolio);
+                Ok(())
+            },
+            JournalRecord::RevokeToken { fingerprint } => {
+                if !self.tokens.revoke(&fingerprint) {
+                    return Err(ErrorType::InvalidState);
+                }
+                Ok(())
+            },
+            JournalRecord::SetPenaltyRule(rule) => self.penalties.set_rule(rule),
+            JournalRecord::SetPriceMode(mode) => {
+                // quotes already in the market would change their meaning
+                let has_orders = self.market.books.values().any(|b| b.all_orders().next().is_some());
+                if has_orders || !self.market.trades.is_empty() {
+                    return Err(ErrorType::PriceModeLocked);
+                }
+                self.market.price_mode = mode;
+                Ok(())
+            },
+        }
+    }
+
+    pub fn add_hook(&mut self, hook: Box<dyn EngineHook>) {
+        self.hooks.push(hook);
+    }
+
+    fn record(&mut self, record: JournalRecord) {
+        self.sequence += 1;
+        self.journal.push(JournalEntry {
+            sequence: self.sequence,
+            record,
+            actor: self.actor.clone(),
+            state_hash: None,
+        });
+    }
+
+    // once an entry is through, unless another one is still being applied around it
+    fn seal(&mut self, outer: Option<u64>) {
+        if outer.is_some() {
+            return;
+        }
+        let hash = audit::state_hash(&self.market).ok();
+        let sequence = self.applying;
+        if let Some(entry) = self.journal.iter_mut().rev().find(|e| Some(e.sequence) == sequence) {
+            entry.state_hash = hash;
+        }
+    }
+
+    fn bill_fee_for(&mut self, event: &Event) -> EngineResult<()> {
+        // admin events are free
+        if let Event::DelistAsset(..) = event {
+            return Ok(());
+        }
+        let fee = checked_mul(self.market.fee_schedule.event_fee, event.orders().len().max(1))?;
+        self.market.bill_fee(event.portfolio(), fee)
+    }
+
+    // when the interval passed, after events and on ticks so quiet books get snapshots too
+    pub fn capture_book_history(&mut self) {
+        let now = self.clock.now();
+        self.book_history.capture_if_due(&self.market, now);
+    }
+
+    // the portfolio the bank trades and issues assets with, created on first use
+    pub fn bank_portfolio(&mut self) -> EngineResult<PortfolioId> {
+        if let Some(id) = self.market.bank_portfolio {
+            return Ok(id);
+        }
+        let id = self.ids.next_id();
+        self.apply(JournalRecord::CreateBankPortfolio { id })?;
+        Ok(id)
+    }
+
+    // market makers and the bank don't compete with the players
+    pub fn is_player(&self, portfolio_id: PortfolioId) -> bool {
+        self.market.bank_portfolio != Some(portfolio_id)
+            && !self.market_makers.iter().any(|mm| mm.portfolio == portfolio_id)
+    }
+
+    // fines the players whose rejections went over the penalty rule's limit, rejections of admins and
+    // the game's own runners don't count
+    fn penalize(&mut self, portfolio_id: PortfolioId, error: &ErrorType) {
+        if !self.actor.is_player() || !self.is_player(portfolio_id) || !penalty::counts_against(error) {
+            return;
+        }
+        if self.market.get_portfolio(portfolio_id).is_err() {
+            return;
+        }
+        if let Some(penalty) = self.penalties.record_rejection(portfolio_id, self.market.tick) {
+            let _ = self.market.collect_penalty(portfolio_id, penalty);
+        }
+    }
+
+    // journaled as admin cancels, so no fees are billed
+    pub fn cancel_all_orders_of(&mut self, portfolio_id: PortfolioId) {
+        for (asset, order) in self.market.get_orders_of(portfolio_id) {
+            let _ = self.apply(JournalRecord::ForceCancelOrder { asset, order });
+        }
+    }
+
+    // the actor for everything `f` journals, the previous one afterwards
+    pub fn acting_as<T>(&mut self, actor: Actor, f: impl FnOnce(&mut Engine) -> T) -> T {
+        let previous = std::mem::replace(&mut self.actor, actor);
+        let result = f(self);
+        self.actor = previous;
+        result
+    }
+
+    pub fn advance_tick(&mut self) {
+        let _ = self.apply(JournalRecord::Tick);
+    }
+
+    // only the prices that moved are journaled
+    pub fn apply_oracle_prices(&mut self, prices: &[(AssetId, usize)]) {
+        for (asset, price) in prices {
+            if self.market.fair_values.get(asset) != Some(price) {
+                let _ = self.apply(JournalRecord::OraclePrice { asset: *asset, price: *price });
+            }
+        }
+    }
+
+    // Game time moves with the clock while the game runs. Time spent paused or before the engine was
+    // loaded doesn't count, so a game restored from a snapshot carries on where it stopped.
+    pub fn sync_clock(&mut self) {
+        let now = self.clock.now();
+        if let Some(seen) = self.clock_seen {
+            if !self.market.paused && !self.market.ended {
+                self.market.game_time += now.saturating_sub(seen);
+            }
+        }
+        self.clock_seen = Some(now);
+    }
+
+    // Expires the orders whose lifetime ran out in game time, journaled so replays expire the same ones.
+    // The assets whose books changed.
+    pub fn expire_orders(&mut self) -> Vec<AssetId> {
+        self.sync_clock();
+        let resting: HashMap<OrderId, AssetId> = self.market.books.values()
+            .flat_map(|book| book.all_orders().map(|o| (o.id, o.asset)))
+            .collect();
+        self.market.expiries.retain(|order, _| resting.contains_key(order));
+        let game_time = self.market.game_time;
+        let mut due: Vec<(Timestamp, OrderId)> = self.market.expiries.iter()
+            .filter(|(_, expires)| **expires <= game_time)
+            .map(|(order, expires)| (*expires, *order))
+            .collect();
+        due.sort();
+        let mut assets = vec![];
+        for (_, order) in due {
+            let asset = resting[&order];
+            if self.apply(JournalRecord::ExpireOrder { asset, order }).is_ok() && !assets.contains(&asset) {
+                assets.push(asset);
+            }
+        }
+        assets
+    }
+
+    // Busts the trades that weren't confirmed in time, oldest first. Returns their assets.
+    pub fn expire_settlements(&mut self) -> Vec<AssetId> {
+        let game_time = self.market.game_time;
+        let due: Vec<(u64, AssetId)> = self.market.pending_settlements.values()
+            .filter(|pending| pending.deadline <= game_time)
+            .map(|pending| (pending.trade.id, pending.trade.asset))
+            .collect();
+        let mut assets = vec![];
+        for (trade, asset) in due {
+            if self.apply(JournalRecord::RejectSettlement { trade }).is_ok() && !assets.contains(&asset) {
+                assets.push(asset);
+            }
+        }
+        assets
+    }
+
+    fn evaluate_alerts(&mut self) {
+        let notifications = self.alerts.evaluate(&self.market, self.market.tick);
+        self.market.notifications.extend(notifications);
+    }
+
+    pub fn process(&mut self, event: Event) -> EngineResult<()> {
+        self.record(JournalRecord::Event(event.clone()));
+        let outer = self.applying.replace(self.sequence);
+        self.market.now = self.clock.now();
+        self.sync_clock();
+        let portfolio = event.portfolio();
+        let assets = event.assets();
+        let trade_count = self.market.trades.len();
+        let seen = self.market.notifications.len();
+        let orders = event.orders();
+        for order in &orders {
+            self.orders.open(order, self.actor.clone(), self.market.fee_schedule.event_fee, self.market.now);
+            // the lifetime it was given starts now in game time
+            let lifetime = order.expires.saturating_sub(order.created_at);
+            self.market.expiries.insert(order.id, self.market.game_time.saturating_add(lifetime));
+        }
+        let mut timer = PhaseTimer::start(self.profiler.is_some());
+        let result = self.validate(&event);
+        timer.lap(Phase::Validation);
+        let result = result.and_then(|_| self.execute(event.clone()));
+        timer.lap(Phase::Matching);
+        timer.carve(Phase::Transfer, std::mem::take(&mut self.market.transfer_time));
+        self.track_orders(&orders, &result, seen);
+        self.report_executions(&orders, trade_count);
+        self.check_sequences();
+        self.run_post_hooks(&event, &result, trade_count);
+        if result.is_ok() {
+            self.domain_events.extend(orders.iter().map(|o| DomainEvent::OrderAccepted {
+                order: o.id,
+                portfolio: o.portfolio,
+                asset: o.asset,
+                side: o.side,
+                quantity: o.quantity,
+            }));
+        }
+        match &result {
+            Ok(_) => self.market.count_activity(&event),
+            Err(e) => {
+                self.alerts.record_rejection(portfolio);
+                self.penalize(portfolio, e);
+            },
+        }
+        self.market.check_margin(portfolio);
+        for asset in assets {
+            if let Some(price) = self.market.last_trade_prices.get(&asset) {
+                self.alerts.record_price(asset, self.market.tick, *price);
+            }
+        }
+        self.evaluate_alerts();
+        self.capture_book_history();
+        timer.lap(Phase::Bookkeeping);
+        if let Some(profiler) = &mut self.profiler {
+            profiler.record(timer);
+        }
+        self.seal(outer);
+        self.applying = outer;
+        result
+    }
+
+    // off again with `false`, turning it on starts over
+    pub fn set_profiling(&mut self, enabled: bool) {
+        self.profiler = if enabled { Some(Profiler::new()) } else { None };
+        self.market.profiling = enabled;
+        self.market.transfer_time = Duration::from_secs(0);
+    }
+
+    // Moves orders along from the fills and cancels the market reported since `seen`, then settles the
+    // event's own orders. Runs once the outcome is final, a rolled back event leaves no fills behind.
+    fn track_orders(&mut self, orders: &[Order], result: &EngineResult<()>, seen: usize) {
+        let now = self.market.now;
+        let mut changed = vec![];
+        for (_, notification) in self.market.notifications.iter().skip(seen) {
+            let (order_id, moved) = match notification {
+                Notification::Fill { order_id, quantity, .. } => (*order_id, self.orders.fill(*order_id, *quantity, now)),
+                Notification::OrderCancelled { order_id, .. } => (*order_id, self.orders.cancel(*order_id, now)),
+                Notification::OrderExpired { order_id, reason, .. } => (*order_id, self.orders.expire(*order_id, *reason, now)),
+                _ => continue,
+            };
+            if moved && !changed.contains(&order_id) {
+                changed.push(order_id);
+            }
+        }
+        for order in orders {
+            let moved = match result {
+                Ok(_) => self.orders.rest(order.id, now),
+                Err(e) => self.orders.reject(order.id, format!("{:?}", e), now),
+            };
+            if moved && !changed.contains(&order.id) {
+                changed.push(order.id);
+            }
+        }
+        for order_id in changed {
+            self.refund_unfilled(order_id);
+            if let Ok(record) = self.orders.get(order_id) {
+                let update = (record.portfolio, Notification::OrderUpdate(record.clone()));
+                self.market.notifications.push(update);
+            }
+        }
+    }
+
+    // The fills of each of the event's orders summed up, after the single fills went out.
+    fn report_executions(&mut self, orders: &[Order], trade_count: usize) {
+        let trades = self.market.trades.get(trade_count..).unwrap_or(&[]);
+        let reports: Vec<_> = orders.iter()
+            .filter_map(|order| ExecutionReport::for_order(order.id, trades).map(|report| (order.portfolio, Notification::Execution(report))))
+            .collect();
+        self.market.notifications.extend(reports);
+    }
+
+    // Part of the placement fee back for an order that was cancelled before anything filled. The bank
+    // pays it as long as it can.
+    fn refund_unfilled(&mut self, order_id: OrderId) {
+        // as for a cancel when the game took the order out, not when it outlived its lifetime
+        let (portfolio, fee) = match self.orders.get(order_id) {
+            Ok(record) if record.filled > 0 => return,
+            Ok(record) => match record.state {
+                OrderState::Cancelled | OrderState::Expired { reason: ExpiryReason::AssetDelisted }
+                | OrderState::Expired { reason: ExpiryReason::GameEnded } => (record.portfolio, record.fee),
+                _ => return,
+            },
+            _ => return,
+        };
+        let refund = self.market.fee_schedule.cancel_refund(fee);
+        if refund > 0 {
+            let _ = self.market.refund_fee(portfolio, refund);
+        }
+    }
+
+    // Streaming clients order the updates of a book by its sequence, one that went backwards is noted
+    // for /admin/debug/state. A delisted book is forgotten.
+    fn check_sequences(&mut self) {
+        let books = &self.market.books;
+        self.book_sequences.retain(|asset, _| books.contains_key(asset));
+        for book in books.values() {
+            let to = book.version();
+            match self.book_sequences.insert(book.asset_id, to) {
+                Some(from) if to < from => {
+                    if self.sequence_regressions.len() == MAX_SEQUENCE_REGRESSIONS {
+                        self.sequence_regressions.remove(0);
+                    }
+                    self.sequence_regressions.push(SequenceRegression { asset: book.asset_id, from, to, journal_sequence: self.sequence });
+                },
+                _ => (),
+            }
+        }
+    }
+
+    fn run_post_hooks(&mut self, event: &Event, result: &EngineResult<()>, trade_count: usize) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let trades: Vec<Trade> = self.market.trades.iter().skip(trade_count).cloned().collect();
+        for hook in self.hooks.iter_mut() {
+            for trade in &trades {
+                hook.post_trade(trade, &mut self.market);
+            }
+            hook.post_event(event, result, &mut self.market);
+        }
+    }
+
+    pub(crate) fn check_access(&self, portfolio_id: PortfolioId) -> EngineResult<()> {
+        self.suspensions.check(portfolio_id, &self.actor)?;
+        self.tokens.check(portfolio_id, &self.actor)
+    }
+
+    fn validate(&mut self, event: &Event) -> EngineResult<()> {
+        if self.market.ended {
+            return Err(ErrorType::GameEnded);
+        }
+        if self.market.paused {
+            return Err(ErrorType::GamePaused);
+        }
+        self.check_access(event.portfolio())?;
+        if self.actor.is_player() {
+            self.penalties.check(event.portfolio(), self.market.tick)?;
+        }
+        for o in event.orders() {
+            self.check_access(o.portfolio)?;
+            self.market.check_session()?;
+            self.market.check_halted(o.asset)?;
+        }
+        for o in event.orders() {
+            if self.market.get_portfolio(o.portfolio)?.bankrupt_since.is_some() {
+                return Err(ErrorType::PortfolioBankrupt(o.portfolio));
+            }
+        }
+        for o in event.orders() {
+            if self.is_player(o.portfolio) {
+                self.market.check_price_band(&o)?;
+            }
+        }
+        for hook in self.hooks.iter_mut() {
+            hook.pre_validate(event, &self.market)?;
+        }
+        self.bill_fee_for(event)
+    }
+
+    fn execute(&mut self, event: Event) -> EngineResult<()> {
+        // every scope is taken before anything runs, so restoring them in any order gets back to the start
+        let scopes: Vec<AssetScope> = event.assets().into_iter()
+            .map(|asset| self.market.snapshot_scope(asset, event.portfolio()))
+            .collect();
+        // delisting touches every holder, more than a scope covers
+        let delisted = match event {
+            Event::DelistAsset(asset, _) => Some(asset),
+            _ => None,
+        };
+        let backup = delisted.map(|_| self.market.clone());
+        // earlier events under the same lock keep theirs
+        let seen = self.market.notifications.len();
+        let trade_count = self.market.trades.len();
+        let result = match event {
+            Event::Order(o) => self.market.fill_order(o),
+            // admins and the game's own runners aren't held to the minimum resting time
+            Event::CancelOrder(portfolio, order, asset) if !self.actor.is_player() => {
+                self.market.withdraw_order(portfolio, order, asset)
+            },
+            Event::CancelOrder(portfolio, order, asset) => {
+                self.market.cancel_order(portfolio, order, asset)
+            },
+            Event::AtomicBatch(orders) => self.market.fill_batch(&orders),
+            Event::Oco { first, second, trigger_quantity } => self.market.fill_oco(first, second, trigger_quantity),
+            Event::DelistAsset(asset, price) => self.market.delist_asset(asset, price),
+        };
+        // the trades are held while the scopes can still take them back
+        let result = result.and_then(|_| self.market.hold_settlements(trade_count));
+        if result.is_err() {
+            if let Some(market) = backup {
+                self.market = market;
+                return result;
+            }
+            for scope in scopes.into_iter().rev() {
+                self.market.restore_scope(scope);
+            }
+            self.market.notifications.truncate(seen);
+            return result;
+        }
+        self.market.resolve_oco_groups(seen);
+        if let Some(asset) = delisted {
+            self.market_makers.retain(|mm| mm.asset != asset);
+        }
+        result
+    }
+
+    // Cancels go to the bus from here, whatever withdrew the order.
+    pub fn take_notifications(&mut self) -> Vec<(PortfolioId, Notification)> {
+        let notifications = std::mem::take(&mut self.market.notifications);
+        self.domain_events.extend(notifications.iter().filter_map(|(portfolio, notification)| match notification {
+            Notification::OrderCancelled { order_id, asset } => Some(DomainEvent::OrderCancelled {
+                order: *order_id,
+                portfolio: *portfolio,
+                asset: *asset,
+            }),
+            _ => None,
+        }));
+        notifications
+    }
+
+    // What happened since the last call: accepted orders and cancels, then trades, then fees. Take
+    // the notifications first, the cancels come with them.
+    pub fn take_domain_events(&mut self) -> Vec<DomainEvent> {
+        let mut events = std::mem::take(&mut self.domain_events);
+        // a market rebuilt from the journal may hold fewer
+        let trades = self.market.trades.get(self.published_trades..).unwrap_or(&[]);
+        events.extend(trades.iter().map(|trade| DomainEvent::TradeExecuted {
+            trade: trade.clone(),
+            notional: self.market.price_mode.notional(trade.price, trade.quantity).map_or(0, |n| n.unsigned_abs() as usize),
+        }));
+        let ledger = self.market.ledger.get(self.published_ledger..).unwrap_or(&[]);
+        events.extend(ledger.iter()
+            .filter(|entry| entry.kind == LedgerKind::Fee || entry.kind == LedgerKind::TakerFee)
+            .map(|entry| DomainEvent::FeesCharged { portfolio: entry.portfolio, kind: entry.kind, amount: entry.amount }));
+        self.published_trades = self.market.trades.len();
+        self.published_ledger = self.market.ledger.len();
+        events
+    }
+}
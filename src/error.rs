@@ -0,0 +1,114 @@
+use uuid::Uuid;
+use crate::order::MinimumRestingTime;
+use crate::validation::FieldError;
+
+#[derive(Debug, Serialize)]
+pub enum ErrorType {
+    AssetNotFound(Uuid),
+    PortfolioNotFound(Uuid),
+    OrderNotFound(Uuid),
+    NotEnoughMatchingOrdersToImmediatelyFillBestOrder,
+    CantLockAmountForBestOrder,
+    CantSplitOrder,
+    InsufficientFreeAmount,
+    InsufficientLockedAmount,
+    ReservationNotFound(Uuid),
+    InvalidAssetId,
+    AssetNameAlreadyTaken(String),
+    AssetNameNotFound(String),
+    InvalidState,
+    NoLimitForBestOrder,
+    QuantityCantBeZero,
+    LimitCantBeZero,
+    EngineWasTooBusy,
+    UnsupportedReplayVersion(u32),
+    UnknownMarketDataLevel(String),
+    AlertNotFound(Uuid),
+    UnknownScoringMetric(String),
+    TradeNotFound(u64),
+    TradeAlreadyBusted(u64),
+    PortfolioBankrupt(Uuid),
+    QueueFull { depth: usize, retry_after_millis: u64 },
+    RateLimited { retry_after_millis: u64 },
+    EngineTimeout { waited_millis: u64, queue_latency_millis: u64 },
+    ArithmeticOverflow,
+    InvalidBatch,
+    InvalidOcoGroup,
+    InvalidTargetWeights,
+    NoPriceForAsset(Uuid),
+    InvalidBookSeed,
+    UnknownOrderSide(String),
+    BookDeltaUnavailable { since_version: u64, oldest_version: u64 },
+    InvalidSchedule,
+    ScheduleNotFound(Uuid),
+    VetoedByHook(String),
+    InvalidWebhookUrl(String),
+    WebhookNotFound(usize),
+    UnknownRenderFormat(String),
+    UnknownLocale(String),
+    InvalidDecimals { max: u32 },
+    PriceModeLocked,
+    PriceOutOfBounds { limit: usize, min: usize, max: usize },
+    OverrideRequiresAdmin,
+    InvalidBookCsv { line: usize },
+    InvalidRosterCsv { line: usize },
+    InvalidBulkProvisioning { max: usize },
+    SandboxNotFound(Uuid),
+    BestOrderInBatchAuction,
+    FaucetDisabled,
+    FaucetCoolingDown { next_tick: u64 },
+    FaucetBudgetExhausted,
+    Suspended,
+    // the portfolio has API tokens and the event didn't come with one of them
+    TokenRequired,
+    InvalidIpAddress(String),
+    InvalidBootstrapPlan,
+    GameAlreadyStarted,
+    InvalidDutchAuction,
+    DutchAuctionNotFound(Uuid),
+    GamePaused,
+    GameEnded,
+    UnsupportedApiVersion { requested: u32, supported: Vec<u32> },
+    CancelTooEarly { remaining: MinimumRestingTime },
+    InvalidPriceCurve,
+    NoOraclePrice(Uuid),
+    // a panic broke the engine and recovering it failed, see `GET /health`
+    EngineUnavailable,
+    InvalidLatency,
+    InvalidPriorityClasses,
+    UnknownPriorityClass(String),
+    PriorityClassNotForSale(String),
+    NoPositionToClose(Uuid),
+    InvalidSettlementPolicy,
+    SettlementNotPending(u64),
+    // a request body with fields it shouldn't have or values out of range, answered with a 422
+    InvalidFields(Vec<FieldError>),
+    InvalidAlgo,
+    AlgoNotFound(Uuid),
+    AssetHalted(Uuid),
+    InvalidDividend,
+    InvalidPenaltyRule,
+    // too many rejected events, none are taken before the tick
+    OrderBanned { until_tick: u64 },
+    NotRecording,
+    UnsupportedScenarioVersion(u32),
+    GameNotFound(Uuid),
+    GameNotEnded,
+    InvalidSessionTimes,
+    // outside the session times of the config
+    MarketClosed,
+}
+
+pub type EngineResult<T> = Result<T, ErrorType>;
+
+pub fn checked_add(a: usize, b: usize) -> EngineResult<usize> {
+    a.checked_add(b).ok_or(ErrorType::ArithmeticOverflow)
+}
+
+pub fn checked_sub(a: usize, b: usize) -> EngineResult<usize> {
+    a.checked_sub(b).ok_or(ErrorType::ArithmeticOverflow)
+}
+
+pub fn checked_mul(a: usize, b: usize) -> EngineResult<usize> {
+    a.checked_mul(b).ok_or(ErrorType::ArithmeticOverflow)
+}
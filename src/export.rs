@@ -0,0 +1,45 @@
+use crate::format::{Formatter, Locale};
+use crate::ledger::LedgerEntry;
+use crate::trades::Trade;
+
+// flat rows for spreadsheets and pandas, none of the fields can contain a comma or quote
+pub trait CsvRow {
+    fn header() -> &'static str;
+    fn row(&self, format: &Formatter) -> String;
+}
+
+// prices with their asset's decimals, but never with a thousands separator
+pub fn to_csv<T: CsvRow>(rows: &[T], format: &Formatter) -> String {
+    let format = format.with_locale(Locale::Plain);
+    let mut csv = String::from(T::header());
+    csv.push('\n');
+    for row in rows {
+        csv.push_str(&row.row(&format));
+        csv.push('\n');
+    }
+    csv
+}
+
+impl CsvRow for Trade {
+    fn header() -> &'static str {
+        "id,tick,timestamp,asset,price,quantity,notional,buyer,seller,buy_order,sell_order,aggressor,busted"
+    }
+
+    fn row(&self, format: &Formatter) -> String {
+        format!("{},{},{},{},{},{},{},{},{},{},{},{:?},{}",
+                self.id, self.tick, self.timestamp, self.asset, format.price(self.asset, self.price), self.quantity,
+                format.price(self.asset, self.price * self.quantity), self.buyer, self.seller, self.buy_order, self.sell_order,
+                self.aggressor, self.busted)
+    }
+}
+
+impl CsvRow for LedgerEntry {
+    fn header() -> &'static str {
+        "tick,portfolio,kind,amount,trade"
+    }
+
+    fn row(&self, _: &Formatter) -> String {
+        let trade = self.trade.map(|t| t.to_string()).unwrap_or_default();
+        format!("{},{},{:?},{},{}", self.tick, self.portfolio, self.kind, self.amount, trade)
+    }
+}
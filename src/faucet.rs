@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::error::{EngineResult, ErrorType};
+
+// Each claim pays `amount` out of the bank account, at most once every `cooldown_ticks` per portfolio
+// and `budget` coins over the whole game.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct FaucetPolicy {
+    pub amount: usize,
+    pub cooldown_ticks: u64,
+    pub budget: usize,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Faucet {
+    pub policy: Option<FaucetPolicy>,
+    pub paid: usize,
+    // tick of every portfolio's last claim
+    pub claims: HashMap<PortfolioId, u64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FaucetStatus {
+    pub policy: Option<FaucetPolicy>,
+    pub paid: usize,
+    pub budget_left: usize,
+}
+
+impl Faucet {
+    // the amount the portfolio gets if it claims at the tick
+    pub fn check(&self, portfolio_id: PortfolioId, tick: u64) -> EngineResult<usize> {
+        let policy = self.policy.ok_or(ErrorType::FaucetDisabled)?;
+        if let Some(last) = self.claims.get(&portfolio_id) {
+            let next_tick = last + policy.cooldown_ticks;
+            if tick < next_tick {
+                return Err(ErrorType::FaucetCoolingDown { next_tick });
+            }
+        }
+        if self.paid + policy.amount > policy.budget {
+            return Err(ErrorType::FaucetBudgetExhausted);
+        }
+        Ok(policy.amount)
+    }
+
+    pub fn record(&mut self, portfolio_id: PortfolioId, amount: usize, tick: u64) {
+        self.paid += amount;
+        self.claims.insert(portfolio_id, tick);
+    }
+
+    pub fn status(&self) -> FaucetStatus {
+        FaucetStatus {
+            policy: self.policy,
+            paid: self.paid,
+            budget_left: self.policy.map_or(0, |p| p.budget.saturating_sub(self.paid)),
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::error::{checked_mul, EngineResult};
+
+// From `min_volume` of traded notional on, made and taken together, these rates replace the base ones.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct VolumeTier {
+    pub min_volume: usize,
+    pub taker_fee_basis_points: usize,
+    pub maker_rebate_basis_points: usize,
+}
+
+// 100 basis points are one percent of a trade's value
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    // billed for every event, per order for batches
+    pub event_fee: usize,
+    pub taker_fee_basis_points: usize,
+    // paid out of the taker fee to the resting side
+    pub maker_rebate_basis_points: usize,
+    // of the event fee an order paid, handed back by the bank when it's cancelled before any fill
+    #[serde(default)]
+    pub cancel_refund_percent: usize,
+    #[serde(default)]
+    pub volume_tiers: Vec<VolumeTier>,
+}
+
+impl Default for FeeSchedule {
+    fn default() -> FeeSchedule {
+        FeeSchedule {
+            event_fee: 1,
+            taker_fee_basis_points: 0,
+            maker_rebate_basis_points: 0,
+            cancel_refund_percent: 0,
+            volume_tiers: vec![],
+        }
+    }
+}
+
+impl FeeSchedule {
+    // the taker fee and maker rebate rates for a portfolio that traded `volume` so far, see `Activity::volume`
+    pub fn rates(&self, volume: usize) -> (usize, usize) {
+        self.volume_tiers.iter()
+            .filter(|tier| volume >= tier.min_volume)
+            .max_by_key(|tier| tier.min_volume)
+            .map_or((self.taker_fee_basis_points, self.maker_rebate_basis_points),
+                    |tier| (tier.taker_fee_basis_points, tier.maker_rebate_basis_points))
+    }
+
+    // The taker fee and the rebate to the maker for a trade leg, each at the rate of its own volume
+    // tier. The rebate never exceeds the fee.
+    pub fn trade_fees(&self, notional: usize, taker_volume: usize, maker_volume: usize) -> EngineResult<(usize, usize)> {
+        let taker_fee = checked_mul(notional, self.rates(taker_volume).0)? / 10000;
+        let rebate = (checked_mul(notional, self.rates(maker_volume).1)? / 10000).min(taker_fee);
+        Ok((taker_fee, rebate))
+    }
+
+    // never more than the fee itself
+    pub fn cancel_refund(&self, fee_paid: usize) -> usize {
+        fee_paid * self.cancel_refund_percent.min(100) / 100
+    }
+}
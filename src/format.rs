@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use crate::market::{AssetId, Market};
+
+// more than that and a usize price can't hold a sensible integer part
+pub const MAX_DECIMALS: u32 = 8;
+
+// How a number is written out. `Plain` has no thousands separator, which keeps it safe for CSV.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Locale {
+    Plain,
+    // 1,234.56
+    En,
+    // 1.234,56
+    De,
+    // 1 234,56
+    Fr,
+    // 1'234.56
+    Ch,
+}
+
+impl Locale {
+    pub fn parse(locale: &str) -> Option<Locale> {
+        // en-US, de_CH and the like, only the language or region we know matters
+        let lower = locale.to_lowercase();
+        if lower == "plain" {
+            return Some(Locale::Plain);
+        }
+        let mut parts = lower.split(['-', '_']);
+        let language = parts.next()?;
+        if parts.next() == Some("ch") {
+            return Some(Locale::Ch);
+        }
+        match language {
+            "en" => Some(Locale::En),
+            "de" | "nl" | "it" | "es" => Some(Locale::De),
+            "fr" => Some(Locale::Fr),
+            _ => None,
+        }
+    }
+
+    fn separators(&self) -> (Option<char>, char) {
+        match self {
+            Locale::Plain => (None, '.'),
+            Locale::En => (Some(','), '.'),
+            Locale::De => (Some('.'), ','),
+            Locale::Fr => (Some(' '), ','),
+            Locale::Ch => (Some('\''), '.'),
+        }
+    }
+}
+
+// `amount` is fixed-point with `decimals` places, 12345 with 2 decimals is 123.45.
+pub fn format_amount(amount: usize, decimals: u32, locale: Locale) -> String {
+    let (thousands, point) = locale.separators();
+    let scale = 10usize.pow(decimals.min(MAX_DECIMALS));
+    let integer = group(amount / scale, thousands);
+    if scale == 1 {
+        return integer;
+    }
+    format!("{}{}{:0width$}", integer, point, amount % scale, width = decimals as usize)
+}
+
+// whole units, quantities and the like
+pub fn format_quantity(quantity: usize, locale: Locale) -> String {
+    format_amount(quantity, 0, locale)
+}
+
+fn group(value: usize, separator: Option<char>) -> String {
+    let digits = value.to_string();
+    let separator = match separator {
+        Some(separator) => separator,
+        None => return digits,
+    };
+    // groups of three from the right, the digits are ASCII
+    let mut groups: Vec<&str> = digits.as_bytes().rchunks(3)
+        .map(|group| std::str::from_utf8(group).unwrap_or(""))
+        .collect();
+    groups.reverse();
+    groups.join(&separator.to_string())
+}
+
+// Knows every asset's decimals, so the CSV exports and the renderers write prices the same way.
+#[derive(Clone, Debug)]
+pub struct Formatter {
+    pub locale: Locale,
+    decimals: HashMap<AssetId, u32>,
+}
+
+impl Formatter {
+    pub fn new(market: &Market, locale: Locale) -> Formatter {
+        Formatter {
+            locale,
+            decimals: market.assets.values().map(|asset| (asset.id, asset.decimals)).collect(),
+        }
+    }
+
+    pub fn with_locale(&self, locale: Locale) -> Formatter {
+        Formatter { locale, ..self.clone() }
+    }
+
+    pub fn decimals(&self, asset: AssetId) -> u32 {
+        self.decimals.get(&asset).cloned().unwrap_or(0)
+    }
+
+    pub fn price(&self, asset: AssetId, price: usize) -> String {
+        format_amount(price, self.decimals(asset), self.locale)
+    }
+
+    pub fn quantity(&self, quantity: usize) -> String {
+        format_quantity(quantity, self.locale)
+    }
+}
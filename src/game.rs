@@ -1,105 +1,1664 @@
-use crate::models::{Engine, Portfolio, PortfolioId, AssetId, Asset, Account, Book, EngineResult, ErrorType, OrderMode, OrderSide, Order};
+use crate::error::{checked_add, checked_mul, EngineResult, ErrorType};
+use crate::order::{Event, MinimumRestingTime, OrderId, OrderMode, OrderSide, Order};
+use crate::book::{BookDelta, DarkPriority, OpenInterest, QueuePosition};
+use crate::account::{PortfolioId, Reservation};
+use crate::market::{AssetId, Asset, Market, Notification, Position, ReferencePricePolicy};
+use crate::engine::Engine;
 use uuid::Uuid;
-use std::sync::{Arc, RwLock, RwLockWriteGuard, RwLockReadGuard};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+use std::collections::BTreeMap;
+use crate::history::{BookHistory, BookSnapshot};
+use crate::stream::StreamHub;
+use crate::journal::JournalRecord;
+use crate::marketdata::{self, Level, SequencedMarketData};
+use crate::render::{self, RenderFormat};
+use crate::format::{Formatter, Locale};
+use crate::alerts::{Alert, AlertRule, FiredAlert};
+use crate::news::{NewsImpact, NewsItem};
+use crate::npc::run_market_makers;
+use crate::risk::RiskMetrics;
+use crate::leaderboard::{self, LeaderboardEntry, ScoringMetric};
+use crate::trades::{ExecutionReport, Trade};
+use crate::bankruptcy::{run_bankruptcy_rules, BankruptcyRule};
+use crate::ledger::{InterestPolicy, LedgerEntry};
+use crate::bank::MonetaryStats;
+use crate::queue::{EventQueue, QueueMetrics};
+use crate::config::GameConfig;
+use crate::heartbeat::{run_heartbeats, Heartbeat};
+use crate::schedule::{run_schedules, RecurringOrder};
+use crate::dutch::{run_dutch_auctions, DutchAuction, DutchAuctionTerms};
+use crate::algo::{run_algos, AlgoStatus, AlgoTerms, ParentOrder};
+use crate::fees::FeeSchedule;
+use crate::pricing::{to_coins, PriceMode};
+use crate::watchlist::WatchQuote;
+use crate::lifecycle::OrderRecord;
+use crate::sandbox::{SandboxFill, SandboxPortfolio};
+use crate::auction::{Clearing, MarketStructure};
+use crate::faucet::{FaucetPolicy, FaucetStatus};
+use crate::actor::{self, Actor, IssuedToken};
+use crate::suspension::Suspension;
+use crate::penalty::{PenaltyRule, PenaltyStanding};
+use crate::profile::ProfileReport;
+use crate::bootstrap::{plan_allocations, Allocation, BootstrapPlan};
+use crate::debug::{self, DebugState};
+use crate::dto::BookDto;
+use crate::journal::JournalEntry;
+use crate::statement::Statement;
+use crate::seed::{parse_book_csv, BookSeed, SeedLevel};
+use crate::roster::{Credential, RosterEntry, MAX_BULK_PORTFOLIOS};
+use crate::tape::{TapePrint, DEFAULT_TAPE_PAGE, MAX_TAPE_PAGE};
+use crate::oracle::{Oracle, OracleFeed, OracleSource};
+use crate::activity::ActivityStats;
+use crate::health::{Health, HealthLog, Recovery, RecoveryOutcome};
+use crate::latency::LatencyPolicy;
+use crate::colocation::{Colocation, PriorityClass};
+use crate::cache::{BookCache, CacheMetrics, CacheStamp, CachedView};
+use crate::settlement::{PendingSettlement, SettlementPolicy};
+use crate::page::PageQuery;
+use crate::rebalance::{self, ClosePosition, RebalanceLeg, TargetWeights};
+use crate::stream::GameEvent;
+use crate::bus::{DomainEvent, DomainEventKind, EventBus, EventCounts};
+use crate::rules::AssetRules;
+use crate::console::{ConsoleFeed, Overview};
+use crate::providers::Timestamp;
+use crate::store::PortfolioStore;
+use crate::scenario::{run_scenario, Recording, Scenario, ScenarioPlayer, ScenarioStatus};
+use crate::report::GameReport;
+use crate::upload::parse_order_csv;
+use crate::account::Portfolio;
+use std::thread::{self, JoinHandle};
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
 
+#[derive(Clone)]
 pub struct Game {
     pub engine: Arc<RwLock<Engine>>,
+    pub stream: Arc<StreamHub>,
+    pub queue: Arc<EventQueue>,
+    // how long callers wait for the engine lock before giving up
+    pub lock_timeout: Duration,
+    // length of a statement period, a day at one tick per second
+    pub statement_period_ticks: u64,
+    // leader at the last tick, to tell when the lead changes hands
+    pub last_leader: Arc<Mutex<Option<PortfolioId>>>,
+    // whom this handle journals for, see `acting_as`
+    pub actor: Actor,
+    pub oracle: Arc<Oracle>,
+    // the panics the engine was recovered from, see `recover`
+    pub health: Arc<Mutex<HealthLog>>,
+    // a writer panicked with the lock held, the lock doesn't keep track of that itself
+    pub poisoned: Arc<AtomicBool>,
+    // serialized public books and market data, see `cached`
+    pub cache: Arc<BookCache>,
+    // where the engine's trades, orders, cancels and fees go out, see `Game::wire_bus`
+    pub bus: Arc<EventBus>,
+    pub event_counts: Arc<EventCounts>,
+    // what the admin console shows, see `console`
+    pub console: Arc<ConsoleFeed>,
+    // the portfolios as of the last write, read without the engine lock
+    pub portfolios: Arc<PortfolioStore>,
 }
 
+// The engine's write lock. Letting go of it catches the portfolio store up with what was written.
+pub struct EngineWriteGuard<'a> {
+    engine: RwLockWriteGuard<'a, Engine>,
+    portfolios: &'a PortfolioStore,
+    poisoned: &'a AtomicBool,
+}
+
+impl<'a> Deref for EngineWriteGuard<'a> {
+    type Target = Engine;
+
+    fn deref(&self) -> &Engine {
+        &self.engine
+    }
+}
+
+impl<'a> DerefMut for EngineWriteGuard<'a> {
+    fn deref_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+}
+
+impl<'a> Drop for EngineWriteGuard<'a> {
+    fn drop(&mut self) {
+        if thread::panicking() {
+            self.poisoned.store(true, Ordering::SeqCst);
+            return;
+        }
+        let _ = self.portfolios.catch_up(&self.engine.market);
+    }
+}
+
+const DEFAULT_LOCK_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_STATEMENT_PERIOD_TICKS: u64 = 86400;
+
+
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
 
 impl Game {
     pub fn new() -> Game  {
+        let stream = Arc::new(StreamHub::new());
+        let event_counts = Arc::new(EventCounts::new());
+        let console = Arc::new(ConsoleFeed::new(stream.clone()));
         Game {
-            engine: Arc::new(RwLock::new(Engine::new()))
+            engine: Arc::new(RwLock::new(Engine::new())),
+            bus: Game::wire_bus(&stream, &event_counts, &console),
+            stream,
+            event_counts,
+            console,
+            queue: Arc::new(EventQueue::new()),
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            statement_period_ticks: DEFAULT_STATEMENT_PERIOD_TICKS,
+            last_leader: Arc::new(Mutex::new(None)),
+            actor: Actor::Anonymous,
+            oracle: Arc::new(Oracle::new()),
+            health: Arc::new(Mutex::new(HealthLog::default())),
+            poisoned: Arc::new(AtomicBool::new(false)),
+            cache: Arc::new(BookCache::new()),
+            portfolios: Arc::new(PortfolioStore::default()),
         }
     }
 
-    pub fn read_engine(&self) -> EngineResult<RwLockReadGuard<Engine>> {
-        self.engine.read().map_err(|_| ErrorType::EngineWasTooBusy)
+    pub fn process(&self, event: Event) -> EngineResult<()> {
+        let (result, notifications, events) = {
+            let mut engine = self.write_engine()?;
+            let assets = event.assets();
+            let delisting = match event {
+                Event::DelistAsset(asset, price) => Some(DomainEvent::Game(GameEvent::AssetDelisted { asset, price })),
+                _ => None,
+            };
+            let portfolio = event.portfolio();
+            let result = engine.process(event);
+            for asset in &assets {
+                if let Ok(book) = engine.market.get_order_book(*asset) {
+                    self.stream.publish_market_data(book);
+                }
+            }
+            let quotes = engine.watchlists.notifications(&engine.market, &assets);
+            engine.market.notifications.extend(quotes);
+            let notifications = engine.take_notifications();
+            let mut events = engine.take_domain_events();
+            match &result {
+                Ok(_) => events.extend(delisting),
+                Err(e) => events.push(DomainEvent::EventRejected { portfolio, assets, error: format!("{:?}", e) }),
+            }
+            (result, notifications, events)
+        };
+        self.stream.publish_notifications(notifications);
+        self.bus.publish(events);
+        result
     }
 
-    pub fn write_engine(&self) -> EngineResult<RwLockWriteGuard<Engine>> {
-        self.engine.write().map_err(|_| ErrorType::EngineWasTooBusy)
+    // The stream's tape, the game's metrics and the console hear from the bus, webhooks subscribe as
+    // they're added.
+    fn wire_bus(stream: &Arc<StreamHub>, event_counts: &Arc<EventCounts>, console: &Arc<ConsoleFeed>) -> Arc<EventBus> {
+        let bus = EventBus::new();
+        bus.subscribe(Box::new(stream.clone())).unwrap();
+        bus.subscribe(Box::new(event_counts.clone())).unwrap();
+        bus.subscribe(Box::new(console.clone())).unwrap();
+        Arc::new(bus)
     }
 
-    pub fn create_portfolio(&mut self, initial_coins: usize) -> PortfolioId {
-        let mut portfolio = Portfolio::new(initial_coins);
-        let mut engine = self.write_engine().unwrap();
-        engine.market.assets.values().for_each(|asset|{
-           portfolio.assets.insert(asset.id, Account::new(0));
+    // Processes queued events one at a time until the queue breaks down.
+    pub fn start_worker(&self) -> JoinHandle<()> {
+        let game = self.clone();
+        thread::spawn(move || {
+            while let Ok(job) = game.queue.next() {
+                let result = game.acting_as(job.actor.clone()).process(job.event.clone());
+                job.reply(result);
+            }
+        })
+    }
+
+    // The same game, journaling what goes through this handle for `actor`.
+    pub fn acting_as(&self, actor: Actor) -> Game {
+        Game {
+            actor,
+            ..self.clone()
+        }
+    }
+
+    // Goes through the queue, so a worker needs to be running.
+    pub fn submit(&self, event: Event) -> EngineResult<()> {
+        self.queue.submit(event, self.actor.clone())?
+            .recv()
+            .map_err(|_| ErrorType::EngineWasTooBusy)?
+    }
+
+    pub fn get_queue_metrics(&self) -> EngineResult<QueueMetrics> {
+        self.queue.get_metrics()
+    }
+
+    pub fn get_console_overview(&self) -> EngineResult<Overview> {
+        let (tick, paused, halted) = {
+            let engine = self.read_engine()?;
+            (engine.market.tick, engine.market.paused, engine.market.halted.iter().cloned().collect())
+        };
+        self.console.overview(tick, paused, self.get_queue_metrics()?, halted)
+    }
+
+    // not journaled, timing the events doesn't change them
+    pub fn set_profiling(&self, enabled: bool) -> EngineResult<()> {
+        self.write_engine()?.set_profiling(enabled);
+        Ok(())
+    }
+
+    // Freezes the game: events are turned down with GamePaused, ticks stop and so do the orders' lifetimes.
+    pub fn set_paused(&self, paused: bool) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetPaused(paused))
+    }
+
+    pub fn get_game_clock(&self) -> EngineResult<GameClock> {
+        let mut engine = self.write_engine()?;
+        engine.sync_clock();
+        Ok(GameClock {
+            game_id: engine.market.game_id,
+            tick: engine.market.tick,
+            game_time: engine.market.game_time,
+            paused: engine.market.paused,
+            ended: engine.market.ended,
+        })
+    }
+
+    // Expires every order in the books and stops the game for good, events get GameEnded and ticks stop.
+    // The game's report is built as it ends.
+    pub fn end_game(&self) -> EngineResult<()> {
+        let (result, notifications, events) = {
+            let mut engine = self.write_engine()?;
+            if engine.market.ended {
+                return Err(ErrorType::GameEnded);
+            }
+            let result = engine.apply(JournalRecord::EndGame);
+            if result.is_ok() {
+                engine.report = GameReport::build(&engine).ok();
+            }
+            for book in engine.market.books.values() {
+                self.stream.publish_market_data(book);
+            }
+            let notifications = engine.take_notifications();
+            (result, notifications, engine.take_domain_events())
+        };
+        self.stream.publish_notifications(notifications);
+        self.bus.publish(events);
+        result?;
+        self.bus.publish(vec![DomainEvent::Game(GameEvent::GameEnded)]);
+        Ok(())
+    }
+
+    // the one built as the game ended, built now for a game that ended before it was loaded
+    pub fn get_report(&self, game_id: Uuid) -> EngineResult<GameReport> {
+        let engine = self.read_engine()?;
+        if engine.market.game_id != game_id {
+            return Err(ErrorType::GameNotFound(game_id));
+        }
+        if !engine.market.ended {
+            return Err(ErrorType::GameNotEnded);
+        }
+        match &engine.report {
+            Some(report) => Ok(report.clone()),
+            None => GameReport::build(&engine),
+        }
+    }
+
+    // `received_at` is when the request came in, on the server's clock
+    pub fn get_server_time(&self, client_time: Option<Timestamp>, received_at: Timestamp) -> EngineResult<ServerTime> {
+        let clock = self.get_game_clock()?;
+        let server_time = self.read_engine()?.clock.now();
+        let handshake = client_time.map(|client_time| ClockHandshake {
+            client_time,
+            received_at,
+            offset_millis: received_at as i64 - client_time as i64,
         });
-        let id = portfolio.id;
-        engine.market.portfolios.insert(id,portfolio );
-        return id;
+        Ok(ServerTime { server_time, clock, handshake })
     }
 
-    pub fn create_asset(&mut self, name: String) -> AssetId {
-        let asset = Asset::new(name);
-        let id = asset.id;
+    pub fn get_profile(&self) -> EngineResult<ProfileReport> {
+        Ok(match &self.read_engine()?.profiler {
+            Some(profiler) => profiler.report(),
+            None => ProfileReport { enabled: false, events: 0, phases: vec![] },
+        })
+    }
+
+    pub fn advance_tick(&self) -> EngineResult<()> {
+        // sampled before taking the engine, a linked game may be waiting for this one
+        let tick = self.read_engine()?.market.tick + 1;
+        let oracle_prices = self.oracle.prices(tick)?;
+        let (notifications, events) = {
+            let mut engine = self.write_engine()?;
+            if engine.market.paused || engine.market.ended {
+                return Ok(());
+            }
+            engine.acting_as(Actor::System, |engine| engine.advance_tick());
+            engine.acting_as(Actor::System, |engine| engine.apply_oracle_prices(&oracle_prices));
+            let mut expired = engine.acting_as(Actor::System, |engine| engine.expire_orders());
+            expired.extend(engine.acting_as(Actor::System, |engine| engine.expire_settlements()));
+            engine.acting_as(Actor::bot("market-maker"), run_market_makers);
+            engine.acting_as(Actor::bot("schedule"), run_schedules);
+            engine.acting_as(Actor::bot("dutch-auction"), run_dutch_auctions);
+            engine.acting_as(Actor::bot("algo"), run_algos);
+            engine.acting_as(Actor::bot("bankruptcy"), run_bankruptcy_rules);
+            engine.acting_as(Actor::bot("heartbeat"), |engine| run_heartbeats(engine, |id| self.stream.is_owner_connected(id)));
+            engine.acting_as(Actor::bot("scenario"), run_scenario);
+            engine.capture_book_history();
+            // the auctions at the end of the tick may have changed any book
+            let mut assets: Vec<AssetId> = match engine.market.market_structure {
+                MarketStructure::BatchAuction => engine.market.assets.keys().cloned().collect(),
+                MarketStructure::Continuous => engine.market_makers.iter().map(|mm| mm.asset).collect(),
+            };
+            assets.extend(expired.into_iter().filter(|asset| !assets.contains(asset)).collect::<Vec<AssetId>>());
+            for asset in &assets {
+                if let Ok(book) = engine.market.get_order_book(*asset) {
+                    self.stream.publish_market_data(book);
+                }
+            }
+            let quotes = engine.watchlists.notifications(&engine.market, &assets);
+            engine.market.notifications.extend(quotes);
+            let notifications = engine.take_notifications();
+            (notifications, engine.take_domain_events())
+        };
+        self.stream.publish_notifications(notifications);
+        self.bus.publish(events);
+        if self.bus.has_consumers(DomainEventKind::Game) {
+            self.publish_leader_change()?;
+        }
+        Ok(())
+    }
+
+    fn publish_leader_change(&self) -> EngineResult<()> {
+        let leader = match self.get_leaderboard(None)?.first() {
+            Some(entry) => entry.portfolio,
+            None => return Ok(()),
+        };
+        let mut last_leader = self.last_leader.lock().map_err(|_| ErrorType::InvalidState)?;
+        if *last_leader != Some(leader) {
+            self.bus.publish(vec![DomainEvent::Game(GameEvent::LeaderChanged { leader, previous: *last_leader })]);
+            *last_leader = Some(leader);
+        }
+        Ok(())
+    }
+
+    pub fn read_engine(&self) -> EngineResult<RwLockReadGuard<'_, Engine>> {
+        self.try_read_for(self.lock_timeout)
+    }
+
+    pub fn write_engine(&self) -> EngineResult<EngineWriteGuard<'_>> {
+        self.try_write_for(self.lock_timeout)
+    }
+
+    // Waits at most `timeout` so a slow matching cycle can't stall every caller forever. The lock is
+    // fair, readers queue up behind a waiting writer instead of starving it.
+    pub fn try_read_for(&self, timeout: Duration) -> EngineResult<RwLockReadGuard<'_, Engine>> {
+        loop {
+            let guard = self.engine.try_read_for(timeout).ok_or_else(|| self.timeout_error(timeout))?;
+            if !self.poisoned.load(Ordering::SeqCst) {
+                return Ok(guard);
+            }
+            // recovering needs the lock to itself
+            drop(guard);
+            self.recover()?;
+        }
+    }
+
+    pub fn try_write_for(&self, timeout: Duration) -> EngineResult<EngineWriteGuard<'_>> {
+        loop {
+            let mut engine = self.engine.try_write_for(timeout).ok_or_else(|| self.timeout_error(timeout))?;
+            if !self.poisoned.load(Ordering::SeqCst) {
+                engine.actor = self.actor.clone();
+                return Ok(EngineWriteGuard { engine, portfolios: &self.portfolios, poisoned: &self.poisoned });
+            }
+            drop(engine);
+            self.recover()?;
+        }
+    }
+
+    // A panic while the engine was locked marks it poisoned and may have left an event half applied, so
+    // the engine is rebuilt from the journal without the entry that panicked before the mark is
+    // cleared. Whatever happened is logged for `GET /health`, and once recovering failed it isn't
+    // tried again.
+    fn recover(&self) -> EngineResult<()> {
+        let mut health = self.health.lock().map_err(|_| ErrorType::EngineUnavailable)?;
+        if health.failed {
+            return Err(ErrorType::EngineUnavailable);
+        }
+        let mut engine = self.engine.write();
+        // someone else recovered it meanwhile
+        if !self.poisoned.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+        // the check runs into whatever broken state made it panic as often as not
+        let issues = panic::catch_unwind(AssertUnwindSafe(|| debug::dump(&engine.market, engine.sequence, &[]).issues)).ok();
+        let outcome = match engine.restore_from_journal() {
+            Ok(dropped_sequence) => {
+                self.cache.clear()?;
+                self.portfolios.resync(&engine.market)?;
+                RecoveryOutcome::RestoredFromJournal { dropped_sequence }
+            },
+            Err(e) => RecoveryOutcome::Failed { reason: format!("{:?}", e) },
+        };
+        let failed = matches!(outcome, RecoveryOutcome::Failed { .. });
+        health.record(Recovery { timestamp: engine.clock.now(), sequence: engine.sequence, issues, outcome });
+        if failed {
+            return Err(ErrorType::EngineUnavailable);
+        }
+        self.poisoned.store(false, Ordering::SeqCst);
+        Ok(())
+    }
+
+    pub fn get_health(&self) -> EngineResult<Health> {
+        let log = self.health.lock().map_err(|_| ErrorType::EngineUnavailable)?.clone();
+        let position = self.read_engine().ok().map(|engine| (engine.sequence, engine.market.tick));
+        Ok(Health {
+            status: log.status(),
+            sequence: position.map(|(sequence, _)| sequence),
+            tick: position.map(|(_, tick)| tick),
+            recoveries: log.recoveries,
+        })
+    }
+
+    fn timeout_error(&self, waited: Duration) -> ErrorType {
+        let queue_latency_millis = self.queue.get_metrics()
+            .map(|m| m.low.average_wait_micros().max(m.high.average_wait_micros()) / 1000)
+            .unwrap_or(0);
+        ErrorType::EngineTimeout {
+            waited_millis: waited.as_millis() as u64,
+            queue_latency_millis,
+        }
+    }
+
+    pub fn from_engine(engine: Engine) -> Game {
+        let queue = EventQueue::new();
+        // a restored game delays events as it did before
+        queue.set_latency(engine.market.latency.clone()).unwrap();
+        let (names, classes) = engine.market.colocation.ranks();
+        queue.set_priority_classes(names, classes).unwrap();
+        let stream = Arc::new(StreamHub::new());
+        let event_counts = Arc::new(EventCounts::new());
+        let console = Arc::new(ConsoleFeed::new(stream.clone()));
+        let portfolios = Arc::new(PortfolioStore::default());
+        portfolios.resync(&engine.market).unwrap();
+        Game {
+            engine: Arc::new(RwLock::new(engine)),
+            bus: Game::wire_bus(&stream, &event_counts, &console),
+            stream,
+            event_counts,
+            console,
+            queue: Arc::new(queue),
+            lock_timeout: DEFAULT_LOCK_TIMEOUT,
+            statement_period_ticks: DEFAULT_STATEMENT_PERIOD_TICKS,
+            last_leader: Arc::new(Mutex::new(None)),
+            actor: Actor::Anonymous,
+            oracle: Arc::new(Oracle::new()),
+            health: Arc::new(Mutex::new(HealthLog::default())),
+            poisoned: Arc::new(AtomicBool::new(false)),
+            cache: Arc::new(BookCache::new()),
+            portfolios,
+        }
+    }
+
+    // Forks the portfolio's coins, holdings and PnL into a new one. Orders and reservations stay
+    // behind, reserved amounts are free in the copy.
+    pub fn clone_portfolio(&self, source: PortfolioId) -> EngineResult<PortfolioId> {
+        let mut engine = self.write_engine()?;
+        let id = engine.ids.next_id();
+        engine.apply(JournalRecord::ClonePortfolio { source, id })?;
+        Ok(id)
+    }
+
+    pub fn create_portfolio(&mut self, initial_coins: usize) -> PortfolioId {
         let mut engine = self.write_engine().unwrap();
-        engine.market.assets.insert(id, asset);
-        engine.market.portfolios.values_mut().for_each(|portfolio|{
-            portfolio.assets.insert(id, Account::new(0));
-        });
-        engine.market.books.insert(id, Book::new(id));
-        return id;
+        let id = engine.ids.next_id();
+        engine.apply(JournalRecord::CreatePortfolio { id, initial_coins }).unwrap();
+        id
+    }
+
+    // A portfolio and a fresh API token for every name, the tokens are only in the returned sheet.
+    pub fn provision_portfolios(&self, names: Vec<String>, coins: Option<usize>) -> EngineResult<Vec<Credential>> {
+        if names.is_empty() || names.len() > MAX_BULK_PORTFOLIOS {
+            return Err(ErrorType::InvalidBulkProvisioning { max: MAX_BULK_PORTFOLIOS });
+        }
+        let mut engine = self.write_engine()?;
+        let coins = coins.unwrap_or(engine.market.starting_coins);
+        let mut entries = vec![];
+        let mut credentials = vec![];
+        for name in names {
+            let portfolio = engine.ids.next_id();
+            let token = engine.ids.next_id().to_simple().to_string();
+            entries.push(RosterEntry { portfolio, name: name.clone(), fingerprint: actor::fingerprint(&token) });
+            credentials.push(Credential { name, portfolio, token, coins });
+        }
+        engine.apply(JournalRecord::ProvisionPortfolios { coins, entries })?;
+        Ok(credentials)
+    }
+
+    pub fn get_roster(&self) -> EngineResult<Vec<RosterEntry>> {
+        Ok(self.read_engine()?.market.roster.clone())
+    }
+
+    pub fn create_asset(&mut self, name: String) -> EngineResult<AssetId> {
+        let mut engine = self.write_engine()?;
+        let id = engine.ids.next_id();
+        engine.apply(JournalRecord::CreateAsset { id, name })?;
+        Ok(id)
+    }
+
+    pub fn new_order(&self,
+                     portfolio: PortfolioId,
+                     asset: AssetId,
+                     side: OrderSide,
+                     quantity: usize,
+                     mode: OrderMode,
+                     expires_in: Duration,
+    ) -> EngineResult<Order> {
+        let stamp = self.read_engine()?.stamp();
+        let expires = stamp.created_at + expires_in.as_millis() as u64;
+        Order::new(stamp, portfolio, asset, side, quantity, mode, expires)
+    }
+
+    // the sell that flattens the position, see `rebalance::plan_close`
+    pub fn plan_close(&self, portfolio_id: PortfolioId, asset_id: AssetId, close: &ClosePosition, expires_in: Duration) -> EngineResult<Order> {
+        let leg = rebalance::plan_close(&self.read_engine()?.market, portfolio_id, asset_id, close)?;
+        self.new_order(portfolio_id, leg.asset, leg.side, leg.quantity, leg.mode, expires_in)
+    }
+
+    // Orders towards the target weights, meant to be run together as one atomic batch.
+    pub fn plan_rebalance(&self, portfolio_id: PortfolioId, targets: &TargetWeights, expires_in: Duration) -> EngineResult<Vec<(Order, RebalanceLeg)>> {
+        let legs = {
+            let engine = self.read_engine()?;
+            rebalance::plan(&engine.market, portfolio_id, targets)?
+        };
+        let mut orders = vec![];
+        for leg in legs {
+            orders.push((self.new_order(portfolio_id, leg.asset, leg.side, leg.quantity, leg.mode, expires_in)?, leg));
+        }
+        Ok(orders)
+    }
+
+    // The rows of an uploaded CSV as orders of the portfolio, meant to be run together as one atomic
+    // batch. Rows that don't make an order are answered with all their problems, see `parse_order_csv`.
+    pub fn plan_upload(&self, portfolio_id: PortfolioId, csv: &str, expires_in: Duration) -> EngineResult<Vec<Order>> {
+        let rows = {
+            let engine = self.read_engine()?;
+            engine.market.get_portfolio(portfolio_id)?;
+            parse_order_csv(csv, |name| engine.market.get_asset_by_name(name).ok().map(|a| a.id))
+                .map_err(ErrorType::InvalidFields)?
+        };
+        let mut orders = vec![];
+        for row in rows {
+            orders.push(self.new_order(portfolio_id, row.asset, row.side, row.quantity, row.mode, expires_in)?);
+        }
+        Ok(orders)
+    }
+
+    pub fn get_asset_by_name(&self, name: &str) -> EngineResult<Asset> {
+        self.read_engine()?.market.get_asset_by_name(name).cloned()
+    }
+
+    // Hands out the supply of each asset as the plan says, before the first trade only. Random
+    // shares are drawn here and journaled as the resulting allocations.
+    pub fn bootstrap(&self, plan: &BootstrapPlan) -> EngineResult<Vec<Allocation>> {
+        let mut engine = self.write_engine()?;
+        let mut players: Vec<PortfolioId> = engine.market.portfolios.values()
+            .filter(|p| p.bankrupt_since.is_none() && engine.is_player(p.id))
+            .map(|p| p.id)
+            .collect();
+        players.sort();
+        let allocations = plan_allocations(plan, players, engine.market.price_mode)?;
+        engine.apply(JournalRecord::Allocate(allocations.clone()))?;
+        Ok(allocations)
     }
 
     pub fn set_asset_amount(&mut self, portfolio: Uuid,  asset: Uuid, amount: usize) {
-        self.write_engine().unwrap().market.portfolios.get_mut(&portfolio).unwrap()
-            .assets.get_mut(&asset).unwrap().add(amount);
+        self.write_engine().unwrap()
+            .apply(JournalRecord::SetAssetAmount { portfolio, asset, amount }).unwrap();
+    }
+
+    pub fn configure_book_history(&self, interval: Duration, capacity: usize) -> EngineResult<()> {
+        self.write_engine()?.book_history = BookHistory::new(interval, capacity);
+        Ok(())
+    }
+
+    pub fn get_book_history(&self, asset_id: Uuid, from: Option<u64>, to: Option<u64>) -> EngineResult<Vec<BookSnapshot>> {
+        let engine = self.read_engine()?;
+        engine.market.get_asset(&asset_id)?;
+        Ok(engine.book_history.get_range(asset_id, from, to))
+    }
+
+    pub fn set_reference_price_policy(&self, policy: ReferencePricePolicy) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetReferencePricePolicy(policy))
+    }
+
+    // Only changes how its prices are shown, internally they stay integers.
+    pub fn set_asset_decimals(&self, asset_id: AssetId, decimals: u32) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetAssetDecimals { asset: asset_id, decimals })
+    }
+
+    pub fn set_fair_value(&self, asset_id: Uuid, price: usize) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetFairValue { asset: asset_id, price })
+    }
+
+    // What another game's oracle sees of the asset: its reference price, else the last trade, else the
+    // fair value.
+    pub fn get_reference_price(&self, asset_id: AssetId) -> EngineResult<Option<usize>> {
+        let engine = self.read_engine()?;
+        let market = &engine.market;
+        market.get_asset(&asset_id)?;
+        Ok(market.reference_price(asset_id)
+            .or_else(|| market.last_trade_prices.get(&asset_id).cloned())
+            .or_else(|| market.fair_values.get(&asset_id).cloned()))
+    }
+
+    // None takes the asset off the oracle, it keeps the fair value it has.
+    pub fn set_oracle(&self, asset_id: AssetId, source: Option<OracleSource>) -> EngineResult<()> {
+        self.read_engine()?.market.get_asset(&asset_id)?;
+        if let Some(OracleSource::Curve(curve)) = &source {
+            curve.check()?;
+        }
+        self.oracle.set(asset_id, source)
+    }
+
+    pub fn get_oracle_feeds(&self) -> EngineResult<Vec<OracleFeed>> {
+        let tick = self.read_engine()?.market.tick;
+        self.oracle.feeds(tick)
+    }
+
+    // the asset's oracle price right now, to settle at
+    pub fn get_oracle_price(&self, asset_id: AssetId) -> EngineResult<usize> {
+        let tick = self.read_engine()?.market.tick;
+        let source = self.oracle.source(asset_id)?.ok_or(ErrorType::NoOraclePrice(asset_id))?;
+        source.price(tick)?.ok_or(ErrorType::NoOraclePrice(asset_id))
+    }
+
+    // from the portfolio store, matching doesn't hold them up
+    pub fn get_reservations(&self, portfolio_id: Uuid) -> EngineResult<Vec<Reservation>> {
+        let portfolio = self.portfolios.get(portfolio_id)?;
+        Ok(portfolio.reservations.values().cloned().collect())
+    }
+
+    // the portfolio and its unrealized PnL as of the last write, without waiting for the engine
+    pub fn get_portfolio(&self, portfolio_id: PortfolioId) -> EngineResult<(Arc<Portfolio>, i64)> {
+        let portfolio = self.portfolios.get(portfolio_id)?;
+        let unrealized_pnl = self.portfolios.unrealized_pnl(&portfolio)?;
+        Ok((portfolio, unrealized_pnl))
+    }
+
+    pub fn get_market_data(&self, asset_id: Uuid, level: Level) -> EngineResult<SequencedMarketData> {
+        let engine = self.read_engine()?;
+        let book = engine.market.get_order_book(asset_id)?;
+        Ok(SequencedMarketData::new(book, level, None))
+    }
+
+    // depth chart of the aggregated levels
+    pub fn render_book(&self, asset_id: Uuid, format: RenderFormat, locale: Locale) -> EngineResult<String> {
+        let engine = self.read_engine()?;
+        let book = engine.market.get_order_book(asset_id)?;
+        Ok(render::render(&marketdata::l2(book), format, &Formatter::new(&engine.market, locale)))
+    }
+
+    pub fn get_formatter(&self, locale: Locale) -> EngineResult<Formatter> {
+        Ok(Formatter::new(&self.read_engine()?.market, locale))
+    }
+
+    pub fn add_alert(&self, portfolio_id: Uuid, rule: AlertRule) -> EngineResult<Uuid> {
+        let mut engine = self.write_engine()?;
+        let id = engine.ids.next_id();
+        engine.apply(JournalRecord::AddAlert { portfolio: portfolio_id, id, rule })?;
+        Ok(id)
+    }
+
+    pub fn remove_alert(&self, portfolio_id: Uuid, alert_id: Uuid) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::RemoveAlert { portfolio: portfolio_id, id: alert_id })
+    }
+
+    pub fn get_alerts(&self, portfolio_id: Uuid) -> EngineResult<(Vec<Alert>, Vec<FiredAlert>)> {
+        let engine = self.read_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        Ok((engine.alerts.get_rules(portfolio_id), engine.alerts.get_fired(portfolio_id)))
+    }
+
+    pub fn post_news(&self, headline: String, impacts: Vec<NewsImpact>) -> EngineResult<Uuid> {
+        let mut engine = self.write_engine()?;
+        let item = NewsItem {
+            id: engine.ids.next_id(),
+            tick: engine.market.tick,
+            headline,
+            impacts,
+        };
+        let id = item.id;
+        engine.apply(JournalRecord::PostNews(item))?;
+        Ok(id)
+    }
+
+    pub fn get_news(&self) -> EngineResult<Vec<NewsItem>> {
+        Ok(self.read_engine()?.news.clone())
+    }
+
+    // Creates a portfolio for the NPC and lets it quote the asset from the next tick on.
+    pub fn add_market_maker(&mut self,
+                            asset: AssetId,
+                            fair_value: usize,
+                            spread_percent: usize,
+                            quantity: usize,
+                            coins: usize,
+                            holdings: usize,
+    ) -> EngineResult<PortfolioId> {
+        self.read_engine()?.market.get_asset(&asset)?;
+        let portfolio = self.create_portfolio(coins);
+        self.set_asset_amount(portfolio, asset, holdings);
+        self.write_engine()?.apply(JournalRecord::AddMarketMaker { portfolio, asset, fair_value, spread_percent, quantity })?;
+        Ok(portfolio)
+    }
+
+    pub fn get_risk_metrics(&self, asset_id: Uuid, window: usize) -> EngineResult<RiskMetrics> {
+        let engine = self.read_engine()?;
+        engine.market.get_asset(&asset_id)?;
+        let prices: Vec<usize> = engine.market.get_trades_for(asset_id).iter()
+            .filter(|t| !t.busted)
+            .map(|t| t.price)
+            .collect();
+        let snapshots = engine.book_history.get_all(asset_id);
+        Ok(RiskMetrics::compute(asset_id, &prices, &snapshots, window))
+    }
+
+    // Applies an admin intervention on the asset and publishes its book and the resulting notifications.
+    fn intervene(&self, asset_id: AssetId, record: JournalRecord) -> EngineResult<()> {
+        let (result, notifications, events) = {
+            let mut engine = self.write_engine()?;
+            let result = engine.apply(record);
+            if let Ok(book) = engine.market.get_order_book(asset_id) {
+                self.stream.publish_market_data(book);
+            }
+            let notifications = engine.take_notifications();
+            (result, notifications, engine.take_domain_events())
+        };
+        self.stream.publish_notifications(notifications);
+        self.bus.publish(events);
+        result
+    }
+
+    // Stops taking orders for the asset, or takes them again. Its book stays as it is, orders in it may
+    // still be cancelled.
+    pub fn set_asset_halted(&self, asset_id: AssetId, halted: bool) -> EngineResult<()> {
+        self.intervene(asset_id, JournalRecord::HaltAsset { asset: asset_id, halted })?;
+        self.bus.publish(vec![DomainEvent::Game(GameEvent::AssetHalted { asset: asset_id, halted })]);
+        Ok(())
+    }
+
+    pub fn pay_dividend(&self, asset_id: AssetId, per_unit: usize) -> EngineResult<()> {
+        if per_unit == 0 {
+            return Err(ErrorType::InvalidDividend);
+        }
+        self.intervene(asset_id, JournalRecord::PayDividend { asset: asset_id, per_unit })?;
+        self.bus.publish(vec![DomainEvent::Game(GameEvent::DividendPaid { asset: asset_id, per_unit })]);
+        Ok(())
+    }
+
+    pub fn force_cancel_order(&self, asset_id: AssetId, order_id: OrderId) -> EngineResult<()> {
+        self.intervene(asset_id, JournalRecord::ForceCancelOrder { asset: asset_id, order: order_id })
+    }
+
+    pub fn cancel_all_orders(&self, asset_id: AssetId) -> EngineResult<Vec<OrderId>> {
+        let order_ids: Vec<OrderId> = {
+            let engine = self.read_engine()?;
+            let book = engine.market.get_order_book(asset_id)?;
+            book.all_orders().map(|o| o.get_id()).collect()
+        };
+        self.intervene(asset_id, JournalRecord::CancelAllOrders { asset: asset_id })?;
+        Ok(order_ids)
+    }
+
+    pub fn bust_trade(&self, trade_id: u64) -> EngineResult<()> {
+        let asset_id = self.read_engine()?.market.get_trade(trade_id)?.asset;
+        self.intervene(asset_id, JournalRecord::BustTrade { trade: trade_id })
+    }
+
+    // Trades from now on wait to be confirmed, see `SettlementPolicy`. None settles what's pending.
+    pub fn set_settlement_policy(&self, policy: Option<SettlementPolicy>) -> EngineResult<()> {
+        if let Some(policy) = &policy {
+            policy.check()?;
+        }
+        let (result, notifications, events) = {
+            let mut engine = self.write_engine()?;
+            let result = engine.apply(JournalRecord::SetSettlementPolicy(policy));
+            let notifications = engine.take_notifications();
+            (result, notifications, engine.take_domain_events())
+        };
+        self.stream.publish_notifications(notifications);
+        self.bus.publish(events);
+        result
+    }
+
+    pub fn get_settlement_policy(&self) -> EngineResult<Option<SettlementPolicy>> {
+        Ok(self.read_engine()?.market.settlement.clone())
+    }
+
+    // oldest first
+    pub fn get_pending_settlements(&self) -> EngineResult<Vec<PendingSettlement>> {
+        Ok(self.read_engine()?.market.pending_settlements.values().cloned().collect())
+    }
+
+    pub fn confirm_settlement(&self, trade_id: u64) -> EngineResult<()> {
+        let asset_id = self.read_engine()?.market.get_trade(trade_id)?.asset;
+        self.intervene(asset_id, JournalRecord::ConfirmSettlement { trade: trade_id })
+    }
+
+    pub fn reject_settlement(&self, trade_id: u64) -> EngineResult<()> {
+        let asset_id = self.read_engine()?.market.get_trade(trade_id)?.asset;
+        self.intervene(asset_id, JournalRecord::RejectSettlement { trade: trade_id })
+    }
+
+    // every asset's trades, oldest first unless the query says otherwise
+    pub fn get_tape(&self, query: &PageQuery) -> EngineResult<(Vec<TapePrint>, Option<String>)> {
+        let query = PageQuery { limit: Some(query.limit.unwrap_or(DEFAULT_TAPE_PAGE).min(MAX_TAPE_PAGE)), ..query.clone() };
+        Ok(query.apply(self.read_engine()?.market.trades.iter().map(TapePrint::from).collect()))
+    }
+
+    pub fn get_trades(&self, asset_id: AssetId, query: &PageQuery) -> EngineResult<(Vec<Trade>, Option<String>)> {
+        let engine = self.read_engine()?;
+        engine.market.get_asset(&asset_id)?;
+        Ok(query.apply(engine.market.get_trades_for(asset_id).into_iter().cloned().collect()))
+    }
+
+    // Arms the portfolio's dead-man's switch or keeps it from firing.
+    pub fn heartbeat(&self, portfolio_id: PortfolioId, window: Option<Duration>) -> EngineResult<Heartbeat> {
+        let mut engine = self.write_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        engine.check_access(portfolio_id)?;
+        let now = engine.clock.now();
+        Ok(engine.heartbeats.beat(portfolio_id, window.map(|w| w.as_millis() as u64), now))
+    }
+
+    // The first order goes out `every_ticks` ticks from now.
+    pub fn schedule_order(&self, portfolio_id: PortfolioId, asset_id: AssetId, side: OrderSide, quantity: usize, mode: OrderMode, every_ticks: u64) -> EngineResult<RecurringOrder> {
+        let mut engine = self.write_engine()?;
+        let order = RecurringOrder {
+            id: engine.ids.next_id(),
+            portfolio: portfolio_id,
+            asset: asset_id,
+            side,
+            quantity,
+            mode,
+            every_ticks,
+            next_tick: engine.market.tick + every_ticks,
+            placed: 0,
+            last_order: None,
+        };
+        engine.apply(JournalRecord::ScheduleOrder(order))?;
+        Ok(order)
+    }
+
+    pub fn cancel_schedule(&self, portfolio_id: PortfolioId, schedule_id: Uuid) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::CancelSchedule { portfolio: portfolio_id, id: schedule_id })
+    }
+
+    pub fn set_watchlist(&self, portfolio_id: PortfolioId, assets: Vec<AssetId>) -> EngineResult<()> {
+        let mut engine = self.write_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        for asset in &assets {
+            engine.market.get_asset(asset)?;
+        }
+        engine.watchlists.set(portfolio_id, assets);
+        Ok(())
+    }
+
+    pub fn get_watchlist(&self, portfolio_id: PortfolioId) -> EngineResult<Vec<AssetId>> {
+        let engine = self.read_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        Ok(engine.watchlists.get(portfolio_id))
+    }
+
+    pub fn get_watchlist_quotes(&self, portfolio_id: PortfolioId) -> EngineResult<Vec<WatchQuote>> {
+        let engine = self.read_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        Ok(engine.watchlists.quotes(&engine.market, portfolio_id))
+    }
+
+    // also orders that left the book, filled, cancelled or rejected
+    pub fn get_order(&self, order_id: OrderId) -> EngineResult<OrderRecord> {
+        Ok(self.read_engine()?.orders.get(order_id)?.clone())
+    }
+
+    // What the order took from the book when it came in, none if it didn't trade on arrival.
+    pub fn get_execution(&self, order_id: OrderId) -> EngineResult<Option<ExecutionReport>> {
+        Ok(ExecutionReport::for_order(order_id, &self.read_engine()?.market.trades))
+    }
+
+    // The portfolio's resting order and how much stands before it at its price.
+    pub fn get_queue_position(&self, portfolio_id: PortfolioId, order_id: OrderId) -> EngineResult<QueuePosition> {
+        let engine = self.read_engine()?;
+        let record = engine.orders.get(order_id)?;
+        if record.portfolio != portfolio_id || record.state.is_final() {
+            return Err(ErrorType::OrderNotFound(order_id));
+        }
+        engine.market.get_order_book(record.asset)?.queue_position(order_id, engine.market.dark_priority)
     }
 
-    pub fn get_public_books(&self) -> EngineResult<Vec<PublicBook>> {
+    pub fn get_orders_of(&self, portfolio_id: PortfolioId) -> EngineResult<Vec<OrderRecord>> {
         let engine = self.read_engine()?;
-        engine.market.assets.values().map(|asset| {
-            engine.market.get_order_book(asset.id.clone())
-                .map(|b| PublicBook::from_book(asset.clone(), b))
-        }).collect()
+        engine.market.get_portfolio(portfolio_id)?;
+        Ok(engine.orders.get_for(portfolio_id).into_iter().cloned().collect())
     }
 
-    pub fn get_public_book_for(&self, asset_id: Uuid) -> EngineResult<PublicBook> {
+    // replaces the sandbox the player had, starting again from the real balances
+    pub fn spawn_sandbox(&self, owner: PortfolioId) -> EngineResult<SandboxPortfolio> {
+        let mut engine = self.write_engine()?;
+        let sandbox = SandboxPortfolio::spawn(&engine.market, owner)?;
+        engine.sandboxes.insert(sandbox.clone());
+        Ok(sandbox)
+    }
+
+    // the sandbox and what it is worth at the last trade prices
+    pub fn get_sandbox(&self, owner: PortfolioId) -> EngineResult<(SandboxPortfolio, usize)> {
+        let engine = self.read_engine()?;
+        let sandbox = engine.sandboxes.get(owner)?;
+        Ok((sandbox.clone(), sandbox.value(&engine.market)?))
+    }
+
+    pub fn remove_sandbox(&self, owner: PortfolioId) -> EngineResult<()> {
+        self.write_engine()?.sandboxes.remove(owner)
+    }
+
+    // Fills the order of the sandbox's owner virtually, the books and the owner's balances stay untouched.
+    pub fn sandbox_order(&self, order: Order) -> EngineResult<Vec<SandboxFill>> {
+        let mut guard = self.write_engine()?;
+        let engine = &mut *guard;
+        engine.sandboxes.get_mut(order.portfolio)?.execute(&engine.market, &order)
+    }
+
+    pub fn get_schedules(&self, portfolio_id: PortfolioId) -> EngineResult<Vec<RecurringOrder>> {
+        let engine = self.read_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        Ok(engine.market.schedules.get_for(portfolio_id))
+    }
+
+    pub fn disarm_heartbeat(&self, portfolio_id: PortfolioId) -> EngineResult<()> {
+        let mut engine = self.write_engine()?;
+        engine.check_access(portfolio_id)?;
+        engine.heartbeats.disarm(portfolio_id);
+        Ok(())
+    }
+
+    pub fn set_bankruptcy_rule(&self, rule: Option<BankruptcyRule>) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetBankruptcyRule(rule))
+    }
+
+    pub fn set_interest_policy(&self, policy: Option<InterestPolicy>) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetInterestPolicy(policy))
+    }
+
+    pub fn set_fee_schedule(&self, schedule: FeeSchedule) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetFeeSchedule(schedule))
+    }
+
+    // only before the first order, quotes in the market would change their meaning otherwise
+    pub fn set_price_mode(&self, mode: PriceMode) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetPriceMode(mode))
+    }
+
+    pub fn get_price_mode(&self) -> EngineResult<PriceMode> {
+        Ok(self.read_engine()?.market.price_mode)
+    }
+
+    // none turns the fat-finger check off
+    pub fn set_price_band(&self, percent: Option<usize>) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetPriceBand(percent))
+    }
+
+    pub fn get_price_band(&self) -> EngineResult<Option<usize>> {
+        Ok(self.read_engine()?.market.price_band_percent)
+    }
+
+    // none lets orders be cancelled right away again
+    pub fn set_minimum_resting_time(&self, minimum: Option<MinimumRestingTime>) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetMinimumRestingTime(minimum))
+    }
+
+    pub fn get_minimum_resting_time(&self) -> EngineResult<Option<MinimumRestingTime>> {
+        Ok(self.read_engine()?.market.minimum_resting_time)
+    }
+
+    // Delays players' events from now on, see `LatencyPolicy`. None lets them through right away.
+    pub fn set_latency_policy(&self, latency: Option<LatencyPolicy>) -> EngineResult<()> {
+        if let Some(latency) = &latency {
+            latency.check()?;
+        }
+        self.write_engine()?.apply(JournalRecord::SetLatency(latency.clone()))?;
+        self.queue.set_latency(latency)
+    }
+
+    pub fn get_latency_policy(&self) -> EngineResult<Option<LatencyPolicy>> {
+        Ok(self.read_engine()?.market.latency.clone())
+    }
+
+    // Changes the given tunables at once: the engine lock keeps it between two events.
+    pub fn set_config(&self, config: GameConfig) -> EngineResult<()> {
+        let queue_max_depth = config.queue_max_depth;
+        self.write_engine()?.apply(JournalRecord::SetConfig(config))?;
+        match queue_max_depth {
+            Some(max_depth) => self.queue.set_max_depth(max_depth),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_config(&self) -> EngineResult<GameConfig> {
+        Ok(GameConfig::of(&self.read_engine()?.market, self.queue.get_max_depth()?))
+    }
+
+    // switching back to continuous crosses the orders collected so far
+    pub fn set_market_structure(&self, structure: MarketStructure) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetMarketStructure(structure))
+    }
+
+    pub fn get_market_structure(&self) -> EngineResult<MarketStructure> {
+        Ok(self.read_engine()?.market.market_structure)
+    }
+
+    // keeps what was paid so far, a new budget counts it too
+    pub fn set_faucet_policy(&self, policy: Option<FaucetPolicy>) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetFaucetPolicy(policy))
+    }
+
+    pub fn get_asset_rules(&self, asset_id: AssetId) -> EngineResult<AssetRules> {
+        AssetRules::of(&self.read_engine()?.market, asset_id)
+    }
+
+    pub fn get_faucet(&self) -> EngineResult<FaucetStatus> {
+        Ok(self.read_engine()?.market.faucet.status())
+    }
+
+    // the coins credited to the portfolio
+    pub fn claim_faucet(&self, portfolio_id: PortfolioId) -> EngineResult<usize> {
+        let mut engine = self.write_engine()?;
+        let amount = engine.market.faucet.check(portfolio_id, engine.market.tick)?;
+        engine.apply(JournalRecord::ClaimFaucet { portfolio: portfolio_id })?;
+        Ok(amount)
+    }
+
+    // Classes are best first, portfolios in none of them are standard and go last.
+    pub fn set_priority_classes(&self, classes: Vec<PriorityClass>) -> EngineResult<()> {
+        self.apply_colocation(JournalRecord::SetPriorityClasses(classes))
+    }
+
+    // none makes the portfolio standard again
+    pub fn assign_priority_class(&self, portfolio: PortfolioId, class: Option<String>) -> EngineResult<()> {
+        self.apply_colocation(JournalRecord::AssignPriorityClass { portfolio, class })
+    }
+
+    // the coins paid
+    pub fn buy_priority_class(&self, portfolio: PortfolioId, class: String) -> EngineResult<usize> {
+        let price = self.read_engine()?.market.colocation.class(&class)?.price.unwrap_or(0);
+        self.apply_colocation(JournalRecord::BuyPriorityClass { portfolio, class })?;
+        Ok(price)
+    }
+
+    pub fn get_colocation(&self) -> EngineResult<Colocation> {
+        Ok(self.read_engine()?.market.colocation.clone())
+    }
+
+    // the queue is updated under the engine lock, so it sees the changes in the order they're made
+    fn apply_colocation(&self, record: JournalRecord) -> EngineResult<()> {
+        let mut engine = self.write_engine()?;
+        engine.apply(record)?;
+        let (names, classes) = engine.market.colocation.ranks();
+        self.queue.set_priority_classes(names, classes)
+    }
+
+    // only decides between orders at the same price, resting ones keep their place
+    pub fn set_dark_priority(&self, priority: DarkPriority) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetDarkPriority(priority))
+    }
+
+    pub fn get_dark_priority(&self) -> EngineResult<DarkPriority> {
+        Ok(self.read_engine()?.market.dark_priority)
+    }
+
+    pub fn get_indicative_clearing(&self, asset_id: AssetId) -> EngineResult<Option<Clearing>> {
+        self.read_engine()?.market.indicative_clearing(asset_id)
+    }
+
+    // cancels what the portfolio or token has in the books, later events get `Suspended`
+    pub fn suspend(&self, suspension: Suspension) -> EngineResult<Vec<OrderId>> {
+        let mut engine = self.write_engine()?;
+        let seen = engine.market.notifications.len();
+        engine.apply(JournalRecord::Suspend(suspension))?;
+        let cancelled = engine.market.notifications.iter().skip(seen)
+            .filter_map(|(_, n)| match n {
+                Notification::OrderCancelled { order_id, .. } => Some(*order_id),
+                _ => None,
+            })
+            .collect();
+        Ok(cancelled)
+    }
+
+    pub fn reinstate(&self, suspension: Suspension) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::Reinstate(suspension))
+    }
+
+    // another token for the portfolio, its events then have to come with one of its tokens
+    pub fn issue_token(&self, portfolio: PortfolioId) -> EngineResult<IssuedToken> {
+        let mut engine = self.write_engine()?;
+        let token = engine.ids.next_id().to_simple().to_string();
+        let fingerprint = actor::fingerprint(&token);
+        engine.apply(JournalRecord::IssueToken { fingerprint: fingerprint.clone(), portfolio })?;
+        Ok(IssuedToken { portfolio, token, fingerprint })
+    }
+
+    pub fn revoke_token(&self, fingerprint: String) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::RevokeToken { fingerprint })
+    }
+
+    // (fingerprint, portfolio) of every issued token
+    pub fn get_tokens(&self) -> EngineResult<Vec<(String, PortfolioId)>> {
+        Ok(self.read_engine()?.tokens.list())
+    }
+
+    pub fn is_token_issued(&self, token: &str) -> EngineResult<bool> {
+        Ok(self.read_engine()?.tokens.is_issued(&actor::fingerprint(token)))
+    }
+
+    // Whether `token` acts for the portfolio, taken as the REST API takes it. The portfolio's private
+    // stream needs this, a portfolio id is no secret.
+    pub fn check_owner(&self, portfolio_id: PortfolioId, token: Option<&str>) -> EngineResult<()> {
+        let engine = self.read_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        let actor = match token {
+            Some(token) if engine.tokens.is_issued(&actor::fingerprint(token)) => Actor::token(token),
+            _ => Actor::Anonymous,
+        };
+        engine.suspensions.check(portfolio_id, &actor)?;
+        engine.tokens.check_owner(portfolio_id, &actor)
+    }
+
+    pub fn is_ip_banned(&self, ip: &str) -> EngineResult<bool> {
+        Ok(self.read_engine()?.suspensions.is_ip_banned(ip))
+    }
+
+    pub fn get_suspensions(&self) -> EngineResult<Vec<Suspension>> {
+        Ok(self.read_engine()?.suspensions.list())
+    }
+
+    pub fn set_penalty_rule(&self, rule: Option<PenaltyRule>) -> EngineResult<()> {
+        if let Some(rule) = &rule {
+            rule.check()?;
+        }
+        self.write_engine()?.apply(JournalRecord::SetPenaltyRule(rule))
+    }
+
+    pub fn get_penalty_rule(&self) -> EngineResult<Option<PenaltyRule>> {
+        Ok(self.read_engine()?.penalties.rule())
+    }
+
+    pub fn get_penalty_standings(&self) -> EngineResult<Vec<PenaltyStanding>> {
+        Ok(self.read_engine()?.penalties.standings())
+    }
+
+    // records from now on, a recording already running starts over
+    pub fn start_recording(&self) -> EngineResult<()> {
+        let mut engine = self.write_engine()?;
+        engine.recording = Some(Recording::new(engine.sequence, engine.market.clone()));
+        Ok(())
+    }
+
+    // what was recorded so far, the recording goes on
+    pub fn get_recording(&self) -> EngineResult<Scenario> {
+        let engine = self.read_engine()?;
+        let recording = engine.recording.as_ref().ok_or(ErrorType::NotRecording)?;
+        Ok(recording.scenario(&engine.journal))
+    }
+
+    pub fn stop_recording(&self) -> EngineResult<Scenario> {
+        let mut engine = self.write_engine()?;
+        let recording = engine.recording.take().ok_or(ErrorType::NotRecording)?;
+        Ok(recording.scenario(&engine.journal))
+    }
+
+    // Plays the scenario's steps from the current tick on, those before its first tick right away. Its
+    // portfolios and assets have to be in this game, as they are in one started from `Scenario::engine`.
+    // Replaces a scenario still playing.
+    pub fn play_scenario(&self, scenario: Scenario) -> EngineResult<()> {
+        let (notifications, events) = {
+            let mut engine = self.write_engine()?;
+            engine.scenario = Some(ScenarioPlayer::new(scenario, engine.market.tick)?);
+            engine.acting_as(Actor::bot("scenario"), run_scenario);
+            (engine.take_notifications(), engine.take_domain_events())
+        };
+        self.stream.publish_notifications(notifications);
+        self.bus.publish(events);
+        Ok(())
+    }
+
+    pub fn stop_scenario(&self) -> EngineResult<()> {
+        self.write_engine()?.scenario.take().map(|_| ()).ok_or(ErrorType::InvalidState)
+    }
+
+    pub fn get_scenario_status(&self) -> EngineResult<Option<ScenarioStatus>> {
+        Ok(self.read_engine()?.scenario.as_ref().map(|player| player.status()))
+    }
+
+    pub fn get_debug_state(&self) -> EngineResult<DebugState> {
+        let engine = self.read_engine()?;
+        Ok(debug::dump(&engine.market, engine.sequence, &engine.sequence_regressions))
+    }
+
+    // journal entries submitted by the actor, by its `Actor::label`, oldest first
+    pub fn get_journal_of(&self, label: &str) -> EngineResult<Vec<JournalEntry>> {
+        Ok(self.read_engine()?.journal.iter()
+            .filter(|entry| entry.actor.label() == label)
+            .cloned()
+            .collect())
+    }
+
+    pub fn get_fee_schedule(&self) -> EngineResult<FeeSchedule> {
+        Ok(self.read_engine()?.market.fee_schedule.clone())
+    }
+
+    pub fn mint(&self, amount: usize) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::Mint { amount })
+    }
+
+    pub fn burn(&self, amount: usize) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::Burn { amount })
+    }
+
+    fn fund_bank_portfolio(engine: &mut Engine, portfolio: PortfolioId, needed: usize) -> EngineResult<()> {
+        let free = engine.market.get_portfolio(portfolio)?.coins.get_free_amount();
+        if free < needed {
+            engine.apply(JournalRecord::FundBankPortfolio { amount: needed - free })?;
+        }
+        Ok(())
+    }
+
+    // The bank trades through its own portfolio, funded from the bank account for buys.
+    pub fn open_market_operation(&self, asset: AssetId, side: OrderSide, quantity: usize, price: usize) -> EngineResult<Order> {
+        let order = {
+            let mut engine = self.write_engine()?;
+            engine.market.get_asset(&asset)?;
+            let portfolio = engine.bank_portfolio()?;
+            // the coins it pays, for a buy above zero or a sell below
+            let notional = engine.market.price_mode.notional(price, quantity)?;
+            let needed = match side {
+                OrderSide::Buy => checked_add(to_coins(notional.max(0))?, 1)?,
+                OrderSide::Sell => checked_add(to_coins((-notional).max(0))?, 1)?,
+            };
+            Game::fund_bank_portfolio(&mut engine, portfolio, needed)?;
+            let stamp = engine.stamp();
+            let expires = stamp.created_at + Duration::from_secs(24*60*60).as_millis() as u64;
+            Order::new(stamp, portfolio, asset, side, quantity, OrderMode::Limit(price), expires)?
+        };
+        self.process(Event::Order(order))?;
+        Ok(order)
+    }
+
+    // Starts selling from the portfolio, or from the bank which issues what it lacks of the asset and
+    // gets the coins for the fees of every step down to the floor. The first ask goes in right away.
+    pub fn start_dutch_auction(&self, asset: AssetId, portfolio: Option<PortfolioId>, terms: DutchAuctionTerms) -> EngineResult<DutchAuction> {
+        let (auction, order) = {
+            let mut engine = self.write_engine()?;
+            engine.market.get_asset(&asset)?;
+            terms.check()?;
+            let portfolio = match portfolio {
+                Some(portfolio) => {
+                    let held = engine.market.get_portfolio(portfolio)?
+                        .assets.get(&asset).map_or(0, |a| a.get_free_amount());
+                    if held < terms.quantity {
+                        return Err(ErrorType::InsufficientFreeAmount);
+                    }
+                    portfolio
+                },
+                None => {
+                    let portfolio = engine.bank_portfolio()?;
+                    // the listing and a cancel and relist on every step
+                    let events = checked_add(1, checked_mul(2, terms.steps())?)?;
+                    let fees = checked_mul(engine.market.fee_schedule.event_fee, events)?;
+                    Game::fund_bank_portfolio(&mut engine, portfolio, fees)?;
+                    let held = engine.market.get_portfolio(portfolio)?
+                        .assets.get(&asset).map_or(0, |a| a.get_free_amount());
+                    if held < terms.quantity {
+                        engine.apply(JournalRecord::SetAssetAmount { portfolio, asset, amount: terms.quantity - held })?;
+                    }
+                    portfolio
+                },
+            };
+            let tick = engine.market.tick;
+            let mut auction = DutchAuction::new(engine.ids.next_id(), portfolio, asset, terms, tick)?;
+            let order = auction.ask(&engine)?;
+            auction.order = Some(order.get_id());
+            engine.dutch_auctions.add(auction);
+            (auction, order)
+        };
+        if let Err(e) = self.process(Event::Order(order)) {
+            self.write_engine()?.dutch_auctions.remove(auction.id);
+            return Err(e);
+        }
+        self.get_dutch_auction(auction.id)
+    }
+
+    // Stops the auction and takes its ask out of the book, what it sold stays sold.
+    pub fn cancel_dutch_auction(&self, id: Uuid) -> EngineResult<DutchAuction> {
+        let open = {
+            let mut engine = self.write_engine()?;
+            let engine = &mut *engine;
+            engine.dutch_auctions.cancel(id, &engine.orders)?
+        };
+        if let Some((portfolio, order, asset)) = open {
+            self.process(Event::CancelOrder(portfolio, order, asset))?;
+        }
+        self.get_dutch_auction(id)
+    }
+
+    pub fn get_dutch_auction(&self, id: Uuid) -> EngineResult<DutchAuction> {
+        let engine = self.read_engine()?;
+        engine.dutch_auctions.get(id, &engine.orders)
+    }
+
+    pub fn get_dutch_auctions(&self) -> EngineResult<Vec<DutchAuction>> {
+        let engine = self.read_engine()?;
+        Ok(engine.dutch_auctions.get_all(&engine.orders))
+    }
+
+    // Starts working the parent order, its first child goes in right away.
+    pub fn start_algo(&self, portfolio_id: PortfolioId, asset_id: AssetId, terms: AlgoTerms) -> EngineResult<AlgoStatus> {
+        let (id, child) = {
+            let mut engine = self.write_engine()?;
+            engine.market.get_portfolio(portfolio_id)?;
+            engine.market.get_asset(&asset_id)?;
+            let mut parent = ParentOrder::new(engine.ids.next_id(), portfolio_id, asset_id, terms, &engine.market)?;
+            let child = parent.next_child(&engine);
+            if let Some(child) = &child {
+                parent.record_child(child);
+            }
+            let id = parent.id;
+            engine.algos.add(parent);
+            (id, child)
+        };
+        if let Some(child) = child {
+            if let Err(e) = self.process(Event::Order(child)) {
+                self.write_engine()?.algos.remove(id);
+                return Err(e);
+            }
+        }
+        self.get_algo(portfolio_id, id)
+    }
+
+    // Stops slicing and takes the children still in the book out of it, what they filled stays filled.
+    pub fn cancel_algo(&self, portfolio_id: PortfolioId, id: Uuid) -> EngineResult<AlgoStatus> {
+        let open = {
+            let mut engine = self.write_engine()?;
+            let engine = &mut *engine;
+            engine.algos.cancel(portfolio_id, id, &engine.orders)?
+        };
+        for (portfolio, order, asset) in open {
+            self.process(Event::CancelOrder(portfolio, order, asset))?;
+        }
+        self.get_algo(portfolio_id, id)
+    }
+
+    pub fn get_algo(&self, portfolio_id: PortfolioId, id: Uuid) -> EngineResult<AlgoStatus> {
+        let engine = self.read_engine()?;
+        engine.algos.get(portfolio_id, id, &engine.orders)
+    }
+
+    pub fn get_algos(&self, portfolio_id: PortfolioId) -> EngineResult<Vec<AlgoStatus>> {
+        let engine = self.read_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        Ok(engine.algos.get_for(portfolio_id, &engine.orders))
+    }
+
+    // Fills both sides of the book with bank orders in one batch. The bank issues whatever it lacks of the asset.
+    pub fn seed_book(&self, asset: AssetId, seed: BookSeed) -> EngineResult<Vec<OrderId>> {
+        self.place_seed_orders(asset, seed.ladder()?, None)
+    }
+
+    // Loads a book exported as CSV, see `parse_book_csv`. Owned by the bank unless a portfolio is given,
+    // which then has to hold the coins and assets for all of it.
+    pub fn import_book(&self, asset: AssetId, csv: &str, portfolio: Option<PortfolioId>) -> EngineResult<Vec<OrderId>> {
+        self.place_seed_orders(asset, parse_book_csv(csv)?, portfolio)
+    }
+
+    fn place_seed_orders(&self, asset: AssetId, levels: Vec<SeedLevel>, owner: Option<PortfolioId>) -> EngineResult<Vec<OrderId>> {
+        let orders = {
+            let mut engine = self.write_engine()?;
+            engine.market.get_asset(&asset)?;
+            let portfolio = match owner {
+                Some(portfolio) => portfolio,
+                None => engine.bank_portfolio()?,
+            };
+            let mut coins = checked_mul(engine.market.fee_schedule.event_fee, levels.len())?;
+            let mut needed = 0;
+            for (side, price, quantity) in &levels {
+                let notional = engine.market.price_mode.notional(*price, *quantity)?;
+                let paid = match side {
+                    OrderSide::Buy => notional.max(0),
+                    OrderSide::Sell => (-notional).max(0),
+                };
+                coins = checked_add(coins, to_coins(paid)?)?;
+                if *side == OrderSide::Sell {
+                    needed = checked_add(needed, *quantity)?;
+                }
+            }
+            let held = engine.market.get_portfolio(portfolio)?
+                .assets.get(&asset).map_or(0, |a| a.get_free_amount());
+            if owner.is_some() {
+                if engine.market.get_portfolio(portfolio)?.coins.get_free_amount() < coins || held < needed {
+                    return Err(ErrorType::InsufficientFreeAmount);
+                }
+            } else {
+                Game::fund_bank_portfolio(&mut engine, portfolio, coins)?;
+                if held < needed {
+                    engine.apply(JournalRecord::SetAssetAmount { portfolio, asset, amount: needed - held })?;
+                }
+            }
+            let mut orders = vec![];
+            for (side, price, quantity) in levels {
+                let stamp = engine.stamp();
+                let expires = stamp.created_at + Duration::from_secs(24*60*60).as_millis() as u64;
+                orders.push(Order::new(stamp, portfolio, asset, side, quantity, OrderMode::Limit(price), expires)?);
+            }
+            orders
+        };
+        // all of it or none, a rejected level mustn't leave half a book behind
+        let ids = orders.iter().map(|o| o.id).collect();
+        self.process(Event::AtomicBatch(orders))?;
+        Ok(ids)
+    }
+
+    pub fn get_positions(&self, portfolio_id: PortfolioId) -> EngineResult<Vec<Position>> {
+        self.read_engine()?.market.positions(portfolio_id)
+    }
+
+    // Maker and taker volume, fill and order-to-trade ratios, and the fee tier they put the portfolio in.
+    pub fn get_activity_stats(&self, portfolio_id: PortfolioId) -> EngineResult<ActivityStats> {
+        let engine = self.read_engine()?;
+        let portfolio = engine.market.get_portfolio(portfolio_id)?;
+        Ok(ActivityStats::new(portfolio_id, &portfolio.activity, &engine.market.fee_schedule))
+    }
+
+    // Per asset and summed up over the market, read off the books' running counts.
+    pub fn get_open_interest(&self) -> EngineResult<OpenInterestStats> {
+        let engine = self.read_engine()?;
+        let mut assets: Vec<AssetOpenInterest> = engine.market.books.values()
+            .map(|book| AssetOpenInterest { asset: book.asset_id, open_interest: book.open_interest() })
+            .collect();
+        assets.sort_by_key(|a| a.asset);
+        let mut total = OpenInterest::default();
+        for asset in &assets {
+            total.merge(&asset.open_interest)?;
+        }
+        Ok(OpenInterestStats { total, assets })
+    }
+
+    pub fn get_monetary_stats(&self) -> EngineResult<MonetaryStats> {
+        Ok(MonetaryStats::from_market(&self.read_engine()?.market))
+    }
+
+    pub fn get_ledger(&self, portfolio_id: PortfolioId, query: &PageQuery) -> EngineResult<(Vec<LedgerEntry>, Option<String>)> {
+        let engine = self.read_engine()?;
+        engine.market.get_portfolio(portfolio_id)?;
+        let entries = engine.market.ledger.iter().cloned().enumerate()
+            .filter(|(_, e)| e.portfolio == portfolio_id)
+            .collect();
+        let (entries, next_cursor) = query.apply(entries);
+        Ok((entries.into_iter().map(|(_, e)| e).collect(), next_cursor))
+    }
+
+    // every portfolio's entries, for the bulk export
+    pub fn get_all_ledger(&self) -> EngineResult<Vec<LedgerEntry>> {
+        Ok(self.read_engine()?.market.ledger.clone())
+    }
+
+    // every asset's trades including busted ones, for the bulk export
+    pub fn get_all_trades(&self) -> EngineResult<Vec<Trade>> {
+        Ok(self.read_engine()?.market.trades.clone())
+    }
+
+    // Statement for the numbered period, the current one if none is given.
+    pub fn get_statement(&self, portfolio_id: PortfolioId, period: Option<u64>) -> EngineResult<Statement> {
+        let engine = self.read_engine()?;
+        let length = self.statement_period_ticks.max(1);
+        let period = period.unwrap_or(engine.market.tick / length);
+        let from_tick = period.saturating_mul(length);
+        Statement::build(&engine.market, &engine.portfolio_history, portfolio_id, from_tick, from_tick.saturating_add(length))
+    }
+
+    pub fn set_scoring_metric(&self, metric: ScoringMetric) -> EngineResult<()> {
+        self.write_engine()?.apply(JournalRecord::SetScoringMetric(metric))
+    }
+
+    // Ranks player portfolios by the given metric, falling back to the one configured for the game.
+    pub fn get_leaderboard(&self, metric: Option<ScoringMetric>) -> EngineResult<Vec<LeaderboardEntry>> {
+        let engine = self.read_engine()?;
+        leaderboard::rank(&engine, metric.unwrap_or(engine.scoring_metric))
+    }
+
+    pub fn get_assets(&self, query: &PageQuery) -> EngineResult<(Vec<Asset>, Option<String>)> {
+        Ok(query.apply(self.read_engine()?.market.assets.values().cloned().collect()))
+    }
+
+    // Pages through the books, the side, price and time filters apply to the orders in them.
+    pub fn get_public_books(&self, query: &PageQuery) -> EngineResult<(Vec<BookDto>, Option<String>)> {
+        let engine = self.read_engine()?;
+        let (assets, next_cursor) = query.apply(engine.market.assets.values().cloned().collect());
+        let filter = PageQuery {
+            side: query.side,
+            min_price: query.min_price,
+            max_price: query.max_price,
+            since: query.since,
+            ..PageQuery::default()
+        };
+        let mut books = vec![];
+        for asset in assets {
+            let book = engine.market.get_order_book(asset.id)?;
+            books.push(BookDto::from_orders(&asset, book.version(), filter.apply(book.sell_orders().chain(book.buy_orders()).collect())));
+        }
+        Ok((books, next_cursor))
+    }
+
+    // The whole public book as JSON and its version, serialized only when the book changed.
+    pub fn get_public_book_json(&self, asset_id: AssetId) -> EngineResult<(u64, Arc<String>)> {
+        self.cached(asset_id, CachedView::Book, |market| {
+            let book = market.get_order_book(asset_id)?;
+            let orders = PageQuery::default().apply(book.sell_orders().chain(book.buy_orders()).collect());
+            Ok(BookDto::from_orders(market.get_asset(&asset_id)?, book.version(), orders))
+        })
+    }
+
+    pub fn get_market_data_json(&self, asset_id: AssetId, level: Level) -> EngineResult<Arc<String>> {
+        let (_, json) = self.cached(asset_id, CachedView::MarketData(level), |market| {
+            Ok(SequencedMarketData::new(market.get_order_book(asset_id)?, level, None))
+        })?;
+        Ok(json)
+    }
+
+    pub fn get_cache_metrics(&self) -> EngineResult<CacheMetrics> {
+        self.cache.get_metrics()
+    }
+
+    pub fn get_event_counts(&self) -> EngineResult<BTreeMap<DomainEventKind, u64>> {
+        self.event_counts.get_all()
+    }
+
+    // Looks the view up with the book's version. On a miss it's built under the read lock and
+    // serialized after letting go of it.
+    fn cached<T: serde::Serialize>(&self, asset_id: AssetId, view: CachedView, build: impl FnOnce(&Market) -> EngineResult<T>)
+                            -> EngineResult<(u64, Arc<String>)> {
+        let (stamp, body) = {
+            let engine = self.read_engine()?;
+            let stamp = CacheStamp {
+                version: engine.market.get_order_book(asset_id)?.version(),
+                decimals: engine.market.get_asset(&asset_id)?.decimals,
+            };
+            if let Some(json) = self.cache.get(asset_id, view, stamp)? {
+                return Ok((stamp.version, json));
+            }
+            (stamp, build(&engine.market)?)
+        };
+        let json = Arc::new(serde_json::to_string(&body).map_err(|_| ErrorType::InvalidState)?);
+        self.cache.insert(asset_id, view, stamp, json.clone())?;
+        Ok((stamp.version, json))
+    }
+
+    pub fn get_book_deltas(&self, asset_id: AssetId, since_version: u64) -> EngineResult<(u64, Vec<BookDelta>)> {
+        let engine = self.read_engine()?;
+        let book = engine.market.get_order_book(asset_id)?;
+        Ok((book.version(), book.deltas_since(since_version)?))
+    }
+
+    pub fn get_public_book_for(&self, asset_id: Uuid, query: &PageQuery) -> EngineResult<BookDto> {
         let engine = self.read_engine()?;
         let book = engine.market.get_order_book(asset_id)?;
         let asset = engine.market.get_asset(&asset_id)?;
-        Ok(PublicBook::from_book(asset.clone(), book))
+        Ok(BookDto::from_orders(asset, book.version(), query.apply(book.sell_orders().chain(book.buy_orders()).collect())))
     }
 
 }
 
-#[derive(Serialize)]
-pub struct  PublicBook {
-    asset: Asset,
-    sell: Vec<PublicOrder>,
-    buy: Vec<PublicOrder>,
+#[derive(Clone, Debug, Serialize)]
+pub struct GameClock {
+    pub game_id: Uuid,
+    pub tick: u64,
+    // milliseconds the game has been running, see `Market::game_time`
+    pub game_time: u64,
+    pub paused: bool,
+    pub ended: bool,
 }
 
-impl PublicBook {
-    pub fn from_book(asset: Asset, book: &Book) -> PublicBook {
-        PublicBook {
-            asset,
-            sell: book.sell_orders.iter().map(|o|PublicOrder::from(o)).collect(),
-            buy: book.buy_orders.iter().map(|o|PublicOrder::from(o)).collect(),
-        }
-    }
+// The clocks a bot needs to read the server's times: orders are stamped and rest by `server_time`,
+// they expire by `game_time`.
+#[derive(Clone, Debug, Serialize)]
+pub struct ServerTime {
+    pub server_time: Timestamp,
+    #[serde(flatten)]
+    pub clock: GameClock,
+    // when the client sent its time along
+    pub handshake: Option<ClockHandshake>,
 }
 
-#[derive(Serialize)]
-pub struct PublicOrder {
-    pub asset: Uuid,
-    pub mode: OrderMode,
-    pub side: OrderSide,
-    pub quantity: usize,
+// With the time the answer arrived, the client's clock is off by
+// ((received_at - client_time) + (server_time - arrived)) / 2, the latency of both ways taken out.
+#[derive(Clone, Debug, Serialize)]
+pub struct ClockHandshake {
+    pub client_time: Timestamp,
+    pub received_at: Timestamp,
+    // received_at - client_time, with the request's latency still in it
+    pub offset_millis: i64,
 }
 
-impl PublicOrder {
-    pub fn from(order: &Order) -> PublicOrder {
-        PublicOrder {
-            asset: order.asset,
-            mode: order.mode,
-            side: order.side,
-            quantity: order.quantity,
-        }
-    }
+#[derive(Clone, Debug, Serialize)]
+pub struct AssetOpenInterest {
+    pub asset: AssetId,
+    #[serde(flatten)]
+    pub open_interest: OpenInterest,
 }
 
+#[derive(Clone, Debug, Serialize)]
+pub struct OpenInterestStats {
+    pub total: OpenInterest,
+    pub assets: Vec<AssetOpenInterest>,
+}
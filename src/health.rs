@@ -0,0 +1,63 @@
+use crate::debug::Inconsistency;
+use crate::providers::Timestamp;
+
+// no more are kept, the first few tell what went wrong
+pub const MAX_RECOVERIES: usize = 20;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum HealthStatus {
+    Ok,
+    // the engine panicked at some point and was recovered, see the recoveries
+    Recovered,
+    // the engine couldn't be recovered, every request fails until the game is restarted
+    Failed,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub enum RecoveryOutcome {
+    // rebuilt from the journal, without the entry that panicked if one was being applied
+    RestoredFromJournal { dropped_sequence: Option<u64> },
+    Failed { reason: String },
+}
+
+// One panic while the engine was locked, and what was done about it.
+#[derive(Clone, Debug, Serialize)]
+pub struct Recovery {
+    pub timestamp: Timestamp,
+    pub sequence: u64,
+    // what the invariant check found before anything was done, none if the check panicked as well
+    pub issues: Option<Vec<Inconsistency>>,
+    pub outcome: RecoveryOutcome,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct HealthLog {
+    pub recoveries: Vec<Recovery>,
+    pub failed: bool,
+}
+
+impl HealthLog {
+    pub fn record(&mut self, recovery: Recovery) {
+        self.failed = matches!(recovery.outcome, RecoveryOutcome::Failed { .. });
+        if self.recoveries.len() < MAX_RECOVERIES {
+            self.recoveries.push(recovery);
+        }
+    }
+
+    pub fn status(&self) -> HealthStatus {
+        match (self.failed, self.recoveries.is_empty()) {
+            (true, _) => HealthStatus::Failed,
+            (false, true) => HealthStatus::Ok,
+            (false, false) => HealthStatus::Recovered,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Health {
+    pub status: HealthStatus,
+    // none while the engine can't be read
+    pub sequence: Option<u64>,
+    pub tick: Option<u64>,
+    pub recoveries: Vec<Recovery>,
+}
@@ -0,0 +1,68 @@
+use crate::account::PortfolioId;
+use crate::engine::Engine;
+use crate::providers::Timestamp;
+use std::collections::HashMap;
+
+pub const DEFAULT_HEARTBEAT_WINDOW_MILLIS: u64 = 10*1000;
+
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct Heartbeat {
+    pub window_millis: u64,
+    pub last_seen: Timestamp,
+}
+
+// Portfolios that opted into cancel-on-disconnect.
+#[derive(Clone, Debug, Default)]
+pub struct Heartbeats {
+    portfolios: HashMap<PortfolioId, Heartbeat>,
+}
+
+impl Heartbeats {
+    pub fn new() -> Heartbeats {
+        Heartbeats {
+            portfolios: HashMap::new(),
+        }
+    }
+
+    // arms the switch on the first beat, later beats keep the window unless a new one is given
+    pub fn beat(&mut self, portfolio_id: PortfolioId, window_millis: Option<u64>, now: Timestamp) -> Heartbeat {
+        let heartbeat = self.portfolios.entry(portfolio_id).or_insert(Heartbeat {
+            window_millis: DEFAULT_HEARTBEAT_WINDOW_MILLIS,
+            last_seen: now,
+        });
+        heartbeat.last_seen = now;
+        if let Some(window_millis) = window_millis {
+            heartbeat.window_millis = window_millis;
+        }
+        *heartbeat
+    }
+
+    pub fn disarm(&mut self, portfolio_id: PortfolioId) -> bool {
+        self.portfolios.remove(&portfolio_id).is_some()
+    }
+
+    pub fn get(&self, portfolio_id: PortfolioId) -> Option<&Heartbeat> {
+        self.portfolios.get(&portfolio_id)
+    }
+
+    pub fn expired(&self, now: Timestamp) -> Vec<PortfolioId> {
+        let mut expired: Vec<PortfolioId> = self.portfolios.iter()
+            .filter(|(_, h)| now > h.last_seen + h.window_millis)
+            .map(|(id, _)| *id)
+            .collect();
+        expired.sort();
+        expired
+    }
+}
+
+// Cancels the open orders of every portfolio that missed its heartbeat. A stream connection the
+// portfolio's owner opened counts as a heartbeat.
+pub fn run_heartbeats(engine: &mut Engine, connected: impl Fn(PortfolioId) -> bool) {
+    let now = engine.clock.now();
+    for portfolio_id in engine.heartbeats.expired(now) {
+        engine.heartbeats.beat(portfolio_id, None, now);
+        if !connected(portfolio_id) {
+            engine.cancel_all_orders_of(portfolio_id);
+        }
+    }
+}
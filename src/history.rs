@@ -0,0 +1,83 @@
+use crate::order::Order;
+use crate::book::Book;
+use crate::market::{AssetId, Market};
+use crate::marketdata::depth;
+use std::collections::{HashMap, VecDeque};
+use crate::providers::Timestamp;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct BookSnapshot {
+    pub timestamp: Timestamp,
+    pub sell: Vec<(usize, usize)>,
+    pub buy: Vec<(usize, usize)>,
+}
+
+impl BookSnapshot {
+    pub fn from_book(timestamp: Timestamp, book: &Book) -> BookSnapshot {
+        BookSnapshot {
+            timestamp,
+            sell: BookSnapshot::levels(book.sell_orders()),
+            buy: BookSnapshot::levels(book.buy_orders()),
+        }
+    }
+
+    // (price, quantity) per price level, in book order
+    fn levels<'a>(orders: impl Iterator<Item = &'a Order>) -> Vec<(usize, usize)> {
+        depth(orders).iter().map(|l| (l.price, l.quantity)).collect()
+    }
+}
+
+pub struct BookHistory {
+    pub interval: Duration,
+    pub capacity: usize,
+    last_capture: Option<Timestamp>,
+    snapshots: HashMap<AssetId, VecDeque<BookSnapshot>>,
+}
+
+impl BookHistory {
+    pub fn new(interval: Duration, capacity: usize) -> BookHistory {
+        BookHistory {
+            interval,
+            capacity,
+            last_capture: None,
+            snapshots: HashMap::new(),
+        }
+    }
+
+    pub fn capture_if_due(&mut self, market: &Market, now: Timestamp) {
+        let due = match self.last_capture {
+            Some(last) => now.saturating_sub(last) >= self.interval.as_millis() as u64,
+            None => true,
+        };
+        if due {
+            self.capture(market, now);
+        }
+    }
+
+    pub fn capture(&mut self, market: &Market, timestamp: Timestamp) {
+        for book in market.books.values() {
+            let history = self.snapshots.entry(book.asset_id).or_default();
+            history.push_back(BookSnapshot::from_book(timestamp, book));
+            while history.len() > self.capacity {
+                history.pop_front();
+            }
+        }
+        self.last_capture = Some(timestamp);
+    }
+
+    pub fn get_all(&self, asset_id: AssetId) -> Vec<BookSnapshot> {
+        self.get_range(asset_id, None, None)
+    }
+
+    pub fn get_range(&self, asset_id: AssetId, from: Option<u64>, to: Option<u64>) -> Vec<BookSnapshot> {
+        let from = from.unwrap_or(0);
+        let to = to.unwrap_or(u64::MAX);
+        self.snapshots.get(&asset_id)
+            .map(|history| history.iter()
+                .filter(|s| s.timestamp >= from && s.timestamp <= to)
+                .cloned()
+                .collect())
+            .unwrap_or_default()
+    }
+}
@@ -0,0 +1,19 @@
+use crate::error::EngineResult;
+use crate::market::Market;
+use crate::order::Event;
+use crate::trades::Trade;
+
+// Custom game rules on top of the matching core. Hooks aren't journaled, a replay only
+// comes out the same when the replaying engine has the same hooks registered.
+pub trait EngineHook: Send + Sync {
+    // runs before anything else of the event, an error rejects it
+    fn pre_validate(&mut self, _event: &Event, _market: &Market) -> EngineResult<()> {
+        Ok(())
+    }
+
+    // runs for every trade of an event that went through
+    fn post_trade(&mut self, _trade: &Trade, _market: &mut Market) {}
+
+    // runs after every event, whether it went through or not
+    fn post_event(&mut self, _event: &Event, _result: &EngineResult<()>, _market: &mut Market) {}
+}
@@ -0,0 +1,111 @@
+use crate::latency::LatencyPolicy;
+use crate::colocation::PriorityClass;
+use crate::settlement::SettlementPolicy;
+use crate::order::{Event, MinimumRestingTime, OrderId};
+use crate::account::PortfolioId;
+use crate::market::{AssetId, Market, ReferencePricePolicy};
+use crate::news::NewsItem;
+use crate::leaderboard::ScoringMetric;
+use crate::bankruptcy::BankruptcyRule;
+use crate::ledger::InterestPolicy;
+use crate::fees::FeeSchedule;
+use crate::pricing::PriceMode;
+use crate::config::GameConfig;
+use crate::auction::MarketStructure;
+use crate::book::DarkPriority;
+use crate::faucet::FaucetPolicy;
+use crate::actor::Actor;
+use crate::suspension::Suspension;
+use crate::penalty::PenaltyRule;
+use crate::alerts::AlertRule;
+use crate::schedule::RecurringOrder;
+use uuid::Uuid;
+use crate::bootstrap::Allocation;
+use crate::roster::RosterEntry;
+
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum JournalRecord {
+    Event(Event),
+    CreatePortfolio { id: PortfolioId, initial_coins: usize },
+    CreateAsset { id: AssetId, name: String },
+    SetAssetAmount { portfolio: PortfolioId, asset: AssetId, amount: usize },
+    SetReferencePricePolicy(ReferencePricePolicy),
+    SetFairValue { asset: AssetId, price: usize },
+    // the fair value as the game's oracle sampled it, market makers follow
+    OraclePrice { asset: AssetId, price: usize },
+    SetAssetDecimals { asset: AssetId, decimals: u32 },
+    Tick,
+    PostNews(NewsItem),
+    SetScoringMetric(ScoringMetric),
+    ForceCancelOrder { asset: AssetId, order: OrderId },
+    CancelAllOrders { asset: AssetId },
+    BustTrade { trade: u64 },
+    SetBankruptcyRule(Option<BankruptcyRule>),
+    DeclareBankrupt { portfolio: PortfolioId },
+    RestartPortfolio { portfolio: PortfolioId, coins: usize },
+    SetInterestPolicy(Option<InterestPolicy>),
+    Mint { amount: usize },
+    Burn { amount: usize },
+    CreateBankPortfolio { id: PortfolioId },
+    FundBankPortfolio { amount: usize },
+    SetFeeSchedule(FeeSchedule),
+    SetPriceMode(PriceMode),
+    SetPriceBand(Option<usize>),
+    SetMinimumRestingTime(Option<MinimumRestingTime>),
+    SetLatency(Option<LatencyPolicy>),
+    SetConfig(GameConfig),
+    SetMarketStructure(MarketStructure),
+    SetDarkPriority(DarkPriority),
+    SetFaucetPolicy(Option<FaucetPolicy>),
+    ClaimFaucet { portfolio: PortfolioId },
+    SetPriorityClasses(Vec<PriorityClass>),
+    AssignPriorityClass { portfolio: PortfolioId, class: Option<String> },
+    BuyPriorityClass { portfolio: PortfolioId, class: String },
+    SetSettlementPolicy(Option<SettlementPolicy>),
+    ConfirmSettlement { trade: u64 },
+    // also when a pending trade timed out
+    RejectSettlement { trade: u64 },
+    Suspend(Suspension),
+    Reinstate(Suspension),
+    // by fingerprint, the token itself is only handed to the admin
+    ScheduleOrder(RecurringOrder),
+    CancelSchedule { portfolio: PortfolioId, id: Uuid },
+    ScheduleFired { id: Uuid, tick: u64, placed: Option<OrderId> },
+    AddMarketMaker { portfolio: PortfolioId, asset: AssetId, fair_value: usize, spread_percent: usize, quantity: usize },
+    AddAlert { portfolio: PortfolioId, id: Uuid, rule: AlertRule },
+    RemoveAlert { portfolio: PortfolioId, id: Uuid },
+    IssueToken { fingerprint: String, portfolio: PortfolioId },
+    RevokeToken { fingerprint: String },
+    SetPenaltyRule(Option<PenaltyRule>),
+    Allocate(Vec<Allocation>),
+    ClonePortfolio { source: PortfolioId, id: PortfolioId },
+    ProvisionPortfolios { coins: usize, entries: Vec<RosterEntry> },
+    SetPaused(bool),
+    // expires every order, nothing is taken after it
+    EndGame,
+    HaltAsset { asset: AssetId, halted: bool },
+    PayDividend { asset: AssetId, per_unit: usize },
+    // cancelled because its lifetime ran out
+    ExpireOrder { asset: AssetId, order: OrderId },
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub sequence: u64,
+    pub record: JournalRecord,
+    // replays ignore it, it's for the organizers
+    #[serde(default)]
+    pub actor: Actor,
+    // of the market once it was applied, only on entries that weren't applied within another one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_hash: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ReplayFile {
+    pub version: u32,
+    pub initial: Market,
+    pub entries: Vec<JournalEntry>,
+}
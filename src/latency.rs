@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+use std::time::Duration;
+use crate::account::PortfolioId;
+use crate::error::{EngineResult, ErrorType};
+
+// a minute, longer would just stall the queue
+pub const MAX_LATENCY_MILLIS: u64 = 60_000;
+
+// How long players' events are held back before they reach matching: `base_millis` plus up to
+// `jitter_millis`, drawn from `seed`. Portfolios listed in `portfolios` get their own base instead,
+// to hand out a speed advantage or to take one away.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct LatencyPolicy {
+    pub base_millis: u64,
+    #[serde(default)]
+    pub jitter_millis: u64,
+    #[serde(default)]
+    pub seed: u64,
+    #[serde(default)]
+    pub portfolios: HashMap<PortfolioId, u64>,
+}
+
+impl LatencyPolicy {
+    pub fn check(&self) -> EngineResult<()> {
+        let longest = self.portfolios.values().chain(Some(&self.base_millis)).max().cloned().unwrap_or(0);
+        if longest.saturating_add(self.jitter_millis) > MAX_LATENCY_MILLIS {
+            return Err(ErrorType::InvalidLatency);
+        }
+        Ok(())
+    }
+
+    // The delay of the portfolio's `n`-th event. Every portfolio draws on its own, so with the same
+    // seed it sees the same delays however the others' events interleave.
+    pub fn delay(&self, portfolio: PortfolioId, n: u64) -> Duration {
+        let base = self.portfolios.get(&portfolio).cloned().unwrap_or(self.base_millis);
+        let jitter = match self.jitter_millis {
+            0 => 0,
+            jitter => draw(self.seed, portfolio, n) % jitter.saturating_add(1),
+        };
+        Duration::from_millis(base.saturating_add(jitter))
+    }
+}
+
+fn draw(seed: u64, portfolio: PortfolioId, n: u64) -> u64 {
+    let bytes = portfolio.as_bytes();
+    let (mut high, mut low) = ([0u8; 8], [0u8; 8]);
+    high.copy_from_slice(&bytes[..8]);
+    low.copy_from_slice(&bytes[8..]);
+    let (high, low) = (u64::from_le_bytes(high), u64::from_le_bytes(low));
+    // xorshift, a zero state would stay zero
+    let mut state = (seed ^ high ^ low.rotate_left(17) ^ n.wrapping_mul(0x9e37_79b9_7f4a_7c15)) | 1;
+    for _ in 0..3 {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+    }
+    state
+}
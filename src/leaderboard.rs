@@ -0,0 +1,155 @@
+use crate::account::PortfolioId;
+use crate::engine::Engine;
+use crate::error::EngineResult;
+use crate::market::Market;
+use std::collections::HashMap;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ScoringMetric {
+    NetWorth,
+    Return,
+    Sharpe,
+}
+
+impl ScoringMetric {
+    pub fn parse(metric: &str) -> Option<ScoringMetric> {
+        match metric.to_lowercase().as_str() {
+            "networth" | "net_worth" => Some(ScoringMetric::NetWorth),
+            "return" => Some(ScoringMetric::Return),
+            "sharpe" => Some(ScoringMetric::Sharpe),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct LeaderboardEntry {
+    pub rank: usize,
+    pub portfolio: PortfolioId,
+    pub net_worth: usize,
+    pub realized_pnl: i64,
+    pub unrealized_pnl: i64,
+    pub score: f64,
+}
+
+// samples kept per portfolio, past it every other one is dropped so a long game keeps its whole
+// span at a coarser resolution
+const MAX_SAMPLES: usize = 4096;
+
+// Keeps every other value, the first and the last among them.
+fn thin_out<T: Copy>(values: &mut Vec<T>) {
+    let last = values.len().saturating_sub(1);
+    let mut index = 0;
+    values.retain(|_| {
+        let keep = index % 2 == 0 || index == last;
+        index += 1;
+        keep
+    });
+}
+
+// portfolio values sampled on creation and on every tick, first value is the initial capital
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct PortfolioHistory {
+    series: HashMap<PortfolioId, Vec<usize>>,
+    // tick of every sample in the series
+    ticks: HashMap<PortfolioId, Vec<u64>>,
+}
+
+impl PortfolioHistory {
+    pub fn new() -> PortfolioHistory {
+        PortfolioHistory {
+            series: HashMap::new(),
+            ticks: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, market: &Market) {
+        for id in market.portfolios.keys() {
+            self.record_one(market, *id);
+        }
+    }
+
+    pub fn record_one(&mut self, market: &Market, portfolio_id: PortfolioId) {
+        if let Ok(value) = market.portfolio_value(portfolio_id) {
+            let series = self.series.entry(portfolio_id).or_default();
+            let ticks = self.ticks.entry(portfolio_id).or_default();
+            series.push(value);
+            ticks.push(market.tick);
+            if series.len() > MAX_SAMPLES {
+                thin_out(series);
+                thin_out(ticks);
+            }
+        }
+    }
+
+    // value at the start of the tick, the first sample for portfolios created later
+    pub fn value_at(&self, portfolio_id: PortfolioId, tick: u64) -> Option<usize> {
+        let series = self.get_series(portfolio_id);
+        let ticks = self.ticks.get(&portfolio_id)?;
+        let index = ticks.iter().rposition(|t| *t <= tick).unwrap_or(0);
+        series.get(index).cloned()
+    }
+
+    pub fn get_series(&self, portfolio_id: PortfolioId) -> &[usize] {
+        self.series.get(&portfolio_id).map(|s| s.as_slice()).unwrap_or(&[])
+    }
+
+    // every sample with its tick, oldest first
+    pub fn get_samples(&self, portfolio_id: PortfolioId) -> Vec<(u64, usize)> {
+        let ticks = self.ticks.get(&portfolio_id).map(|t| t.as_slice()).unwrap_or(&[]);
+        ticks.iter().cloned().zip(self.get_series(portfolio_id).iter().cloned()).collect()
+    }
+
+    pub fn score(&self, portfolio_id: PortfolioId, net_worth: usize, metric: ScoringMetric) -> f64 {
+        let series = self.get_series(portfolio_id);
+        match metric {
+            ScoringMetric::NetWorth => net_worth as f64,
+            ScoringMetric::Return => match series.first() {
+                Some(initial) if *initial > 0 => (net_worth as f64 - *initial as f64) / *initial as f64 * 100.0,
+                _ => 0.0,
+            },
+            ScoringMetric::Sharpe => sharpe(series),
+        }
+    }
+}
+
+// Player portfolios by score, best first.
+pub fn rank(engine: &Engine, metric: ScoringMetric) -> EngineResult<Vec<LeaderboardEntry>> {
+    let mut entries = vec![];
+    for id in engine.market.portfolios.keys() {
+        if !engine.is_player(*id) {
+            continue;
+        }
+        let net_worth = engine.market.portfolio_value(*id)?;
+        entries.push(LeaderboardEntry {
+            rank: 0,
+            portfolio: *id,
+            net_worth,
+            realized_pnl: engine.market.get_portfolio(*id)?.realized_pnl,
+            unrealized_pnl: engine.market.unrealized_pnl(*id)?,
+            score: engine.portfolio_history.score(*id, net_worth, metric),
+        });
+    }
+    entries.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    for (i, entry) in entries.iter_mut().enumerate() {
+        entry.rank = i + 1;
+    }
+    Ok(entries)
+}
+
+// mean tick return over its standard deviation, risk free rate assumed zero
+fn sharpe(series: &[usize]) -> f64 {
+    let returns: Vec<f64> = series.windows(2)
+        .filter(|w| w[0] > 0)
+        .map(|w| (w[1] as f64 - w[0] as f64) / w[0] as f64)
+        .collect();
+    if returns.len() < 2 {
+        return 0.0;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    if variance == 0.0 {
+        return 0.0;
+    }
+    mean / variance.sqrt()
+}
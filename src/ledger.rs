@@ -0,0 +1,52 @@
+use crate::account::PortfolioId;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LedgerKind {
+    // paid by the bank to the portfolio
+    Interest,
+    // paid by the portfolio to the bank
+    Fee,
+    TakerFee,
+    // paid to the portfolio out of the taker fee
+    MakerRebate,
+    // paid by the bank for holdings of a delisted asset
+    Settlement,
+    // paid by the bank on a faucet claim
+    Faucet,
+    // paid by the portfolio to the bank for assets auctioned at game start
+    Allocation,
+    // paid by the bank for an order cancelled without a fill, see `FeeSchedule::cancel_refund_percent`
+    FeeRefund,
+    // paid by the portfolio to the bank for a priority class
+    Colocation,
+    // paid by the bank per unit held of an asset
+    Dividend,
+    // paid by the portfolio to the bank for too many rejected events, see `PenaltyRule`
+    Penalty,
+    // paid by the portfolio to the bank, the coins it had left when restarted after bankruptcy
+    Forfeit,
+    // paid by the bank for the fresh balance of a restart
+    Restart,
+    // paid back to the portfolio by the other side of a busted trade
+    BustRefund,
+    // paid by the portfolio to the other side of a busted trade
+    BustCharge,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub tick: u64,
+    pub portfolio: PortfolioId,
+    pub kind: LedgerKind,
+    pub amount: usize,
+    // the busted trade the entry reverses
+    #[serde(default)]
+    pub trade: Option<u64>,
+}
+
+// paid on free coins every `every_ticks` ticks, 100 basis points are one percent
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct InterestPolicy {
+    pub rate_basis_points: usize,
+    pub every_ticks: u64,
+}
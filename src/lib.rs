@@ -1,8 +1,85 @@
-#![feature(proc_macro_hygiene, decl_macro)]
+#![cfg_attr(feature = "server", feature(proc_macro_hygiene, decl_macro))]
+#[cfg(feature = "server")]
 #[macro_use] extern crate rocket;
 #[macro_use] extern crate serde;
 
 
-pub mod models;
+pub mod error;
+pub mod order;
+pub mod book;
+pub mod account;
+pub mod market;
+pub mod engine;
+pub mod prelude;
 pub mod game;
-pub mod server;
\ No newline at end of file
+#[cfg(feature = "server")]
+pub mod server;
+pub mod history;
+pub mod stream;
+pub mod journal;
+pub mod marketdata;
+pub mod providers;
+pub mod alerts;
+pub mod news;
+pub mod npc;
+pub mod trades;
+pub mod risk;
+pub mod leaderboard;
+pub mod bankruptcy;
+pub mod ledger;
+pub mod bank;
+pub mod queue;
+pub mod heartbeat;
+pub mod oco;
+pub mod fees;
+pub mod statement;
+pub mod rebalance;
+pub mod seed;
+pub mod audit;
+pub mod page;
+pub mod schedule;
+pub mod hooks;
+pub mod export;
+pub mod render;
+pub mod pricing;
+pub mod watchlist;
+pub mod config;
+pub mod lifecycle;
+pub mod sandbox;
+pub mod auction;
+pub mod faucet;
+pub mod actor;
+pub mod suspension;
+pub mod profile;
+pub mod bootstrap;
+pub mod debug;
+pub mod dto;
+pub mod dutch;
+pub mod format;
+pub mod roster;
+pub mod tape;
+pub mod oracle;
+pub mod activity;
+pub mod health;
+pub mod latency;
+pub mod colocation;
+pub mod cache;
+pub mod settlement;
+pub mod bus;
+pub mod validation;
+pub mod rules;
+pub mod algo;
+pub mod console;
+pub mod penalty;
+pub mod store;
+pub mod scenario;
+pub mod report;
+pub mod upload;
+#[cfg(feature = "server")]
+pub mod webhook;
+#[cfg(feature = "server")]
+pub mod versioning;
+#[cfg(feature = "server")]
+pub mod timestamps;
+#[cfg(feature = "wasm")]
+pub mod wasm;
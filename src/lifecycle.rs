@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::actor::Actor;
+use crate::error::{EngineResult, ErrorType};
+use crate::market::AssetId;
+use crate::order::{Order, OrderId, OrderSide};
+use crate::providers::Timestamp;
+
+// Why an order left the book without being filled or cancelled.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ExpiryReason {
+    // outlived its lifetime in game time, see `Market::game_time`
+    GoodTillDate,
+    GameEnded,
+    AssetDelisted,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum OrderState {
+    // accepted by the engine, not matched yet
+    New,
+    Resting,
+    PartiallyFilled,
+    Filled,
+    Cancelled,
+    Expired { reason: ExpiryReason },
+    Rejected,
+}
+
+impl OrderState {
+    pub fn is_final(&self) -> bool {
+        match self {
+            OrderState::Filled | OrderState::Cancelled | OrderState::Expired { .. } | OrderState::Rejected => true,
+            OrderState::New | OrderState::Resting | OrderState::PartiallyFilled => false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OrderRecord {
+    pub order_id: OrderId,
+    pub asset: AssetId,
+    pub portfolio: PortfolioId,
+    // who submitted the order
+    pub actor: Actor,
+    pub side: OrderSide,
+    pub quantity: usize,
+    pub filled: usize,
+    pub remaining: usize,
+    pub state: OrderState,
+    pub created_at: Timestamp,
+    pub updated_at: Timestamp,
+    // why the engine turned the order down
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    // the event fee billed for placing it
+    #[serde(skip)]
+    pub fee: usize,
+}
+
+// Every order the engine has seen and where it stands, orders stay here after they leave the book.
+#[derive(Clone, Debug, Default)]
+pub struct OrderRegistry {
+    orders: HashMap<OrderId, OrderRecord>,
+}
+
+impl OrderRegistry {
+    pub fn new() -> OrderRegistry {
+        OrderRegistry {
+            orders: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn open(&mut self, order: &Order, actor: Actor, fee: usize, now: Timestamp) {
+        self.orders.insert(order.id, OrderRecord {
+            order_id: order.id,
+            asset: order.asset,
+            portfolio: order.portfolio,
+            actor,
+            side: order.side,
+            quantity: order.quantity,
+            filled: 0,
+            remaining: order.quantity,
+            state: OrderState::New,
+            created_at: order.created_at,
+            updated_at: now,
+            reason: None,
+            fee,
+        });
+    }
+
+    // final states stay as they are, false if nothing changed
+    fn transition<F: FnOnce(&mut OrderRecord)>(&mut self, order_id: OrderId, now: Timestamp, update: F) -> bool {
+        match self.orders.get_mut(&order_id) {
+            Some(record) if !record.state.is_final() => {
+                update(record);
+                record.updated_at = now;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    pub(crate) fn fill(&mut self, order_id: OrderId, quantity: usize, now: Timestamp) -> bool {
+        self.transition(order_id, now, |record| {
+            record.filled = (record.filled + quantity).min(record.quantity);
+            record.remaining = record.quantity - record.filled;
+            record.state = if record.remaining == 0 { OrderState::Filled } else { OrderState::PartiallyFilled };
+        })
+    }
+
+    // an order that went through without a fill is on the book now
+    pub(crate) fn rest(&mut self, order_id: OrderId, now: Timestamp) -> bool {
+        match self.orders.get(&order_id) {
+            Some(record) if record.state == OrderState::New => {
+                self.transition(order_id, now, |record| record.state = OrderState::Resting)
+            },
+            _ => false,
+        }
+    }
+
+    pub(crate) fn cancel(&mut self, order_id: OrderId, now: Timestamp) -> bool {
+        self.transition(order_id, now, |record| record.state = OrderState::Cancelled)
+    }
+
+    pub(crate) fn expire(&mut self, order_id: OrderId, reason: ExpiryReason, now: Timestamp) -> bool {
+        self.transition(order_id, now, |record| record.state = OrderState::Expired { reason })
+    }
+
+    pub(crate) fn reject(&mut self, order_id: OrderId, reason: String, now: Timestamp) -> bool {
+        self.transition(order_id, now, |record| {
+            record.state = OrderState::Rejected;
+            record.reason = Some(reason);
+        })
+    }
+
+    pub fn get(&self, order_id: OrderId) -> EngineResult<&OrderRecord> {
+        self.orders.get(&order_id).ok_or(ErrorType::OrderNotFound(order_id))
+    }
+
+    // (asset, order) of everything the actor submitted that may still be in a book
+    pub fn open_by(&self, actor: &Actor) -> Vec<(AssetId, OrderId)> {
+        let mut open: Vec<&OrderRecord> = self.orders.values()
+            .filter(|r| r.actor == *actor && !r.state.is_final())
+            .collect();
+        open.sort_by_key(|r| (r.created_at, r.order_id));
+        open.into_iter().map(|r| (r.asset, r.order_id)).collect()
+    }
+
+    // oldest first
+    pub fn get_for(&self, portfolio_id: PortfolioId) -> Vec<&OrderRecord> {
+        let mut records: Vec<&OrderRecord> = self.orders.values()
+            .filter(|r| r.portfolio == portfolio_id)
+            .collect();
+        records.sort_by_key(|r| (r.created_at, r.order_id));
+        records
+    }
+}
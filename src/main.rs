@@ -1,17 +1,84 @@
-use market_game::models::*;
-use std::time::{Instant, Duration};
-use std::ops::Add;
+use market_game::prelude::*;
+use std::time::Duration;
 use market_game::game::Game;
 use market_game::server::start_server;
+use market_game::journal::ReplayFile;
+use market_game::scenario::Scenario;
+use market_game::audit::diff_snapshots;
+use market_game::providers::{ids_by_name, SystemClock};
+use std::env;
+use std::fs::File;
 
 
+fn replay(args: &[String]) {
+    let path = args.first().expect("usage: market_game replay <file> [sequence] [--serve]");
+    let file: ReplayFile = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+    let until = args.get(1).and_then(|s| s.parse::<u64>().ok());
+    let engine = Engine::replay(file, until).unwrap();
+    println!("sequence: {}", engine.sequence);
+    println!("{}", serde_json::to_string_pretty(&engine.market).unwrap());
+    if args.iter().any(|a| a == "--serve") {
+        start_server(Game::from_engine(engine));
+    }
+}
+
+// serves the game the scenario was recorded in and plays it back as the ticks go by
+fn scenario(args: &[String]) {
+    let path = args.first().expect("usage: market_game scenario <file>");
+    let scenario: Scenario = serde_json::from_reader(File::open(path).unwrap()).unwrap();
+    let game = Game::from_engine(scenario.engine().unwrap());
+    game.play_scenario(scenario).unwrap();
+    start_server(game);
+}
+
+fn verify(args: &[String]) {
+    let usage = "usage: market_game verify <replay file> <market snapshot>";
+    let file: ReplayFile = serde_json::from_reader(File::open(args.first().expect(usage)).unwrap()).unwrap();
+    let snapshot: Market = serde_json::from_reader(File::open(args.get(1).expect(usage)).unwrap()).unwrap();
+    match Engine::verify_replay(file, &snapshot).unwrap() {
+        None => println!("replay matches the snapshot"),
+        Some(divergence) => println!("{}", serde_json::to_string_pretty(&divergence).unwrap()),
+    }
+}
+
+fn diff(args: &[String]) {
+    let usage = "usage: market_game diff <market snapshot a> <market snapshot b>";
+    let a: Market = serde_json::from_reader(File::open(args.first().expect(usage)).unwrap()).unwrap();
+    let b: Market = serde_json::from_reader(File::open(args.get(1).expect(usage)).unwrap()).unwrap();
+    let diff = diff_snapshots(&a, &b);
+    if diff.is_empty() {
+        println!("no differences in portfolios, balances or open orders");
+    } else {
+        println!("{}", serde_json::to_string_pretty(&diff).unwrap());
+    }
+}
+
 fn main() {
-    let mut game = Game::new() ;
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(|s| s.as_str()) == Some("replay") {
+        return replay(&args[2..]);
+    }
+    if args.get(1).map(|s| s.as_str()) == Some("scenario") {
+        return scenario(&args[2..]);
+    }
+    if args.get(1).map(|s| s.as_str()) == Some("verify") {
+        return verify(&args[2..]);
+    }
+    if args.get(1).map(|s| s.as_str()) == Some("diff") {
+        return diff(&args[2..]);
+    }
+
+    let mut game = match env::var("ID_STRATEGY") {
+        Ok(name) => {
+            let ids = ids_by_name(&name).expect("ID_STRATEGY is one of random, sequential, ulid");
+            Game::from_engine(Engine::with_providers(Market::new(), Box::new(SystemClock), ids))
+        },
+        Err(_) => Game::new(),
+    };
     let p1 = game.create_portfolio(1000);
     let p2 = game.create_portfolio(1000);
-    let a1 = game.create_asset("a1_name".to_string());
-    let a1 = game.create_asset("a2_name".to_string());
-    //let a2 = game.create_asset("a2_name".to_string());
+    let a1 = game.create_asset("a1_name".to_string()).unwrap();
+    let _a2 = game.create_asset("a2_name".to_string()).unwrap();
     game.set_asset_amount(p1, a1, 100);
     game.set_asset_amount(p2, a1, 100);
 
@@ -22,16 +89,16 @@ fn main() {
     println!("port {:?}", game.read_engine().unwrap().market.portfolios.get(&p2).unwrap() );
 
     // sell order
-    let o1 = Order::new(
+    let o1 = game.new_order(
         p1,
         a1,
         OrderSide::Buy,
         10,
         OrderMode::Limit(1),
-        Instant::now().add(Duration::from_secs(24*60*60))
+        Duration::from_secs(24*60*60)
     ).unwrap();
 
-    let r1 = game.engine.write().unwrap().process(Event::Order(o1));
+    let r1 = game.process(Event::Order(o1));
     println!("resuult => {:?}", r1);
 
     println!("--------------");
@@ -43,15 +110,15 @@ fn main() {
 
     // buy order
 
-    let o2 = Order::new(
+    let o2 = game.new_order(
         p2,
         a1,
         OrderSide::Sell,
         5,
         OrderMode::Best,
-        Instant::now().add(Duration::from_secs(30*24*60*60))
+        Duration::from_secs(30*24*60*60)
     ).unwrap();
-    let r2 = game.write_engine().unwrap().process(Event::Order(o2));
+    let r2 = game.process(Event::Order(o2));
 
     println!("result => {:?}", r2);
     println!("--------------");
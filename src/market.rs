@@ -0,0 +1,1431 @@
+use uuid::Uuid;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::time::{Duration, Instant};
+use crate::error::{checked_add, checked_mul, checked_sub, EngineResult, ErrorType};
+use crate::latency::LatencyPolicy;
+use crate::order::{Event, MinimumRestingTime, Order, OrderId, OrderMode, OrderSide};
+use crate::book::{Book, DarkPriority, Fill};
+use crate::faucet::Faucet;
+use crate::colocation::Colocation;
+use crate::settlement::{PendingSettlement, SettlementPolicy};
+use crate::bootstrap::{self, Allocation};
+use crate::account::{Account, Portfolio, PortfolioId, Reservation, ReservedAccount};
+use crate::trades::{ExecutionReport, Trade};
+use crate::ledger::{InterestPolicy, LedgerEntry, LedgerKind};
+use crate::rules::SessionTimes;
+use crate::schedule::Schedules;
+use crate::providers::{default_ids, Timestamp};
+use crate::oco::OcoGroup;
+use crate::fees::FeeSchedule;
+use crate::pricing::{to_coins, to_signed, PriceMode};
+use crate::watchlist::WatchQuote;
+use crate::lifecycle::{ExpiryReason, OrderRecord};
+use crate::penalty::Penalty;
+use crate::store::Touched;
+use crate::auction::{self, Clearing, MarketStructure};
+use crate::roster::{default_starting_coins, RosterEntry};
+
+pub type AssetId = Uuid;
+
+#[derive(Clone, Debug, Serialize)]
+pub enum Notification {
+    Fill {
+        order_id: OrderId,
+        asset: AssetId,
+        side: OrderSide,
+        quantity: usize,
+        price: usize,
+    },
+    OrderCancelled {
+        order_id: OrderId,
+        asset: AssetId,
+    },
+    MarginWarning {
+        free_coins: usize,
+        threshold: usize,
+    },
+    Alert {
+        alert: Uuid,
+        message: String,
+    },
+    TradeBusted {
+        trade_id: u64,
+        asset: AssetId,
+        quantity: usize,
+        price: usize,
+    },
+    // confirmed from outside, what the trade brought in is free now
+    TradeSettled {
+        trade_id: u64,
+        asset: AssetId,
+    },
+    Bankrupt {
+        net_worth: usize,
+    },
+    Restarted {
+        coins: usize,
+    },
+    Delisted {
+        asset: AssetId,
+        quantity: usize,
+        price: usize,
+    },
+    // paid by the bank for what the portfolio held of the asset
+    Dividend {
+        asset: AssetId,
+        quantity: usize,
+        per_unit: usize,
+        amount: usize,
+    },
+    // taken out of the book without a fill or a cancel
+    OrderExpired {
+        order_id: OrderId,
+        asset: AssetId,
+        reason: ExpiryReason,
+    },
+    // one rejected event too many, see `PenaltyRule`. The fine is what the free coins covered of it
+    Penalized {
+        fine: usize,
+        banned_until: Option<u64>,
+    },
+    // for assets on the portfolio's watchlist, whenever their book changes
+    Quote(WatchQuote),
+    // every change of an order's state, see `OrderState`
+    OrderUpdate(OrderRecord),
+    // once per incoming order that traded, all its fills in one
+    Execution(ExecutionReport),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ReferencePricePolicy {
+    Disabled,
+    LastTrade,
+    Midpoint,
+    FairValue,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Asset {
+    pub id: Uuid,
+    pub name: String,
+    // places after the point its prices are shown with, 12345 with 2 is 123.45
+    #[serde(default)]
+    pub decimals: u32,
+}
+
+impl Asset {
+    pub fn with_id(id: AssetId, name: String) -> Asset {
+        Asset {
+            id,
+            name,
+            decimals: 0,
+        }
+    }
+}
+
+// A holding with what it's worth. Without a trade there's no mark price, and without a cost basis
+// no PnL, assets handed out by an admin have none.
+#[derive(Clone, Debug, Serialize)]
+pub struct Position {
+    pub asset: AssetId,
+    pub name: String,
+    pub quantity: usize,
+    pub locked: usize,
+    pub average_cost: Option<f64>,
+    pub mark_price: Option<usize>,
+    pub market_value: Option<i64>,
+    pub unrealized_pnl: Option<i64>,
+}
+
+// The part of the market a single event on one asset can touch.
+pub struct AssetScope {
+    asset_id: AssetId,
+    book: Option<Book>,
+    portfolios: Vec<Portfolio>,
+    last_trade_price: Option<usize>,
+    trade_count: usize,
+    ledger_count: usize,
+    bank_account: usize,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Market {
+    pub bank_account: usize,
+    pub portfolios: HashMap<Uuid, Portfolio>,
+    pub assets: HashMap<Uuid, Asset>,
+    pub asset_names: HashMap<String, AssetId>,
+    pub books: HashMap<Uuid, Book>,
+    pub reference_price_policy: ReferencePricePolicy,
+    pub last_trade_prices: HashMap<AssetId, usize>,
+    pub fair_values: HashMap<AssetId, usize>,
+    pub margin_warning_threshold: usize,
+    // what new portfolios start with unless told otherwise
+    #[serde(default = "default_starting_coins")]
+    pub starting_coins: usize,
+    // the portfolios handed out in bulk, in the order they were created
+    #[serde(default)]
+    pub roster: Vec<RosterEntry>,
+    #[serde(default)]
+    pub trades: Vec<Trade>,
+    #[serde(default)]
+    pub tick: u64,
+    #[serde(default)]
+    pub now: Timestamp,
+    // Milliseconds the game has been running, time spent paused or not loaded doesn't count. Orders
+    // expire by it.
+    #[serde(default)]
+    pub game_time: Timestamp,
+    #[serde(default)]
+    pub paused: bool,
+    // for good, no events are taken and time stands still
+    #[serde(default)]
+    pub ended: bool,
+    // the game time each order placed expires at, dropped once it's no longer in a book
+    #[serde(default)]
+    pub expiries: HashMap<OrderId, Timestamp>,
+    #[serde(default)]
+    pub interest_policy: Option<InterestPolicy>,
+    #[serde(default)]
+    pub ledger: Vec<LedgerEntry>,
+    // trades on behalf of the bank in open market operations
+    #[serde(default)]
+    pub bank_portfolio: Option<PortfolioId>,
+    #[serde(default)]
+    pub minted: usize,
+    #[serde(default)]
+    pub burned: usize,
+    #[serde(default)]
+    pub oco_groups: HashMap<OrderId, OcoGroup>,
+    // the group of each leg
+    #[serde(default)]
+    oco_legs: HashMap<OrderId, OrderId>,
+    #[serde(default)]
+    pub fee_schedule: FeeSchedule,
+    #[serde(default)]
+    pub price_mode: PriceMode,
+    // limits further than this many percent from the reference price are rejected
+    #[serde(default)]
+    pub price_band_percent: Option<usize>,
+    #[serde(default)]
+    pub minimum_resting_time: Option<MinimumRestingTime>,
+    // applied by the queue in front of the engine, kept here so it's journaled and restored
+    #[serde(default)]
+    pub latency: Option<LatencyPolicy>,
+    #[serde(default)]
+    pub market_structure: MarketStructure,
+    #[serde(default)]
+    pub dark_priority: DarkPriority,
+    #[serde(default)]
+    pub faucet: Faucet,
+    // which portfolios' events the queue takes first
+    #[serde(default)]
+    pub colocation: Colocation,
+    // trades wait for confirmation from outside while it's set
+    #[serde(default)]
+    pub settlement: Option<SettlementPolicy>,
+    #[serde(default)]
+    pub pending_settlements: BTreeMap<u64, PendingSettlement>,
+    // no orders are taken for them, orders already in their books may still be cancelled
+    #[serde(default)]
+    pub halted: BTreeSet<AssetId>,
+    // recurring orders, placed by `run_schedules`
+    #[serde(default)]
+    pub schedules: Schedules,
+    // no new orders outside them, none means always open
+    #[serde(default)]
+    pub session_times: Option<SessionTimes>,
+    // names the game in `GET /game/<gid>/report`, nil for games saved before it had one
+    #[serde(default)]
+    pub game_id: Uuid,
+    #[serde(skip)]
+    pub notifications: Vec<(PortfolioId, Notification)>,
+    // time spent in `process_trade` while the engine profiles, taken by the engine after each event
+    #[serde(skip)]
+    pub(crate) profiling: bool,
+    #[serde(skip)]
+    pub(crate) transfer_time: Duration,
+    // what `PortfolioStore` still has to catch up on
+    #[serde(skip)]
+    pub(crate) touched: Touched,
+}
+
+// what the portfolio's cost basis is up or down at the prices
+pub fn unrealized_pnl_at(portfolio: &Portfolio, prices: &HashMap<AssetId, usize>, price_mode: PriceMode) -> EngineResult<i64> {
+    let mut pnl = 0;
+    for (asset, basis) in &portfolio.cost_basis {
+        if let Some(price) = prices.get(asset) {
+            pnl += to_signed(price_mode.notional(*price, basis.quantity)? - basis.total_cost as i128)?;
+        }
+    }
+    Ok(pnl)
+}
+
+impl Default for Market {
+    fn default() -> Market {
+        Market::new()
+    }
+}
+
+impl Market {
+
+    pub fn new() -> Market {
+        Market {
+            bank_account: 0,
+            portfolios: HashMap::new(),
+            assets: HashMap::new(),
+            asset_names: HashMap::new(),
+            books: HashMap::new(),
+            reference_price_policy: ReferencePricePolicy::Disabled,
+            last_trade_prices: HashMap::new(),
+            fair_values: HashMap::new(),
+            margin_warning_threshold: 10,
+            starting_coins: default_starting_coins(),
+            roster: vec![],
+            trades: vec![],
+            tick: 0,
+            now: 0,
+            game_time: 0,
+            paused: false,
+            ended: false,
+            expiries: HashMap::new(),
+            interest_policy: None,
+            ledger: vec![],
+            bank_portfolio: None,
+            minted: 0,
+            burned: 0,
+            oco_groups: HashMap::new(),
+            oco_legs: HashMap::new(),
+            fee_schedule: FeeSchedule::default(),
+            price_mode: PriceMode::Unsigned,
+            price_band_percent: None,
+            session_times: None,
+            schedules: Schedules::default(),
+            minimum_resting_time: None,
+            latency: None,
+            market_structure: MarketStructure::Continuous,
+            dark_priority: DarkPriority::BehindLit,
+            faucet: Faucet::default(),
+            colocation: Colocation::default(),
+            settlement: None,
+            pending_settlements: BTreeMap::new(),
+            halted: BTreeSet::new(),
+            game_id: default_ids().next_id(),
+            notifications: vec![],
+            profiling: false,
+            transfer_time: Duration::from_secs(0),
+            touched: Touched::default(),
+        }
+    }
+
+    pub fn add_portfolio(&mut self, mut portfolio: Portfolio) -> PortfolioId {
+        self.assets.values().for_each(|asset|{
+            portfolio.ensure_asset_account(asset.id);
+        });
+        let id = portfolio.id;
+        self.portfolios.insert(id, portfolio);
+        self.touched.mark(id);
+        id
+    }
+
+    pub fn add_asset(&mut self, asset: Asset) -> EngineResult<AssetId> {
+        if self.asset_names.contains_key(&asset.name) {
+            return Err(ErrorType::AssetNameAlreadyTaken(asset.name));
+        }
+        let id = asset.id;
+        self.asset_names.insert(asset.name.clone(), id);
+        self.assets.insert(id, asset);
+        let touched = &self.touched;
+        self.portfolios.values_mut().for_each(|portfolio|{
+            portfolio.assets.insert(id, Account::new(0));
+            touched.mark(portfolio.id);
+        });
+        self.books.insert(id, Book::new(id));
+        Ok(id)
+    }
+
+    fn get_order_book_mut(&mut self, asset_id: Uuid) -> EngineResult<&mut Book> {
+        self.books.get_mut(&asset_id)
+            .ok_or(ErrorType::AssetNotFound(asset_id))
+    }
+
+    pub fn get_order_book(&self, asset_id: Uuid) -> EngineResult<&Book> {
+        self.books.get(&asset_id)
+            .ok_or(ErrorType::AssetNotFound(asset_id))
+    }
+
+    pub fn get_asset(&self, asset_id: &Uuid) -> EngineResult<&Asset> {
+        self.assets.get(asset_id)
+            .ok_or(ErrorType::AssetNotFound(*asset_id))
+    }
+
+    pub fn get_asset_by_name(&self, name: &str) -> EngineResult<&Asset> {
+        self.asset_names.get(name)
+            .and_then(|id| self.assets.get(id))
+            .ok_or(ErrorType::AssetNameNotFound(name.to_string()))
+    }
+
+    pub fn snapshot_scope(&self, asset_id: AssetId, portfolio_id: PortfolioId) -> AssetScope {
+        let book = self.books.get(&asset_id).cloned();
+        let mut portfolio_ids: Vec<PortfolioId> = vec![portfolio_id];
+        if let Some(book) = &book {
+            book.all_orders()
+                .for_each(|o| portfolio_ids.push(o.portfolio));
+        }
+        portfolio_ids.sort();
+        portfolio_ids.dedup();
+        AssetScope {
+            asset_id,
+            book,
+            portfolios: portfolio_ids.iter()
+                .filter_map(|id| self.portfolios.get(id).cloned())
+                .collect(),
+            last_trade_price: self.last_trade_prices.get(&asset_id).cloned(),
+            trade_count: self.trades.len(),
+            ledger_count: self.ledger.len(),
+            bank_account: self.bank_account,
+        }
+    }
+
+    pub fn restore_scope(&mut self, scope: AssetScope) {
+        if let Some(book) = scope.book {
+            self.books.insert(scope.asset_id, book);
+        }
+        for portfolio in scope.portfolios {
+            self.touched.mark(portfolio.id);
+            self.portfolios.insert(portfolio.id, portfolio);
+        }
+        match scope.last_trade_price {
+            Some(price) => self.last_trade_prices.insert(scope.asset_id, price),
+            None => self.last_trade_prices.remove(&scope.asset_id),
+        };
+        for trade in self.trades.iter().skip(scope.trade_count) {
+            self.pending_settlements.remove(&trade.id);
+        }
+        self.trades.truncate(scope.trade_count);
+        self.ledger.truncate(scope.ledger_count);
+        self.bank_account = scope.bank_account;
+    }
+
+    // coins plus holdings valued at the last trade price, nothing if negative prices outweigh the rest
+    pub fn portfolio_value(&self, portfolio_id: PortfolioId) -> EngineResult<usize> {
+        let portfolio = self.get_portfolio(portfolio_id)?;
+        let mut value = portfolio.coins.get_total_amount() as i128;
+        for (asset, account) in &portfolio.assets {
+            if let Some(price) = self.last_trade_prices.get(asset) {
+                value += self.price_mode.notional(*price, account.get_total_amount())?;
+            }
+        }
+        to_coins(value.max(0))
+    }
+
+    // what the positions with a known cost would gain or lose if sold at the last trade price
+    pub fn unrealized_pnl(&self, portfolio_id: PortfolioId) -> EngineResult<i64> {
+        unrealized_pnl_at(self.get_portfolio(portfolio_id)?, &self.last_trade_prices, self.price_mode)
+    }
+
+    // Every asset the portfolio holds or has a cost for, marked at the last trade price like
+    // `unrealized_pnl`, by asset name.
+    pub fn positions(&self, portfolio_id: PortfolioId) -> EngineResult<Vec<Position>> {
+        let portfolio = self.get_portfolio(portfolio_id)?;
+        let mut assets: Vec<AssetId> = portfolio.assets.iter()
+            .filter(|(_, account)| account.get_total_amount() > 0)
+            .map(|(asset, _)| *asset)
+            .chain(portfolio.cost_basis.keys().cloned())
+            .collect();
+        assets.sort();
+        assets.dedup();
+        let mut positions = vec![];
+        for asset in assets {
+            let (quantity, locked) = portfolio.assets.get(&asset)
+                .map_or((0, 0), |account| (account.get_total_amount(), account.get_locked_amount()));
+            let basis = portfolio.cost_basis.get(&asset).filter(|basis| basis.quantity > 0);
+            let mark_price = self.last_trade_prices.get(&asset).cloned();
+            let (market_value, unrealized_pnl) = match mark_price {
+                Some(price) => (
+                    Some(to_signed(self.price_mode.notional(price, quantity)?)?),
+                    match basis {
+                        Some(basis) => Some(to_signed(self.price_mode.notional(price, basis.quantity)? - basis.total_cost as i128)?),
+                        None => None,
+                    },
+                ),
+                None => (None, None),
+            };
+            positions.push(Position {
+                asset,
+                name: self.assets.get(&asset).map(|a| a.name.clone()).unwrap_or_default(),
+                quantity,
+                locked,
+                average_cost: basis.map(|basis| basis.average_cost()),
+                mark_price,
+                market_value,
+                unrealized_pnl,
+            });
+        }
+        positions.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(positions)
+    }
+
+    pub fn reference_price(&self, asset_id: AssetId) -> Option<usize> {
+        match self.reference_price_policy {
+            ReferencePricePolicy::Disabled => None,
+            ReferencePricePolicy::LastTrade => self.last_trade_prices.get(&asset_id).cloned(),
+            ReferencePricePolicy::FairValue => self.fair_values.get(&asset_id).cloned(),
+            ReferencePricePolicy::Midpoint => {
+                let book = self.books.get(&asset_id)?;
+                let best_bid = book.buy_orders().filter_map(|o| o.mode.get_limit().ok()).max()?;
+                let best_ask = book.sell_orders().filter_map(|o| o.mode.get_limit().ok()).min()?;
+                Some((best_bid + best_ask) / 2)
+            },
+        }
+    }
+
+    // Fat-finger protection, against the reference price or the last trade if there's no policy.
+    // Without either the asset hasn't traded yet and there's nothing to compare with.
+    pub fn check_price_band(&self, order: &Order) -> EngineResult<()> {
+        let (percent, limit) = match (self.price_band_percent, order.mode) {
+            (Some(percent), OrderMode::Limit(limit)) if !order.overrides_price_band => (percent, limit),
+            _ => return Ok(()),
+        };
+        let (min, max) = match self.price_band_limits(order.asset, percent)? {
+            Some(limits) => limits,
+            None => return Ok(()),
+        };
+        if limit < min || limit > max {
+            return Err(ErrorType::PriceOutOfBounds { limit, min, max });
+        }
+        Ok(())
+    }
+
+    // The lowest and highest limit a band of `percent` lets through, none without a price to compare with.
+    pub fn price_band_limits(&self, asset_id: AssetId, percent: usize) -> EngineResult<Option<(usize, usize)>> {
+        let reference = match self.reference_price(asset_id).or_else(|| self.last_trade_prices.get(&asset_id).cloned()) {
+            Some(reference) => reference,
+            None => return Ok(None),
+        };
+        let band = checked_mul(reference, percent)? / 100;
+        Ok(Some((reference.saturating_sub(band), checked_add(reference, band)?)))
+    }
+
+    // A Best order that can't be filled immediately rests as a limit order at the reference price.
+    fn resting_order(&self, order: Order) -> EngineResult<Order> {
+        if order.mode != OrderMode::Best {
+            return Ok(order);
+        }
+        match self.reference_price(order.asset) {
+            Some(price) if price > 0 => {
+                let mut resting = order;
+                resting.mode = OrderMode::Limit(price);
+                Ok(resting)
+            },
+            _ => Err(ErrorType::NotEnoughMatchingOrdersToImmediatelyFillBestOrder),
+        }
+    }
+
+    pub(crate) fn bill_fee(&mut self, portfolio_id: Uuid, amount: usize) -> EngineResult<()>{
+        self.get_portfolio_mut(portfolio_id)?
+            .coins.spend_from_free_amount(amount)?;
+        self.bank_account = checked_add(self.bank_account, amount)?;
+        self.record_ledger(portfolio_id, LedgerKind::Fee, amount);
+        Ok(())
+    }
+
+    // as much of the fine as the free coins cover goes to the bank account, the coins taken
+    pub(crate) fn collect_penalty(&mut self, portfolio_id: PortfolioId, penalty: Penalty) -> EngineResult<usize> {
+        let fine = penalty.fine.min(self.get_portfolio(portfolio_id)?.coins.get_free_amount());
+        self.get_portfolio_mut(portfolio_id)?.coins.spend_from_free_amount(fine)?;
+        self.bank_account = checked_add(self.bank_account, fine)?;
+        self.record_ledger(portfolio_id, LedgerKind::Penalty, fine);
+        self.notifications.push((portfolio_id, Notification::Penalized { fine, banned_until: penalty.banned_until }));
+        Ok(fine)
+    }
+
+    // out of the bank account, which has to afford it
+    pub(crate) fn refund_fee(&mut self, portfolio_id: PortfolioId, amount: usize) -> EngineResult<()> {
+        if self.bank_account < amount {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        self.get_portfolio_mut(portfolio_id)?.coins.add(amount)?;
+        self.bank_account -= amount;
+        self.record_ledger(portfolio_id, LedgerKind::FeeRefund, amount);
+        Ok(())
+    }
+
+    // the taker pays from its free coins, the bank keeps what isn't rebated to the maker
+    fn charge_trade_fees(&mut self, taker: PortfolioId, maker: PortfolioId, notional: usize) -> EngineResult<()> {
+        let taker_volume = self.get_portfolio(taker)?.activity.volume();
+        let maker_volume = self.get_portfolio(maker)?.activity.volume();
+        let (taker_fee, rebate) = self.fee_schedule.trade_fees(notional, taker_volume, maker_volume)?;
+        if taker_fee == 0 {
+            return Ok(());
+        }
+        self.get_portfolio_mut(taker)?.coins.spend_from_free_amount(taker_fee)?;
+        self.get_portfolio_mut(maker)?.coins.add(rebate)?;
+        self.bank_account = checked_add(self.bank_account, taker_fee - rebate)?;
+        self.record_ledger(taker, LedgerKind::TakerFee, taker_fee);
+        self.record_ledger(maker, LedgerKind::MakerRebate, rebate);
+        Ok(())
+    }
+
+    fn record_ledger(&mut self, portfolio: PortfolioId, kind: LedgerKind, amount: usize) {
+        if amount == 0 {
+            return;
+        }
+        self.ledger.push(LedgerEntry {
+            tick: self.tick,
+            portfolio,
+            kind,
+            amount,
+            trade: None,
+        });
+    }
+
+    // pays interest on free coins from the bank for as long as the bank can afford it
+    pub fn pay_interest(&mut self) {
+        let policy = match self.interest_policy {
+            Some(policy) if self.tick.checked_rem(policy.every_ticks) == Some(0) => policy,
+            _ => return,
+        };
+        let mut portfolio_ids: Vec<PortfolioId> = self.portfolios.keys().cloned().collect();
+        portfolio_ids.sort();
+        for id in portfolio_ids {
+            let portfolio = &self.portfolios[&id];
+            if portfolio.bankrupt_since.is_some() || self.bank_portfolio == Some(id) {
+                continue;
+            }
+            let amount = match checked_mul(portfolio.coins.get_free_amount(), policy.rate_basis_points) {
+                Ok(amount) => (amount / 10000).min(self.bank_account),
+                Err(_) => continue,
+            };
+            if amount == 0 {
+                continue;
+            }
+            self.touched.mark(id);
+            let paid = self.portfolios.get_mut(&id).map(|p| p.coins.add(amount).is_ok());
+            if paid != Some(true) {
+                continue;
+            }
+            self.bank_account -= amount;
+            self.record_ledger(id, LedgerKind::Interest, amount);
+        }
+    }
+
+    // pays a faucet claim out of the bank account, bankrupt portfolios wait for their restart
+    pub fn claim_faucet(&mut self, portfolio_id: PortfolioId) -> EngineResult<usize> {
+        if self.get_portfolio(portfolio_id)?.bankrupt_since.is_some() {
+            return Err(ErrorType::PortfolioBankrupt(portfolio_id));
+        }
+        let amount = self.faucet.check(portfolio_id, self.tick)?;
+        if self.bank_account < amount {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        self.get_portfolio_mut(portfolio_id)?.coins.add(amount)?;
+        self.bank_account -= amount;
+        self.faucet.record(portfolio_id, amount, self.tick);
+        self.record_ledger(portfolio_id, LedgerKind::Faucet, amount);
+        Ok(amount)
+    }
+
+    // pays the class's price to the bank account, the coins paid
+    pub fn buy_priority_class(&mut self, portfolio_id: PortfolioId, class: &str) -> EngineResult<usize> {
+        if self.get_portfolio(portfolio_id)?.bankrupt_since.is_some() {
+            return Err(ErrorType::PortfolioBankrupt(portfolio_id));
+        }
+        let price = self.colocation.class(class)?.price.ok_or_else(|| ErrorType::PriorityClassNotForSale(class.to_string()))?;
+        if self.colocation.class_of(portfolio_id) == class {
+            return Err(ErrorType::InvalidState);
+        }
+        self.get_portfolio_mut(portfolio_id)?.coins.spend_from_free_amount(price)?;
+        self.bank_account = checked_add(self.bank_account, price)?;
+        self.colocation.assign(portfolio_id, Some(class.to_string()))?;
+        self.record_ledger(portfolio_id, LedgerKind::Colocation, price);
+        Ok(price)
+    }
+
+    // all or nothing, auctioned quantities are bought at their cost
+    pub(crate) fn allocate(&mut self, allocations: &[Allocation]) -> EngineResult<()> {
+        for allocation in allocations {
+            self.get_asset(&allocation.asset)?;
+            self.get_portfolio(allocation.portfolio)?;
+        }
+        for (portfolio_id, cost) in bootstrap::costs(allocations)? {
+            if self.get_portfolio(portfolio_id)?.coins.get_free_amount() < cost {
+                return Err(ErrorType::InsufficientFreeAmount);
+            }
+        }
+        for allocation in allocations {
+            let portfolio = self.get_portfolio_mut(allocation.portfolio)?;
+            portfolio.ensure_asset_account(allocation.asset).add(allocation.quantity)?;
+            if allocation.cost > 0 {
+                portfolio.coins.spend_from_free_amount(allocation.cost)?;
+                portfolio.record_buy(allocation.asset, allocation.quantity, allocation.cost as i128)?;
+                self.bank_account = checked_add(self.bank_account, allocation.cost)?;
+                self.record_ledger(allocation.portfolio, LedgerKind::Allocation, allocation.cost);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn mint(&mut self, amount: usize) -> EngineResult<()> {
+        self.bank_account = checked_add(self.bank_account, amount)?;
+        self.minted = self.minted.saturating_add(amount);
+        Ok(())
+    }
+
+    pub fn burn(&mut self, amount: usize) -> EngineResult<()> {
+        if self.bank_account < amount {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        self.bank_account -= amount;
+        self.burned += amount;
+        Ok(())
+    }
+
+    pub fn create_bank_portfolio(&mut self, id: PortfolioId) -> EngineResult<()> {
+        if self.bank_portfolio.is_some() {
+            return Err(ErrorType::InvalidState);
+        }
+        self.add_portfolio(Portfolio::with_id(id, 0));
+        self.bank_portfolio = Some(id);
+        Ok(())
+    }
+
+    // moves coins from the bank account to the portfolio the bank trades with
+    pub fn fund_bank_portfolio(&mut self, amount: usize) -> EngineResult<()> {
+        let id = self.bank_portfolio.ok_or(ErrorType::InvalidState)?;
+        if self.bank_account < amount {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        self.get_portfolio_mut(id)?.coins.add(amount)?;
+        self.bank_account -= amount;
+        Ok(())
+    }
+
+    pub fn get_ledger_for(&self, portfolio_id: PortfolioId) -> Vec<&LedgerEntry> {
+        self.ledger.iter().filter(|e| e.portfolio == portfolio_id).collect()
+    }
+
+    // the caller rolls back what the earlier orders did if a later one fails
+    pub(crate) fn fill_batch(&mut self, orders: &[Order]) -> EngineResult<()> {
+        let portfolio = orders.first().ok_or(ErrorType::InvalidBatch)?.portfolio;
+        if orders.iter().any(|o| o.portfolio != portfolio) {
+            return Err(ErrorType::InvalidBatch);
+        }
+        for order in orders {
+            self.fill_order(*order)?;
+        }
+        Ok(())
+    }
+
+    // Places both orders and links whatever of them is left resting. The caller rolls back on failure.
+    pub(crate) fn fill_oco(&mut self, first: Order, second: Order, trigger_quantity: Option<usize>) -> EngineResult<()> {
+        if first.portfolio != second.portfolio || first.id == second.id {
+            return Err(ErrorType::InvalidOcoGroup);
+        }
+        let group = OcoGroup::new(&first, &second, trigger_quantity);
+        self.fill_order(first)?;
+        // filled enough on entry, the second order never goes in
+        if group.is_triggered(&group.legs[0], self.remaining_quantity(first.asset, first.id)) {
+            self.expiries.remove(&second.id);
+            self.notifications.push((second.portfolio, Notification::OrderCancelled {
+                order_id: second.id,
+                asset: second.asset,
+            }));
+            return Ok(());
+        }
+        self.fill_order(second)?;
+        for leg in &group.legs {
+            self.oco_legs.insert(leg.order, group.id);
+        }
+        self.oco_groups.insert(group.id, group);
+        Ok(())
+    }
+
+    pub fn get_oco_group_of(&self, order_id: OrderId) -> Option<&OcoGroup> {
+        self.oco_legs.get(&order_id).and_then(|group| self.oco_groups.get(group))
+    }
+
+    fn remove_oco_group(&mut self, group_id: OrderId) {
+        if let Some(group) = self.oco_groups.remove(&group_id) {
+            for leg in &group.legs {
+                self.oco_legs.remove(&leg.order);
+            }
+        }
+    }
+
+    fn remaining_quantity(&self, asset_id: AssetId, order_id: OrderId) -> usize {
+        self.books.get(&asset_id)
+            .and_then(|book| book.get_order(order_id).ok())
+            .map_or(0, |o| o.quantity)
+    }
+
+    // Cancels the other order of every group where one order filled enough since the notifications
+    // up to `seen`. Runs after an event went through, cancels dissolve their group right away so a
+    // leg missing from its book here was filled.
+    pub(crate) fn resolve_oco_groups(&mut self, seen: usize) {
+        let mut groups: Vec<OcoGroup> = self.notifications.iter().skip(seen)
+            .filter_map(|(_, notification)| match notification {
+                Notification::Fill { order_id, .. } => self.get_oco_group_of(*order_id).cloned(),
+                _ => None,
+            })
+            .collect();
+        groups.sort_by_key(|g| g.id);
+        groups.dedup_by_key(|g| g.id);
+        for group in groups {
+            let triggered = group.legs.iter()
+                .position(|leg| group.is_triggered(leg, self.remaining_quantity(leg.asset, leg.order)));
+            if let Some(index) = triggered {
+                self.remove_oco_group(group.id);
+                let other = group.legs[1 - index];
+                if self.remaining_quantity(other.asset, other.order) > 0 {
+                    let _ = self.force_cancel_order(other.asset, other.order);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn fill_order(&mut self, order: Order)  -> EngineResult<()> {
+        // a sell below zero is paid for by the seller, it needs the coins up front
+        if let (OrderSide::Sell, OrderMode::Limit(limit)) = (order.side, order.mode) {
+            let notional = self.price_mode.notional(limit, order.quantity)?;
+            if notional < 0 && (self.get_portfolio(order.portfolio)?.coins.get_free_amount() as i128) < -notional {
+                return Err(ErrorType::InsufficientFreeAmount);
+            }
+        }
+
+        // crossed when the tick ends, see `run_auctions`
+        if self.market_structure == MarketStructure::BatchAuction {
+            if order.mode == OrderMode::Best {
+                return Err(ErrorType::BestOrderInBatchAuction);
+            }
+            return self.add_order(order, true);
+        }
+
+        let dark_priority = self.dark_priority;
+        let book = self.get_order_book_mut(order.asset)?;
+
+        let fills = book.find_fills(&order, dark_priority);
+        if fills.is_empty() {
+            let resting = self.resting_order(order)?;
+            self.add_order(resting, true)?;
+        }else {
+            let filled: usize = fills.iter().map(|f| f.quantity).sum();
+            let remaining = order.quantity - filled;
+            let mut filled_order = order;
+            filled_order.quantity = filled;
+            let started = if self.profiling { Some(Instant::now()) } else { None };
+            self.process_trade(filled_order, fills)?;
+            if let Some(started) = started {
+                self.transfer_time += started.elapsed();
+            }
+            if remaining > 0 {
+                let mut remainder = order;
+                remainder.quantity = remaining;
+                let resting = self.resting_order(remainder)?;
+                self.add_order(resting, true)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // What the order would get from the book right now, (price, quantity) per resting order it
+    // meets, priced like `process_trade`. The book stays as it is.
+    pub fn shadow_fill(&self, order: &Order) -> EngineResult<Vec<(usize, usize)>> {
+        let book = self.get_order_book(order.asset)?;
+        book.find_fills(order, self.dark_priority).into_iter()
+            .map(|fill| {
+                let other = book.get_order_by_key(fill.key).ok_or(ErrorType::InvalidState)?;
+                let price = match order.mode {
+                    OrderMode::Best => other.mode.get_limit()?,
+                    OrderMode::Limit(limit) => limit,
+                };
+                Ok((price, fill.quantity))
+            })
+            .collect()
+    }
+
+    fn process_trade(&mut self, filled_order: Order, fills: Vec<Fill>) -> EngineResult<()> {
+        for fill in fills {
+            let other = *self.get_order_book(filled_order.asset)?
+                .get_order_by_key(fill.key)
+                .ok_or(ErrorType::InvalidState)?;
+            debug_assert_eq!(filled_order.asset, other.asset);
+
+            let price_per_asset = match (filled_order.side, filled_order.mode) {
+                (_, OrderMode::Best) => other.mode.get_limit()?,
+                (_, OrderMode::Limit(limit)) => limit,
+            };
+
+            // the resting side pays from the reservation of its order
+            let (buyer, seller, reservations) = match filled_order.side {
+                OrderSide::Buy => (filled_order.portfolio, other.portfolio, (None, Some(other.id))),
+                OrderSide::Sell => (other.portfolio, filled_order.portfolio, (Some(other.id), None)),
+            };
+
+            self.exchange(
+                buyer,
+                seller,
+                filled_order.asset,
+                fill.quantity,
+                price_per_asset,
+                reservations,
+            )?;
+            let notional = to_coins(self.price_mode.notional(price_per_asset, fill.quantity)?.abs())?;
+            self.charge_trade_fees(filled_order.portfolio, other.portfolio, notional)?;
+            self.get_portfolio_mut(filled_order.portfolio)?.activity.record_fill(true, fill.quantity, notional);
+            self.get_portfolio_mut(other.portfolio)?.activity.record_fill(false, fill.quantity, notional);
+            self.last_trade_prices.insert(filled_order.asset, price_per_asset);
+            self.record_trade(filled_order, other, fill.quantity, price_per_asset);
+            self.notify_fill(filled_order, fill.quantity, price_per_asset);
+            self.notify_fill(other, fill.quantity, price_per_asset);
+
+            let remaining = self.get_order_book_mut(other.asset)?.reduce_order(other.id, fill.quantity)?;
+            if remaining == 0 {
+                self.get_portfolio_mut(other.portfolio)?.release_reservation(other.id)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Crosses every book at its clearing price. A book that fails to settle is put back as it was.
+    pub(crate) fn run_auctions(&mut self) {
+        // a halted book doesn't cross
+        let mut assets: Vec<AssetId> = self.books.keys().filter(|asset| !self.halted.contains(asset)).cloned().collect();
+        assets.sort();
+        for asset in assets {
+            let seen = self.notifications.len();
+            let scope = self.snapshot_scope(asset, Uuid::nil());
+            if self.run_auction(asset).is_err() {
+                self.restore_scope(scope);
+                self.notifications.truncate(seen);
+            }
+        }
+    }
+
+    // where the book would cross if the tick ended now
+    pub fn indicative_clearing(&self, asset_id: AssetId) -> EngineResult<Option<Clearing>> {
+        let book = self.get_order_book(asset_id)?;
+        Ok(auction::clearing(book, self.last_trade_prices.get(&asset_id).cloned()))
+    }
+
+    fn run_auction(&mut self, asset_id: AssetId) -> EngineResult<()> {
+        let clearing = match self.indicative_clearing(asset_id)? {
+            Some(clearing) => clearing,
+            None => return Ok(()),
+        };
+        let legs = auction::allocate(self.get_order_book(asset_id)?, &clearing, self.dark_priority);
+        for (buy, sell, quantity) in legs {
+            self.cross(buy, sell, quantity, clearing.price)?;
+        }
+        Ok(())
+    }
+
+    // One leg of an auction. Both orders rest in the book and pay from their reservations, there's
+    // no taker, so no trade fees either.
+    fn cross(&mut self, buy: Order, sell: Order, quantity: usize, price: usize) -> EngineResult<()> {
+        self.exchange(buy.portfolio, sell.portfolio, buy.asset, quantity, price, (Some(buy.id), Some(sell.id)))?;
+        let notional = to_coins(self.price_mode.notional(price, quantity)?.abs())?;
+        for portfolio in &[buy.portfolio, sell.portfolio] {
+            self.get_portfolio_mut(*portfolio)?.activity.record_fill(false, quantity, notional);
+        }
+        self.last_trade_prices.insert(buy.asset, price);
+        // the later order counts as the aggressor
+        let (incoming, resting) = if buy.created_at > sell.created_at { (buy, sell) } else { (sell, buy) };
+        self.record_trade(incoming, resting, quantity, price);
+        self.notify_fill(buy, quantity, price);
+        self.notify_fill(sell, quantity, price);
+        for order in &[buy, sell] {
+            if self.get_order_book_mut(order.asset)?.reduce_order(order.id, quantity)? == 0 {
+                self.get_portfolio_mut(order.portfolio)?.release_reservation(order.id)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn record_trade(&mut self, incoming: Order, resting: Order, quantity: usize, price: usize) {
+        let (buy, sell) = match incoming.side {
+            OrderSide::Buy => (incoming, resting),
+            OrderSide::Sell => (resting, incoming),
+        };
+        let trade = Trade {
+            id: self.trades.len() as u64 + 1,
+            tick: self.tick,
+            timestamp: self.now,
+            asset: incoming.asset,
+            price,
+            quantity,
+            buyer: buy.portfolio,
+            seller: sell.portfolio,
+            buy_order: buy.id,
+            sell_order: sell.id,
+            aggressor: incoming.side,
+            busted: false,
+        };
+        self.trades.push(trade);
+    }
+
+    pub fn get_trades_for(&self, asset_id: AssetId) -> Vec<&Trade> {
+        self.trades.iter().filter(|t| t.asset == asset_id).collect()
+    }
+
+    fn notify_fill(&mut self, order: Order, quantity: usize, price: usize) {
+        self.notifications.push((order.portfolio, Notification::Fill {
+            order_id: order.id,
+            asset: order.asset,
+            side: order.side,
+            quantity,
+            price,
+        }));
+    }
+
+    pub(crate) fn check_margin(&mut self, portfolio_id: PortfolioId) {
+        let free_coins = match self.portfolios.get(&portfolio_id) {
+            Some(portfolio) => portfolio.coins.get_free_amount(),
+            None => return,
+        };
+        if free_coins < self.margin_warning_threshold {
+            self.notifications.push((portfolio_id, Notification::MarginWarning {
+                free_coins,
+                threshold: self.margin_warning_threshold,
+            }));
+        }
+    }
+
+    fn exchange(&mut self,
+                buyer: PortfolioId,
+                seller: PortfolioId,
+                asset_id: Uuid,
+                asset_count: usize,
+                price_per_asset: usize,
+                // the orders the coins and the assets were locked for
+                (coin_reservation, asset_reservation): (Option<OrderId>, Option<OrderId>),
+    ) -> EngineResult<()> {
+        self.transfer_asset(
+            seller,
+            buyer,
+            asset_id,
+            asset_count,
+            asset_reservation
+        )?;
+
+        let notional = self.price_mode.notional(price_per_asset, asset_count)?;
+        if notional >= 0 {
+            self.transfer_coins(buyer, seller, to_coins(notional)?, coin_reservation)?;
+        } else {
+            // below zero the seller pays to get rid of the asset, a resting sell out of what its order locked
+            let owed = to_coins(-notional)?;
+            match asset_reservation {
+                Some(order_id) => {
+                    self.get_portfolio_mut(seller)?.spend_owed(order_id, owed)?;
+                    self.get_portfolio_mut(buyer)?.coins.add(owed)?;
+                },
+                None => self.transfer_coins(seller, buyer, owed, None)?,
+            }
+        }
+
+        self.get_portfolio_mut(buyer)?.record_buy(asset_id, asset_count, notional)?;
+        self.get_portfolio_mut(seller)?.record_sell(asset_id, asset_count, notional)?;
+        Ok(())
+    }
+
+    fn transfer_asset(&mut self,
+                      from: PortfolioId,
+                      to: PortfolioId,
+                      asset: AssetId,
+                      amount: usize,
+                      reservation: Option<OrderId>
+    ) -> EngineResult<()>{
+        {
+            let from_portfolio = self.get_portfolio_mut(from)?;
+            match reservation {
+                Some(order_id) => from_portfolio.spend_reservation(order_id, amount)?,
+                None => from_portfolio.ensure_asset_account(asset).spend_from_free_amount(amount)?,
+            }
+        }
+        {
+            let to_account = self.get_portfolio_mut(to)?
+                .ensure_asset_account(asset);
+            to_account.add(amount)?;
+        }
+
+        Ok(())
+    }
+
+    fn transfer_coins(&mut self,
+                      from: PortfolioId,
+                      to: PortfolioId,
+                      amount: usize,
+                      reservation: Option<OrderId>
+    ) -> EngineResult<()>{
+        {
+            let from_portfolio = self.get_portfolio_mut(from)?;
+            match reservation {
+                Some(order_id) => from_portfolio.spend_reservation(order_id, amount)?,
+                None => from_portfolio.coins.spend_from_free_amount(amount)?,
+            }
+        }
+        {
+            let to_portfolio = self.get_portfolio_mut(to)?;
+            to_portfolio.coins.add(amount)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_order(&mut self, order: Order, lock_amount: bool) -> EngineResult<()> {
+
+        let price_mode = self.price_mode;
+        let portfolio = self.get_portfolio_mut(order.portfolio)?;
+        if lock_amount {
+            portfolio.reserve(Reservation::for_order(&order, price_mode)?)?;
+        }
+        self.get_order_book_mut(order.asset)?.add_order(order)?;
+
+        Ok(())
+    }
+
+    pub(crate) fn get_portfolio_mut(&mut self, portfolio_id: Uuid) -> EngineResult<&mut Portfolio> {
+        self.touched.mark(portfolio_id);
+        self.portfolios.get_mut(&portfolio_id)
+            .ok_or(ErrorType::PortfolioNotFound(portfolio_id))
+    }
+
+    pub fn get_portfolio(&self, portfolio_id: Uuid) -> EngineResult<&Portfolio> {
+        self.portfolios.get(&portfolio_id)
+            .ok_or(ErrorType::PortfolioNotFound(portfolio_id))
+    }
+
+    // The owner's cancel, turned down while the order hasn't rested for the minimum resting time.
+    pub(crate) fn cancel_order(&mut self, portfolio_id: Uuid, order_id: Uuid, asset_id: Uuid) ->  EngineResult<()> {
+        let order = *self.get_order_book(asset_id)?.get_order(order_id)?;
+        if let Some(remaining) = self.minimum_resting_time.and_then(|minimum| minimum.remaining(&order, self.now, self.tick)) {
+            return Err(ErrorType::CancelTooEarly { remaining });
+        }
+        self.withdraw_order(portfolio_id, order_id, asset_id)
+    }
+
+    pub(crate) fn withdraw_order(&mut self, portfolio_id: Uuid, order_id: Uuid, asset_id: Uuid) ->  EngineResult<()> {
+        self.take_out_order(portfolio_id, order_id, asset_id)?;
+        self.notifications.push((portfolio_id, Notification::OrderCancelled {
+            order_id,
+            asset: asset_id,
+        }));
+        Ok(())
+    }
+
+    pub(crate) fn expire_order(&mut self, asset_id: AssetId, order_id: OrderId, reason: ExpiryReason) -> EngineResult<()> {
+        let portfolio_id = self.get_order_book(asset_id)?.get_order(order_id)?.portfolio;
+        self.take_out_order(portfolio_id, order_id, asset_id)?;
+        self.notifications.push((portfolio_id, Notification::OrderExpired {
+            order_id,
+            asset: asset_id,
+            reason,
+        }));
+        Ok(())
+    }
+
+    pub(crate) fn expire_all_orders(&mut self, asset_id: AssetId, reason: ExpiryReason) -> EngineResult<()> {
+        let order_ids: Vec<OrderId> = self.get_order_book(asset_id)?.all_orders().map(|o| o.id).collect();
+        for order_id in order_ids {
+            self.expire_order(asset_id, order_id, reason)?;
+        }
+        Ok(())
+    }
+
+    fn take_out_order(&mut self, portfolio_id: Uuid, order_id: Uuid, asset_id: Uuid) -> EngineResult<()> {
+        let order = *self.get_order_book(asset_id)?.get_order(order_id)?;
+        if order.asset != asset_id {
+            return Err(ErrorType::InvalidAssetId);
+        }
+        self.get_portfolio_mut(portfolio_id)?.release_reservation(order_id)?;
+        self.get_order_book_mut(asset_id)?.remove_order(order_id);
+        if let Some(group) = self.oco_legs.get(&order_id).cloned() {
+            self.remove_oco_group(group);
+        }
+        Ok(())
+    }
+
+    // the orders and cancels of an event that went through, for the senders' activity stats
+    pub(crate) fn count_activity(&mut self, event: &Event) {
+        if let Event::CancelOrder(portfolio, _, _) = event {
+            if let Ok(portfolio) = self.get_portfolio_mut(*portfolio) {
+                portfolio.activity.cancels += 1;
+            }
+        }
+        for order in event.orders() {
+            if let Ok(portfolio) = self.get_portfolio_mut(order.portfolio) {
+                portfolio.activity.orders += 1;
+                portfolio.activity.ordered_quantity = portfolio.activity.ordered_quantity.saturating_add(order.quantity);
+            }
+        }
+    }
+
+    // cancels on behalf of whoever owns the order
+    pub fn force_cancel_order(&mut self, asset_id: AssetId, order_id: OrderId) -> EngineResult<()> {
+        let portfolio_id = self.get_order_book(asset_id)?.get_order(order_id)?.portfolio;
+        self.withdraw_order(portfolio_id, order_id, asset_id)
+    }
+
+    pub fn cancel_all_orders(&mut self, asset_id: AssetId) -> EngineResult<Vec<OrderId>> {
+        let book = self.get_order_book(asset_id)?;
+        let order_ids: Vec<OrderId> = book.all_orders()
+            .map(|o| o.id)
+            .collect();
+        for order_id in &order_ids {
+            self.force_cancel_order(asset_id, *order_id)?;
+        }
+        Ok(order_ids)
+    }
+
+    // Expires every order, pays the holders out of the bank account and forgets the asset. The caller
+    // rolls back if the bank can't cover the settlement. Holders pay nothing for a negative price.
+    pub(crate) fn delist_asset(&mut self, asset_id: AssetId, price: usize) -> EngineResult<()> {
+        let name = self.get_asset(&asset_id)?.name.clone();
+        self.expire_all_orders(asset_id, ExpiryReason::AssetDelisted)?;
+        let mut holders: Vec<(PortfolioId, usize)> = self.portfolios.values()
+            .filter_map(|p| p.assets.get(&asset_id).map(|a| (p.id, a.get_total_amount())))
+            .collect();
+        holders.sort();
+        for (portfolio_id, quantity) in holders {
+            let amount = to_coins(self.price_mode.notional(price, quantity)?.max(0))?;
+            self.bank_account = checked_sub(self.bank_account, amount)
+                .map_err(|_| ErrorType::InsufficientFreeAmount)?;
+            let portfolio = self.get_portfolio_mut(portfolio_id)?;
+            portfolio.record_sell(asset_id, quantity, amount as i128)?;
+            portfolio.cost_basis.remove(&asset_id);
+            portfolio.assets.remove(&asset_id);
+            portfolio.coins.add(amount)?;
+            self.record_ledger(portfolio_id, LedgerKind::Settlement, amount);
+            self.notifications.push((portfolio_id, Notification::Delisted { asset: asset_id, quantity, price }));
+        }
+        self.books.remove(&asset_id);
+        self.assets.remove(&asset_id);
+        self.asset_names.remove(&name);
+        self.last_trade_prices.remove(&asset_id);
+        self.fair_values.remove(&asset_id);
+        self.halted.remove(&asset_id);
+        self.schedules.cancel_asset(asset_id);
+        Ok(())
+    }
+
+    pub fn set_halted(&mut self, asset_id: AssetId, halted: bool) -> EngineResult<()> {
+        self.get_asset(&asset_id)?;
+        match halted {
+            true => self.halted.insert(asset_id),
+            false => self.halted.remove(&asset_id),
+        };
+        Ok(())
+    }
+
+    pub fn check_session(&self) -> EngineResult<()> {
+        match self.session_times {
+            Some(times) if !times.is_open(self.tick) => Err(ErrorType::MarketClosed),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn check_halted(&self, asset_id: AssetId) -> EngineResult<()> {
+        match self.halted.contains(&asset_id) {
+            true => Err(ErrorType::AssetHalted(asset_id)),
+            false => Ok(()),
+        }
+    }
+
+    // Pays every holder `per_unit` for each unit they hold, locked ones included, out of the bank
+    // account. Nothing is paid unless the bank covers all of it.
+    pub(crate) fn pay_dividend(&mut self, asset_id: AssetId, per_unit: usize) -> EngineResult<()> {
+        self.get_asset(&asset_id)?;
+        let mut holders: Vec<(PortfolioId, usize)> = self.portfolios.values()
+            .filter_map(|p| p.assets.get(&asset_id).map(|a| (p.id, a.get_total_amount())))
+            .filter(|(_, quantity)| *quantity > 0)
+            .collect();
+        holders.sort();
+        let mut payments = vec![];
+        for (portfolio_id, quantity) in holders {
+            let amount = to_coins(self.price_mode.notional(per_unit, quantity)?.max(0))?;
+            payments.push((portfolio_id, quantity, amount));
+        }
+        let total = payments.iter().try_fold(0, |total, (_, _, amount)| checked_add(total, *amount))?;
+        self.bank_account = checked_sub(self.bank_account, total)
+            .map_err(|_| ErrorType::InsufficientFreeAmount)?;
+        for (portfolio_id, quantity, amount) in payments {
+            self.get_portfolio_mut(portfolio_id)?.coins.add(amount)?;
+            self.record_ledger(portfolio_id, LedgerKind::Dividend, amount);
+            self.notifications.push((portfolio_id, Notification::Dividend { asset: asset_id, quantity, per_unit, amount }));
+        }
+        Ok(())
+    }
+
+    pub fn get_orders_of(&self, portfolio_id: PortfolioId) -> Vec<(AssetId, OrderId)> {
+        let mut orders = vec![];
+        for (asset_id, book) in &self.books {
+            book.all_orders()
+                .filter(|o| o.portfolio == portfolio_id)
+                .for_each(|o| orders.push((*asset_id, o.id)));
+        }
+        orders
+    }
+
+    pub fn declare_bankrupt(&mut self, portfolio_id: PortfolioId) -> EngineResult<()> {
+        let net_worth = self.portfolio_value(portfolio_id)?;
+        let tick = self.tick;
+        self.get_portfolio_mut(portfolio_id)?.bankrupt_since = Some(tick);
+        self.notifications.push((portfolio_id, Notification::Bankrupt { net_worth }));
+        Ok(())
+    }
+
+    // Wipes the portfolio and hands out a fresh balance. The coins it had left go back to the bank
+    // account, which pays the fresh balance, and its holdings go back to the bank portfolio that
+    // issues the assets, so neither money nor assets come out of nowhere or vanish.
+    pub fn restart_portfolio(&mut self, portfolio_id: PortfolioId, coins: usize) -> EngineResult<()> {
+        if !self.get_orders_of(portfolio_id).is_empty() {
+            return Err(ErrorType::InvalidState);
+        }
+        let (left, holdings) = {
+            let portfolio = self.get_portfolio(portfolio_id)?;
+            let holdings: Vec<(AssetId, usize)> = portfolio.assets.iter()
+                .map(|(asset, account)| (*asset, account.get_total_amount()))
+                .filter(|(_, amount)| *amount > 0)
+                .collect();
+            (portfolio.coins.get_total_amount(), holdings)
+        };
+        let bank_account = checked_add(self.bank_account, left)?;
+        if bank_account < coins {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        if !holdings.is_empty() {
+            let issuer = self.bank_portfolio.filter(|id| *id != portfolio_id).ok_or(ErrorType::InvalidState)?;
+            let issuer = self.get_portfolio_mut(issuer)?;
+            for (asset, amount) in holdings {
+                issuer.ensure_asset_account(asset).add(amount)?;
+            }
+        }
+        self.bank_account = bank_account - coins;
+        if left > 0 {
+            self.record_ledger(portfolio_id, LedgerKind::Forfeit, left);
+        }
+        if coins > 0 {
+            self.record_ledger(portfolio_id, LedgerKind::Restart, coins);
+        }
+        let portfolio = self.get_portfolio_mut(portfolio_id)?;
+        portfolio.coins = Account::new(coins);
+        portfolio.assets.values_mut().for_each(|account| *account = Account::new(0));
+        portfolio.cost_basis.clear();
+        portfolio.realized_pnl = 0;
+        portfolio.settlement_holds.clear();
+        portfolio.bankrupt_since = None;
+        self.notifications.push((portfolio_id, Notification::Restarted { coins }));
+        Ok(())
+    }
+
+    pub fn get_trade(&self, trade_id: u64) -> EngineResult<&Trade> {
+        self.trades.iter().find(|t| t.id == trade_id).ok_or(ErrorType::TradeNotFound(trade_id))
+    }
+
+    // Holds what the trades from `from` on brought in and waits for them to be confirmed, see `SettlementPolicy`.
+    pub(crate) fn hold_settlements(&mut self, from: usize) -> EngineResult<()> {
+        let policy = match &self.settlement {
+            Some(policy) => policy.clone(),
+            None => return Ok(()),
+        };
+        let trades: Vec<Trade> = self.trades.iter().skip(from).filter(|t| !t.busted).cloned().collect();
+        for trade in trades {
+            let notional = self.price_mode.notional(trade.price, trade.quantity)?;
+            // whoever received the coins holds them
+            let payee = if notional >= 0 { trade.seller } else { trade.buyer };
+            self.get_portfolio_mut(trade.buyer)?.hold(trade.id, ReservedAccount::Asset(trade.asset), trade.quantity)?;
+            self.get_portfolio_mut(payee)?.hold(trade.id, ReservedAccount::Coins, to_coins(notional.abs())?)?;
+            let deadline = self.game_time.saturating_add(policy.timeout_millis);
+            self.pending_settlements.insert(trade.id, PendingSettlement { trade, deadline });
+        }
+        Ok(())
+    }
+
+    // lets go of what the trade holds, whatever happens to it next
+    fn release_settlement(&mut self, trade_id: u64) -> EngineResult<Option<PendingSettlement>> {
+        let pending = self.pending_settlements.remove(&trade_id);
+        if let Some(pending) = &pending {
+            for portfolio in &[pending.trade.buyer, pending.trade.seller] {
+                self.get_portfolio_mut(*portfolio)?.release_holds(trade_id)?;
+            }
+        }
+        Ok(pending)
+    }
+
+    pub fn confirm_settlement(&mut self, trade_id: u64) -> EngineResult<()> {
+        let pending = self.release_settlement(trade_id)?.ok_or(ErrorType::SettlementNotPending(trade_id))?;
+        for portfolio in &[pending.trade.buyer, pending.trade.seller] {
+            self.notifications.push((*portfolio, Notification::TradeSettled { trade_id, asset: pending.trade.asset }));
+        }
+        Ok(())
+    }
+
+    // busts it, unless it can't be reversed anymore, then it stands
+    pub fn reject_settlement(&mut self, trade_id: u64) -> EngineResult<()> {
+        if !self.pending_settlements.contains_key(&trade_id) {
+            return Err(ErrorType::SettlementNotPending(trade_id));
+        }
+        self.reverse_trade(trade_id)
+    }
+
+    // turning settlement off settles what's pending
+    pub(crate) fn set_settlement_policy(&mut self, policy: Option<SettlementPolicy>) -> EngineResult<()> {
+        if policy.is_none() {
+            let pending: Vec<u64> = self.pending_settlements.keys().cloned().collect();
+            for trade_id in pending {
+                self.confirm_settlement(trade_id)?;
+            }
+        }
+        self.settlement = policy;
+        Ok(())
+    }
+
+    // moves the traded assets and coins back between buyer and seller
+    pub fn reverse_trade(&mut self, trade_id: u64) -> EngineResult<()> {
+        let trade = self.get_trade(trade_id)?.clone();
+        if trade.busted {
+            return Err(ErrorType::TradeAlreadyBusted(trade_id));
+        }
+        // a busted trade is never settled
+        self.release_settlement(trade_id)?;
+        let notional = self.price_mode.notional(trade.price, trade.quantity)?;
+        // whoever received the coins pays them back
+        let (payer, payee) = if notional >= 0 { (trade.seller, trade.buyer) } else { (trade.buyer, trade.seller) };
+        let coins = to_coins(notional.abs())?;
+        let returnable = self.get_portfolio(trade.buyer)?
+            .assets.get(&trade.asset).map_or(0, |a| a.get_free_amount());
+        if returnable < trade.quantity || self.get_portfolio(payer)?.coins.get_free_amount() < coins {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+        self.transfer_asset(trade.buyer, trade.seller, trade.asset, trade.quantity, None)?;
+        self.transfer_coins(payer, payee, coins, None)?;
+        if coins > 0 {
+            for (portfolio, kind) in &[(payer, LedgerKind::BustCharge), (payee, LedgerKind::BustRefund)] {
+                self.ledger.push(LedgerEntry {
+                    tick: self.tick,
+                    portfolio: *portfolio,
+                    kind: *kind,
+                    amount: coins,
+                    trade: Some(trade_id),
+                });
+            }
+        }
+        // booked as the opposite trade, at the same price
+        self.get_portfolio_mut(trade.buyer)?.record_sell(trade.asset, trade.quantity, notional)?;
+        self.get_portfolio_mut(trade.seller)?.record_buy(trade.asset, trade.quantity, notional)?;
+        if let Some(t) = self.trades.iter_mut().find(|t| t.id == trade_id) {
+            t.busted = true;
+        }
+        for portfolio in &[trade.buyer, trade.seller] {
+            self.notifications.push((*portfolio, Notification::TradeBusted {
+                trade_id,
+                asset: trade.asset,
+                quantity: trade.quantity,
+                price: trade.price,
+            }));
+        }
+        Ok(())
+    }
+
+}
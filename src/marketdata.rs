@@ -0,0 +1,153 @@
+use crate::order::{Order, OrderId, OrderMode};
+use crate::book::Book;
+use crate::market::AssetId;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Level {
+    L1,
+    L2,
+    L3,
+}
+
+impl Level {
+    pub fn parse(level: &str) -> Option<Level> {
+        match level.to_uppercase().as_str() {
+            "L1" => Some(Level::L1),
+            "L2" => Some(Level::L2),
+            "L3" => Some(Level::L3),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PriceLevel {
+    pub price: usize,
+    pub quantity: usize,
+    pub orders: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct L1Quote {
+    pub asset: AssetId,
+    pub best_bid: Option<PriceLevel>,
+    pub best_ask: Option<PriceLevel>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct L2Depth {
+    pub asset: AssetId,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct L3Order {
+    pub id: OrderId,
+    pub price: usize,
+    pub quantity: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct L3Book {
+    pub asset: AssetId,
+    pub bids: Vec<L3Order>,
+    pub asks: Vec<L3Order>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub enum MarketData {
+    L1(L1Quote),
+    L2(L2Depth),
+    L3(L3Book),
+}
+
+impl MarketData {
+    pub fn from_book(book: &Book, level: Level) -> MarketData {
+        match level {
+            Level::L1 => MarketData::L1(l1(book)),
+            Level::L2 => MarketData::L2(l2(book)),
+            Level::L3 => MarketData::L3(l3(book)),
+        }
+    }
+}
+
+// A snapshot with the book's sequence, the version that /book/<asset>/delta counts. On the stream
+// `previous_sequence` is the one of the message before for that book: a client that last saw a different
+// one missed an update and fetches the deltas since the one it has.
+#[derive(Clone, Debug, Serialize)]
+pub struct SequencedMarketData {
+    pub sequence: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub previous_sequence: Option<u64>,
+    #[serde(flatten)]
+    pub data: MarketData,
+}
+
+impl SequencedMarketData {
+    pub fn new(book: &Book, level: Level, previous_sequence: Option<u64>) -> SequencedMarketData {
+        SequencedMarketData {
+            sequence: book.version(),
+            previous_sequence,
+            data: MarketData::from_book(book, level),
+        }
+    }
+}
+
+pub fn l1(book: &Book) -> L1Quote {
+    L1Quote {
+        asset: book.asset_id,
+        best_bid: depth(book.buy_orders()).into_iter().next(),
+        best_ask: depth(book.sell_orders()).into_iter().next(),
+    }
+}
+
+pub fn l2(book: &Book) -> L2Depth {
+    L2Depth {
+        asset: book.asset_id,
+        bids: depth(book.buy_orders()),
+        asks: depth(book.sell_orders()),
+    }
+}
+
+pub fn l3(book: &Book) -> L3Book {
+    L3Book {
+        asset: book.asset_id,
+        bids: book.buy_orders().filter_map(l3_order).collect(),
+        asks: book.sell_orders().filter_map(l3_order).collect(),
+    }
+}
+
+fn l3_order(order: &Order) -> Option<L3Order> {
+    match order.mode {
+        OrderMode::Limit(price) => Some(L3Order {
+            id: order.id,
+            price,
+            quantity: order.quantity,
+        }),
+        OrderMode::Best => None,
+    }
+}
+
+// Aggregates one side of the book into price levels, keeping the book's best-first order.
+pub fn depth<'a>(orders: impl Iterator<Item = &'a Order>) -> Vec<PriceLevel> {
+    let mut levels: Vec<PriceLevel> = vec![];
+    for order in orders {
+        let price = match order.mode {
+            OrderMode::Limit(limit) => limit,
+            OrderMode::Best => continue,
+        };
+        match levels.last_mut() {
+            Some(level) if level.price == price => {
+                level.quantity += order.quantity;
+                level.orders += 1;
+            },
+            _ => levels.push(PriceLevel {
+                price,
+                quantity: order.quantity,
+                orders: 1,
+            }),
+        }
+    }
+    levels
+}
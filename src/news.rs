@@ -0,0 +1,17 @@
+use crate::market::AssetId;
+use uuid::Uuid;
+
+// Impact is the percentage by which NPC valuations of the asset move.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct NewsImpact {
+    pub asset: AssetId,
+    pub impact: i32,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NewsItem {
+    pub id: Uuid,
+    pub tick: u64,
+    pub headline: String,
+    pub impacts: Vec<NewsImpact>,
+}
@@ -0,0 +1,81 @@
+use crate::order::{Event, Order, OrderId, OrderMode, OrderSide};
+use crate::account::PortfolioId;
+use crate::market::AssetId;
+use crate::engine::Engine;
+use crate::news::NewsImpact;
+
+const QUOTE_LIFETIME_MILLIS: u64 = 24*60*60*1000;
+
+// Quotes both sides of one asset around its own valuation of the asset.
+#[derive(Clone, Debug, Serialize)]
+pub struct MarketMaker {
+    pub portfolio: PortfolioId,
+    pub asset: AssetId,
+    pub fair_value: usize,
+    pub spread_percent: usize,
+    pub quantity: usize,
+    pub quotes: Vec<OrderId>,
+    // of the other orders in the book when it last quoted, see `OpenInterest::imbalance_percent`
+    pub imbalance_percent: i64,
+}
+
+impl MarketMaker {
+    pub fn new(portfolio: PortfolioId, asset: AssetId, fair_value: usize, spread_percent: usize, quantity: usize) -> MarketMaker {
+        MarketMaker {
+            portfolio,
+            asset,
+            fair_value,
+            spread_percent,
+            quantity,
+            quotes: vec![],
+            imbalance_percent: 0,
+        }
+    }
+
+    pub fn apply_impact(&mut self, impact: &NewsImpact) {
+        if impact.asset != self.asset {
+            return;
+        }
+        let adjusted = self.fair_value as i64 * (100 + impact.impact as i64) / 100;
+        self.fair_value = adjusted.max(1) as usize;
+    }
+
+    // Leans towards the side with more resting, by up to half the spread when only one side rests.
+    pub fn quoted_value(&self) -> usize {
+        let skew = self.imbalance_percent * self.spread_percent as i64 / 2;
+        (self.fair_value as i64 * (100*100 + skew) / (100*100)).max(1) as usize
+    }
+
+    pub fn bid(&self) -> usize {
+        (self.quoted_value() * (100 - self.spread_percent.min(99)) / 100).max(1)
+    }
+
+    pub fn ask(&self) -> usize {
+        (self.quoted_value() * (100 + self.spread_percent) / 100).max(self.bid() + 1)
+    }
+}
+
+// Replaces every market maker's quotes. Runs through `Engine::process`, so the
+// resulting events are journaled like any player's.
+pub fn run_market_makers(engine: &mut Engine) {
+    let mut market_makers = std::mem::take(&mut engine.market_makers);
+    for mm in market_makers.iter_mut() {
+        for quote in mm.quotes.drain(..) {
+            let _ = engine.process(Event::CancelOrder(mm.portfolio, quote, mm.asset));
+        }
+        mm.imbalance_percent = engine.market.get_order_book(mm.asset)
+            .map_or(0, |book| book.open_interest().imbalance_percent());
+        for (side, price) in [(OrderSide::Buy, mm.bid()), (OrderSide::Sell, mm.ask())].iter() {
+            let stamp = engine.stamp();
+            let expires = stamp.created_at + QUOTE_LIFETIME_MILLIS;
+            let order = match Order::new(stamp, mm.portfolio, mm.asset, *side, mm.quantity, OrderMode::Limit(*price), expires) {
+                Ok(order) => order,
+                Err(_) => continue,
+            };
+            if engine.process(Event::Order(order)).is_ok() {
+                mm.quotes.push(order.get_id());
+            }
+        }
+    }
+    engine.market_makers = market_makers;
+}
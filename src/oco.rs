@@ -0,0 +1,37 @@
+use crate::market::AssetId;
+use crate::order::{Order, OrderId};
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct OcoLeg {
+    pub order: OrderId,
+    pub asset: AssetId,
+    pub quantity: usize,
+}
+
+// Two orders where filling one cancels the other. Without a trigger quantity only a
+// full fill cancels the other order.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct OcoGroup {
+    pub id: OrderId,
+    pub legs: [OcoLeg; 2],
+    pub trigger_quantity: Option<usize>,
+}
+
+impl OcoGroup {
+    pub fn new(first: &Order, second: &Order, trigger_quantity: Option<usize>) -> OcoGroup {
+        let leg = |o: &Order| OcoLeg { order: o.id, asset: o.asset, quantity: o.quantity };
+        OcoGroup {
+            id: first.id,
+            legs: [leg(first), leg(second)],
+            trigger_quantity,
+        }
+    }
+
+    pub fn is_triggered(&self, leg: &OcoLeg, remaining: usize) -> bool {
+        let filled = leg.quantity.saturating_sub(remaining);
+        match self.trigger_quantity {
+            Some(trigger) => filled >= trigger.min(leg.quantity),
+            None => remaining == 0,
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use crate::error::{EngineResult, ErrorType};
+use crate::game::Game;
+use crate::market::AssetId;
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CurvePoint {
+    pub tick: u64,
+    pub price: usize,
+}
+
+// Prices set out over ticks, straight lines in between and flat before the first and after the last point.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PriceCurve {
+    pub points: Vec<CurvePoint>,
+}
+
+impl PriceCurve {
+    pub fn new(points: Vec<CurvePoint>) -> EngineResult<PriceCurve> {
+        let curve = PriceCurve { points };
+        curve.check()?;
+        Ok(curve)
+    }
+
+    // at least one point, ticks strictly increasing and no zero prices
+    pub fn check(&self) -> EngineResult<()> {
+        if self.points.is_empty()
+            || self.points.iter().any(|p| p.price == 0)
+            || self.points.windows(2).any(|w| w[0].tick >= w[1].tick) {
+            return Err(ErrorType::InvalidPriceCurve);
+        }
+        Ok(())
+    }
+
+    pub fn price_at(&self, tick: u64) -> usize {
+        let after = match self.points.iter().position(|p| p.tick > tick) {
+            Some(0) => return self.points[0].price,
+            Some(after) => after,
+            None => return self.points.last().map_or(0, |p| p.price),
+        };
+        let (a, b) = (self.points[after - 1], self.points[after]);
+        let (from, to) = (a.price as i128, b.price as i128);
+        let progress = (tick - a.tick) as i128;
+        (from + (to - from) * progress / (b.tick - a.tick) as i128) as usize
+    }
+}
+
+// Where an asset's reference price comes from.
+#[derive(Clone)]
+pub enum OracleSource {
+    // one of the assets of another game running in this process
+    Game { game: Game, asset: AssetId },
+    Curve(PriceCurve),
+}
+
+impl OracleSource {
+    // Locks the other game's engine, so never call it while holding this game's.
+    pub fn price(&self, tick: u64) -> EngineResult<Option<usize>> {
+        match self {
+            OracleSource::Game { game, asset } => game.get_reference_price(*asset),
+            OracleSource::Curve(curve) => Ok(Some(curve.price_at(tick))),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct OracleFeed {
+    pub asset: AssetId,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub curve: Option<PriceCurve>,
+    // the asset of the linked game the price is taken from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub linked_asset: Option<AssetId>,
+    pub price: Option<usize>,
+}
+
+// The assets of a game whose fair value follows a source outside its own books. Market makers quote
+// around it and a delisting without a price settles at it, see `Game::advance_tick`.
+#[derive(Default)]
+pub struct Oracle {
+    sources: Mutex<HashMap<AssetId, OracleSource>>,
+}
+
+impl Oracle {
+    pub fn new() -> Oracle {
+        Oracle::default()
+    }
+
+    pub fn set(&self, asset: AssetId, source: Option<OracleSource>) -> EngineResult<()> {
+        let mut sources = self.sources.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        match source {
+            Some(source) => sources.insert(asset, source),
+            None => sources.remove(&asset),
+        };
+        Ok(())
+    }
+
+    pub fn source(&self, asset: AssetId) -> EngineResult<Option<OracleSource>> {
+        Ok(self.sources.lock().map_err(|_| ErrorType::EngineWasTooBusy)?.get(&asset).cloned())
+    }
+
+    // The sources are copied out first, a linked game may take its time answering.
+    fn snapshot(&self) -> EngineResult<Vec<(AssetId, OracleSource)>> {
+        let sources = self.sources.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        Ok(sources.iter().map(|(asset, source)| (*asset, source.clone())).collect())
+    }
+
+    // The price of every asset with a source at `tick`. A linked game that can't answer right now is
+    // skipped, the asset keeps its last price.
+    pub fn prices(&self, tick: u64) -> EngineResult<Vec<(AssetId, usize)>> {
+        Ok(self.snapshot()?.into_iter()
+            .filter_map(|(asset, source)| match source.price(tick) {
+                Ok(Some(price)) => Some((asset, price)),
+                _ => None,
+            })
+            .collect())
+    }
+
+    pub fn feeds(&self, tick: u64) -> EngineResult<Vec<OracleFeed>> {
+        let mut feeds = vec![];
+        for (asset, source) in self.snapshot()? {
+            let price = source.price(tick).unwrap_or(None);
+            let (curve, linked_asset) = match source {
+                OracleSource::Game { asset, .. } => (None, Some(asset)),
+                OracleSource::Curve(curve) => (Some(curve), None),
+            };
+            feeds.push(OracleFeed { asset, curve, linked_asset, price });
+        }
+        feeds.sort_by_key(|feed| feed.asset);
+        Ok(feeds)
+    }
+}
@@ -0,0 +1,214 @@
+use uuid::Uuid;
+use crate::error::{EngineResult, ErrorType};
+use crate::account::PortfolioId;
+use crate::market::AssetId;
+use crate::providers::Timestamp;
+
+pub type OrderId = Uuid;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Event {
+    Order(Order),
+    CancelOrder(PortfolioId, OrderId, AssetId),
+    // all orders of one portfolio execute or none does
+    AtomicBatch(Vec<Order>),
+    // two orders of one portfolio, filling one cancels the other
+    Oco { first: Order, second: Order, trigger_quantity: Option<usize> },
+    // admin only: retires the asset, holdings are paid out at the settlement price
+    DelistAsset(AssetId, usize),
+}
+
+impl Event {
+    pub fn assets(&self) -> Vec<AssetId> {
+        match self {
+            Event::Order(o) => vec![o.asset],
+            Event::CancelOrder(_, _, asset) => vec![*asset],
+            Event::AtomicBatch(orders) => {
+                let mut assets: Vec<AssetId> = orders.iter().map(|o| o.asset).collect();
+                assets.sort();
+                assets.dedup();
+                assets
+            },
+            Event::Oco { first, second, .. } if first.asset == second.asset => vec![first.asset],
+            Event::Oco { first, second, .. } => vec![first.asset, second.asset],
+            Event::DelistAsset(asset, _) => vec![*asset],
+        }
+    }
+
+    pub fn portfolio(&self) -> PortfolioId {
+        match self {
+            Event::Order(o) => o.portfolio,
+            Event::CancelOrder(portfolio, _, _) => *portfolio,
+            Event::AtomicBatch(orders) => orders.first().map_or(Uuid::nil(), |o| o.portfolio),
+            Event::Oco { first, .. } => first.portfolio,
+            Event::DelistAsset(..) => Uuid::nil(),
+        }
+    }
+
+    pub fn orders(&self) -> Vec<Order> {
+        match self {
+            Event::Order(o) => vec![*o],
+            Event::CancelOrder(..) | Event::DelistAsset(..) => vec![],
+            Event::AtomicBatch(orders) => orders.clone(),
+            Event::Oco { first, second, .. } => vec![*first, *second],
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OrderMode {
+    Best,
+    Limit(usize),
+}
+
+impl OrderMode {
+    pub(crate) fn get_limit(&self) -> EngineResult<usize> {
+        match self {
+            OrderMode::Limit(limit) => Ok(*limit),
+            OrderMode::Best => Err(ErrorType::NoLimitForBestOrder),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum OrderSide {
+    Sell,
+    Buy,
+}
+
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct Order{
+    pub(crate) id: Uuid,
+    pub(crate) asset: Uuid,
+    pub(crate) mode: OrderMode,
+    pub(crate) side: OrderSide,
+    pub quantity: usize,
+    pub(crate) expires: Timestamp,
+    pub(crate) created_at: Timestamp,
+    // the tick it was created in
+    #[serde(default)]
+    pub(crate) tick: u64,
+    pub(crate) portfolio: Uuid,
+    // placed by an admin past the fat-finger check
+    #[serde(default)]
+    pub(crate) overrides_price_band: bool,
+    // rests in the book without being shown, see `Book::queue`
+    #[serde(default)]
+    pub(crate) hidden: bool,
+}
+
+
+#[derive(Copy, Clone, Debug)]
+pub struct Stamp {
+    pub id: OrderId,
+    pub created_at: Timestamp,
+    pub tick: u64,
+}
+
+// How long an order has to rest before its owner may cancel it, against quote stuffing.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MinimumRestingTime {
+    Millis(u64),
+    Ticks(u64),
+}
+
+impl MinimumRestingTime {
+    // what's left of it for `order` at `now` in `tick`, none once it has passed
+    pub fn remaining(&self, order: &Order, now: Timestamp, tick: u64) -> Option<MinimumRestingTime> {
+        match *self {
+            MinimumRestingTime::Millis(millis) => {
+                let rested = now.saturating_sub(order.created_at);
+                if rested < millis { Some(MinimumRestingTime::Millis(millis - rested)) } else { None }
+            },
+            MinimumRestingTime::Ticks(ticks) => {
+                let rested = tick.saturating_sub(order.tick);
+                if rested < ticks { Some(MinimumRestingTime::Ticks(ticks - rested)) } else { None }
+            },
+        }
+    }
+}
+
+impl Order {
+    pub fn new(
+        stamp: Stamp,
+        portfolio: PortfolioId,
+        asset: AssetId,
+        side: OrderSide,
+        quantity: usize,
+        mode: OrderMode,
+        expires: Timestamp) -> EngineResult<Order> {
+
+        if quantity ==  0 {
+            return Err(ErrorType::QuantityCantBeZero);
+        }
+        if let OrderMode::Limit(0) = mode {
+            return Err(ErrorType::LimitCantBeZero);
+        }
+
+        Ok(Order {
+            id: stamp.id,
+            asset,
+            side,
+            quantity,
+            mode,
+            expires,
+            portfolio,
+            created_at: stamp.created_at,
+            tick: stamp.tick,
+            overrides_price_band: false,
+            hidden: false,
+        })
+    }
+
+    pub fn override_price_band(mut self) -> Order {
+        self.overrides_price_band = true;
+        self
+    }
+
+    pub fn hide(mut self) -> Order {
+        self.hidden = true;
+        self
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    pub fn get_id(&self) -> OrderId {
+        self.id
+    }
+
+    pub fn get_portfolio(&self) -> PortfolioId {
+        self.portfolio
+    }
+
+    pub fn get_side(&self) -> OrderSide {
+        self.side
+    }
+
+    pub fn get_mode(&self) -> OrderMode {
+        self.mode
+    }
+
+    pub(crate) fn matches(&self, o: &Order) -> bool {
+        let assets_matching = self.asset == o.asset;
+        if !assets_matching {return  false};
+
+        let sides_matching = matches!((self.side, o.side), (OrderSide::Sell, OrderSide::Buy) | (OrderSide::Buy, OrderSide::Sell));
+        if !sides_matching {return  false};
+
+        match (self.mode, o.mode) {
+            (OrderMode::Limit(_), OrderMode::Best) => true,
+            (OrderMode::Best, OrderMode::Limit(_)) => true,
+            (OrderMode::Best, OrderMode::Best) => false,
+            (OrderMode::Limit(a), OrderMode::Limit(b)) => {
+                match (self.side, o.side) {
+                    (OrderSide::Sell, OrderSide::Buy) => a <= b,
+                    (OrderSide::Buy, OrderSide::Sell) => a >= b,
+                    _ => false,
+                }
+            }
+        }
+    }
+
+}
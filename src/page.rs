@@ -0,0 +1,141 @@
+use crate::actor;
+use crate::ledger::LedgerEntry;
+use crate::market::Asset;
+use crate::order::{Order, OrderMode, OrderSide};
+use crate::providers::Timestamp;
+use crate::trades::Trade;
+
+// Filters that don't apply to a kind of item let every item of it through.
+#[derive(Clone, Debug, Default)]
+pub struct PageQuery {
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+    // continues after the item with this cursor, or after where it was if it's gone by now
+    pub cursor: Option<String>,
+    pub side: Option<OrderSide>,
+    pub min_price: Option<usize>,
+    pub max_price: Option<usize>,
+    pub since: Option<Timestamp>,
+}
+
+// Cursors sort like the items, so a page picks up at the right place even after items before it were removed.
+pub trait Listed {
+    fn cursor(&self) -> String;
+
+    fn side(&self) -> Option<OrderSide> {
+        None
+    }
+
+    fn price(&self) -> Option<usize> {
+        None
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        None
+    }
+}
+
+impl PageQuery {
+    // The same for queries asking for the same page however they were written, so it can tell
+    // cached pages apart.
+    pub fn key(&self) -> String {
+        actor::fingerprint(&format!("{:?};{:?};{:?};{:?};{:?};{:?};{:?}",
+            self.limit, self.offset, self.cursor, self.side, self.min_price, self.max_price, self.since))
+    }
+
+    fn accepts<T: Listed>(&self, item: &T) -> bool {
+        let side = match (self.side, item.side()) {
+            (Some(wanted), Some(side)) => wanted == side,
+            _ => true,
+        };
+        let price = match item.price() {
+            Some(p) => p >= self.min_price.unwrap_or(0) && p <= self.max_price.unwrap_or(usize::MAX),
+            None => true,
+        };
+        let since = match (self.since, item.timestamp()) {
+            (Some(since), Some(t)) => t >= since,
+            _ => true,
+        };
+        side && price && since
+    }
+
+    // Sorts by cursor, filters and cuts out the page. The cursor of the last item comes along if there's more.
+    pub fn apply<T: Listed>(&self, items: Vec<T>) -> (Vec<T>, Option<String>) {
+        let mut items: Vec<(String, T)> = items.into_iter()
+            .filter(|item| self.accepts(item))
+            .map(|item| (item.cursor(), item))
+            .collect();
+        items.sort_by(|a, b| a.0.cmp(&b.0));
+        let remaining: Vec<(String, T)> = items.into_iter()
+            .filter(|(cursor, _)| match &self.cursor {
+                Some(after) => cursor > after,
+                None => true,
+            })
+            .skip(self.offset.unwrap_or(0))
+            .collect();
+        // an empty page would hand out the cursor of an item it never showed
+        let limit = self.limit.unwrap_or(remaining.len()).max(1);
+        let next_cursor = match remaining.len() > limit {
+            true => remaining.get(limit.saturating_sub(1)).map(|(cursor, _)| cursor.clone()),
+            false => None,
+        };
+        (remaining.into_iter().take(limit).map(|(_, item)| item).collect(), next_cursor)
+    }
+}
+
+impl Listed for Asset {
+    fn cursor(&self) -> String {
+        self.id.to_string()
+    }
+}
+
+// sells from the lowest price up, then buys from the highest down, earlier orders first at a price
+impl Listed for &Order {
+    fn cursor(&self) -> String {
+        let (side, price) = match (self.side, self.mode) {
+            (OrderSide::Sell, OrderMode::Limit(price)) => (0, price),
+            (OrderSide::Buy, OrderMode::Limit(price)) => (1, usize::MAX - price),
+            (OrderSide::Sell, OrderMode::Best) => (0, 0),
+            (OrderSide::Buy, OrderMode::Best) => (1, 0),
+        };
+        format!("{}-{:020}-{:020}-{}", side, price, self.created_at, self.id)
+    }
+
+    fn side(&self) -> Option<OrderSide> {
+        Some(self.side)
+    }
+
+    fn price(&self) -> Option<usize> {
+        self.mode.get_limit().ok()
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        Some(self.created_at)
+    }
+}
+
+// the side that took liquidity
+impl Listed for Trade {
+    fn cursor(&self) -> String {
+        format!("{:020}", self.id)
+    }
+
+    fn side(&self) -> Option<OrderSide> {
+        Some(self.aggressor)
+    }
+
+    fn price(&self) -> Option<usize> {
+        Some(self.price)
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        Some(self.timestamp)
+    }
+}
+
+// the ledger only ever grows, so the position of an entry is stable
+impl Listed for (usize, LedgerEntry) {
+    fn cursor(&self) -> String {
+        format!("{:020}", self.0)
+    }
+}
@@ -0,0 +1,147 @@
+use std::collections::{HashMap, VecDeque};
+use crate::account::PortfolioId;
+use crate::error::{EngineResult, ErrorType};
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PenaltyRule {
+    // rejected events within the window a portfolio gets away with, the one after is an offence
+    pub max_rejections: usize,
+    pub window_ticks: u64,
+    // coins taken on the first offence within the window, doubled on every one after it
+    pub fine: usize,
+    // no events for as many ticks after the first offence, doubled the same way. None bans nobody
+    pub ban_ticks: Option<u64>,
+}
+
+impl PenaltyRule {
+    pub fn check(&self) -> EngineResult<()> {
+        let bans = self.ban_ticks.is_some_and(|ticks| ticks > 0);
+        if self.max_rejections == 0 || self.window_ticks == 0 || (self.fine == 0 && !bans) {
+            return Err(ErrorType::InvalidPenaltyRule);
+        }
+        Ok(())
+    }
+
+    fn escalation(&self, offences: usize) -> usize {
+        2usize.saturating_pow(offences.saturating_sub(1) as u32)
+    }
+}
+
+// What an offence cost the portfolio.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Penalty {
+    pub fine: usize,
+    pub banned_until: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Record {
+    // ticks of the rejections since the last offence
+    rejections: VecDeque<u64>,
+    // ticks of the offences
+    offences: VecDeque<u64>,
+    banned_until: Option<u64>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PenaltyStanding {
+    pub portfolio: PortfolioId,
+    pub rejections: usize,
+    pub offences: usize,
+    pub banned_until: Option<u64>,
+}
+
+// Rejected events of the players, counted per portfolio while a rule is set. Only events and the
+// journaled rule change it, so replays end up with the same standings.
+#[derive(Clone, Debug, Default)]
+pub struct Penalties {
+    rule: Option<PenaltyRule>,
+    records: HashMap<PortfolioId, Record>,
+}
+
+impl Penalties {
+    pub fn new() -> Penalties {
+        Penalties {
+            rule: None,
+            records: HashMap::new(),
+        }
+    }
+
+    pub fn rule(&self) -> Option<PenaltyRule> {
+        self.rule
+    }
+
+    // a new rule starts everyone over, bans included
+    pub fn set_rule(&mut self, rule: Option<PenaltyRule>) -> EngineResult<()> {
+        if let Some(rule) = &rule {
+            rule.check()?;
+        }
+        self.rule = rule;
+        self.records.clear();
+        Ok(())
+    }
+
+    pub fn check(&self, portfolio_id: PortfolioId, tick: u64) -> EngineResult<()> {
+        match self.records.get(&portfolio_id).and_then(|r| r.banned_until) {
+            Some(until_tick) if tick < until_tick => Err(ErrorType::OrderBanned { until_tick }),
+            _ => Ok(()),
+        }
+    }
+
+    // the penalty if this rejection was one too many
+    pub fn record_rejection(&mut self, portfolio_id: PortfolioId, tick: u64) -> Option<Penalty> {
+        let rule = self.rule?;
+        let record = self.records.entry(portfolio_id).or_default();
+        let within = |t: &u64| t.saturating_add(rule.window_ticks) > tick;
+        record.rejections.retain(within);
+        record.offences.retain(within);
+        record.rejections.push_back(tick);
+        if record.rejections.len() <= rule.max_rejections {
+            return None;
+        }
+        record.rejections.clear();
+        record.offences.push_back(tick);
+        let escalation = rule.escalation(record.offences.len());
+        let banned_until = rule.ban_ticks
+            .filter(|ticks| *ticks > 0)
+            .map(|ticks| tick.saturating_add(ticks.saturating_mul(escalation as u64)));
+        if banned_until.is_some() {
+            record.banned_until = banned_until;
+        }
+        Some(Penalty {
+            fine: rule.fine.saturating_mul(escalation),
+            banned_until,
+        })
+    }
+
+    // portfolios with rejections, offences or a ban on record, a ban that ran out included
+    pub fn standings(&self) -> Vec<PenaltyStanding> {
+        let mut standings: Vec<PenaltyStanding> = self.records.iter()
+            .filter(|(_, r)| !r.rejections.is_empty() || !r.offences.is_empty() || r.banned_until.is_some())
+            .map(|(portfolio, r)| PenaltyStanding {
+                portfolio: *portfolio,
+                rejections: r.rejections.len(),
+                offences: r.offences.len(),
+                banned_until: r.banned_until,
+            })
+            .collect();
+        standings.sort_by_key(|s| s.portfolio);
+        standings
+    }
+}
+
+// whether the player is to blame for the rejection, the game turning events away isn't held against anyone
+pub fn counts_against(error: &ErrorType) -> bool {
+    !matches!(error, ErrorType::EngineWasTooBusy
+        | ErrorType::QueueFull { .. }
+        | ErrorType::RateLimited { .. }
+        | ErrorType::EngineTimeout { .. }
+        | ErrorType::EngineUnavailable
+        | ErrorType::ArithmeticOverflow
+        | ErrorType::InvalidState
+        | ErrorType::GamePaused
+        | ErrorType::GameEnded
+        | ErrorType::AssetHalted(_)
+        | ErrorType::Suspended
+        | ErrorType::OrderBanned { .. })
+}
@@ -0,0 +1,13 @@
+// The types embedders need to drive a game, importable with `use market_game::prelude::*`.
+pub use crate::error::{EngineResult, ErrorType};
+pub use crate::order::{Event, Order, OrderId, OrderMode, OrderSide, Stamp};
+pub use crate::book::Book;
+pub use crate::account::{Account, Portfolio, PortfolioId, Reservation, ReservationPurpose, ReservedAccount};
+pub use crate::market::{Asset, AssetId, Market, Notification, ReferencePricePolicy};
+pub use crate::pricing::PriceMode;
+pub use crate::lifecycle::{OrderRecord, OrderState};
+pub use crate::engine::Engine;
+pub use crate::game::Game;
+pub use crate::providers::{Clock, IdProvider, Timestamp};
+pub use crate::hooks::EngineHook;
+pub use crate::trades::Trade;
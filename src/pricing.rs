@@ -0,0 +1,35 @@
+use std::convert::TryFrom;
+use crate::error::{EngineResult, ErrorType};
+
+// Prices are quoted unsigned in both modes. In signed mode a quoted price stands for `price - zero`
+// coins, so quotes below `zero` are negative prices and the seller pays the buyer to take the asset.
+// Only prices go negative, balances stay unsigned in both modes: whoever pays needs the coins.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum PriceMode {
+    #[default]
+    Unsigned,
+    Signed { zero: usize },
+}
+
+impl PriceMode {
+    // coins one unit is worth at a quoted price
+    pub fn value(&self, price: usize) -> i128 {
+        match self {
+            PriceMode::Unsigned => price as i128,
+            PriceMode::Signed { zero } => price as i128 - *zero as i128,
+        }
+    }
+
+    // what the buyer pays the seller, negative if the seller pays
+    pub fn notional(&self, price: usize, quantity: usize) -> EngineResult<i128> {
+        self.value(price).checked_mul(quantity as i128).ok_or(ErrorType::ArithmeticOverflow)
+    }
+}
+
+pub fn to_coins(amount: i128) -> EngineResult<usize> {
+    usize::try_from(amount).map_err(|_| ErrorType::ArithmeticOverflow)
+}
+
+pub fn to_signed(amount: i128) -> EngineResult<i64> {
+    i64::try_from(amount).map_err(|_| ErrorType::ArithmeticOverflow)
+}
@@ -0,0 +1,130 @@
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// samples kept per phase, the percentiles cover the most recent events
+const MAX_SAMPLES: usize = 10_000;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize)]
+pub enum Phase {
+    // suspensions, bankruptcy, price band, hooks and the event fee
+    Validation,
+    // walking the books, resting and cancelling orders
+    Matching,
+    // moving coins and assets between the portfolios of a trade
+    Transfer,
+    // order states, post hooks, alerts and book history
+    Bookkeeping,
+}
+
+const PHASES: [Phase; 4] = [Phase::Validation, Phase::Matching, Phase::Transfer, Phase::Bookkeeping];
+
+// Laps of one event. Does nothing when profiling is off, `Instant` isn't available everywhere.
+pub struct PhaseTimer {
+    last: Option<Instant>,
+    laps: Vec<(Phase, Duration)>,
+}
+
+impl PhaseTimer {
+    pub fn start(enabled: bool) -> PhaseTimer {
+        PhaseTimer {
+            last: if enabled { Some(Instant::now()) } else { None },
+            laps: vec![],
+        }
+    }
+
+    // the time since the previous lap goes to the phase
+    pub fn lap(&mut self, phase: Phase) {
+        if let Some(last) = self.last {
+            let now = Instant::now();
+            self.laps.push((phase, now - last));
+            self.last = Some(now);
+        }
+    }
+
+    // moves time measured within the last lap over to another phase
+    pub fn carve(&mut self, phase: Phase, elapsed: Duration) {
+        if let Some((_, last)) = self.laps.last_mut() {
+            let elapsed = elapsed.min(*last);
+            *last -= elapsed;
+            self.laps.push((phase, elapsed));
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PhaseStats {
+    pub phase: Phase,
+    pub samples: usize,
+    pub p50_nanos: u64,
+    pub p90_nanos: u64,
+    pub p99_nanos: u64,
+    pub max_nanos: u64,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ProfileReport {
+    pub enabled: bool,
+    pub events: u64,
+    pub phases: Vec<PhaseStats>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Profiler {
+    events: u64,
+    samples: HashMap<Phase, VecDeque<u64>>,
+}
+
+impl Profiler {
+    pub fn new() -> Profiler {
+        Profiler {
+            events: 0,
+            samples: HashMap::new(),
+        }
+    }
+
+    // one sample per phase and event, phases of the same event are added up
+    pub fn record(&mut self, timer: PhaseTimer) {
+        if timer.laps.is_empty() {
+            return;
+        }
+        self.events += 1;
+        for phase in PHASES.iter() {
+            let nanos: u64 = timer.laps.iter()
+                .filter(|(p, _)| p == phase)
+                .map(|(_, elapsed)| elapsed.as_nanos() as u64)
+                .sum();
+            let samples = self.samples.entry(*phase).or_default();
+            if samples.len() == MAX_SAMPLES {
+                samples.pop_front();
+            }
+            samples.push_back(nanos);
+        }
+    }
+
+    pub fn report(&self) -> ProfileReport {
+        ProfileReport {
+            enabled: true,
+            events: self.events,
+            phases: PHASES.iter()
+                .filter_map(|phase| self.samples.get(phase).map(|samples| stats(*phase, samples)))
+                .collect(),
+        }
+    }
+}
+
+fn stats(phase: Phase, samples: &VecDeque<u64>) -> PhaseStats {
+    let mut sorted: Vec<u64> = samples.iter().cloned().collect();
+    sorted.sort();
+    let percentile = |p: usize| match sorted.len() {
+        0 => 0,
+        len => sorted[((len - 1) * p + 50) / 100],
+    };
+    PhaseStats {
+        phase,
+        samples: sorted.len(),
+        p50_nanos: percentile(50),
+        p90_nanos: percentile(90),
+        p99_nanos: percentile(99),
+        max_nanos: sorted.last().cloned().unwrap_or(0),
+    }
+}
@@ -0,0 +1,157 @@
+use std::sync::Arc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+// Milliseconds on the clock the engine was given.
+pub type Timestamp = u64;
+
+pub trait Clock: Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+pub trait IdProvider: Send + Sync {
+    fn next_id(&self) -> Uuid;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        SystemTime::now().duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0)
+    }
+}
+
+// uuid can't generate random ids on wasm32, see SequentialIds
+#[cfg(not(target_arch = "wasm32"))]
+pub struct RandomIds;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl IdProvider for RandomIds {
+    fn next_id(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn default_ids() -> Box<dyn IdProvider> {
+    Box::new(RandomIds)
+}
+
+// Picks the provider by name, for embedders configuring the engine from outside: "random",
+// "sequential" or "ulid".
+#[cfg(not(target_arch = "wasm32"))]
+pub fn ids_by_name(name: &str) -> Option<Box<dyn IdProvider>> {
+    match name {
+        "random" => Some(Box::new(RandomIds)),
+        "sequential" => Some(Box::new(SequentialIds::new())),
+        "ulid" => Some(Box::new(UlidIds::new(Box::new(SystemClock)))),
+        _ => None,
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn default_ids() -> Box<dyn IdProvider> {
+    Box::new(SequentialIds::new())
+}
+
+// Deterministic ids, e.g. for environments without a random source.
+pub struct SequentialIds {
+    next: AtomicU64,
+}
+
+impl Default for SequentialIds {
+    fn default() -> SequentialIds {
+        SequentialIds::new()
+    }
+}
+
+impl SequentialIds {
+    pub fn new() -> SequentialIds {
+        SequentialIds {
+            next: AtomicU64::new(1),
+        }
+    }
+}
+
+impl IdProvider for SequentialIds {
+    fn next_id(&self) -> Uuid {
+        let mut bytes = [0u8; 16];
+        bytes[8..].copy_from_slice(&self.next.fetch_add(1, Ordering::SeqCst).to_be_bytes());
+        Uuid::from_bytes(bytes)
+    }
+}
+
+// ULIDs carried in the 128 bits of a uuid: 48 bits of milliseconds then 80 random bits, so ids sort
+// by creation time. Ids made within the same millisecond count up from the first one.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct UlidIds {
+    clock: Box<dyn Clock>,
+    last: Mutex<(Timestamp, u128)>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl UlidIds {
+    pub fn new(clock: Box<dyn Clock>) -> UlidIds {
+        UlidIds {
+            clock,
+            last: Mutex::new((0, 0)),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl IdProvider for UlidIds {
+    fn next_id(&self) -> Uuid {
+        const RANDOM_MASK: u128 = (1 << 80) - 1;
+        let now = self.clock.now() & ((1 << 48) - 1);
+        let mut last = self.last.lock().unwrap_or_else(|e| e.into_inner());
+        let random = || u128::from_be_bytes(*Uuid::new_v4().as_bytes()) & RANDOM_MASK;
+        *last = if now > last.0 {
+            (now, random())
+        } else if last.1 < RANDOM_MASK {
+            (last.0, last.1 + 1)
+        } else {
+            // the millisecond ran out of ids, borrow the next one
+            (last.0 + 1, random())
+        };
+        Uuid::from_bytes(((u128::from(last.0) << 80) | last.1).to_be_bytes())
+    }
+}
+
+// Clock that only moves when told to, for embedders that drive time themselves.
+pub struct ManualClock {
+    now: AtomicU64,
+}
+
+impl ManualClock {
+    pub fn new(start: Timestamp) -> ManualClock {
+        ManualClock {
+            now: AtomicU64::new(start),
+        }
+    }
+
+    pub fn advance(&self, millis: u64) {
+        self.now.fetch_add(millis, Ordering::SeqCst);
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        self.now.store(now, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Timestamp {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+impl<C: Clock> Clock for Arc<C> {
+    fn now(&self) -> Timestamp {
+        C::now(self)
+    }
+}
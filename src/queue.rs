@@ -0,0 +1,295 @@
+use crate::error::{EngineResult, ErrorType};
+use crate::order::Event;
+use crate::actor::Actor;
+use crate::account::PortfolioId;
+use crate::latency::LatencyPolicy;
+use crate::colocation::STANDARD_CLASS;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Condvar, Mutex};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Instant;
+
+// Cancels jump ahead of new orders so players can always reduce their risk.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize)]
+pub enum Priority {
+    High,
+    Low,
+}
+
+impl Priority {
+    pub fn of(event: &Event) -> Priority {
+        match event {
+            Event::CancelOrder(..) | Event::DelistAsset(..) => Priority::High,
+            Event::Order(_) | Event::AtomicBatch(_) | Event::Oco { .. } => Priority::Low,
+        }
+    }
+}
+
+pub struct Job {
+    pub event: Event,
+    pub actor: Actor,
+    pub priority: Priority,
+    // the rank of the portfolio's priority class, lower goes first within the lane
+    class: usize,
+    enqueued_at: Instant,
+    // later than `enqueued_at` by the latency the job was given, see `LatencyPolicy`
+    ready_at: Instant,
+    reply: Sender<EngineResult<()>>,
+}
+
+impl Job {
+    pub fn reply(self, result: EngineResult<()>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct LaneMetrics {
+    pub depth: usize,
+    pub max_depth: usize,
+    pub processed: u64,
+    pub total_wait_micros: u64,
+}
+
+impl LaneMetrics {
+    pub fn average_wait_micros(&self) -> u64 {
+        if self.processed == 0 {
+            return 0;
+        }
+        self.total_wait_micros / self.processed
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ClassMetrics {
+    pub class: String,
+    #[serde(flatten)]
+    pub metrics: LaneMetrics,
+}
+
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct QueueMetrics {
+    pub high: LaneMetrics,
+    pub low: LaneMetrics,
+    // both lanes, per priority class best first
+    pub classes: Vec<ClassMetrics>,
+}
+
+impl QueueMetrics {
+    pub fn depth(&self) -> usize {
+        self.high.depth + self.low.depth
+    }
+
+    // how long new orders have been waiting so far, at least a second
+    pub fn retry_after_millis(&self) -> u64 {
+        (self.low.average_wait_micros() / 1000).max(1000)
+    }
+
+    fn lane_mut(&mut self, priority: Priority) -> &mut LaneMetrics {
+        match priority {
+            Priority::High => &mut self.high,
+            Priority::Low => &mut self.low,
+        }
+    }
+
+    fn class_mut(&mut self, class: usize) -> Option<&mut LaneMetrics> {
+        self.classes.get_mut(class).map(|c| &mut c.metrics)
+    }
+}
+
+struct Lanes {
+    high: VecDeque<Job>,
+    low: VecDeque<Job>,
+    metrics: QueueMetrics,
+    // new orders are turned away beyond this depth, cancels are always accepted
+    max_depth: Option<usize>,
+    latency: Option<LatencyPolicy>,
+    // events each portfolio had delayed so far, the next one draws its delay with this count
+    delayed: HashMap<PortfolioId, u64>,
+    // the ranks of portfolios not in the standard class, which is ranked last
+    classes: HashMap<PortfolioId, usize>,
+}
+
+impl Lanes {
+    fn lane_mut(&mut self, priority: Priority) -> &mut VecDeque<Job> {
+        match priority {
+            Priority::High => &mut self.high,
+            Priority::Low => &mut self.low,
+        }
+    }
+
+    fn standard_class(&self) -> usize {
+        self.metrics.classes.len().saturating_sub(1)
+    }
+
+    fn class_of(&self, portfolio: PortfolioId) -> usize {
+        self.classes.get(&portfolio).cloned().unwrap_or_else(|| self.standard_class())
+    }
+
+    // The lane's next job: of the ready ones the best class, then the one ready first. Without latency
+    // or classes that's the one at the front. Also when the first job that isn't ready yet will be.
+    fn pick(&self, priority: Priority, now: Instant) -> (Option<usize>, Option<Instant>) {
+        let lane = match priority {
+            Priority::High => &self.high,
+            Priority::Low => &self.low,
+        };
+        let ready = lane.iter().enumerate()
+            .filter(|(_, job)| job.ready_at <= now)
+            .min_by_key(|(_, job)| (job.class, job.ready_at))
+            .map(|(i, _)| i);
+        let waiting = lane.iter().map(|job| job.ready_at).filter(|ready_at| *ready_at > now).min();
+        (ready, waiting)
+    }
+}
+
+pub struct EventQueue {
+    lanes: Mutex<Lanes>,
+    available: Condvar,
+}
+
+impl Default for EventQueue {
+    fn default() -> EventQueue {
+        EventQueue::new()
+    }
+}
+
+impl EventQueue {
+    pub fn new() -> EventQueue {
+        EventQueue::with_max_depth(None)
+    }
+
+    pub fn with_max_depth(max_depth: Option<usize>) -> EventQueue {
+        EventQueue {
+            lanes: Mutex::new(Lanes {
+                high: VecDeque::new(),
+                low: VecDeque::new(),
+                metrics: QueueMetrics {
+                    classes: vec![ClassMetrics { class: STANDARD_CLASS.to_string(), metrics: LaneMetrics::default() }],
+                    ..QueueMetrics::default()
+                },
+                max_depth,
+                latency: None,
+                delayed: HashMap::new(),
+                classes: HashMap::new(),
+            }),
+            available: Condvar::new(),
+        }
+    }
+
+    pub fn submit(&self, event: Event, actor: Actor) -> EngineResult<Receiver<EngineResult<()>>> {
+        let (reply, receiver) = channel();
+        let priority = Priority::of(&event);
+        let mut lanes = self.lanes.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        let depth = lanes.high.len() + lanes.low.len();
+        if priority == Priority::Low && lanes.max_depth.is_some_and(|max| depth >= max) {
+            return Err(ErrorType::QueueFull {
+                depth,
+                retry_after_millis: lanes.metrics.retry_after_millis(),
+            });
+        }
+        let enqueued_at = Instant::now();
+        let ready_at = match lanes.latency.clone() {
+            // admins aren't slowed down
+            Some(latency) if actor.is_player() => {
+                let portfolio = event.portfolio();
+                let n = lanes.delayed.entry(portfolio).or_insert(0);
+                *n += 1;
+                enqueued_at + latency.delay(portfolio, *n)
+            },
+            _ => enqueued_at,
+        };
+        let class = lanes.class_of(event.portfolio());
+        let job = Job { event, actor, priority, class, enqueued_at, ready_at, reply };
+        lanes.lane_mut(priority).push_back(job);
+        if let Some(metrics) = lanes.metrics.class_mut(class) {
+            metrics.depth += 1;
+            metrics.max_depth = metrics.max_depth.max(metrics.depth);
+        }
+        let depth = match priority {
+            Priority::High => lanes.high.len(),
+            Priority::Low => lanes.low.len(),
+        };
+        let lane = lanes.metrics.lane_mut(priority);
+        lane.depth = depth;
+        lane.max_depth = lane.max_depth.max(depth);
+        self.available.notify_one();
+        Ok(receiver)
+    }
+
+    // Blocks until a job is ready, high priority first. Without latency that's the one at the front.
+    pub fn next(&self) -> EngineResult<Job> {
+        let mut lanes = self.lanes.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        loop {
+            let now = Instant::now();
+            let (high, high_waiting) = lanes.pick(Priority::High, now);
+            let (low, low_waiting) = lanes.pick(Priority::Low, now);
+            let ready = high.map(|i| (Priority::High, i)).or_else(|| low.map(|i| (Priority::Low, i)));
+            if let Some(job) = ready.and_then(|(priority, i)| lanes.lane_mut(priority).remove(i)) {
+                let depth = match job.priority {
+                    Priority::High => lanes.high.len(),
+                    Priority::Low => lanes.low.len(),
+                };
+                let lane = lanes.metrics.lane_mut(job.priority);
+                lane.depth = depth;
+                lane.processed += 1;
+                let wait_micros = job.enqueued_at.elapsed().as_micros() as u64;
+                lane.total_wait_micros += wait_micros;
+                if let Some(metrics) = lanes.metrics.class_mut(job.class) {
+                    metrics.depth = metrics.depth.saturating_sub(1);
+                    metrics.processed += 1;
+                    metrics.total_wait_micros += wait_micros;
+                }
+                return Ok(job);
+            }
+            let wake_at = high_waiting.into_iter().chain(low_waiting).min();
+            lanes = match wake_at {
+                Some(wake_at) => self.available.wait_timeout(lanes, wake_at - now).map_err(|_| ErrorType::EngineWasTooBusy)?.0,
+                None => self.available.wait(lanes).map_err(|_| ErrorType::EngineWasTooBusy)?,
+            };
+        }
+    }
+
+    pub fn get_metrics(&self) -> EngineResult<QueueMetrics> {
+        Ok(self.lanes.lock().map_err(|_| ErrorType::EngineWasTooBusy)?.metrics.clone())
+    }
+
+    // jobs already queued beyond a lower depth stay queued
+    pub fn set_max_depth(&self, max_depth: Option<usize>) -> EngineResult<()> {
+        self.lanes.lock().map_err(|_| ErrorType::EngineWasTooBusy)?.max_depth = max_depth;
+        Ok(())
+    }
+
+    // applies to events submitted from now on
+    pub fn set_latency(&self, latency: Option<LatencyPolicy>) -> EngineResult<()> {
+        self.lanes.lock().map_err(|_| ErrorType::EngineWasTooBusy)?.latency = latency;
+        Ok(())
+    }
+
+    // Names best first, the standard class last. Queued jobs move to their portfolio's new class,
+    // the metrics of classes that are kept carry on.
+    pub fn set_priority_classes(&self, names: Vec<String>, classes: HashMap<PortfolioId, usize>) -> EngineResult<()> {
+        let mut lanes = self.lanes.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        let mut previous = std::mem::take(&mut lanes.metrics.classes);
+        lanes.metrics.classes = names.into_iter()
+            .map(|class| {
+                let metrics = previous.iter_mut().find(|c| c.class == class).map(|c| std::mem::take(&mut c.metrics)).unwrap_or_default();
+                ClassMetrics { class, metrics: LaneMetrics { depth: 0, ..metrics } }
+            })
+            .collect();
+        lanes.classes = classes;
+        let mut queued = std::mem::take(&mut lanes.high);
+        queued.append(&mut lanes.low);
+        for mut job in queued {
+            job.class = lanes.class_of(job.event.portfolio());
+            if let Some(metrics) = lanes.metrics.class_mut(job.class) {
+                metrics.depth += 1;
+            }
+            lanes.lane_mut(job.priority).push_back(job);
+        }
+        Ok(())
+    }
+
+    pub fn get_max_depth(&self) -> EngineResult<Option<usize>> {
+        Ok(self.lanes.lock().map_err(|_| ErrorType::EngineWasTooBusy)?.max_depth)
+    }
+}
@@ -0,0 +1,114 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::error::{checked_add, checked_mul, EngineResult, ErrorType};
+use crate::auction::MarketStructure;
+use crate::market::{AssetId, Market};
+use crate::order::{OrderId, OrderMode, OrderSide};
+use crate::pricing::to_coins;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum RebalancePricing {
+    #[default]
+    Market,
+    LimitAtMid,
+}
+
+// percent of the portfolio value per asset, whatever is left stays in coins
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TargetWeights {
+    pub weights: HashMap<AssetId, usize>,
+    #[serde(default)]
+    pub pricing: RebalancePricing,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ClosePosition {
+    #[serde(default)]
+    pub pricing: RebalancePricing,
+}
+
+#[derive(Copy, Clone, Debug, Serialize)]
+pub struct RebalanceLeg {
+    pub asset: AssetId,
+    pub side: OrderSide,
+    pub quantity: usize,
+    pub mode: OrderMode,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RebalanceOutcome {
+    pub orders: Vec<(OrderId, RebalanceLeg)>,
+}
+
+// mid of the book, the last trade price if one side is empty
+fn price_of(market: &Market, asset_id: AssetId) -> EngineResult<Option<usize>> {
+    let book = match market.get_order_book(asset_id) {
+        Ok(book) => book,
+        Err(_) => return Ok(None),
+    };
+    let best_bid = book.buy_orders().filter_map(|o| o.mode.get_limit().ok()).max();
+    let best_ask = book.sell_orders().filter_map(|o| o.mode.get_limit().ok()).min();
+    match (best_bid, best_ask) {
+        (Some(bid), Some(ask)) => Ok(Some(checked_add(bid, ask)? / 2)),
+        _ => Ok(market.last_trade_prices.get(&asset_id).cloned()),
+    }
+}
+
+// The smallest set of orders that moves the targeted positions to their weights. Sells come
+// first so their proceeds pay for the buys when the legs run as one batch.
+pub fn plan(market: &Market, portfolio_id: PortfolioId, targets: &TargetWeights) -> EngineResult<Vec<RebalanceLeg>> {
+    if targets.weights.values().try_fold(0, |sum, weight| checked_add(sum, *weight))? > 100 {
+        return Err(ErrorType::InvalidTargetWeights);
+    }
+    let portfolio = market.get_portfolio(portfolio_id)?;
+    let value = market.portfolio_value(portfolio_id)?;
+    let mut assets: Vec<&AssetId> = targets.weights.keys().collect();
+    assets.sort();
+
+    let mut sells = vec![];
+    let mut buys = vec![];
+    for asset in assets {
+        market.get_asset(asset)?;
+        let held = portfolio.assets.get(asset).map_or(0, |a| a.get_total_amount());
+        let weight = targets.weights[asset];
+        if held == 0 && weight == 0 {
+            continue;
+        }
+        let price = price_of(market, *asset)?.filter(|p| market.price_mode.value(*p) > 0)
+            .ok_or(ErrorType::NoPriceForAsset(*asset))?;
+        let target = checked_mul(value, weight)? / 100 / to_coins(market.price_mode.value(price))?;
+        let mode = match targets.pricing {
+            RebalancePricing::Market => OrderMode::Best,
+            RebalancePricing::LimitAtMid => OrderMode::Limit(price),
+        };
+        match target.cmp(&held) {
+            Ordering::Less => sells.push(RebalanceLeg { asset: *asset, side: OrderSide::Sell, quantity: held - target, mode }),
+            Ordering::Greater => buys.push(RebalanceLeg { asset: *asset, side: OrderSide::Buy, quantity: target - held, mode }),
+            Ordering::Equal => (),
+        }
+    }
+    sells.extend(buys);
+    Ok(sells)
+}
+
+// The sell that flattens the portfolio's position: all of the free holdings, what's already offered
+// stays as it is. Best orders don't go in batch auctions, there it's a limit at the best bid.
+pub fn plan_close(market: &Market, portfolio_id: PortfolioId, asset_id: AssetId, close: &ClosePosition) -> EngineResult<RebalanceLeg> {
+    market.get_asset(&asset_id)?;
+    let quantity = market.get_portfolio(portfolio_id)?.assets.get(&asset_id).map_or(0, |a| a.get_free_amount());
+    if quantity == 0 {
+        return Err(ErrorType::NoPositionToClose(asset_id));
+    }
+    let mode = match (close.pricing, market.market_structure) {
+        (RebalancePricing::Market, MarketStructure::Continuous) => OrderMode::Best,
+        (RebalancePricing::Market, MarketStructure::BatchAuction) => {
+            let book = market.get_order_book(asset_id)?;
+            let best_bid = book.buy_orders().filter_map(|o| o.mode.get_limit().ok()).max();
+            OrderMode::Limit(best_bid.ok_or(ErrorType::NoPriceForAsset(asset_id))?)
+        },
+        (RebalancePricing::LimitAtMid, _) => OrderMode::Limit(price_of(market, asset_id)?.ok_or(ErrorType::NoPriceForAsset(asset_id))?),
+    };
+    Ok(RebalanceLeg { asset: asset_id, side: OrderSide::Sell, quantity, mode })
+}
@@ -0,0 +1,113 @@
+use crate::format::Formatter;
+use crate::marketdata::{L2Depth, PriceLevel};
+
+// levels per side, a projector can't show more anyway
+const MAX_LEVELS: usize = 20;
+const BAR_WIDTH: usize = 40;
+const SVG_WIDTH: usize = 600;
+const SVG_HEIGHT: usize = 300;
+const SVG_MARGIN: usize = 30;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RenderFormat {
+    Ascii,
+    Svg,
+}
+
+impl RenderFormat {
+    pub fn parse(format: &str) -> Option<RenderFormat> {
+        match format.to_lowercase().as_str() {
+            "ascii" => Some(RenderFormat::Ascii),
+            "svg" => Some(RenderFormat::Svg),
+            _ => None,
+        }
+    }
+}
+
+pub fn render(depth: &L2Depth, format: RenderFormat, numbers: &Formatter) -> String {
+    match format {
+        RenderFormat::Ascii => ascii(depth, numbers),
+        RenderFormat::Svg => svg(depth, numbers),
+    }
+}
+
+// (price, cumulative quantity) from the best level outwards
+fn cumulative(levels: &[PriceLevel]) -> Vec<(usize, usize)> {
+    let mut total = 0;
+    levels.iter().take(MAX_LEVELS).map(|level| {
+        total += level.quantity;
+        (level.price, total)
+    }).collect()
+}
+
+// Asks from the top down to the spread, bids below it, bars grow with the cumulative depth.
+pub fn ascii(depth: &L2Depth, numbers: &Formatter) -> String {
+    let asks = cumulative(&depth.asks);
+    let bids = cumulative(&depth.bids);
+    let max = asks.iter().chain(bids.iter()).map(|(_, total)| *total).max().unwrap_or(0).max(1);
+    let line = |symbol: char, price: usize, total: usize| {
+        let bar = symbol.to_string().repeat((total * BAR_WIDTH).div_ceil(max));
+        format!("{:>10} | {:<width$} {}\n", numbers.price(depth.asset, price), bar, numbers.quantity(total), width = BAR_WIDTH)
+    };
+
+    let mut out = format!("book {}\n", depth.asset);
+    for (price, total) in asks.iter().rev() {
+        out.push_str(&line('-', *price, *total));
+    }
+    let spread = match (depth.bids.first(), depth.asks.first()) {
+        (Some(bid), Some(ask)) => format!("spread {}", numbers.price(depth.asset, ask.price.saturating_sub(bid.price))),
+        _ => "no spread".to_string(),
+    };
+    out.push_str(&format!("{:>10} + {}\n", "", spread));
+    for (price, total) in bids.iter() {
+        out.push_str(&line('+', *price, *total));
+    }
+    out
+}
+
+// Classic depth chart, price to the right and cumulative quantity upwards, bids green and asks red.
+pub fn svg(depth: &L2Depth, numbers: &Formatter) -> String {
+    let asks = cumulative(&depth.asks);
+    let bids = cumulative(&depth.bids);
+    let mut out = format!("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">\n", SVG_WIDTH, SVG_HEIGHT);
+    let prices: Vec<usize> = asks.iter().chain(bids.iter()).map(|(price, _)| *price).collect();
+    let (low, high) = match (prices.iter().min(), prices.iter().max()) {
+        (Some(low), Some(high)) => (low.saturating_sub(1), high + 1),
+        _ => {
+            out.push_str(&format!("<text x=\"{}\" y=\"{}\">empty book</text>\n</svg>\n", SVG_MARGIN, SVG_HEIGHT / 2));
+            return out;
+        },
+    };
+    let max = asks.iter().chain(bids.iter()).map(|(_, total)| *total).max().unwrap_or(0).max(1);
+    let plot_width = (SVG_WIDTH - 2 * SVG_MARGIN) as f64;
+    let plot_height = (SVG_HEIGHT - 2 * SVG_MARGIN) as f64;
+    let x = |price: usize| SVG_MARGIN as f64 + (price - low) as f64 / (high - low) as f64 * plot_width;
+    let y = |total: usize| (SVG_HEIGHT - SVG_MARGIN) as f64 - total as f64 / max as f64 * plot_height;
+
+    // steps outwards from the best price, down to the axis at the edge of the chart
+    let area = |side: &[(usize, usize)], edge: usize| {
+        let mut points = vec![];
+        let mut previous = 0;
+        for (price, total) in side {
+            points.push(format!("{:.1},{:.1}", x(*price), y(previous)));
+            points.push(format!("{:.1},{:.1}", x(*price), y(*total)));
+            previous = *total;
+        }
+        points.push(format!("{:.1},{:.1}", x(edge), y(previous)));
+        points.push(format!("{:.1},{:.1}", x(edge), y(0)));
+        points.join(" ")
+    };
+    if !bids.is_empty() {
+        out.push_str(&format!("<polygon points=\"{}\" fill=\"#9be29b\" stroke=\"#2e8b2e\"/>\n", area(&bids, low)));
+    }
+    if !asks.is_empty() {
+        out.push_str(&format!("<polygon points=\"{}\" fill=\"#f2a0a0\" stroke=\"#b22222\"/>\n", area(&asks, high)));
+    }
+    out.push_str(&format!("<line x1=\"{m}\" y1=\"{b}\" x2=\"{r}\" y2=\"{b}\" stroke=\"black\"/>\n",
+                          m = SVG_MARGIN, b = SVG_HEIGHT - SVG_MARGIN, r = SVG_WIDTH - SVG_MARGIN));
+    out.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", SVG_MARGIN, SVG_HEIGHT - 8, numbers.price(depth.asset, low)));
+    out.push_str(&format!("<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\n", SVG_WIDTH - SVG_MARGIN, SVG_HEIGHT - 8, numbers.price(depth.asset, high)));
+    out.push_str(&format!("<text x=\"{}\" y=\"{}\">{}</text>\n", SVG_MARGIN, SVG_MARGIN - 8, numbers.quantity(max)));
+    out.push_str("</svg>\n");
+    out
+}
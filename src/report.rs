@@ -0,0 +1,169 @@
+use std::collections::BTreeMap;
+use uuid::Uuid;
+use crate::account::PortfolioId;
+use crate::engine::Engine;
+use crate::error::EngineResult;
+use crate::leaderboard::{self, LeaderboardEntry, ScoringMetric};
+use crate::ledger::LedgerKind;
+use crate::market::AssetId;
+use crate::trades::Trade;
+
+// of the biggest trades, the report keeps no more
+pub const NOTABLE_TRADES: usize = 10;
+
+// Where a player's net worth came from. The ledger amounts are coins in or out, `realized_pnl` and
+// `unrealized_pnl` are the trading, fees not included.
+#[derive(Clone, Debug, Serialize)]
+pub struct PnlDecomposition {
+    pub portfolio: PortfolioId,
+    // the first sample of the net worth, none for portfolios never sampled
+    pub starting_value: Option<usize>,
+    pub final_value: usize,
+    pub realized_pnl: i64,
+    pub unrealized_pnl: i64,
+    pub fees: usize,
+    pub fee_refunds: usize,
+    pub rebates: usize,
+    pub interest: usize,
+    pub dividends: usize,
+    pub settlements: usize,
+    pub faucet: usize,
+    pub penalties: usize,
+    // fresh balances of restarts after bankruptcy, less what was left when restarting
+    pub restarts: i64,
+    // allocations at game start and priority classes
+    pub purchases: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct AssetVolume {
+    pub asset: AssetId,
+    pub name: String,
+    pub trades: usize,
+    pub quantity: usize,
+    pub notional: usize,
+    pub last_price: Option<usize>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NotableTrade {
+    pub trade: Trade,
+    pub notional: usize,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct NetWorthSeries {
+    pub portfolio: PortfolioId,
+    // (tick, net worth)
+    pub points: Vec<(u64, usize)>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PriceSeries {
+    pub asset: AssetId,
+    // (tick, price of the tick's last trade), ticks without trades are left out
+    pub points: Vec<(u64, usize)>,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Charts {
+    pub net_worth: Vec<NetWorthSeries>,
+    pub prices: Vec<PriceSeries>,
+}
+
+// Everything there is to say about a game once it's over, built from the trade log, the ledger and
+// the portfolio history. Busted trades don't count.
+#[derive(Clone, Debug, Serialize)]
+pub struct GameReport {
+    pub game_id: Uuid,
+    pub ended_at_tick: u64,
+    pub game_time: u64,
+    pub scoring_metric: ScoringMetric,
+    pub leaderboard: Vec<LeaderboardEntry>,
+    pub pnl: Vec<PnlDecomposition>,
+    pub volume: Vec<AssetVolume>,
+    // the biggest by notional, biggest first
+    pub notable_trades: Vec<NotableTrade>,
+    pub charts: Charts,
+}
+
+impl GameReport {
+    pub fn build(engine: &Engine) -> EngineResult<GameReport> {
+        let market = &engine.market;
+        let leaderboard = leaderboard::rank(engine, engine.scoring_metric)?;
+        let trades: Vec<&Trade> = market.trades.iter().filter(|t| !t.busted).collect();
+        let notional = |trade: &Trade| market.price_mode.notional(trade.price, trade.quantity).map_or(0, |n| n.unsigned_abs() as usize);
+
+        let mut pnl = vec![];
+        for entry in &leaderboard {
+            let ledger = market.get_ledger_for(entry.portfolio);
+            let sum = |kinds: &[LedgerKind]| ledger.iter()
+                .filter(|e| kinds.contains(&e.kind))
+                .map(|e| e.amount)
+                .sum::<usize>();
+            pnl.push(PnlDecomposition {
+                portfolio: entry.portfolio,
+                starting_value: engine.portfolio_history.get_series(entry.portfolio).first().cloned(),
+                final_value: entry.net_worth,
+                realized_pnl: entry.realized_pnl,
+                unrealized_pnl: entry.unrealized_pnl,
+                fees: sum(&[LedgerKind::Fee, LedgerKind::TakerFee]),
+                fee_refunds: sum(&[LedgerKind::FeeRefund]),
+                rebates: sum(&[LedgerKind::MakerRebate]),
+                interest: sum(&[LedgerKind::Interest]),
+                dividends: sum(&[LedgerKind::Dividend]),
+                settlements: sum(&[LedgerKind::Settlement]),
+                faucet: sum(&[LedgerKind::Faucet]),
+                penalties: sum(&[LedgerKind::Penalty]),
+                restarts: sum(&[LedgerKind::Restart]) as i64 - sum(&[LedgerKind::Forfeit]) as i64,
+                purchases: sum(&[LedgerKind::Allocation, LedgerKind::Colocation]),
+            });
+        }
+
+        let mut volume: BTreeMap<AssetId, AssetVolume> = BTreeMap::new();
+        let mut prices: BTreeMap<AssetId, BTreeMap<u64, usize>> = BTreeMap::new();
+        for trade in &trades {
+            let entry = volume.entry(trade.asset).or_insert_with(|| AssetVolume {
+                asset: trade.asset,
+                name: market.assets.get(&trade.asset).map(|a| a.name.clone()).unwrap_or_default(),
+                trades: 0,
+                quantity: 0,
+                notional: 0,
+                last_price: None,
+            });
+            entry.trades += 1;
+            entry.quantity = entry.quantity.saturating_add(trade.quantity);
+            entry.notional = entry.notional.saturating_add(notional(trade));
+            entry.last_price = Some(trade.price);
+            prices.entry(trade.asset).or_default().insert(trade.tick, trade.price);
+        }
+
+        let mut notable: Vec<NotableTrade> = trades.iter()
+            .map(|trade| NotableTrade { trade: (*trade).clone(), notional: notional(trade) })
+            .collect();
+        notable.sort_by(|a, b| b.notional.cmp(&a.notional).then(a.trade.id.cmp(&b.trade.id)));
+        notable.truncate(NOTABLE_TRADES);
+
+        Ok(GameReport {
+            game_id: market.game_id,
+            ended_at_tick: market.tick,
+            game_time: market.game_time,
+            scoring_metric: engine.scoring_metric,
+            charts: Charts {
+                net_worth: leaderboard.iter()
+                    .map(|entry| NetWorthSeries {
+                        portfolio: entry.portfolio,
+                        points: engine.portfolio_history.get_samples(entry.portfolio),
+                    })
+                    .collect(),
+                prices: prices.into_iter()
+                    .map(|(asset, points)| PriceSeries { asset, points: points.into_iter().collect() })
+                    .collect(),
+            },
+            leaderboard,
+            pnl,
+            volume: volume.into_values().collect(),
+            notable_trades: notable,
+        })
+    }
+}
@@ -0,0 +1,68 @@
+use crate::history::BookSnapshot;
+use crate::market::AssetId;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct RiskMetrics {
+    pub asset: AssetId,
+    pub trades: usize,
+    pub volatility: Option<f64>,
+    pub max_drawdown: Option<f64>,
+    pub average_spread: Option<f64>,
+}
+
+impl RiskMetrics {
+    // `prices` are trade prices oldest first, of which the last `window` are used for volatility.
+    pub fn compute(asset: AssetId, prices: &[usize], snapshots: &[BookSnapshot], window: usize) -> RiskMetrics {
+        let start = prices.len().saturating_sub(window + 1);
+        RiskMetrics {
+            asset,
+            trades: prices.len(),
+            volatility: volatility(&prices[start..]),
+            max_drawdown: max_drawdown(prices),
+            average_spread: average_spread(snapshots),
+        }
+    }
+}
+
+// standard deviation of trade-to-trade returns, in percent
+pub fn volatility(prices: &[usize]) -> Option<f64> {
+    let returns: Vec<f64> = prices.windows(2)
+        .filter(|w| w[0] > 0)
+        .map(|w| (w[1] as f64 - w[0] as f64) / w[0] as f64)
+        .collect();
+    if returns.len() < 2 {
+        return None;
+    }
+    let mean = returns.iter().sum::<f64>() / returns.len() as f64;
+    let variance = returns.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / (returns.len() - 1) as f64;
+    Some(variance.sqrt() * 100.0)
+}
+
+// largest peak-to-trough decline, in percent of the peak
+pub fn max_drawdown(prices: &[usize]) -> Option<f64> {
+    if prices.is_empty() {
+        return None;
+    }
+    let mut peak = prices[0];
+    let mut drawdown = 0.0;
+    for price in prices {
+        peak = peak.max(*price);
+        if peak > 0 {
+            drawdown = f64::max(drawdown, (peak - price) as f64 / peak as f64 * 100.0);
+        }
+    }
+    Some(drawdown)
+}
+
+pub fn average_spread(snapshots: &[BookSnapshot]) -> Option<f64> {
+    let spreads: Vec<usize> = snapshots.iter()
+        .filter_map(|s| match (s.buy.first(), s.sell.first()) {
+            (Some((bid, _)), Some((ask, _))) if ask >= bid => Some(ask - bid),
+            _ => None,
+        })
+        .collect();
+    if spreads.is_empty() {
+        return None;
+    }
+    Some(spreads.iter().sum::<usize>() as f64 / spreads.len() as f64)
+}
@@ -0,0 +1,61 @@
+use crate::account::PortfolioId;
+use crate::error::{EngineResult, ErrorType};
+use crate::export::CsvRow;
+use crate::format::Formatter;
+
+// more than any class, a typo in the count shouldn't create a million portfolios
+pub const MAX_BULK_PORTFOLIOS: usize = 500;
+
+pub(crate) fn default_starting_coins() -> usize {
+    1000
+}
+
+// Whom a provisioned portfolio was handed to. Only the fingerprint of the token is kept, see `Actor::token`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RosterEntry {
+    pub portfolio: PortfolioId,
+    pub name: String,
+    pub fingerprint: String,
+}
+
+// The credential sheet's rows, the only place the tokens ever show up.
+#[derive(Clone, Debug, Serialize)]
+pub struct Credential {
+    pub name: String,
+    pub portfolio: PortfolioId,
+    pub token: String,
+    pub coins: usize,
+}
+
+impl CsvRow for Credential {
+    fn header() -> &'static str {
+        "name,portfolio,token,coins"
+    }
+
+    fn row(&self, _: &Formatter) -> String {
+        format!("{},{},{},{}", self.name, self.portfolio, self.token, self.coins)
+    }
+}
+
+// One display name per line, the first column counts and a `name` header is skipped. The names
+// end up in a CSV again, so they can't contain commas or quotes.
+pub fn parse_roster_csv(csv: &str) -> EngineResult<Vec<String>> {
+    let mut names = vec![];
+    for (i, line) in csv.lines().enumerate() {
+        let name = line.split(',').next().unwrap_or("").trim();
+        if name.is_empty() || (i == 0 && name.eq_ignore_ascii_case("name")) {
+            continue;
+        }
+        if name.contains('"') {
+            return Err(ErrorType::InvalidRosterCsv { line: i + 1 });
+        }
+        names.push(name.to_string());
+    }
+    Ok(names)
+}
+
+// `student-01` to `student-<count>`, padded so they sort
+pub fn numbered_names(count: usize) -> Vec<String> {
+    let width = count.to_string().len().max(2);
+    (1..=count).map(|i| format!("student-{:0width$}", i, width = width)).collect()
+}
@@ -0,0 +1,103 @@
+use crate::auction::MarketStructure;
+use crate::error::{EngineResult, ErrorType};
+use crate::fees::FeeSchedule;
+use crate::market::{AssetId, Market};
+use crate::order::MinimumRestingTime;
+use crate::pricing::PriceMode;
+
+// How far limits may be from the reference price right now, see `Market::check_price_band`.
+#[derive(Clone, Debug, Serialize)]
+pub struct PriceBand {
+    pub percent: usize,
+    // none until the asset has a reference price or traded, there's no band to keep to until then
+    pub min: Option<usize>,
+    pub max: Option<usize>,
+}
+
+// ticks in a day, at one a second
+pub const TICKS_PER_DAY: u64 = 86400;
+
+// Daily trading hours as ticks into the day, orders outside them get `MarketClosed`. Closing before
+// opening spans midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SessionTimes {
+    pub opens_at: u64,
+    pub closes_at: u64,
+}
+
+impl SessionTimes {
+    pub fn validate(&self) -> EngineResult<()> {
+        if self.opens_at >= TICKS_PER_DAY || self.closes_at >= TICKS_PER_DAY || self.opens_at == self.closes_at {
+            return Err(ErrorType::InvalidSessionTimes);
+        }
+        Ok(())
+    }
+
+    pub fn is_open(&self, tick: u64) -> bool {
+        let time = tick % TICKS_PER_DAY;
+        match self.opens_at < self.closes_at {
+            true => time >= self.opens_at && time < self.closes_at,
+            false => time >= self.opens_at || time < self.closes_at,
+        }
+    }
+}
+
+// An asset is open until the game ends, whenever the game isn't paused, the asset isn't halted and
+// it's within the session times, if the game has them.
+#[derive(Clone, Debug, Serialize)]
+pub struct Session {
+    pub open: bool,
+    pub market_structure: MarketStructure,
+    pub tick: u64,
+}
+
+// What a bot needs to know to place valid orders for one asset, gathered from the game's settings.
+#[derive(Clone, Debug, Serialize)]
+pub struct AssetRules {
+    pub asset: AssetId,
+    pub name: String,
+    // prices are whole numbers shown with this many places after the point
+    pub decimals: u32,
+    // prices and quantities are whole numbers, one is the smallest step of either
+    pub tick_size: usize,
+    pub lot_size: usize,
+    pub price_mode: PriceMode,
+    pub price_band: Option<PriceBand>,
+    pub minimum_resting_time: Option<MinimumRestingTime>,
+    pub session: Session,
+    // volume tiers apply by the portfolio's own volume, see `GET /portfolio/<id>/activity-stats`
+    pub fees: FeeSchedule,
+    // none, a position is only limited by the coins or holdings to pay for it
+    pub position_limit: Option<usize>,
+}
+
+impl AssetRules {
+    pub fn of(market: &Market, asset_id: AssetId) -> EngineResult<AssetRules> {
+        let asset = market.get_asset(&asset_id)?;
+        let price_band = match market.price_band_percent {
+            Some(percent) => {
+                let limits = market.price_band_limits(asset_id, percent)?;
+                Some(PriceBand { percent, min: limits.map(|(min, _)| min), max: limits.map(|(_, max)| max) })
+            },
+            None => None,
+        };
+        Ok(AssetRules {
+            asset: asset_id,
+            name: asset.name.clone(),
+            decimals: asset.decimals,
+            tick_size: 1,
+            lot_size: 1,
+            price_mode: market.price_mode,
+            price_band,
+            minimum_resting_time: market.minimum_resting_time,
+            session: Session {
+                open: !market.ended && !market.paused && !market.halted.contains(&asset_id)
+                    && market.check_session().is_ok(),
+                market_structure: market.market_structure,
+                tick: market.tick,
+            },
+            fees: market.fee_schedule.clone(),
+            position_limit: None,
+        })
+    }
+}
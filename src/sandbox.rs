@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::error::{EngineResult, ErrorType};
+use crate::market::{AssetId, Market};
+use crate::order::{Order, OrderMode, OrderSide};
+use crate::pricing::to_coins;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct SandboxFill {
+    pub tick: u64,
+    pub asset: AssetId,
+    pub side: OrderSide,
+    pub price: usize,
+    pub quantity: usize,
+}
+
+// A paper copy of a player's portfolio. Its orders are filled against the real books as they are,
+// without ever touching them or the player's real balances.
+#[derive(Clone, Debug, Serialize)]
+pub struct SandboxPortfolio {
+    pub owner: PortfolioId,
+    pub coins: usize,
+    pub assets: HashMap<AssetId, usize>,
+    pub fills: Vec<SandboxFill>,
+    pub created_tick: u64,
+}
+
+impl SandboxPortfolio {
+    // starts out with everything the owner has, reserved amounts included
+    pub fn spawn(market: &Market, owner: PortfolioId) -> EngineResult<SandboxPortfolio> {
+        let portfolio = market.get_portfolio(owner)?;
+        Ok(SandboxPortfolio {
+            owner,
+            coins: portfolio.coins.get_total_amount(),
+            assets: portfolio.assets.iter()
+                .map(|(asset, account)| (*asset, account.get_total_amount()))
+                .filter(|(_, amount)| *amount > 0)
+                .collect(),
+            fills: vec![],
+            created_tick: market.tick,
+        })
+    }
+
+    // coins plus holdings at the last trade prices, like `Market::portfolio_value`
+    pub fn value(&self, market: &Market) -> EngineResult<usize> {
+        let mut value = self.coins as i128;
+        for (asset, quantity) in &self.assets {
+            if let Some(price) = market.last_trade_prices.get(asset) {
+                value += market.price_mode.notional(*price, *quantity)?;
+            }
+        }
+        to_coins(value.max(0))
+    }
+
+    // Sandbox orders never rest: they take what the book offers right now and the rest is dropped.
+    // No fees are charged, nothing of this reaches the bank.
+    pub fn execute(&mut self, market: &Market, order: &Order) -> EngineResult<Vec<SandboxFill>> {
+        let legs = market.shadow_fill(order)?;
+        if legs.is_empty() && order.mode == OrderMode::Best {
+            return Err(ErrorType::NotEnoughMatchingOrdersToImmediatelyFillBestOrder);
+        }
+        let mut coins = self.coins as i128;
+        let mut held = self.assets.get(&order.asset).cloned().unwrap_or(0) as i128;
+        for (price, quantity) in &legs {
+            let notional = market.price_mode.notional(*price, *quantity)?;
+            match order.side {
+                OrderSide::Buy => {
+                    coins -= notional;
+                    held += *quantity as i128;
+                },
+                OrderSide::Sell => {
+                    coins += notional;
+                    held -= *quantity as i128;
+                },
+            }
+        }
+        if coins < 0 || held < 0 {
+            return Err(ErrorType::InsufficientFreeAmount);
+        }
+
+        self.coins = to_coins(coins)?;
+        let held = to_coins(held)?;
+        if held == 0 {
+            self.assets.remove(&order.asset);
+        } else {
+            self.assets.insert(order.asset, held);
+        }
+        let fills: Vec<SandboxFill> = legs.into_iter()
+            .map(|(price, quantity)| SandboxFill {
+                tick: market.tick,
+                asset: order.asset,
+                side: order.side,
+                price,
+                quantity,
+            })
+            .collect();
+        self.fills.extend(fills.iter().cloned());
+        Ok(fills)
+    }
+}
+
+// At most one sandbox per player, spawning again starts over.
+#[derive(Clone, Debug, Default)]
+pub struct Sandboxes {
+    portfolios: HashMap<PortfolioId, SandboxPortfolio>,
+}
+
+impl Sandboxes {
+    pub fn new() -> Sandboxes {
+        Sandboxes {
+            portfolios: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, sandbox: SandboxPortfolio) {
+        self.portfolios.insert(sandbox.owner, sandbox);
+    }
+
+    pub fn get(&self, owner: PortfolioId) -> EngineResult<&SandboxPortfolio> {
+        self.portfolios.get(&owner).ok_or(ErrorType::SandboxNotFound(owner))
+    }
+
+    pub fn get_mut(&mut self, owner: PortfolioId) -> EngineResult<&mut SandboxPortfolio> {
+        self.portfolios.get_mut(&owner).ok_or(ErrorType::SandboxNotFound(owner))
+    }
+
+    pub fn remove(&mut self, owner: PortfolioId) -> EngineResult<()> {
+        self.portfolios.remove(&owner).map(|_| ()).ok_or(ErrorType::SandboxNotFound(owner))
+    }
+}
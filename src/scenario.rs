@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+use crate::actor::Actor;
+use crate::engine::Engine;
+use crate::error::{EngineResult, ErrorType};
+use crate::journal::{JournalEntry, JournalRecord};
+use crate::market::Market;
+
+pub const SCENARIO_FORMAT_VERSION: u32 = 1;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScenarioStep {
+    // who it came from in the recorded game, it's played back by the scenario bot
+    pub actor: Actor,
+    pub record: JournalRecord,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScenarioTick {
+    // ticks since the scenario started, the steps before the first tick have 0
+    pub offset: u64,
+    pub steps: Vec<ScenarioStep>,
+}
+
+// What the admins and the outside world did to a live game, tick by tick. Whatever the game's own
+// runners did is left out, the game playing it back runs its own.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scenario {
+    pub version: u32,
+    // the market when the recording started
+    pub initial: Market,
+    pub ticks: Vec<ScenarioTick>,
+}
+
+impl Scenario {
+    // the game the scenario was recorded in, as it was when the recording started
+    pub fn engine(&self) -> EngineResult<Engine> {
+        self.check()?;
+        Ok(Engine::from_market(self.initial.clone()))
+    }
+
+    fn check(&self) -> EngineResult<()> {
+        if self.version != SCENARIO_FORMAT_VERSION {
+            return Err(ErrorType::UnsupportedScenarioVersion(self.version));
+        }
+        Ok(())
+    }
+}
+
+// admins and players, and the oracle's prices from the outside
+fn recorded(entry: &JournalEntry) -> bool {
+    match entry.actor {
+        Actor::Admin | Actor::Anonymous | Actor::Token(_) => true,
+        Actor::System => matches!(entry.record, JournalRecord::OraclePrice { .. }),
+        Actor::Bot(_) => false,
+    }
+}
+
+// Where the journal stood when the recording started. Never journaled, it's for the organizers.
+#[derive(Clone)]
+pub struct Recording {
+    from_sequence: u64,
+    initial: Market,
+}
+
+impl Recording {
+    pub fn new(from_sequence: u64, initial: Market) -> Recording {
+        Recording {
+            from_sequence,
+            initial,
+        }
+    }
+
+    // everything recorded up to the end of the journal
+    pub fn scenario(&self, journal: &[JournalEntry]) -> Scenario {
+        let mut ticks = vec![];
+        let mut current = ScenarioTick { offset: 0, steps: vec![] };
+        for entry in journal.iter().filter(|e| e.sequence > self.from_sequence) {
+            if let JournalRecord::Tick = entry.record {
+                let offset = current.offset + 1;
+                let done = std::mem::replace(&mut current, ScenarioTick { offset, steps: vec![] });
+                if !done.steps.is_empty() {
+                    ticks.push(done);
+                }
+                continue;
+            }
+            if recorded(entry) {
+                current.steps.push(ScenarioStep { actor: entry.actor.clone(), record: entry.record.clone() });
+            }
+        }
+        if !current.steps.is_empty() {
+            ticks.push(current);
+        }
+        Scenario {
+            version: SCENARIO_FORMAT_VERSION,
+            initial: self.initial.clone(),
+            ticks,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct ScenarioStatus {
+    pub started_at_tick: u64,
+    // offset of the next tick with steps, none once all of them were played
+    pub next_offset: Option<u64>,
+    pub remaining_steps: usize,
+}
+
+// The steps of a scenario still to come, each tick's once the game got as far past the start.
+#[derive(Clone, Debug)]
+pub struct ScenarioPlayer {
+    start_tick: u64,
+    ticks: VecDeque<ScenarioTick>,
+}
+
+impl ScenarioPlayer {
+    pub fn new(scenario: Scenario, start_tick: u64) -> EngineResult<ScenarioPlayer> {
+        scenario.check()?;
+        Ok(ScenarioPlayer {
+            start_tick,
+            ticks: scenario.ticks.into_iter().collect(),
+        })
+    }
+
+    pub fn status(&self) -> ScenarioStatus {
+        ScenarioStatus {
+            started_at_tick: self.start_tick,
+            next_offset: self.ticks.front().map(|t| t.offset),
+            remaining_steps: self.ticks.iter().map(|t| t.steps.len()).sum(),
+        }
+    }
+}
+
+// Plays the steps that are due. They go through `Engine::apply`, so they're journaled like the
+// admin's and the players' own, a step the game turns down is skipped.
+pub fn run_scenario(engine: &mut Engine) {
+    let mut player = match engine.scenario.take() {
+        Some(player) => player,
+        None => return,
+    };
+    let elapsed = engine.market.tick.saturating_sub(player.start_tick);
+    while player.ticks.front().is_some_and(|t| t.offset <= elapsed) {
+        if let Some(tick) = player.ticks.pop_front() {
+            for step in tick.steps {
+                let _ = engine.apply(step.record);
+            }
+        }
+    }
+    if !player.ticks.is_empty() {
+        engine.scenario = Some(player);
+    }
+}
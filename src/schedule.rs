@@ -0,0 +1,106 @@
+use uuid::Uuid;
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::engine::Engine;
+use crate::journal::JournalRecord;
+use crate::error::{EngineResult, ErrorType};
+use crate::market::AssetId;
+use crate::order::{Event, Order, OrderId, OrderMode, OrderSide};
+
+const ORDER_LIFETIME_MILLIS: u64 = 24*60*60*1000;
+
+// Places the same order every `every_ticks` ticks until cancelled.
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct RecurringOrder {
+    pub id: Uuid,
+    pub portfolio: PortfolioId,
+    pub asset: AssetId,
+    pub side: OrderSide,
+    pub quantity: usize,
+    pub mode: OrderMode,
+    pub every_ticks: u64,
+    pub next_tick: u64,
+    pub placed: usize,
+    pub last_order: Option<OrderId>,
+}
+
+// Part of the market, so a saved game keeps its schedules.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Schedules {
+    orders: HashMap<Uuid, RecurringOrder>,
+}
+
+impl Schedules {
+    pub fn new() -> Schedules {
+        Schedules {
+            orders: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, order: RecurringOrder) -> EngineResult<()> {
+        if order.every_ticks == 0 || order.quantity == 0 {
+            return Err(ErrorType::InvalidSchedule);
+        }
+        self.orders.insert(order.id, order);
+        Ok(())
+    }
+
+    pub fn cancel(&mut self, portfolio_id: PortfolioId, id: Uuid) -> EngineResult<()> {
+        match self.orders.get(&id) {
+            Some(order) if order.portfolio == portfolio_id => {
+                self.orders.remove(&id);
+                Ok(())
+            },
+            _ => Err(ErrorType::ScheduleNotFound(id)),
+        }
+    }
+
+    // the asset is gone, there's nothing left to place
+    pub fn cancel_asset(&mut self, asset_id: AssetId) {
+        self.orders.retain(|_, o| o.asset != asset_id);
+    }
+
+    // moves it on to its next turn, `placed` is the order it placed this one if any
+    pub fn fired(&mut self, id: Uuid, tick: u64, placed: Option<OrderId>) -> EngineResult<()> {
+        let order = self.orders.get_mut(&id).ok_or(ErrorType::ScheduleNotFound(id))?;
+        order.next_tick = tick + order.every_ticks;
+        if placed.is_some() {
+            order.placed += 1;
+            order.last_order = placed;
+        }
+        Ok(())
+    }
+
+    pub fn get_for(&self, portfolio_id: PortfolioId) -> Vec<RecurringOrder> {
+        let mut orders: Vec<RecurringOrder> = self.orders.values()
+            .filter(|o| o.portfolio == portfolio_id)
+            .cloned()
+            .collect();
+        orders.sort_by_key(|o| (o.next_tick, o.id));
+        orders
+    }
+
+    fn due(&self, tick: u64) -> Vec<Uuid> {
+        let mut due: Vec<Uuid> = self.orders.values()
+            .filter(|o| o.next_tick <= tick)
+            .map(|o| o.id)
+            .collect();
+        due.sort();
+        due
+    }
+}
+
+// Places the orders that are due. Runs through `Engine::process`, so the orders are journaled
+// like any player's, and journals each turn. A rejected order just waits for the next turn.
+pub fn run_schedules(engine: &mut Engine) {
+    let tick = engine.market.tick;
+    for id in engine.market.schedules.due(tick) {
+        let schedule = engine.market.schedules.orders[&id];
+        let stamp = engine.stamp();
+        let expires = stamp.created_at + ORDER_LIFETIME_MILLIS;
+        let placed = Order::new(stamp, schedule.portfolio, schedule.asset, schedule.side, schedule.quantity, schedule.mode, expires).ok()
+            .filter(|order| engine.process(Event::Order(*order)).is_ok())
+            .map(|order| order.get_id());
+        let _ = engine.apply(JournalRecord::ScheduleFired { id, tick, placed });
+    }
+}
@@ -0,0 +1,66 @@
+use crate::error::{checked_add, checked_mul, EngineResult, ErrorType};
+use crate::order::OrderSide;
+
+// `levels` limit orders per side, `step` apart, the closest one `step` away from the price
+#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+pub struct BookSeed {
+    pub price: usize,
+    pub step: usize,
+    pub levels: usize,
+    pub quantity: usize,
+}
+
+// side, price and quantity of one resting order
+pub type SeedLevel = (OrderSide, usize, usize);
+
+impl BookSeed {
+    pub fn ladder(&self) -> EngineResult<Vec<SeedLevel>> {
+        let lowest = checked_mul(self.step, self.levels)?;
+        if self.step == 0 || self.levels == 0 || self.quantity == 0 || lowest >= self.price {
+            return Err(ErrorType::InvalidBookSeed);
+        }
+        let mut ladder = vec![];
+        for level in 1..=self.levels {
+            // at most `lowest`, which is below the price
+            let offset = self.step * level;
+            ladder.push((OrderSide::Buy, self.price - offset, self.quantity));
+            ladder.push((OrderSide::Sell, checked_add(self.price, offset)?, self.quantity));
+        }
+        Ok(ladder)
+    }
+}
+
+// Rows of `price,quantity,side` like exchanges export their books, side being buy/bid or sell/ask.
+// A header row and blank lines are skipped. The sides mustn't cross, the orders would trade away.
+pub fn parse_book_csv(csv: &str) -> EngineResult<Vec<SeedLevel>> {
+    let mut levels = vec![];
+    for (i, line) in csv.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (i == 0 && line.to_lowercase().starts_with("price")) {
+            continue;
+        }
+        let invalid = || ErrorType::InvalidBookCsv { line: i + 1 };
+        let fields: Vec<&str> = line.split(',').map(|f| f.trim()).collect();
+        let (price, quantity, side) = match fields.as_slice() {
+            [price, quantity, side] => (price, quantity, side),
+            _ => return Err(invalid()),
+        };
+        let price: usize = price.parse().map_err(|_| invalid())?;
+        let quantity: usize = quantity.parse().map_err(|_| invalid())?;
+        let side = match side.to_lowercase().as_str() {
+            "buy" | "bid" => OrderSide::Buy,
+            "sell" | "ask" => OrderSide::Sell,
+            _ => return Err(invalid()),
+        };
+        if price == 0 || quantity == 0 {
+            return Err(invalid());
+        }
+        levels.push((side, price, quantity));
+    }
+    let best_bid = levels.iter().filter(|l| l.0 == OrderSide::Buy).map(|l| l.1).max();
+    let best_ask = levels.iter().filter(|l| l.0 == OrderSide::Sell).map(|l| l.1).min();
+    if levels.is_empty() || matches!((best_bid, best_ask), (Some(bid), Some(ask)) if bid >= ask) {
+        return Err(ErrorType::InvalidBookSeed);
+    }
+    Ok(levels)
+}
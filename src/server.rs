@@ -1,127 +1,1950 @@
-use crate::models::{Asset, EngineResult, Portfolio, Order, OrderSide, OrderMode, Event, ErrorType};
-use crate::game::{Game, PublicBook};
-use rocket::{State, Request, response, Response};
+use crate::error::{EngineResult, ErrorType};
+use crate::latency::LatencyPolicy;
+use crate::colocation::{Colocation, PriorityClass};
+use crate::order::{MinimumRestingTime, OrderSide, OrderMode, Order, Event};
+use crate::market::Asset;
+use crate::game::{Game, GameClock, OpenInterestStats, ServerTime};
+use crate::history::BookSnapshot;
+use crate::journal::{JournalEntry, ReplayFile};
+use crate::marketdata::Level;
+use crate::cache::CacheMetrics;
+use crate::alerts::{Alert, AlertRule, FiredAlert};
+use crate::news::{NewsImpact, NewsItem};
+use crate::risk::RiskMetrics;
+use crate::leaderboard::{LeaderboardEntry, ScoringMetric};
+use crate::trades::Trade;
+use crate::bankruptcy::BankruptcyRule;
+use crate::ledger::{InterestPolicy, LedgerEntry};
+use crate::bank::MonetaryStats;
+use crate::queue::QueueMetrics;
+use crate::heartbeat::Heartbeat;
+use crate::schedule::RecurringOrder;
+use crate::fees::FeeSchedule;
+use crate::pricing::PriceMode;
+use crate::watchlist::WatchQuote;
+use crate::sandbox::{SandboxFill, SandboxPortfolio};
+use crate::auction::{Clearing, MarketStructure};
+use crate::faucet::{FaucetPolicy, FaucetStatus};
+use crate::actor::{Actor, IssuedToken};
+use crate::suspension::Suspension;
+use crate::penalty::{PenaltyRule, PenaltyStanding};
+use crate::scenario::{Scenario, ScenarioStatus};
+use crate::report::GameReport;
+use crate::profile::ProfileReport;
+use crate::bootstrap::{Allocation, BootstrapPlan};
+use crate::dutch::{DutchAuction, DutchAuctionTerms};
+use crate::debug::DebugState;
+use crate::dto::{BookDto, ExecutionDto, OrderDto, PlacementDto, PortfolioDto, PositionDto, ReservationDto};
+use crate::statement::Statement;
+use crate::seed::BookSeed;
+use crate::page::PageQuery;
+use crate::book::{BookDelta, DarkPriority, QueuePosition};
+use crate::rebalance::{ClosePosition, RebalanceOutcome, TargetWeights};
+use crate::stream::GameEvent;
+use crate::bus::{DomainEvent, DomainEventKind};
+use crate::export::to_csv;
+use crate::render::RenderFormat;
+use crate::format::Locale;
+use crate::roster::{numbered_names, parse_roster_csv, RosterEntry};
+use crate::tape::{RateLimiter, TapePrint};
+use crate::oracle::{OracleFeed, OracleSource, PriceCurve};
+use crate::activity::ActivityStats;
+use crate::health::{Health, HealthStatus};
+use crate::webhook::{self, WebhookConfig, Webhooks};
+use crate::settlement::{PendingSettlement, SettlementPolicy};
+use crate::config::GameConfig;
+use crate::versioning::ApiVersioning;
+use crate::timestamps::{ReceiveTimestamps, ReceivedAt};
+use crate::rules::AssetRules;
+use crate::algo::{AlgoStatus, AlgoStrategy, AlgoTerms};
+use crate::validation::{check, check_mode, check_positive, nested, FieldError, Validate};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::collections::BTreeMap;
+use rocket::{State, Request, response, Response, Outcome};
+use rocket::request::{self, FromRequest, LenientForm};
+use rocket::data::{self, Data, FromDataSimple};
 use rocket_contrib::json::{Json};
 use rocket_contrib::uuid::Uuid;
 use rocket::http::ContentType;
-use std::time::{Instant, Duration};
-use std::ops::Add;
-use rocket::response::Responder;
-use std::io::Cursor;
+use std::time::{Duration, Instant};
+use rocket::response::{status, Responder};
+use rocket::response::content::Content;
+use std::io::{Cursor, Read};
+use std::ops::Deref;
+use std::thread;
+use std::env;
 
 #[get("/portfolio/<id>")]
-fn get_portfolio(id: Uuid, game: State<Game>) -> EngineResult<Json<Portfolio>> {
-    return game.read_engine()?.market.get_portfolio(*id).map(|p|Json(p.clone()))
+fn get_portfolio(id: Uuid, game: State<Game>) -> EngineResult<Json<PortfolioDto>> {
+    let (portfolio, unrealized_pnl) = game.get_portfolio(*id)?;
+    Ok(Json(PortfolioDto::new(&portfolio, unrealized_pnl)))
 }
 
-#[get("/asset")]
-fn get_assets(game: State<Game>) -> EngineResult<Json<Assets>>{
+#[get("/portfolio/<id>/positions")]
+fn get_positions(id: Uuid, game: State<Game>) -> EngineResult<Json<Positions>> {
+    let positions = game.get_positions(*id)?;
+    Ok(Json(Positions { positions: positions.iter().map(PositionDto::from).collect() }))
+}
+
+#[get("/portfolio/<id>/reservations")]
+fn get_reservations(id: Uuid, game: State<Game>) -> EngineResult<Json<Reservations>> {
+    Ok(Json(Reservations{
+        reservations: game.get_reservations(*id)?.iter().map(ReservationDto::from).collect()
+    }))
+}
+
+#[get("/portfolio/<id>/ledger?<query..>")]
+fn get_ledger(id: Uuid, query: LenientForm<ListQuery>, game: State<Game>) -> EngineResult<Json<Ledger>> {
+    let (entries, next_cursor) = game.get_ledger(*id, &query.to_page_query()?)?;
+    Ok(Json(Ledger{
+        entries,
+        next_cursor,
+    }))
+}
+
+#[get("/portfolio/<id>/ledger.csv")]
+fn export_ledger(id: Uuid, game: State<Game>) -> EngineResult<Content<String>> {
+    let (entries, _) = game.get_ledger(*id, &PageQuery::default())?;
+    Ok(Content(ContentType::CSV, to_csv(&entries, &game.get_formatter(Locale::Plain)?)))
+}
+
+#[get("/portfolio/<id>/watchlist")]
+fn get_watchlist(id: Uuid, game: State<Game>) -> EngineResult<Json<Watchlist>> {
+    Ok(Json(Watchlist {
+        assets: game.get_watchlist(*id)?
+    }))
+}
+
+#[put("/portfolio/<id>/watchlist", data="<data>")]
+fn set_watchlist(id: Uuid, data: Strict<Watchlist>, game: Acting) -> EngineResult<()> {
+    game.set_watchlist(*id, data.into_inner().assets)
+}
+
+#[get("/portfolio/<id>/watchlist/quotes")]
+fn get_watchlist_quotes(id: Uuid, game: State<Game>) -> EngineResult<Json<Quotes>> {
+    Ok(Json(Quotes {
+        quotes: game.get_watchlist_quotes(*id)?
+    }))
+}
+
+#[get("/portfolio/<id>/statement?<period>")]
+fn get_statement(id: Uuid, period: Option<u64>, game: State<Game>) -> EngineResult<Json<Statement>> {
+    Ok(Json(game.get_statement(*id, period)?))
+}
+
+#[post("/portfolio/<id>/heartbeat?<window_millis>")]
+fn heartbeat(id: Uuid, window_millis: Option<u64>, game: Acting) -> EngineResult<Json<Heartbeat>> {
+    Ok(Json(game.heartbeat(*id, window_millis.map(Duration::from_millis))?))
+}
+
+#[get("/portfolio/<id>/schedules")]
+fn get_schedules(id: Uuid, game: State<Game>) -> EngineResult<Json<Schedules>> {
+    Ok(Json(Schedules{
+        schedules: game.get_schedules(*id)?
+    }))
+}
+
+#[post("/portfolio/<id>/schedules", data="<data>")]
+fn schedule_order(id: Uuid, data: Strict<SchedulePlacement>, game: Acting) -> EngineResult<Json<RecurringOrder>> {
+    Ok(Json(game.schedule_order(*id, data.asset, data.side, data.quantity, data.mode, data.every_ticks)?))
+}
+
+#[get("/portfolio/<id>/algos")]
+fn get_algos(id: Uuid, game: State<Game>) -> EngineResult<Json<Vec<AlgoStatus>>> {
+    Ok(Json(game.get_algos(*id)?))
+}
+
+#[post("/portfolio/<id>/algos", data="<data>")]
+fn start_algo(id: Uuid, data: Strict<AlgoPlacement>, game: Acting) -> EngineResult<Json<AlgoStatus>> {
+    let terms = AlgoTerms {
+        side: data.side,
+        quantity: data.quantity,
+        mode: data.mode,
+        strategy: data.strategy,
+    };
+    Ok(Json(game.start_algo(*id, data.asset, terms)?))
+}
+
+#[delete("/portfolio/<id>/algos/<algo>")]
+fn cancel_algo(id: Uuid, algo: Uuid, game: Acting) -> EngineResult<Json<AlgoStatus>> {
+    Ok(Json(game.cancel_algo(*id, *algo)?))
+}
+
+#[delete("/portfolio/<id>/schedules/<schedule>")]
+fn cancel_schedule(id: Uuid, schedule: Uuid, game: Acting) -> EngineResult<()> {
+    game.cancel_schedule(*id, *schedule)
+}
+
+#[delete("/portfolio/<id>/heartbeat")]
+fn disarm_heartbeat(id: Uuid, game: Acting) -> EngineResult<()> {
+    game.disarm_heartbeat(*id)
+}
+
+#[get("/portfolio/<id>/alerts")]
+fn get_alerts(id: Uuid, game: State<Game>) -> EngineResult<Json<Alerts>> {
+    let (rules, fired) = game.get_alerts(*id)?;
+    Ok(Json(Alerts{
+        rules,
+        fired,
+    }))
+}
+
+#[post("/portfolio/<id>/alerts", data="<rule>")]
+fn add_alert(id: Uuid, rule: Json<AlertRule>, game: Acting) -> EngineResult<Json<uuid::Uuid>> {
+    Ok(Json(game.add_alert(*id, rule.into_inner())?))
+}
+
+#[delete("/portfolio/<id>/alerts/<alert>")]
+fn remove_alert(id: Uuid, alert: Uuid, game: Acting) -> EngineResult<()> {
+    game.remove_alert(*id, *alert)
+}
+
+#[get("/asset?<query..>")]
+fn get_assets(query: LenientForm<ListQuery>, game: State<Game>) -> EngineResult<Json<Assets>>{
+    let (assets, next_cursor) = game.get_assets(&query.to_page_query()?)?;
     Ok(Json(Assets{
-        assets: game.read_engine()?.market.assets.values().map(|a|a.clone()).collect::<Vec<_>>()
+        assets,
+        next_cursor,
+    }))
+}
+
+#[get("/book?<query..>")]
+fn get_books(query: LenientForm<ListQuery>, game: State<Game>) -> EngineResult<Json<Books>>{
+    let (books, next_cursor) = game.get_public_books(&query.to_page_query()?)?;
+    Ok(Json(Books{
+        books,
+        next_cursor,
+    }))
+}
+
+#[get("/book/<asset>?<query..>")]
+fn get_book(asset: Uuid, query: LenientForm<ListQuery>, if_none_match: IfNoneMatch, game: State<Game>) -> EngineResult<Tagged<BookDto>>{
+    // the whole book is what dashboards poll, it comes from the cache
+    if query.is_empty() {
+        let (version, json) = game.get_public_book_json(*asset)?;
+        return Ok(Tagged::new(format!("\"{}\"", version), Body::Cached(CachedJson(json)), &if_none_match));
+    }
+    // another page of the same book is another representation
+    let query = query.to_page_query()?;
+    let book = game.get_public_book_for(*asset, &query)?;
+    Ok(Tagged::new(format!("\"{}-{}\"", book.version, query.key()), Body::Json(Json(book)), &if_none_match))
+}
+
+#[get("/book/<asset>/delta?<since_version>")]
+fn get_book_delta(asset: Uuid, since_version: u64, game: State<Game>) -> EngineResult<Json<BookDeltas>> {
+    let (version, deltas) = game.get_book_deltas(*asset, since_version)?;
+    Ok(Json(BookDeltas{
+        asset: *asset,
+        version,
+        deltas,
+    }))
+}
+
+#[get("/marketdata/<asset>?<level>")]
+fn get_market_data(asset: Uuid, level: Option<String>, game: State<Game>) -> EngineResult<CachedJson> {
+    let level = match level {
+        Some(level) => Level::parse(&level).ok_or(ErrorType::UnknownMarketDataLevel(level))?,
+        None => Level::L2,
+    };
+    Ok(CachedJson(game.get_market_data_json(*asset, level)?))
+}
+
+#[get("/book/<asset>/render?<format>&<locale>")]
+fn render_book(asset: Uuid, format: Option<String>, locale: Option<String>, game: State<Game>) -> EngineResult<Content<String>> {
+    let format = match format {
+        Some(format) => RenderFormat::parse(&format).ok_or(ErrorType::UnknownRenderFormat(format))?,
+        None => RenderFormat::Ascii,
+    };
+    let locale = match locale {
+        Some(locale) => Locale::parse(&locale).ok_or(ErrorType::UnknownLocale(locale))?,
+        None => Locale::En,
+    };
+    let content_type = match format {
+        RenderFormat::Ascii => ContentType::Plain,
+        RenderFormat::Svg => ContentType::SVG,
+    };
+    Ok(Content(content_type, game.render_book(*asset, format, locale)?))
+}
+
+#[put("/admin/asset/<asset>/decimals", data="<decimals>")]
+fn set_asset_decimals(_admin: Admin, asset: Uuid, decimals: Json<u32>, game: Acting) -> EngineResult<()> {
+    game.set_asset_decimals(*asset, decimals.into_inner())
+}
+
+#[get("/asset/<id>")]
+fn get_asset(id: Uuid, game: State<Game>) -> EngineResult<Json<Asset>> {
+    return game.read_engine()?.market.assets.get(&id)
+        .map(|a|Json(a.clone())).ok_or(ErrorType::AssetNotFound(*id))
+}
+
+#[get("/asset/<id>/rules")]
+fn get_asset_rules(id: Uuid, game: State<Game>) -> EngineResult<Json<AssetRules>> {
+    Ok(Json(game.get_asset_rules(*id)?))
+}
+
+#[get("/asset/<id>/book-history?<from>&<to>")]
+fn get_book_history(id: Uuid, from: Option<u64>, to: Option<u64>, game: State<Game>) -> EngineResult<Json<BookHistory>> {
+    Ok(Json(BookHistory{
+        snapshots: game.get_book_history(*id, from, to)?
+    }))
+}
+
+#[get("/asset/<id>/trades?<query..>")]
+fn get_trades(id: Uuid, query: LenientForm<ListQuery>, game: State<Game>) -> EngineResult<Json<Trades>> {
+    let (trades, next_cursor) = game.get_trades(*id, &query.to_page_query()?)?;
+    Ok(Json(Trades{
+        trades,
+        next_cursor,
+    }))
+}
+
+// Rate limited by client address, the tape is meant to be followed over the stream.
+#[get("/tape?<query..>")]
+fn get_tape(query: LenientForm<ListQuery>, client: SocketAddr, limiter: State<RateLimiter>, game: State<Game>) -> EngineResult<Json<Tape>> {
+    limiter.check(&client.ip().to_string(), Instant::now())?;
+    let (trades, next_cursor) = game.get_tape(&query.to_page_query()?)?;
+    Ok(Json(Tape {
+        trades,
+        next_cursor,
+    }))
+}
+
+#[get("/asset/<id>/trades.csv")]
+fn export_trades(id: Uuid, game: State<Game>) -> EngineResult<Content<String>> {
+    let (trades, _) = game.get_trades(*id, &PageQuery::default())?;
+    Ok(Content(ContentType::CSV, to_csv(&trades, &game.get_formatter(Locale::Plain)?)))
+}
+
+#[get("/asset/<id>/risk?<window>")]
+fn get_risk(id: Uuid, window: Option<usize>, game: State<Game>) -> EngineResult<Json<RiskMetrics>> {
+    Ok(Json(game.get_risk_metrics(*id, window.unwrap_or(50))?))
+}
+
+
+#[delete("/portfolio/<portfolio>/asset/<asset>/order/<order>")]
+fn cancel_order(portfolio: Uuid, asset: Uuid, order: Uuid, game: Acting) -> EngineResult<Json<OrderDto>> {
+    game.submit(Event::CancelOrder(*portfolio,*order,*asset))?;
+    Ok(Json(OrderDto::from(&game.get_order(*order)?)))
+}
+
+// where each of the orders stands right after the request went through
+fn order_records(game: &Game, ids: Vec<uuid::Uuid>) -> EngineResult<Vec<OrderDto>> {
+    ids.into_iter().map(|id| Ok(OrderDto::from(&game.get_order(id)?))).collect()
+}
+
+fn placement(game: &Game, order: uuid::Uuid) -> EngineResult<PlacementDto> {
+    Ok(PlacementDto {
+        order: OrderDto::from(&game.get_order(order)?),
+        execution: game.get_execution(order)?.as_ref().map(ExecutionDto::from),
+    })
+}
+
+#[post("/portfolio/<id>/faucet")]
+fn claim_faucet(id: Uuid, game: Acting) -> EngineResult<Json<CoinAmount>> {
+    Ok(Json(CoinAmount { amount: game.claim_faucet(*id)? }))
+}
+
+#[post("/portfolio/<id>/priority-class/<class>")]
+fn buy_priority_class(id: Uuid, class: String, game: Acting) -> EngineResult<Json<CoinAmount>> {
+    Ok(Json(CoinAmount { amount: game.buy_priority_class(*id, class)? }))
+}
+
+#[get("/priority-classes")]
+fn get_priority_classes(game: State<Game>) -> EngineResult<Json<Colocation>> {
+    Ok(Json(game.get_colocation()?))
+}
+
+#[put("/admin/priority-classes", data="<classes>")]
+fn set_priority_classes(_admin: Admin, classes: Json<Vec<PriorityClass>>, game: Acting) -> EngineResult<()> {
+    game.set_priority_classes(classes.into_inner())
+}
+
+#[put("/admin/portfolio/<id>/priority-class", data="<class>")]
+fn assign_priority_class(_admin: Admin, id: Uuid, class: Json<Option<String>>, game: Acting) -> EngineResult<()> {
+    game.assign_priority_class(*id, class.into_inner())
+}
+
+#[get("/faucet")]
+fn get_faucet(game: State<Game>) -> EngineResult<Json<FaucetStatus>> {
+    Ok(Json(game.get_faucet()?))
+}
+
+#[put("/admin/faucet", data="<policy>")]
+fn set_faucet_policy(_admin: Admin, policy: Json<Option<FaucetPolicy>>, game: Acting) -> EngineResult<()> {
+    game.set_faucet_policy(policy.into_inner())
+}
+
+#[post("/portfolio/<id>/sandbox")]
+fn spawn_sandbox(id: Uuid, game: Acting) -> EngineResult<Json<Sandbox>> {
+    game.spawn_sandbox(*id)?;
+    let (sandbox, value) = game.get_sandbox(*id)?;
+    Ok(Json(Sandbox { sandbox, value }))
+}
+
+#[get("/portfolio/<id>/sandbox")]
+fn get_sandbox(id: Uuid, game: State<Game>) -> EngineResult<Json<Sandbox>> {
+    let (sandbox, value) = game.get_sandbox(*id)?;
+    Ok(Json(Sandbox { sandbox, value }))
+}
+
+#[delete("/portfolio/<id>/sandbox")]
+fn remove_sandbox(id: Uuid, game: Acting) -> EngineResult<()> {
+    game.remove_sandbox(*id)
+}
+
+#[post("/portfolio/<id>/sandbox/asset/<asset>/<side>", data="<data>")]
+fn sandbox_order(id: Uuid, asset: Uuid, side: String, data: Strict<OrderPlacement>, game: Acting) -> EngineResult<Json<Vec<SandboxFill>>> {
+    let side = match side.to_lowercase().as_str() {
+        "buy" => OrderSide::Buy,
+        "sell" => OrderSide::Sell,
+        _ => return Err(ErrorType::UnknownOrderSide(side)),
+    };
+    let order = game.new_order(*id, *asset, side, data.quantity, data.mode, Duration::from_secs(0))?;
+    Ok(Json(game.sandbox_order(order)?))
+}
+
+#[get("/portfolio/<portfolio>/orders")]
+fn get_orders(portfolio: Uuid, game: State<Game>) -> EngineResult<Json<Orders>> {
+    Ok(Json(Orders {
+        orders: game.get_orders_of(*portfolio)?.iter().map(OrderDto::from).collect()
+    }))
+}
+
+#[get("/portfolio/<portfolio>/orders/<order>")]
+fn get_order(portfolio: Uuid, order: Uuid, game: State<Game>) -> EngineResult<Json<OrderDto>> {
+    let record = game.get_order(*order)?;
+    if record.portfolio != *portfolio {
+        return Err(ErrorType::OrderNotFound(*order));
+    }
+    Ok(Json(OrderDto::from(&record)))
+}
+
+
+#[get("/portfolio/<portfolio>/activity-stats")]
+fn get_activity_stats(portfolio: Uuid, game: State<Game>) -> EngineResult<Json<ActivityStats>> {
+    Ok(Json(game.get_activity_stats(*portfolio)?))
+}
+
+#[get("/portfolio/<portfolio>/order/<order>/queue")]
+fn get_queue_position(portfolio: Uuid, order: Uuid, game: State<Game>) -> EngineResult<Json<QueuePosition>> {
+    Ok(Json(game.get_queue_position(*portfolio, *order)?))
+}
+
+#[post("/portfolio/<portfolio>/asset/<asset>/sell", data="<data>")]
+fn sell_order(portfolio: Uuid, asset: Uuid, data: Strict<OrderPlacement>, admin: Option<Admin>, game: Acting, )
+              -> EngineResult<Json<PlacementDto>> {
+    let o =  game.new_order(
+        *portfolio,
+        *asset,
+        OrderSide::Sell,
+        data.quantity,
+        data.mode,
+        Duration::from_secs(24*60*60)
+    )?;
+    let o = data.visibility(data.checked_override(o, admin)?);
+    game.submit(Event::Order(o))?;
+    Ok(Json(placement(&game, o.id)?))
+}
+
+#[post("/portfolio/<portfolio>/asset/<asset>/buy", data="<data>")]
+fn buy_order(portfolio: Uuid, asset: Uuid, data: Strict<OrderPlacement>, admin: Option<Admin>, game: Acting, )
+              -> EngineResult<Json<PlacementDto>> {
+    let o =  game.new_order(
+        *portfolio,
+        *asset,
+        OrderSide::Buy,
+        data.quantity,
+        data.mode,
+        Duration::from_secs(24*60*60)
+    )?;
+    let o = data.visibility(data.checked_override(o, admin)?);
+    game.submit(Event::Order(o))?;
+    Ok(Json(placement(&game, o.id)?))
+}
+
+#[post("/portfolio/<portfolio>/asset/<asset>/close", data="<data>")]
+fn close_position(portfolio: Uuid, asset: Uuid, data: Strict<Option<ClosePosition>>, game: Acting) -> EngineResult<Json<PlacementDto>> {
+    let close = data.into_inner().unwrap_or_default();
+    let o = game.plan_close(*portfolio, *asset, &close, Duration::from_secs(24*60*60))?;
+    game.submit(Event::Order(o))?;
+    Ok(Json(placement(&game, o.id)?))
+}
+
+#[post("/portfolio/<portfolio>/batch", data="<data>")]
+fn batch_order(portfolio: Uuid, data: Strict<BatchPlacement>, game: Acting) -> EngineResult<Json<Vec<OrderDto>>> {
+    let mut orders = vec![];
+    for leg in &data.legs {
+        orders.push(game.new_order(
+            *portfolio,
+            leg.asset,
+            leg.side,
+            leg.quantity,
+            leg.mode,
+            Duration::from_secs(24*60*60)
+        )?);
+    }
+    let ids: Vec<uuid::Uuid> = orders.iter().map(|o| o.id).collect();
+    game.submit(Event::AtomicBatch(orders))?;
+    Ok(Json(order_records(&game, ids)?))
+}
+
+#[post("/portfolio/<portfolio>/orders/upload", data="<csv>")]
+fn upload_orders(portfolio: Uuid, csv: String, game: Acting) -> EngineResult<Json<Vec<OrderDto>>> {
+    let orders = game.plan_upload(*portfolio, &csv, Duration::from_secs(24*60*60))?;
+    let ids: Vec<uuid::Uuid> = orders.iter().map(|o| o.id).collect();
+    game.submit(Event::AtomicBatch(orders))?;
+    Ok(Json(order_records(&game, ids)?))
+}
+
+#[post("/portfolio/<portfolio>/oco", data="<data>")]
+fn oco_order(portfolio: Uuid, data: Strict<OcoPlacement>, game: Acting) -> EngineResult<Json<Vec<OrderDto>>> {
+    let new_order = |leg: &OrderLeg| game.new_order(
+        *portfolio,
+        leg.asset,
+        leg.side,
+        leg.quantity,
+        leg.mode,
+        Duration::from_secs(24*60*60)
+    );
+    let first = new_order(&data.first)?;
+    let second = new_order(&data.second)?;
+    game.submit(Event::Oco { first, second, trigger_quantity: data.trigger_quantity })?;
+    Ok(Json(order_records(&game, vec![first.id, second.id])?))
+}
+
+#[post("/portfolio/<portfolio>/rebalance", data="<targets>")]
+fn rebalance(portfolio: Uuid, targets: Json<TargetWeights>, game: Acting) -> EngineResult<Json<RebalanceOutcome>> {
+    let planned = game.plan_rebalance(*portfolio, &targets, Duration::from_secs(24*60*60))?;
+    if !planned.is_empty() {
+        game.submit(Event::AtomicBatch(planned.iter().map(|(o, _)| *o).collect()))?;
+    }
+    Ok(Json(RebalanceOutcome {
+        orders: planned.into_iter().map(|(o, leg)| (o.id, leg)).collect(),
+    }))
+}
+
+#[get("/admin/replay")]
+fn get_replay(_admin: Admin, game: State<Game>) -> EngineResult<Json<ReplayFile>> {
+    Ok(Json(game.read_engine()?.export_replay()))
+}
+
+#[post("/admin/recording")]
+fn start_recording(_admin: Admin, game: State<Game>) -> EngineResult<()> {
+    game.start_recording()
+}
+
+#[get("/admin/recording")]
+fn get_recording(_admin: Admin, game: State<Game>) -> EngineResult<Json<Scenario>> {
+    Ok(Json(game.get_recording()?))
+}
+
+#[delete("/admin/recording")]
+fn stop_recording(_admin: Admin, game: State<Game>) -> EngineResult<Json<Scenario>> {
+    Ok(Json(game.stop_recording()?))
+}
+
+#[post("/admin/scenario", data="<scenario>")]
+fn play_scenario(_admin: Admin, scenario: Json<Scenario>, game: Acting) -> EngineResult<()> {
+    game.play_scenario(scenario.into_inner())
+}
+
+#[get("/admin/scenario")]
+fn get_scenario_status(_admin: Admin, game: State<Game>) -> EngineResult<Json<Option<ScenarioStatus>>> {
+    Ok(Json(game.get_scenario_status()?))
+}
+
+#[delete("/admin/scenario")]
+fn stop_scenario(_admin: Admin, game: Acting) -> EngineResult<()> {
+    game.stop_scenario()
+}
+
+#[put("/admin/portfolio/<id>/suspension")]
+fn suspend_portfolio(_admin: Admin, id: Uuid, game: Acting) -> EngineResult<Json<Vec<uuid::Uuid>>> {
+    Ok(Json(game.suspend(Suspension::Portfolio(*id))?))
+}
+
+#[delete("/admin/portfolio/<id>/suspension")]
+fn reinstate_portfolio(_admin: Admin, id: Uuid, game: Acting) -> EngineResult<()> {
+    game.reinstate(Suspension::Portfolio(*id))
+}
+
+#[put("/admin/token/<fingerprint>/suspension")]
+fn suspend_token(_admin: Admin, fingerprint: String, game: Acting) -> EngineResult<Json<Vec<uuid::Uuid>>> {
+    Ok(Json(game.suspend(Suspension::Token(fingerprint))?))
+}
+
+#[delete("/admin/token/<fingerprint>/suspension")]
+fn reinstate_token(_admin: Admin, fingerprint: String, game: Acting) -> EngineResult<()> {
+    game.reinstate(Suspension::Token(fingerprint))
+}
+
+#[put("/admin/ip/<ip>/suspension")]
+fn suspend_ip(_admin: Admin, ip: String, game: Acting) -> EngineResult<Json<Vec<uuid::Uuid>>> {
+    Ok(Json(game.suspend(Suspension::Ip(parse_ip(&ip)?))?))
+}
+
+#[delete("/admin/ip/<ip>/suspension")]
+fn reinstate_ip(_admin: Admin, ip: String, game: Acting) -> EngineResult<()> {
+    game.reinstate(Suspension::Ip(parse_ip(&ip)?))
+}
+
+// the way `client_ip` prints it, so `::1` and `0:0:0:0:0:0:0:1` are the same ban
+fn parse_ip(ip: &str) -> EngineResult<String> {
+    ip.parse::<IpAddr>()
+        .map(|ip| ip.to_string())
+        .map_err(|_| ErrorType::InvalidIpAddress(ip.to_string()))
+}
+
+#[post("/admin/portfolio/<id>/token")]
+fn issue_token(_admin: Admin, id: Uuid, game: Acting) -> EngineResult<Json<IssuedToken>> {
+    Ok(Json(game.issue_token(*id)?))
+}
+
+#[get("/admin/tokens")]
+fn get_tokens(_admin: Admin, game: State<Game>) -> EngineResult<Json<Vec<TokenEntry>>> {
+    Ok(Json(game.get_tokens()?.into_iter()
+        .map(|(fingerprint, portfolio)| TokenEntry { fingerprint, portfolio })
+        .collect()))
+}
+
+#[delete("/admin/token/<fingerprint>")]
+fn revoke_token(_admin: Admin, fingerprint: String, game: Acting) -> EngineResult<()> {
+    game.revoke_token(fingerprint)
+}
+
+#[get("/admin/suspensions")]
+fn get_suspensions(_admin: Admin, game: State<Game>) -> EngineResult<Json<Suspensions>> {
+    Ok(Json(Suspensions {
+        suspensions: game.get_suspensions()?
+    }))
+}
+
+#[put("/admin/penalties", data="<rule>")]
+fn set_penalty_rule(_admin: Admin, rule: Json<Option<PenaltyRule>>, game: Acting) -> EngineResult<()> {
+    game.set_penalty_rule(rule.into_inner())
+}
+
+#[get("/admin/penalties")]
+fn get_penalties(_admin: Admin, game: State<Game>) -> EngineResult<Json<Penalties>> {
+    Ok(Json(Penalties {
+        rule: game.get_penalty_rule()?,
+        standings: game.get_penalty_standings()?,
+    }))
+}
+
+#[get("/admin/journal?<actor>")]
+fn get_journal(_admin: Admin, actor: String, game: State<Game>) -> EngineResult<Json<Journal>> {
+    Ok(Json(Journal {
+        entries: game.get_journal_of(&actor)?
+    }))
+}
+
+#[get("/admin/debug/state")]
+fn get_debug_state(_admin: Admin, game: State<Game>) -> EngineResult<Json<DebugState>> {
+    Ok(Json(game.get_debug_state()?))
+}
+
+#[get("/leaderboard?<metric>")]
+fn get_leaderboard(metric: Option<String>, game: State<Game>) -> EngineResult<Json<Leaderboard>> {
+    let metric = match metric {
+        Some(metric) => Some(ScoringMetric::parse(&metric).ok_or(ErrorType::UnknownScoringMetric(metric))?),
+        None => None,
+    };
+    Ok(Json(Leaderboard{
+        entries: game.get_leaderboard(metric)?
     }))
 }
 
-#[get("/book")]
-fn get_books(game: State<Game>) -> EngineResult<Json<Books>>{
-    Ok(Json(Books{
-        books: game.get_public_books()?
-    }))
+#[put("/admin/leaderboard/metric", data="<metric>")]
+fn set_scoring_metric(_admin: Admin, metric: Json<ScoringMetric>, game: Acting) -> EngineResult<()> {
+    game.set_scoring_metric(metric.into_inner())
+}
+
+#[delete("/admin/asset/<asset>/order/<order>")]
+fn force_cancel_order(_admin: Admin, asset: Uuid, order: Uuid, game: Acting) -> EngineResult<Json<OrderDto>> {
+    game.force_cancel_order(*asset, *order)?;
+    Ok(Json(OrderDto::from(&game.get_order(*order)?)))
+}
+
+#[delete("/admin/asset/<asset>/orders")]
+fn cancel_all_orders(_admin: Admin, asset: Uuid, game: Acting) -> EngineResult<Json<Vec<OrderDto>>> {
+    let ids = game.cancel_all_orders(*asset)?;
+    Ok(Json(order_records(&game, ids)?))
+}
+
+#[post("/admin/asset/<asset>/delist", data="<data>")]
+fn delist_asset(_admin: Admin, asset: Uuid, data: Strict<Delisting>, game: Acting) -> EngineResult<()> {
+    let price = match data.settlement_price {
+        Some(price) => price,
+        None => game.get_oracle_price(*asset)?,
+    };
+    game.submit(Event::DelistAsset(*asset, price))
+}
+
+#[put("/admin/asset/<asset>/halted", data="<halted>")]
+fn set_asset_halted(_admin: Admin, asset: Uuid, halted: Json<bool>, game: Acting) -> EngineResult<()> {
+    game.set_asset_halted(*asset, halted.into_inner())
+}
+
+#[post("/admin/asset/<asset>/dividend", data="<data>")]
+fn pay_dividend(_admin: Admin, asset: Uuid, data: Strict<Dividend>, game: Acting) -> EngineResult<()> {
+    game.pay_dividend(*asset, data.per_unit)
+}
+
+#[post("/admin/trade/<id>/bust")]
+fn bust_trade(_admin: Admin, id: u64, game: Acting) -> EngineResult<()> {
+    game.bust_trade(id)
+}
+
+#[put("/admin/bankruptcy", data="<rule>")]
+fn set_bankruptcy_rule(_admin: Admin, rule: Json<Option<BankruptcyRule>>, game: Acting) -> EngineResult<()> {
+    game.set_bankruptcy_rule(rule.into_inner())
+}
+
+#[put("/admin/interest", data="<policy>")]
+fn set_interest_policy(_admin: Admin, policy: Json<Option<InterestPolicy>>, game: Acting) -> EngineResult<()> {
+    game.set_interest_policy(policy.into_inner())
+}
+
+#[get("/stats/cache")]
+fn get_cache_metrics(game: State<Game>) -> EngineResult<Json<CacheMetrics>> {
+    Ok(Json(game.get_cache_metrics()?))
+}
+
+#[get("/stats/events")]
+fn get_event_counts(game: State<Game>) -> EngineResult<Json<BTreeMap<DomainEventKind, u64>>> {
+    Ok(Json(game.get_event_counts()?))
+}
+
+#[get("/stats/profile")]
+fn get_profile(game: State<Game>) -> EngineResult<Json<ProfileReport>> {
+    Ok(Json(game.get_profile()?))
+}
+
+#[put("/admin/profiling", data="<enabled>")]
+fn set_profiling(_admin: Admin, enabled: Json<bool>, game: Acting) -> EngineResult<()> {
+    game.set_profiling(enabled.into_inner())
+}
+
+#[post("/admin/game/end")]
+fn end_game(_admin: Admin, game: Acting) -> EngineResult<()> {
+    game.end_game()
+}
+
+#[put("/admin/paused", data="<paused>")]
+fn set_paused(_admin: Admin, paused: Json<bool>, game: Acting) -> EngineResult<()> {
+    game.set_paused(paused.into_inner())
+}
+
+#[get("/game-clock")]
+fn get_game_clock(game: State<Game>) -> EngineResult<Json<GameClock>> {
+    Ok(Json(game.get_game_clock()?))
+}
+
+#[get("/game/<gid>/report")]
+fn get_report(gid: Uuid, game: State<Game>) -> EngineResult<Json<GameReport>> {
+    Ok(Json(game.get_report(*gid)?))
+}
+
+#[get("/time?<client_time>")]
+fn get_time(client_time: Option<u64>, received_at: ReceivedAt, game: State<Game>) -> EngineResult<Json<ServerTime>> {
+    Ok(Json(game.get_server_time(client_time, received_at.0)?))
+}
+
+// 503 once the engine couldn't be recovered from a panic, for load balancers
+#[get("/health")]
+fn get_health(game: State<Game>) -> EngineResult<status::Custom<Json<Health>>> {
+    let health = game.get_health()?;
+    let code = match health.status {
+        HealthStatus::Failed => rocket::http::Status::ServiceUnavailable,
+        _ => rocket::http::Status::Ok,
+    };
+    Ok(status::Custom(code, Json(health)))
+}
+
+#[get("/queue")]
+fn get_queue(game: State<Game>) -> EngineResult<Json<QueueMetrics>> {
+    Ok(Json(game.get_queue_metrics()?))
+}
+
+#[get("/price-mode")]
+fn get_price_mode(game: State<Game>) -> EngineResult<Json<PriceMode>> {
+    Ok(Json(game.get_price_mode()?))
+}
+
+#[put("/admin/price-mode", data="<mode>")]
+fn set_price_mode(_admin: Admin, mode: Json<PriceMode>, game: Acting) -> EngineResult<()> {
+    game.set_price_mode(mode.into_inner())
+}
+
+#[get("/oracle")]
+fn get_oracle(game: State<Game>) -> EngineResult<Json<Oracle>> {
+    Ok(Json(Oracle { feeds: game.get_oracle_feeds()? }))
+}
+
+#[put("/admin/oracle/<asset>", data="<curve>")]
+fn set_oracle_curve(_admin: Admin, asset: Uuid, curve: Json<PriceCurve>, game: State<Game>) -> EngineResult<()> {
+    game.set_oracle(*asset, Some(OracleSource::Curve(curve.into_inner())))
+}
+
+#[delete("/admin/oracle/<asset>")]
+fn remove_oracle(_admin: Admin, asset: Uuid, game: State<Game>) -> EngineResult<()> {
+    game.set_oracle(*asset, None)
+}
+
+#[get("/price-band")]
+fn get_price_band(game: State<Game>) -> EngineResult<Json<Option<usize>>> {
+    Ok(Json(game.get_price_band()?))
+}
+
+#[put("/admin/price-band", data="<percent>")]
+fn set_price_band(_admin: Admin, percent: Json<Option<usize>>, game: Acting) -> EngineResult<()> {
+    game.set_price_band(percent.into_inner())
+}
+
+#[get("/minimum-resting-time")]
+fn get_minimum_resting_time(game: State<Game>) -> EngineResult<Json<Option<MinimumRestingTime>>> {
+    Ok(Json(game.get_minimum_resting_time()?))
+}
+
+#[put("/admin/minimum-resting-time", data="<minimum>")]
+fn set_minimum_resting_time(_admin: Admin, minimum: Json<Option<MinimumRestingTime>>, game: Acting) -> EngineResult<()> {
+    game.set_minimum_resting_time(minimum.into_inner())
+}
+
+#[get("/latency")]
+fn get_latency_policy(game: State<Game>) -> EngineResult<Json<Option<LatencyPolicy>>> {
+    Ok(Json(game.get_latency_policy()?))
+}
+
+#[put("/admin/latency", data="<latency>")]
+fn set_latency_policy(_admin: Admin, latency: Json<Option<LatencyPolicy>>, game: Acting) -> EngineResult<()> {
+    game.set_latency_policy(latency.into_inner())
+}
+
+#[get("/admin/config")]
+fn get_config(_admin: Admin, game: State<Game>) -> EngineResult<Json<GameConfig>> {
+    Ok(Json(game.get_config()?))
+}
+
+#[put("/admin/config", data="<config>")]
+fn set_config(_admin: Admin, config: Json<GameConfig>, game: Acting) -> EngineResult<()> {
+    game.set_config(config.into_inner())
+}
+
+#[get("/dark-priority")]
+fn get_dark_priority(game: State<Game>) -> EngineResult<Json<DarkPriority>> {
+    Ok(Json(game.get_dark_priority()?))
+}
+
+#[put("/admin/dark-priority", data="<priority>")]
+fn set_dark_priority(_admin: Admin, priority: Json<DarkPriority>, game: Acting) -> EngineResult<()> {
+    game.set_dark_priority(priority.into_inner())
+}
+
+#[get("/market-structure")]
+fn get_market_structure(game: State<Game>) -> EngineResult<Json<MarketStructure>> {
+    Ok(Json(game.get_market_structure()?))
+}
+
+#[put("/admin/market-structure", data="<structure>")]
+fn set_market_structure(_admin: Admin, structure: Json<MarketStructure>, game: Acting) -> EngineResult<()> {
+    game.set_market_structure(structure.into_inner())
+}
+
+#[get("/book/<asset>/auction")]
+fn get_auction(asset: Uuid, game: State<Game>) -> EngineResult<Json<Option<Clearing>>> {
+    Ok(Json(game.get_indicative_clearing(*asset)?))
+}
+
+#[get("/fees")]
+fn get_fees(game: State<Game>) -> EngineResult<Json<FeeSchedule>> {
+    Ok(Json(game.get_fee_schedule()?))
+}
+
+#[put("/admin/fees", data="<schedule>")]
+fn set_fees(_admin: Admin, schedule: Json<FeeSchedule>, game: Acting) -> EngineResult<()> {
+    game.set_fee_schedule(schedule.into_inner())
+}
+
+#[get("/stats")]
+fn get_stats(game: State<Game>) -> EngineResult<Json<MonetaryStats>> {
+    Ok(Json(game.get_monetary_stats()?))
+}
+
+#[get("/stats/open-interest")]
+fn get_open_interest(game: State<Game>) -> EngineResult<Json<OpenInterestStats>> {
+    Ok(Json(game.get_open_interest()?))
+}
+
+#[post("/admin/bank/mint", data="<data>")]
+fn mint(_admin: Admin, data: Strict<CoinAmount>, game: Acting) -> EngineResult<()> {
+    game.mint(data.amount)
+}
+
+#[post("/admin/bank/burn", data="<data>")]
+fn burn(_admin: Admin, data: Strict<CoinAmount>, game: Acting) -> EngineResult<()> {
+    game.burn(data.amount)
+}
+
+#[post("/admin/bank/asset/<asset>/buy", data="<data>")]
+fn bank_buy(_admin: Admin, asset: Uuid, data: Strict<BankOrderPlacement>, game: Acting) -> EngineResult<Json<OrderDto>> {
+    let order = game.open_market_operation(*asset, OrderSide::Buy, data.quantity, data.price)?;
+    Ok(Json(OrderDto::from(&game.get_order(order.get_id())?)))
+}
+
+#[post("/admin/bank/asset/<asset>/sell", data="<data>")]
+fn bank_sell(_admin: Admin, asset: Uuid, data: Strict<BankOrderPlacement>, game: Acting) -> EngineResult<Json<OrderDto>> {
+    let order = game.open_market_operation(*asset, OrderSide::Sell, data.quantity, data.price)?;
+    Ok(Json(OrderDto::from(&game.get_order(order.get_id())?)))
+}
+
+#[post("/admin/portfolio/<id>/clone")]
+fn clone_portfolio(_admin: Admin, id: Uuid, game: Acting) -> EngineResult<Json<PortfolioDto>> {
+    let clone = game.clone_portfolio(*id)?;
+    let engine = game.read_engine()?;
+    Ok(Json(PortfolioDto::new(engine.market.get_portfolio(clone)?, engine.market.unrealized_pnl(clone)?)))
+}
+
+#[post("/admin/asset/<asset>/dutch-auction", data="<data>")]
+fn start_dutch_auction(_admin: Admin, asset: Uuid, data: Strict<DutchAuctionPlacement>, game: Acting) -> EngineResult<Json<DutchAuction>> {
+    let terms = DutchAuctionTerms {
+        quantity: data.quantity,
+        start_price: data.start_price,
+        floor: data.floor,
+        decay_per_tick: data.decay_per_tick,
+    };
+    Ok(Json(game.start_dutch_auction(*asset, data.portfolio, terms)?))
+}
+
+#[delete("/admin/dutch-auction/<id>")]
+fn cancel_dutch_auction(_admin: Admin, id: Uuid, game: Acting) -> EngineResult<Json<DutchAuction>> {
+    Ok(Json(game.cancel_dutch_auction(*id)?))
+}
+
+#[get("/dutch-auctions")]
+fn get_dutch_auctions(game: State<Game>) -> EngineResult<Json<DutchAuctions>> {
+    Ok(Json(DutchAuctions {
+        auctions: game.get_dutch_auctions()?
+    }))
+}
+
+#[post("/admin/bootstrap", data="<plan>")]
+fn bootstrap(_admin: Admin, plan: Json<BootstrapPlan>, game: Acting) -> EngineResult<Json<Allocations>> {
+    Ok(Json(Allocations {
+        allocations: game.bootstrap(&plan)?
+    }))
+}
+
+// Names from the roster CSV in the body, or `count` numbered ones when it's empty.
+#[post("/admin/portfolios/bulk?<count>&<coins>", data="<roster>")]
+fn provision_portfolios(_admin: Admin, count: Option<usize>, coins: Option<usize>, roster: String, game: Acting)
+                        -> EngineResult<Download> {
+    let names = match roster.trim().is_empty() {
+        true => numbered_names(count.unwrap_or(0)),
+        false => parse_roster_csv(&roster)?,
+    };
+    let credentials = game.provision_portfolios(names, coins)?;
+    Ok(Download {
+        filename: "credentials.csv".to_string(),
+        content: to_csv(&credentials, &game.get_formatter(Locale::Plain)?),
+    })
+}
+
+#[get("/admin/roster")]
+fn get_roster(_admin: Admin, game: State<Game>) -> EngineResult<Json<Roster>> {
+    Ok(Json(Roster {
+        roster: game.get_roster()?
+    }))
+}
+
+#[post("/admin/asset/<asset>/seed-book", data="<seed>")]
+fn seed_book(_admin: Admin, asset: Uuid, seed: Json<BookSeed>, game: Acting) -> EngineResult<Json<Vec<OrderDto>>> {
+    let ids = game.seed_book(*asset, seed.into_inner())?;
+    Ok(Json(order_records(&game, ids)?))
+}
+
+#[get("/admin/export/trades.csv")]
+fn export_all_trades(_admin: Admin, game: State<Game>) -> EngineResult<Content<String>> {
+    Ok(Content(ContentType::CSV, to_csv(&game.get_all_trades()?, &game.get_formatter(Locale::Plain)?)))
+}
+
+#[get("/admin/export/ledger.csv")]
+fn export_all_ledger(_admin: Admin, game: State<Game>) -> EngineResult<Content<String>> {
+    Ok(Content(ContentType::CSV, to_csv(&game.get_all_ledger()?, &game.get_formatter(Locale::Plain)?)))
+}
+
+#[get("/admin/settlement")]
+fn get_settlement_policy(_admin: Admin, game: State<Game>) -> EngineResult<Json<Option<SettlementPolicy>>> {
+    Ok(Json(game.get_settlement_policy()?))
+}
+
+#[put("/admin/settlement", data="<policy>")]
+fn set_settlement_policy(_admin: Admin, policy: Json<Option<SettlementPolicy>>, game: Acting) -> EngineResult<()> {
+    if let Some(url) = policy.as_ref().and_then(|policy| policy.url.as_ref()) {
+        webhook::check_url(url)?;
+    }
+    game.set_settlement_policy(policy.into_inner())
+}
+
+#[get("/admin/settlements")]
+fn get_pending_settlements(_admin: Admin, game: State<Game>) -> EngineResult<Json<PendingSettlements>> {
+    Ok(Json(PendingSettlements { pending: game.get_pending_settlements()? }))
+}
+
+#[post("/admin/settlement/<trade>/confirm")]
+fn confirm_settlement(_admin: Admin, trade: u64, game: Acting) -> EngineResult<()> {
+    game.confirm_settlement(trade)
+}
+
+#[post("/admin/settlement/<trade>/reject")]
+fn reject_settlement(_admin: Admin, trade: u64, game: Acting) -> EngineResult<()> {
+    game.reject_settlement(trade)
+}
+
+#[get("/admin/webhooks")]
+fn get_webhooks(_admin: Admin, webhooks: State<Webhooks>) -> EngineResult<Json<WebhookList>> {
+    Ok(Json(WebhookList {
+        webhooks: webhooks.get_all()?.into_iter()
+            .map(|(id, config)| RegisteredWebhook { id, config })
+            .collect()
+    }))
+}
+
+#[post("/admin/webhooks", data="<config>")]
+fn add_webhook(_admin: Admin, config: Json<WebhookConfig>, webhooks: State<Webhooks>) -> EngineResult<Json<usize>> {
+    Ok(Json(webhooks.add(config.into_inner())?))
+}
+
+#[delete("/admin/webhooks/<id>")]
+fn remove_webhook(_admin: Admin, id: usize, webhooks: State<Webhooks>) -> EngineResult<()> {
+    webhooks.remove(id)
+}
+
+#[post("/admin/asset/<asset>/import-book?<portfolio>", data="<csv>")]
+fn import_book(_admin: Admin, asset: Uuid, portfolio: Option<Uuid>, csv: String, game: Acting) -> EngineResult<Json<Vec<OrderDto>>> {
+    let ids = game.import_book(*asset, &csv, portfolio.map(|p| *p))?;
+    Ok(Json(order_records(&game, ids)?))
+}
+
+#[get("/news")]
+fn get_news(game: State<Game>) -> EngineResult<Json<News>> {
+    Ok(Json(News{
+        news: game.get_news()?
+    }))
+}
+
+#[post("/admin/news", data="<data>")]
+fn post_news(_admin: Admin, data: Strict<NewsPost>, game: Acting) -> EngineResult<Json<uuid::Uuid>> {
+    let data = data.into_inner();
+    Ok(Json(game.post_news(data.headline, data.impacts)?))
+}
+
+#[post("/admin/asset/<asset>/market-maker", data="<data>")]
+fn add_market_maker(_admin: Admin, asset: Uuid, data: Strict<MarketMakerPlacement>, game: Acting)
+                    -> EngineResult<Json<uuid::Uuid>> {
+    let mut game = game.0;
+    Ok(Json(game.add_market_maker(
+        *asset,
+        data.fair_value,
+        data.spread_percent,
+        data.quantity,
+        data.coins,
+        data.holdings,
+    )?))
+}
+
+pub struct Admin;
+
+impl<'a, 'r> FromRequest<'a, 'r> for Admin {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Admin, ()> {
+        let expected = env::var("ADMIN_TOKEN").ok();
+        match (expected, request.headers().get_one("X-Admin-Token")) {
+            (Some(expected), Some(token)) if !expected.is_empty() && expected == token => Outcome::Success(Admin),
+            _ => Outcome::Failure((rocket::http::Status::Forbidden, ())),
+        }
+    }
+}
+
+// The managed game acting for whoever sent the request: the admin, a player by the X-Api-Token
+// header or nobody in particular.
+pub struct Acting(Game);
+
+impl<'a, 'r> FromRequest<'a, 'r> for Acting {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<Acting, ()> {
+        let game = match request.guard::<State<Game>>() {
+            Outcome::Success(game) => game,
+            _ => return Outcome::Failure((rocket::http::Status::InternalServerError, ())),
+        };
+        if let Outcome::Success(_) = Admin::from_request(request) {
+            return Outcome::Success(Acting(game.acting_as(Actor::Admin)));
+        }
+        let banned = request.client_ip().map(|ip| game.is_ip_banned(&ip.to_string()));
+        if let Some(Ok(true)) = banned {
+            return Outcome::Failure((rocket::http::Status::Forbidden, ()));
+        }
+        // only tokens the server issued, a made up one would dodge its suspension
+        let actor = match request.headers().get_one("X-Api-Token") {
+            Some(token) if !token.is_empty() => match game.is_token_issued(token) {
+                Ok(true) => Actor::token(token),
+                _ => return Outcome::Failure((rocket::http::Status::Unauthorized, ())),
+            },
+            _ => Actor::Anonymous,
+        };
+        Outcome::Success(Acting(game.acting_as(actor)))
+    }
+}
+
+impl Deref for Acting {
+    type Target = Game;
+
+    fn deref(&self) -> &Game {
+        &self.0
+    }
 }
 
-#[get("/book/<asset>")]
-fn get_book(asset: Uuid, game: State<Game>) -> EngineResult<Json<PublicBook>>{
-    Ok(Json(game.get_public_book_for(*asset)?))
+pub struct IfNoneMatch(Option<String>);
+
+impl<'a, 'r> FromRequest<'a, 'r> for IfNoneMatch {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<IfNoneMatch, ()> {
+        Outcome::Success(IfNoneMatch(request.headers().get_one("If-None-Match").map(String::from)))
+    }
 }
 
-#[get("/asset/<id>")]
-fn get_asset(id: Uuid, game: State<Game>) -> EngineResult<Json<Asset>> {
-    return game.read_engine()?.market.assets.get(&id)
-        .map(|a|Json(a.clone())).ok_or(ErrorType::AssetNotFound(*id))
+impl IfNoneMatch {
+    fn matches(&self, etag: &str) -> bool {
+        self.0.as_ref().map_or(false, |tags| tags.split(',')
+            .map(|tag| tag.trim().trim_start_matches("W/"))
+            .any(|tag| tag == "*" || tag == etag))
+    }
 }
 
+// JSON serialized before, see `Game::cached`
+pub struct CachedJson(Arc<String>);
 
-#[delete("/portfolio/<portfolio>/asset/<asset>/order/<order>")]
-fn cancel_order(portfolio: Uuid, asset: Uuid, order: Uuid, game: State<Game>) -> EngineResult<()> {
-    return game.write_engine()?.process(Event::CancelOrder(*portfolio,*order,*asset))
+impl<'r> Responder<'r> for CachedJson {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        Response::build()
+            .sized_body(Cursor::new(self.0.as_bytes().to_vec()))
+            .header(ContentType::JSON)
+            .ok()
+    }
 }
 
+pub enum Body<T> {
+    Json(Json<T>),
+    Cached(CachedJson),
+}
 
+// A body with its ETag, or a 304 without body if the client already has it.
+pub struct Tagged<T> {
+    etag: String,
+    body: Option<Body<T>>,
+}
 
-#[post("/portfolio/<portfolio>/asset/<asset>/sell", data="<data>")]
-fn sell_order(portfolio: Uuid, asset: Uuid, data: Json<OrderPlacement>, game: State<Game>, )
-              -> EngineResult<Json<uuid::Uuid>> {
-    let o =  Order::new(
-        *portfolio,
-        *asset,
-        OrderSide::Sell,
-        data.quantity,
-        data.mode,
-        Instant::now().add(Duration::from_secs(24*60*60))
-    )?;
-    game.write_engine()?.process(Event::Order(o))?;
-    return Ok(Json(o.id));
+impl<T> Tagged<T> {
+    fn new(etag: String, body: Body<T>, if_none_match: &IfNoneMatch) -> Tagged<T> {
+        let body = match if_none_match.matches(&etag) {
+            true => None,
+            false => Some(body),
+        };
+        Tagged { etag, body }
+    }
 }
 
-#[post("/portfolio/<portfolio>/asset/<asset>/buy", data="<data>")]
-fn buy_order(portfolio: Uuid, asset: Uuid, data: Json<OrderPlacement>, game: State<Game>, )
-              -> EngineResult<Json<uuid::Uuid>> {
-    let o =  Order::new(
-        *portfolio,
-        *asset,
-        OrderSide::Buy,
-        data.quantity,
-        data.mode,
-        Instant::now().add(Duration::from_secs(24*60*60))
-    )?;
-    game.write_engine()?.process(Event::Order(o))?;
-    return Ok(Json(o.id));
+impl<'r, T: serde::Serialize> Responder<'r> for Tagged<T> {
+    fn respond_to(self, request: &Request) -> response::Result<'r> {
+        let mut response = match self.body {
+            Some(Body::Json(body)) => Response::build_from(body.respond_to(request)?),
+            Some(Body::Cached(body)) => Response::build_from(body.respond_to(request)?),
+            None => {
+                let mut response = Response::build();
+                response.status(rocket::http::Status::NotModified);
+                response
+            },
+        };
+        response.raw_header("ETag", self.etag).ok()
+    }
+}
+
+// a CSV the browser saves instead of showing
+pub struct Download {
+    filename: String,
+    content: String,
+}
+
+impl<'r> Responder<'r> for Download {
+    fn respond_to(self, _: &Request) -> response::Result<'r> {
+        Response::build()
+            .sized_body(Cursor::new(self.content))
+            .header(ContentType::CSV)
+            .raw_header("Content-Disposition", format!("attachment; filename=\"{}\"", self.filename))
+            .ok()
+    }
+}
+
+// A JSON body that may only have the fields `T` has, with values in range. Anything else is turned
+// down with a 422 naming the fields, see `unprocessable`. No body at all reads as null, which an
+// optional body takes for none.
+pub struct Strict<T>(T);
+
+impl<T> Strict<T> {
+    fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> Deref for Strict<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+const BODY_LIMIT: u64 = 1 << 20;
+
+// what the 422 catcher answers, kept with the request by `Strict`
+struct RejectedBody(String);
+
+impl<T: serde::de::DeserializeOwned + Validate> FromDataSimple for Strict<T> {
+    type Error = ErrorType;
+
+    fn from_data(request: &Request, data: Data) -> data::Outcome<Strict<T>, ErrorType> {
+        let mut body = String::new();
+        let fields = match data.open().take(BODY_LIMIT).read_to_string(&mut body) {
+            Err(e) => vec![FieldError { field: None, problem: e.to_string() }],
+            Ok(_) => {
+                let body = if body.trim().is_empty() { "null" } else { body.as_str() };
+                match serde_json::from_str::<T>(body) {
+                    Ok(value) => match value.invalid_fields() {
+                        fields if fields.is_empty() => return Outcome::Success(Strict(value)),
+                        fields => fields,
+                    },
+                    Err(e) => vec![FieldError::from_serde(&e)],
+                }
+            },
+        };
+        let error = ErrorType::InvalidFields(fields);
+        request.local_cache(|| RejectedBody(serde_json::to_string(&error).unwrap_or_default()));
+        Outcome::Failure((rocket::http::Status::UnprocessableEntity, error))
+    }
+}
+
+// Bodies other guards failed to parse have no `RejectedBody`.
+#[catch(422)]
+fn unprocessable(request: &Request) -> Content<String> {
+    let rejected = request.local_cache(|| {
+        let error = ErrorType::InvalidFields(vec![FieldError { field: None, problem: "the body doesn't parse".to_string() }]);
+        RejectedBody(serde_json::to_string(&error).unwrap_or_default())
+    });
+    Content(ContentType::JSON, rejected.0.clone())
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NewsPost {
+    headline: String,
+    impacts: Vec<NewsImpact>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CoinAmount {
+    amount: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Delisting {
+    // the asset's oracle price if left out
+    #[serde(default)]
+    settlement_price: Option<usize>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dividend {
+    per_unit: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BankOrderPlacement {
+    quantity: usize,
+    price: usize,
+}
+
+// sold from the bank unless a portfolio is given
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct DutchAuctionPlacement {
+    #[serde(default)]
+    portfolio: Option<uuid::Uuid>,
+    // the terms spelled out, serde doesn't turn down unknown fields next to a flattened struct
+    quantity: usize,
+    start_price: usize,
+    floor: usize,
+    decay_per_tick: usize,
 }
 
 #[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MarketMakerPlacement {
+    fair_value: usize,
+    spread_percent: usize,
+    quantity: usize,
+    coins: usize,
+    holdings: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct OrderPlacement {
     quantity: usize,
     mode: OrderMode,
+    // past the fat-finger check, admins only
+    #[serde(default)]
+    override_price_band: bool,
+    // rests as a dark order, out of every public view of the book
+    #[serde(default)]
+    hidden: bool,
+}
+
+impl Validate for NewsPost {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        check(!self.headline.trim().is_empty(), "headline", "can't be empty").into_iter().collect()
+    }
+}
+
+impl Validate for CoinAmount {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        check_positive(self.amount, "amount").into_iter().collect()
+    }
+}
+
+impl Validate for Delisting {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![]
+    }
+}
+
+impl Validate for Dividend {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        check_positive(self.per_unit, "per_unit").into_iter().collect()
+    }
+}
+
+impl Validate for BankOrderPlacement {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![check_positive(self.quantity, "quantity"), check_positive(self.price, "price")]
+            .into_iter().flatten().collect()
+    }
+}
+
+impl Validate for DutchAuctionPlacement {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![
+            check_positive(self.quantity, "quantity"),
+            check_positive(self.decay_per_tick, "decay_per_tick"),
+            check(self.floor <= self.start_price, "floor", "can't be above start_price"),
+        ].into_iter().flatten().collect()
+    }
+}
+
+impl Validate for MarketMakerPlacement {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![
+            check_positive(self.fair_value, "fair_value"),
+            check(self.spread_percent <= 100, "spread_percent", "can't be above 100"),
+            check_positive(self.quantity, "quantity"),
+        ].into_iter().flatten().collect()
+    }
+}
+
+impl Validate for OrderPlacement {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![check_positive(self.quantity, "quantity"), check_mode(self.mode, "mode")]
+            .into_iter().flatten().collect()
+    }
+}
+
+impl OrderPlacement {
+    fn checked_override(&self, order: Order, admin: Option<Admin>) -> EngineResult<Order> {
+        match (self.override_price_band, admin) {
+            (false, _) => Ok(order),
+            (true, Some(_)) => Ok(order.override_price_band()),
+            (true, None) => Err(ErrorType::OverrideRequiresAdmin),
+        }
+    }
+
+    fn visibility(&self, order: Order) -> Order {
+        if self.hidden { order.hide() } else { order }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OrderLeg {
+    asset: uuid::Uuid,
+    side: OrderSide,
+    quantity: usize,
+    mode: OrderMode,
+}
+
+impl Validate for OrderLeg {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![check_positive(self.quantity, "quantity"), check_mode(self.mode, "mode")]
+            .into_iter().flatten().collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SchedulePlacement {
+    asset: uuid::Uuid,
+    side: OrderSide,
+    quantity: usize,
+    mode: OrderMode,
+    every_ticks: u64,
+}
+
+impl Validate for SchedulePlacement {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![
+            check_positive(self.quantity, "quantity"),
+            check_mode(self.mode, "mode"),
+            check(self.every_ticks > 0, "every_ticks", "must be above 0"),
+        ].into_iter().flatten().collect()
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AlgoPlacement {
+    asset: uuid::Uuid,
+    side: OrderSide,
+    quantity: usize,
+    mode: OrderMode,
+    strategy: AlgoStrategy,
+}
+
+impl Validate for AlgoPlacement {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        let mut fields: Vec<FieldError> = vec![check_positive(self.quantity, "quantity"), check_mode(self.mode, "mode")]
+            .into_iter().flatten().collect();
+        match self.strategy {
+            AlgoStrategy::Twap { duration_ticks, slices } => {
+                fields.extend(check(slices > 0, "strategy.Twap.slices", "must be above 0"));
+                fields.extend(check(slices <= duration_ticks, "strategy.Twap.slices", "can't be more than duration_ticks"));
+            },
+            AlgoStrategy::Pov { percent, max_percent } => {
+                fields.extend(check(percent > 0, "strategy.Pov.percent", "must be above 0"));
+                fields.extend(check(percent <= max_percent, "strategy.Pov.percent", "can't be more than max_percent"));
+                fields.extend(check(max_percent <= 100, "strategy.Pov.max_percent", "can't be more than 100"));
+            },
+        }
+        fields
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BatchPlacement {
+    legs: Vec<OrderLeg>,
+}
+
+impl Validate for BatchPlacement {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        let mut fields: Vec<FieldError> = check(!self.legs.is_empty(), "legs", "needs at least one leg").into_iter().collect();
+        for (i, leg) in self.legs.iter().enumerate() {
+            fields.extend(nested(&format!("legs[{}]", i), leg.invalid_fields()));
+        }
+        fields
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OcoPlacement {
+    first: OrderLeg,
+    second: OrderLeg,
+    trigger_quantity: Option<usize>,
+}
+
+impl Validate for OcoPlacement {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        let mut fields = nested("first", self.first.invalid_fields());
+        fields.extend(nested("second", self.second.invalid_fields()));
+        fields.extend(check(self.trigger_quantity != Some(0), "trigger_quantity", "must be above 0"));
+        fields
+    }
+}
+
+impl Validate for ClosePosition {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![]
+    }
 }
 
 
 
-pub fn start_server(game: Game) {
+pub fn start_server(mut game: Game) {
 
     let aaa = OrderPlacement {
         quantity: 2,
         mode: OrderMode::Limit(3),
+        override_price_band: false,
+        hidden: false,
     };
     let encoded = serde_json::to_string(&aaa).unwrap();
     println!("{}", encoded);
-    rocket::ignite().mount("/", routes![
+
+    let max_depth = env::var("QUEUE_MAX_DEPTH").ok().and_then(|d| d.parse().ok()).unwrap_or(1000);
+    // set on the game's queue, it already delays events as a restored game did
+    let _ = game.queue.set_max_depth(Some(max_depth));
+    if let Some(millis) = env::var("LOCK_TIMEOUT_MILLIS").ok().and_then(|t| t.parse().ok()) {
+        game.lock_timeout = Duration::from_millis(millis);
+    }
+    if let Some(ticks) = env::var("STATEMENT_PERIOD_TICKS").ok().and_then(|t| t.parse().ok()) {
+        game.statement_period_ticks = ticks;
+    }
+    if env::var("PROFILE").map_or(false, |p| p == "1") {
+        let _ = game.set_profiling(true);
+    }
+    game.start_worker();
+
+    // trades are shown on the admin console from $CONSOLE_BIG_TRADE_NOTIONAL coins on
+    let big_trade_notional = env::var("CONSOLE_BIG_TRADE_NOTIONAL").ok().and_then(|n| n.parse().ok());
+    let _ = game.console.set_big_trade_notional(big_trade_notional);
+    let stream_address = format!("0.0.0.0:{}", env::var("STREAM_PORT").unwrap_or("8001".to_string()));
+    let streamed = game.clone();
+    thread::spawn(move || crate::stream::listen(stream_address, streamed));
+
+    // applied at startup and again whenever the file changes
+    if let Ok(path) = env::var("CONFIG_FILE") {
+        let watched = game.acting_as(Actor::System);
+        thread::spawn(move || crate::config::watch(watched, path.into(), Duration::from_secs(1)));
+    }
+
+    // comma separated, trades are posted from $WEBHOOK_BIG_TRADE_NOTIONAL coins on
+    let webhooks = Webhooks::new(game.bus.clone());
+    let big_trade_notional = env::var("WEBHOOK_BIG_TRADE_NOTIONAL").ok().and_then(|n| n.parse().ok());
+    for url in env::var("WEBHOOK_URLS").unwrap_or_default().split(',').filter(|u| !u.trim().is_empty()) {
+        let config = WebhookConfig { big_trade_notional, ..WebhookConfig::new(url.trim().to_string()) };
+        if let Err(e) = webhooks.add(config) {
+            println!("skipping webhook: {:?}", e);
+        }
+    }
+    webhook::dispatch_settlements(game.clone());
+    game.bus.publish(vec![DomainEvent::Game(GameEvent::GameStarted)]);
+
+    let tick = Duration::from_millis(env::var("TICK_MILLIS").ok().and_then(|t| t.parse().ok()).unwrap_or(1000));
+    let ticker = game.clone();
+    thread::spawn(move || loop {
+        thread::sleep(tick);
+        let _ = ticker.advance_tick();
+    });
+
+    let tape_rate_limit = env::var("TAPE_RATE_LIMIT").ok().and_then(|l| l.parse().ok()).unwrap_or(60);
+
+    // every route under its version, the unprefixed ones are the deprecated aliases of the latest
+    rocket::ignite()
+        .attach(ApiVersioning)
+        .attach(ReceiveTimestamps)
+        .mount("/v1", api_routes())
+        .mount("/", api_routes())
+        .register(catchers![unprocessable])
+        .manage(game)
+        .manage(webhooks)
+        .manage(RateLimiter::new(tape_rate_limit, Duration::from_secs(60)))
+        .launch();
+}
+
+fn api_routes() -> Vec<rocket::Route> {
+    routes![
         get_portfolio,
+        get_positions,
+        get_reservations,
+        get_alerts,
+        add_alert,
+        remove_alert,
         get_asset,
+        get_asset_rules,
         get_assets,
         sell_order,
         buy_order,
+        close_position,
+        batch_order,
+        upload_orders,
+        rebalance,
+        oco_order,
         cancel_order,
         get_book,
+        get_book_delta,
         get_books,
-    ]).manage(game).launch();
+        get_market_data,
+        get_book_history,
+        get_risk,
+        get_trades,
+        get_tape,
+        get_replay,
+        start_recording,
+        get_recording,
+        stop_recording,
+        play_scenario,
+        get_scenario_status,
+        stop_scenario,
+        get_journal,
+        get_debug_state,
+        get_profile,
+        get_cache_metrics,
+        get_event_counts,
+        set_profiling,
+        set_paused,
+        end_game,
+        get_game_clock,
+        get_time,
+        get_report,
+        suspend_portfolio,
+        reinstate_portfolio,
+        suspend_token,
+        reinstate_token,
+        suspend_ip,
+        reinstate_ip,
+        issue_token,
+        get_tokens,
+        revoke_token,
+        get_suspensions,
+        set_penalty_rule,
+        get_penalties,
+        get_news,
+        post_news,
+        get_leaderboard,
+        set_scoring_metric,
+        force_cancel_order,
+        cancel_all_orders,
+        bust_trade,
+        delist_asset,
+        set_asset_halted,
+        pay_dividend,
+        set_bankruptcy_rule,
+        set_interest_policy,
+        get_ledger,
+        get_statement,
+        heartbeat,
+        disarm_heartbeat,
+        get_schedules,
+        schedule_order,
+        cancel_schedule,
+        get_algos,
+        start_algo,
+        cancel_algo,
+        get_stats,
+        get_open_interest,
+        get_fees,
+        set_fees,
+        get_queue,
+        get_health,
+        mint,
+        burn,
+        bank_buy,
+        bank_sell,
+        start_dutch_auction,
+        cancel_dutch_auction,
+        get_dutch_auctions,
+        bootstrap,
+        provision_portfolios,
+        get_roster,
+        clone_portfolio,
+        seed_book,
+        import_book,
+        add_market_maker,
+        get_webhooks,
+        add_webhook,
+        remove_webhook,
+        get_settlement_policy,
+        set_settlement_policy,
+        get_pending_settlements,
+        confirm_settlement,
+        reject_settlement,
+        export_trades,
+        export_ledger,
+        export_all_trades,
+        export_all_ledger,
+        render_book,
+        set_asset_decimals,
+        get_price_mode,
+        set_price_mode,
+        get_oracle,
+        set_oracle_curve,
+        remove_oracle,
+        get_price_band,
+        set_price_band,
+        get_minimum_resting_time,
+        set_minimum_resting_time,
+        get_latency_policy,
+        set_latency_policy,
+        get_watchlist,
+        set_watchlist,
+        get_watchlist_quotes,
+        get_orders,
+        get_order,
+        get_queue_position,
+        get_activity_stats,
+        spawn_sandbox,
+        get_sandbox,
+        remove_sandbox,
+        sandbox_order,
+        get_market_structure,
+        set_market_structure,
+        get_dark_priority,
+        set_dark_priority,
+        claim_faucet,
+        get_faucet,
+        buy_priority_class,
+        get_priority_classes,
+        set_priority_classes,
+        assign_priority_class,
+        set_faucet_policy,
+        get_auction,
+        get_config,
+        set_config,
+    ]
+}
+
+// shared by the list endpoints, see `PageQuery`
+#[derive(FromForm)]
+struct ListQuery {
+    limit: Option<usize>,
+    offset: Option<usize>,
+    cursor: Option<String>,
+    side: Option<String>,
+    min_price: Option<usize>,
+    max_price: Option<usize>,
+    since: Option<u64>,
+}
+
+impl ListQuery {
+    fn is_empty(&self) -> bool {
+        self.limit.is_none() && self.offset.is_none() && self.cursor.is_none() && self.side.is_none()
+            && self.min_price.is_none() && self.max_price.is_none() && self.since.is_none()
+    }
+
+    fn to_page_query(&self) -> EngineResult<PageQuery> {
+        let side = match self.side.as_ref().map(|s| s.to_lowercase()) {
+            None => None,
+            Some(ref s) if s == "buy" => Some(OrderSide::Buy),
+            Some(ref s) if s == "sell" => Some(OrderSide::Sell),
+            Some(s) => return Err(ErrorType::UnknownOrderSide(s)),
+        };
+        Ok(PageQuery {
+            limit: self.limit,
+            offset: self.offset,
+            cursor: self.cursor.clone(),
+            side,
+            min_price: self.min_price,
+            max_price: self.max_price,
+            since: self.since,
+        })
+    }
 }
 
 #[derive(Serialize)]
 struct Assets {
-    assets: Vec<Asset>
+    assets: Vec<Asset>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct BookDeltas {
+    asset: uuid::Uuid,
+    version: u64,
+    deltas: Vec<BookDelta>,
 }
 
 #[derive(Serialize)]
 struct Books {
-    books: Vec<PublicBook>
+    books: Vec<BookDto>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct News {
+    news: Vec<NewsItem>
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Watchlist {
+    assets: Vec<uuid::Uuid>,
+}
+
+impl Validate for Watchlist {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        vec![]
+    }
+}
+
+#[derive(Serialize)]
+struct Sandbox {
+    sandbox: SandboxPortfolio,
+    value: usize,
+}
+
+#[derive(Serialize)]
+struct Allocations {
+    allocations: Vec<Allocation>,
+}
+
+#[derive(Serialize)]
+struct Suspensions {
+    suspensions: Vec<Suspension>,
+}
+
+#[derive(Serialize)]
+struct TokenEntry {
+    fingerprint: String,
+    portfolio: uuid::Uuid,
+}
+
+#[derive(Serialize)]
+struct Penalties {
+    rule: Option<PenaltyRule>,
+    standings: Vec<PenaltyStanding>,
+}
+
+#[derive(Serialize)]
+struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+#[derive(Serialize)]
+struct DutchAuctions {
+    auctions: Vec<DutchAuction>,
+}
+
+#[derive(Serialize)]
+struct Orders {
+    orders: Vec<OrderDto>,
+}
+
+#[derive(Serialize)]
+struct Quotes {
+    quotes: Vec<WatchQuote>,
+}
+
+#[derive(Serialize)]
+struct WebhookList {
+    webhooks: Vec<RegisteredWebhook>,
+}
+
+#[derive(Serialize)]
+struct RegisteredWebhook {
+    id: usize,
+    #[serde(flatten)]
+    config: WebhookConfig,
+}
+
+#[derive(Serialize)]
+struct Schedules {
+    schedules: Vec<RecurringOrder>
+}
+
+#[derive(Serialize)]
+struct Ledger {
+    entries: Vec<LedgerEntry>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Trades {
+    trades: Vec<Trade>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Leaderboard {
+    entries: Vec<LeaderboardEntry>
+}
+
+#[derive(Serialize)]
+struct Alerts {
+    rules: Vec<Alert>,
+    fired: Vec<FiredAlert>,
+}
+
+#[derive(Serialize)]
+struct PendingSettlements {
+    pending: Vec<PendingSettlement>,
+}
+
+#[derive(Serialize)]
+struct Positions {
+    positions: Vec<PositionDto>,
+}
+
+#[derive(Serialize)]
+struct Reservations {
+    reservations: Vec<ReservationDto>
+}
+
+#[derive(Serialize)]
+struct Oracle {
+    feeds: Vec<OracleFeed>,
+}
+
+#[derive(Serialize)]
+struct Tape {
+    trades: Vec<TapePrint>,
+    next_cursor: Option<String>,
+}
+
+#[derive(Serialize)]
+struct Roster {
+    roster: Vec<RosterEntry>
+}
+
+#[derive(Serialize)]
+struct BookHistory {
+    snapshots: Vec<BookSnapshot>
 }
 
 
 impl Responder<'_> for ErrorType {
     fn respond_to(self, _: &Request) -> response::Result<'static> {
-        Response::build()
-            .sized_body(Cursor::new(format!("{:?}", self)))
-            .header(ContentType::new("text", "text"))
-            .status(rocket::http::Status::BadRequest)
-            .ok()
+        use rocket::http::Status;
+        let retry_after = match self {
+            ErrorType::QueueFull { retry_after_millis, .. } | ErrorType::RateLimited { retry_after_millis } =>
+                Some((retry_after_millis + 999) / 1000),
+            _ => None,
+        };
+        let status = match self {
+            ErrorType::QueueFull { .. } | ErrorType::RateLimited { .. } => Status::TooManyRequests,
+            ErrorType::Suspended | ErrorType::OrderBanned { .. } | ErrorType::TokenRequired => Status::Forbidden,
+            ErrorType::GamePaused | ErrorType::GameEnded | ErrorType::GameNotEnded => Status::Conflict,
+            ErrorType::EngineUnavailable | ErrorType::EngineTimeout { .. } => Status::ServiceUnavailable,
+            ErrorType::InvalidFields(_) => Status::UnprocessableEntity,
+            _ => Status::BadRequest,
+        };
+        // the ones a client reads fields from come as JSON
+        let (body, content_type) = match self {
+            ErrorType::InvalidFields(_) | ErrorType::EngineTimeout { .. } =>
+                (serde_json::to_string(&self).unwrap_or_default(), ContentType::JSON),
+            _ => (format!("{:?}", self), ContentType::new("text", "text")),
+        };
+        let mut response = Response::build();
+        response.sized_body(Cursor::new(body)).header(content_type).status(status);
+        if let Some(retry_after) = retry_after {
+            response.raw_header("Retry-After", retry_after.to_string());
+        }
+        response.ok()
     }
 }
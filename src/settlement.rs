@@ -0,0 +1,40 @@
+use crate::account::ReservedAccount;
+use crate::error::{EngineResult, ErrorType};
+use crate::providers::Timestamp;
+use crate::trades::Trade;
+
+// Trades wait for an outside system, one with its own inventories, to confirm them before they're
+// final. Until then what each side received is held, and a trade that isn't confirmed within
+// `timeout_millis` of game time is busted.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SettlementPolicy {
+    pub timeout_millis: u64,
+    // pending trades are posted here, a 2xx answer confirms them and a 4xx rejects them. Without it
+    // they're only confirmed through the API
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl SettlementPolicy {
+    pub fn check(&self) -> EngineResult<()> {
+        if self.timeout_millis == 0 {
+            return Err(ErrorType::InvalidSettlementPolicy);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PendingSettlement {
+    pub trade: Trade,
+    // in game time, see `Market::game_time`
+    pub deadline: Timestamp,
+}
+
+// Part of an account locked until the trade that brought it in is settled.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SettlementHold {
+    pub trade: u64,
+    pub account: ReservedAccount,
+    pub amount: usize,
+}
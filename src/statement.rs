@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+use crate::account::PortfolioId;
+use crate::error::EngineResult;
+use crate::leaderboard::PortfolioHistory;
+use crate::ledger::{LedgerEntry, LedgerKind};
+use crate::market::{AssetId, Market};
+use crate::pricing::to_signed;
+use crate::trades::Trade;
+
+// everything that happened to a portfolio from `from_tick` up to, but not including, `to_tick`
+#[derive(Clone, Debug, Serialize)]
+pub struct Statement {
+    pub portfolio: PortfolioId,
+    pub from_tick: u64,
+    pub to_tick: u64,
+    pub trades: Vec<Trade>,
+    pub ledger: Vec<LedgerEntry>,
+    pub fees: usize,
+    pub fee_refunds: usize,
+    pub rebates: usize,
+    pub interest: usize,
+    pub settlements: usize,
+    pub penalties: usize,
+    pub opening_value: usize,
+    pub closing_value: usize,
+    pub pnl: i64,
+    pub coins: usize,
+    pub positions: HashMap<AssetId, usize>,
+}
+
+impl Statement {
+    pub fn build(market: &Market, history: &PortfolioHistory, portfolio_id: PortfolioId, from_tick: u64, to_tick: u64) -> EngineResult<Statement> {
+        let portfolio = market.get_portfolio(portfolio_id)?;
+        let in_period = |tick: u64| tick >= from_tick && tick < to_tick;
+        let involved = |t: &&Trade| t.buyer == portfolio_id || t.seller == portfolio_id;
+
+        let trades: Vec<Trade> = market.trades.iter()
+            .filter(involved)
+            .filter(|t| in_period(t.tick))
+            .cloned()
+            .collect();
+        let ledger: Vec<LedgerEntry> = market.get_ledger_for(portfolio_id).into_iter()
+            .filter(|e| in_period(e.tick))
+            .cloned()
+            .collect();
+        let sum = |kinds: &[LedgerKind]| ledger.iter()
+            .filter(|e| kinds.contains(&e.kind))
+            .map(|e| e.amount)
+            .sum::<usize>();
+
+        // positions at the end of a closed period are the current ones with everything that came after taken back
+        let mut coins = portfolio.coins.get_total_amount() as i64;
+        let mut positions: HashMap<AssetId, i64> = portfolio.assets.iter()
+            .map(|(asset, account)| (*asset, account.get_total_amount() as i64))
+            .collect();
+        for trade in market.trades.iter().filter(involved).filter(|t| t.tick >= to_tick && !t.busted) {
+            let notional = to_signed(market.price_mode.notional(trade.price, trade.quantity)?)?;
+            let quantity = trade.quantity as i64;
+            let (coins_delta, asset_delta) = if trade.buyer == portfolio_id { (notional, -quantity) } else { (-notional, quantity) };
+            coins += coins_delta;
+            *positions.entry(trade.asset).or_insert(0) += asset_delta;
+        }
+        for entry in market.get_ledger_for(portfolio_id).into_iter().filter(|e| e.tick >= to_tick) {
+            match entry.kind {
+                LedgerKind::Interest | LedgerKind::MakerRebate | LedgerKind::Settlement | LedgerKind::Faucet
+                | LedgerKind::FeeRefund | LedgerKind::Dividend | LedgerKind::Restart => coins -= entry.amount as i64,
+                LedgerKind::Fee | LedgerKind::TakerFee | LedgerKind::Allocation
+                | LedgerKind::Colocation | LedgerKind::Penalty | LedgerKind::Forfeit => coins += entry.amount as i64,
+                // busted trades are left out above as if they never happened, their reversal with them
+                LedgerKind::BustRefund | LedgerKind::BustCharge => (),
+            }
+        }
+
+        let current_value = market.portfolio_value(portfolio_id)?;
+        let opening_value = history.value_at(portfolio_id, from_tick).unwrap_or(current_value);
+        let closing_value = if market.tick >= to_tick {
+            history.value_at(portfolio_id, to_tick).unwrap_or(current_value)
+        } else {
+            current_value
+        };
+
+        Ok(Statement {
+            portfolio: portfolio_id,
+            from_tick,
+            to_tick,
+            trades,
+            fees: sum(&[LedgerKind::Fee, LedgerKind::TakerFee]),
+            fee_refunds: sum(&[LedgerKind::FeeRefund]),
+            rebates: sum(&[LedgerKind::MakerRebate]),
+            interest: sum(&[LedgerKind::Interest]),
+            settlements: sum(&[LedgerKind::Settlement]),
+            penalties: sum(&[LedgerKind::Penalty]),
+            ledger,
+            opening_value,
+            closing_value,
+            pnl: closing_value as i64 - opening_value as i64,
+            coins: coins.max(0) as usize,
+            positions: positions.into_iter()
+                .map(|(asset, amount)| (asset, amount.max(0) as usize))
+                .collect(),
+        })
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use crate::account::{Portfolio, PortfolioId};
+use crate::error::{EngineResult, ErrorType};
+use crate::market::{unrealized_pnl_at, AssetId, Market};
+use crate::pricing::PriceMode;
+
+const SHARDS: usize = 16;
+
+// Portfolios the market changed since the store last caught up. Clones of the market share it, so
+// whatever a market rolled back to its copy had changed is still caught up.
+#[derive(Clone, Default)]
+pub struct Touched(Arc<Mutex<BTreeSet<PortfolioId>>>);
+
+impl Touched {
+    pub fn mark(&self, portfolio_id: PortfolioId) {
+        if let Ok(mut touched) = self.0.lock() {
+            touched.insert(portfolio_id);
+        }
+    }
+
+    fn take(&self) -> BTreeSet<PortfolioId> {
+        self.0.lock().map(|mut touched| std::mem::take(&mut *touched)).unwrap_or_default()
+    }
+}
+
+// the last trade prices the portfolios are marked at
+#[derive(Default)]
+struct Marks {
+    prices: HashMap<AssetId, usize>,
+    price_mode: PriceMode,
+}
+
+// Copies of the portfolios as of the last write to the engine, read without the engine lock. Only
+// whoever holds the engine's write lock catches them up, see `Game::write_engine`, so readers
+// only ever wait on the shard they read while one of its portfolios is swapped.
+#[derive(Default)]
+pub struct PortfolioStore {
+    shards: [RwLock<HashMap<PortfolioId, Arc<Portfolio>>>; SHARDS],
+    marks: RwLock<Arc<Marks>>,
+}
+
+fn shard_of(portfolio_id: PortfolioId) -> usize {
+    portfolio_id.as_bytes()[15] as usize % SHARDS
+}
+
+impl PortfolioStore {
+    fn shard(&self, portfolio_id: PortfolioId) -> &RwLock<HashMap<PortfolioId, Arc<Portfolio>>> {
+        &self.shards[shard_of(portfolio_id)]
+    }
+
+    pub fn get(&self, portfolio_id: PortfolioId) -> EngineResult<Arc<Portfolio>> {
+        self.shard(portfolio_id).read().map_err(|_| ErrorType::InvalidState)?
+            .get(&portfolio_id)
+            .cloned()
+            .ok_or(ErrorType::PortfolioNotFound(portfolio_id))
+    }
+
+    // as `Market::unrealized_pnl` had it at the last write
+    pub fn unrealized_pnl(&self, portfolio: &Portfolio) -> EngineResult<i64> {
+        let marks = self.marks.read().map_err(|_| ErrorType::InvalidState)?.clone();
+        unrealized_pnl_at(portfolio, &marks.prices, marks.price_mode)
+    }
+
+    fn len(&self) -> usize {
+        self.shards.iter().filter_map(|shard| shard.read().ok()).map(|shard| shard.len()).sum()
+    }
+
+    // copies what the market changed since the last time, all of it if a portfolio came or went
+    // behind the market's back
+    pub fn catch_up(&self, market: &Market) -> EngineResult<()> {
+        for id in market.touched.take() {
+            let mut shard = self.shard(id).write().map_err(|_| ErrorType::InvalidState)?;
+            match market.portfolios.get(&id) {
+                Some(portfolio) => shard.insert(id, Arc::new(portfolio.clone())),
+                None => shard.remove(&id),
+            };
+        }
+        if self.len() != market.portfolios.len() {
+            return self.resync(market);
+        }
+        self.update_marks(market)
+    }
+
+    // starts over from the market, after it was replaced as a whole
+    pub fn resync(&self, market: &Market) -> EngineResult<()> {
+        market.touched.take();
+        let mut fresh: Vec<HashMap<PortfolioId, Arc<Portfolio>>> = (0..SHARDS).map(|_| HashMap::new()).collect();
+        for (id, portfolio) in &market.portfolios {
+            fresh[shard_of(*id)].insert(*id, Arc::new(portfolio.clone()));
+        }
+        // shard by shard, readers never find a portfolio missing that is there
+        for (shard, portfolios) in self.shards.iter().zip(fresh) {
+            *shard.write().map_err(|_| ErrorType::InvalidState)? = portfolios;
+        }
+        self.update_marks(market)
+    }
+
+    fn update_marks(&self, market: &Market) -> EngineResult<()> {
+        let mut marks = self.marks.write().map_err(|_| ErrorType::InvalidState)?;
+        if marks.prices != market.last_trade_prices || marks.price_mode != market.price_mode {
+            *marks = Arc::new(Marks {
+                prices: market.last_trade_prices.clone(),
+                price_mode: market.price_mode,
+            });
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,378 @@
+use crate::book::Book;
+use crate::bus::{BusConsumer, DomainEvent, DomainEventKind};
+use crate::account::PortfolioId;
+use crate::market::{AssetId, Notification};
+#[cfg(feature = "server")]
+use crate::actor::Actor;
+#[cfg(feature = "server")]
+use crate::console::{ConsoleCommand, ConsoleMessage};
+#[cfg(feature = "server")]
+use crate::game::Game;
+#[cfg(feature = "server")]
+use crate::providers::{Clock, SystemClock};
+use crate::marketdata::{Level, SequencedMarketData};
+use crate::tape::TapePrint;
+use crate::trades::Trade;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use uuid::Uuid;
+#[cfg(feature = "server")]
+use ws::{CloseCode, Handler, Handshake, Message, Sender};
+#[cfg(feature = "server")]
+use ws::util::Token;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Topic {
+    Portfolio(PortfolioId),
+    MarketData(AssetId, Level),
+    // every trade, anonymized, see `TapePrint`
+    Tape,
+    // the game master's, see `console`
+    Console,
+}
+
+impl Topic {
+    // "/portfolio/<id>" is the private channel of that portfolio,
+    // "/marketdata/<asset>/<level>" the public feed of a book, "/tape" the trades of all books,
+    // "/console" the admin's. A query is left out
+    pub fn parse(resource: &str) -> Option<Topic> {
+        let path = resource.split('?').next().unwrap_or("");
+        let parts: Vec<&str> = path.trim_matches('/').split('/').collect();
+        match parts.as_slice() {
+            ["tape"] => Some(Topic::Tape),
+            ["console"] => Some(Topic::Console),
+            ["portfolio", id] => Uuid::parse_str(id).ok().map(Topic::Portfolio),
+            ["marketdata", asset, level] => {
+                let asset = Uuid::parse_str(asset).ok()?;
+                Level::parse(level).map(|level| Topic::MarketData(asset, level))
+            },
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum GameEvent {
+    GameStarted,
+    GameEnded,
+    Trade { asset: AssetId, price: usize, quantity: usize, notional: usize },
+    AssetDelisted { asset: AssetId, price: usize },
+    LeaderChanged { leader: PortfolioId, previous: Option<PortfolioId> },
+    AssetHalted { asset: AssetId, halted: bool },
+    DividendPaid { asset: AssetId, per_unit: usize },
+}
+
+impl GameEvent {
+    // one line for humans, e.g. a chat message
+    pub fn summary(&self) -> String {
+        match self {
+            GameEvent::GameStarted => "The game has started".to_string(),
+            GameEvent::GameEnded => "The game has ended".to_string(),
+            GameEvent::Trade { asset, price, quantity, notional } =>
+                format!("{} of {} traded at {} ({} coins)", quantity, asset, price, notional),
+            GameEvent::AssetDelisted { asset, price } =>
+                format!("{} was delisted, holders were paid {} per unit", asset, price),
+            GameEvent::LeaderChanged { leader, previous: Some(previous) } =>
+                format!("{} took the lead from {}", leader, previous),
+            GameEvent::LeaderChanged { leader, previous: None } =>
+                format!("{} leads the game", leader),
+            GameEvent::AssetHalted { asset, halted: true } => format!("Trading in {} was halted", asset),
+            GameEvent::AssetHalted { asset, halted: false } => format!("Trading in {} resumed", asset),
+            GameEvent::DividendPaid { asset, per_unit } =>
+                format!("{} paid a dividend of {} per unit", asset, per_unit),
+        }
+    }
+}
+
+pub trait Subscriber: Send {
+    fn id(&self) -> usize;
+    fn send(&self, message: String);
+}
+
+pub struct StreamHub {
+    subscribers: Mutex<HashMap<Topic, Vec<Box<dyn Subscriber>>>>,
+    // the sequence of the last market data published for each book
+    published: Mutex<HashMap<AssetId, u64>>,
+    // connections that authenticated as the portfolio's owner, they keep its heartbeat alive
+    owners: Mutex<HashMap<PortfolioId, usize>>,
+}
+
+impl Default for StreamHub {
+    fn default() -> StreamHub {
+        StreamHub::new()
+    }
+}
+
+impl StreamHub {
+    pub fn new() -> StreamHub {
+        StreamHub {
+            subscribers: Mutex::new(HashMap::new()),
+            published: Mutex::new(HashMap::new()),
+            owners: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn subscribe(&self, topic: Topic, subscriber: Box<dyn Subscriber>) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            subscribers.entry(topic).or_insert_with(Vec::new).push(subscriber);
+        }
+    }
+
+    pub fn unsubscribe(&self, topic: Topic, subscriber_id: usize) {
+        if let Ok(mut subscribers) = self.subscribers.lock() {
+            if let Some(senders) = subscribers.get_mut(&topic) {
+                senders.retain(|s| s.id() != subscriber_id);
+            }
+        }
+    }
+
+    pub fn publish(&self, topic: Topic, message: String) {
+        if let Ok(subscribers) = self.subscribers.lock() {
+            if let Some(senders) = subscribers.get(&topic) {
+                senders.iter().for_each(|s| s.send(message.clone()));
+            }
+        }
+    }
+
+    pub fn has_subscribers(&self, topic: Topic) -> bool {
+        self.subscribers.lock()
+            .map(|subscribers| subscribers.get(&topic).is_some_and(|s| !s.is_empty()))
+            .unwrap_or(false)
+    }
+
+    pub fn owner_connected(&self, portfolio: PortfolioId) {
+        if let Ok(mut owners) = self.owners.lock() {
+            *owners.entry(portfolio).or_default() += 1;
+        }
+    }
+
+    pub fn owner_disconnected(&self, portfolio: PortfolioId) {
+        if let Ok(mut owners) = self.owners.lock() {
+            if let Some(count) = owners.get_mut(&portfolio) {
+                *count -= 1;
+                if *count == 0 {
+                    owners.remove(&portfolio);
+                }
+            }
+        }
+    }
+
+    pub fn is_owner_connected(&self, portfolio: PortfolioId) -> bool {
+        self.owners.lock().is_ok_and(|owners| owners.contains_key(&portfolio))
+    }
+
+    pub fn publish_market_data(&self, book: &Book) {
+        let previous = self.published.lock().ok()
+            .and_then(|mut published| published.insert(book.asset_id, book.version()));
+        for level in [Level::L1, Level::L2, Level::L3].iter() {
+            let topic = Topic::MarketData(book.asset_id, *level);
+            if !self.has_subscribers(topic) {
+                continue;
+            }
+            if let Ok(message) = serde_json::to_string(&SequencedMarketData::new(book, *level, previous)) {
+                self.publish(topic, message);
+            }
+        }
+    }
+
+    pub fn publish_tape(&self, trades: &[Trade]) {
+        if !self.has_subscribers(Topic::Tape) {
+            return;
+        }
+        for trade in trades {
+            if let Ok(message) = serde_json::to_string(&TapePrint::from(trade)) {
+                self.publish(Topic::Tape, message);
+            }
+        }
+    }
+
+    pub fn publish_notifications(&self, notifications: Vec<(PortfolioId, Notification)>) {
+        for (portfolio, notification) in notifications {
+            if let Ok(message) = serde_json::to_string(&notification) {
+                self.publish(Topic::Portfolio(portfolio), message);
+            }
+        }
+    }
+}
+
+// The tape is fed from the bus, books and notifications are published by the game directly.
+impl BusConsumer for StreamHub {
+    fn wants(&self, kind: DomainEventKind) -> bool {
+        kind == DomainEventKind::TradeExecuted && self.has_subscribers(Topic::Tape)
+    }
+
+    fn consume(&self, event: &DomainEvent) {
+        if let DomainEvent::TradeExecuted { trade, .. } = event {
+            self.publish_tape(std::slice::from_ref(trade));
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+impl Subscriber for Sender {
+    fn id(&self) -> usize {
+        self.connection_id() as usize
+    }
+
+    fn send(&self, message: String) {
+        let _ = Sender::send(self, message);
+    }
+}
+
+// Sends every message as {"sent_at":<millis on the server's clock>,"message":<the message>}, for
+// connections that asked with `?timestamps=true`.
+#[cfg(feature = "server")]
+struct Stamped(Sender);
+
+#[cfg(feature = "server")]
+fn stamp(message: String) -> String {
+    format!("{{\"sent_at\":{},\"message\":{}}}", SystemClock.now(), message)
+}
+
+#[cfg(feature = "server")]
+impl Subscriber for Stamped {
+    fn id(&self) -> usize {
+        self.0.connection_id() as usize
+    }
+
+    fn send(&self, message: String) {
+        let _ = Sender::send(&self.0, stamp(message));
+    }
+}
+
+#[cfg(feature = "server")]
+fn query_param<'a>(resource: &'a str, name: &str) -> Option<&'a str> {
+    resource.split('?').nth(1)?
+        .split('&')
+        .find_map(|pair| pair.strip_prefix(name)?.strip_prefix('='))
+}
+
+#[cfg(feature = "server")]
+const OVERVIEW: Token = Token(1);
+#[cfg(feature = "server")]
+const OVERVIEW_INTERVAL_MILLIS: u64 = 1000;
+
+#[cfg(feature = "server")]
+struct Connection {
+    out: Sender,
+    game: Game,
+    topic: Option<Topic>,
+    stamped: bool,
+}
+
+// The console takes the admin token of the REST API, in the X-Admin-Token header or, as browsers
+// can't set it, in a `token` query parameter.
+#[cfg(feature = "server")]
+fn is_admin(shake: &Handshake) -> bool {
+    let expected = match std::env::var("ADMIN_TOKEN") {
+        Ok(expected) if !expected.is_empty() => expected,
+        _ => return false,
+    };
+    let header = shake.request.header("X-Admin-Token").and_then(|token| std::str::from_utf8(token).ok());
+    header.or_else(|| query_param(shake.request.resource(), "token")).map_or(false, |token| token == expected)
+}
+
+// A portfolio's channel takes one of its API tokens, in the X-Api-Token header or a `token` query
+// parameter.
+#[cfg(feature = "server")]
+fn api_token(shake: &Handshake) -> Option<&str> {
+    let header = shake.request.header("X-Api-Token").and_then(|token| std::str::from_utf8(token).ok());
+    header.or_else(|| query_param(shake.request.resource(), "token"))
+}
+
+#[cfg(feature = "server")]
+impl Connection {
+    fn authorize(&self, topic: Topic, shake: &Handshake) -> bool {
+        match topic {
+            Topic::Portfolio(id) => self.game.check_owner(id, api_token(shake)).is_ok(),
+            Topic::MarketData(asset, _) => self.game.read_engine()
+                .map_or(false, |engine| engine.market.get_order_book(asset).is_ok()),
+            Topic::Tape => true,
+            Topic::Console => is_admin(shake),
+        }
+    }
+
+    fn send(&self, message: &ConsoleMessage) {
+        if let Ok(message) = serde_json::to_string(message) {
+            let _ = match self.stamped {
+                true => self.out.send(stamp(message)),
+                false => self.out.send(message),
+            };
+        }
+    }
+
+    fn send_overview(&self) {
+        self.send(&ConsoleCommand::Overview.run(&self.game));
+    }
+}
+
+#[cfg(feature = "server")]
+impl Handler for Connection {
+    fn on_open(&mut self, shake: Handshake) -> ws::Result<()> {
+        match Topic::parse(shake.request.resource()) {
+            Some(topic) if self.authorize(topic, &shake) => {
+                self.stamped = query_param(shake.request.resource(), "timestamps") == Some("true");
+                let subscriber: Box<dyn Subscriber> = match self.stamped {
+                    true => Box::new(Stamped(self.out.clone())),
+                    false => Box::new(self.out.clone()),
+                };
+                self.game.stream.subscribe(topic, subscriber);
+                if let Topic::Portfolio(id) = topic {
+                    self.game.stream.owner_connected(id);
+                }
+                self.topic = Some(topic);
+                if topic == Topic::Console {
+                    self.send_overview();
+                    self.out.timeout(OVERVIEW_INTERVAL_MILLIS, OVERVIEW)?;
+                }
+                Ok(())
+            },
+            _ => self.out.close_with_reason(CloseCode::Policy, "unknown channel"),
+        }
+    }
+
+    // only the console takes messages, the other channels ignore them
+    fn on_message(&mut self, message: Message) -> ws::Result<()> {
+        if self.topic != Some(Topic::Console) {
+            return Ok(());
+        }
+        let reply = match serde_json::from_str::<ConsoleCommand>(message.as_text()?) {
+            Ok(command) => command.run(&self.game),
+            Err(e) => ConsoleMessage::Unreadable { problem: e.to_string() },
+        };
+        self.send(&reply);
+        Ok(())
+    }
+
+    fn on_timeout(&mut self, event: Token) -> ws::Result<()> {
+        if event != OVERVIEW {
+            return Ok(());
+        }
+        self.send_overview();
+        self.out.timeout(OVERVIEW_INTERVAL_MILLIS, OVERVIEW)
+    }
+
+    fn on_close(&mut self, _: CloseCode, _: &str) {
+        if let Some(topic) = self.topic {
+            self.game.stream.unsubscribe(topic, self.out.connection_id() as usize);
+            if let Topic::Portfolio(id) = topic {
+                self.game.stream.owner_disconnected(id);
+            }
+        }
+    }
+}
+
+// The console's commands run on the game as the admin.
+#[cfg(feature = "server")]
+pub fn listen(address: String, game: Game) {
+    let game = game.acting_as(Actor::Admin);
+    let result = ws::listen(address, |out| Connection {
+        out,
+        game: game.clone(),
+        topic: None,
+        stamped: false,
+    });
+    if let Err(e) = result {
+        println!("stream server stopped: {:?}", e);
+    }
+}
@@ -0,0 +1,77 @@
+use std::collections::HashSet;
+use crate::account::PortfolioId;
+use crate::actor::Actor;
+use crate::error::{EngineResult, ErrorType};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Suspension {
+    Portfolio(PortfolioId),
+    // an API token by its fingerprint, as `Actor::Token` holds it
+    Token(String),
+    // a client address, its requests are refused before they reach the game
+    Ip(String),
+}
+
+// Participants kept out of the game by an admin until they are reinstated.
+#[derive(Clone, Debug, Default)]
+pub struct Suspensions {
+    portfolios: HashSet<PortfolioId>,
+    tokens: HashSet<String>,
+    ips: HashSet<String>,
+}
+
+impl Suspensions {
+    pub fn new() -> Suspensions {
+        Suspensions {
+            portfolios: HashSet::new(),
+            tokens: HashSet::new(),
+            ips: HashSet::new(),
+        }
+    }
+
+    // false if it was suspended already
+    pub fn suspend(&mut self, suspension: Suspension) -> bool {
+        match suspension {
+            Suspension::Portfolio(id) => self.portfolios.insert(id),
+            Suspension::Token(fingerprint) => self.tokens.insert(fingerprint),
+            Suspension::Ip(ip) => self.ips.insert(ip),
+        }
+    }
+
+    // false if it wasn't suspended
+    pub fn reinstate(&mut self, suspension: &Suspension) -> bool {
+        match suspension {
+            Suspension::Portfolio(id) => self.portfolios.remove(id),
+            Suspension::Token(fingerprint) => self.tokens.remove(fingerprint),
+            Suspension::Ip(ip) => self.ips.remove(ip),
+        }
+    }
+
+    pub fn check(&self, portfolio_id: PortfolioId, actor: &Actor) -> EngineResult<()> {
+        let revoked = match actor {
+            Actor::Token(fingerprint) => self.tokens.contains(fingerprint),
+            _ => false,
+        };
+        if revoked || self.portfolios.contains(&portfolio_id) {
+            return Err(ErrorType::Suspended);
+        }
+        Ok(())
+    }
+
+    pub fn is_ip_banned(&self, ip: &str) -> bool {
+        self.ips.contains(ip)
+    }
+
+    pub fn list(&self) -> Vec<Suspension> {
+        let mut portfolios: Vec<PortfolioId> = self.portfolios.iter().cloned().collect();
+        portfolios.sort();
+        let mut tokens: Vec<String> = self.tokens.iter().cloned().collect();
+        tokens.sort();
+        let mut ips: Vec<String> = self.ips.iter().cloned().collect();
+        ips.sort();
+        portfolios.into_iter().map(Suspension::Portfolio)
+            .chain(tokens.into_iter().map(Suspension::Token))
+            .chain(ips.into_iter().map(Suspension::Ip))
+            .collect()
+    }
+}
@@ -0,0 +1,93 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::error::{EngineResult, ErrorType};
+use crate::market::AssetId;
+use crate::order::OrderSide;
+use crate::page::Listed;
+use crate::providers::Timestamp;
+use crate::trades::Trade;
+
+// a page of the tape never holds more, whatever the limit asked for
+pub const MAX_TAPE_PAGE: usize = 1000;
+pub const DEFAULT_TAPE_PAGE: usize = 100;
+
+// A trade as everyone may see it, without the portfolios and orders behind it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TapePrint {
+    pub id: u64,
+    pub tick: u64,
+    pub timestamp: Timestamp,
+    pub asset: AssetId,
+    pub price: usize,
+    pub quantity: usize,
+    pub aggressor: OrderSide,
+    pub busted: bool,
+}
+
+impl From<&Trade> for TapePrint {
+    fn from(trade: &Trade) -> TapePrint {
+        TapePrint {
+            id: trade.id,
+            tick: trade.tick,
+            timestamp: trade.timestamp,
+            asset: trade.asset,
+            price: trade.price,
+            quantity: trade.quantity,
+            aggressor: trade.aggressor,
+            busted: trade.busted,
+        }
+    }
+}
+
+impl Listed for TapePrint {
+    fn cursor(&self) -> String {
+        format!("{:020}", self.id)
+    }
+
+    fn side(&self) -> Option<OrderSide> {
+        Some(self.aggressor)
+    }
+
+    fn price(&self) -> Option<usize> {
+        Some(self.price)
+    }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        Some(self.timestamp)
+    }
+}
+
+// At most `max_requests` per client within any `window`.
+pub struct RateLimiter {
+    max_requests: usize,
+    window: Duration,
+    requests: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl RateLimiter {
+    pub fn new(max_requests: usize, window: Duration) -> RateLimiter {
+        RateLimiter {
+            max_requests,
+            window,
+            requests: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, client: &str, now: Instant) -> EngineResult<()> {
+        let mut requests = self.requests.lock().map_err(|_| ErrorType::EngineWasTooBusy)?;
+        // clients that went quiet are forgotten
+        let window = self.window;
+        requests.retain(|_, times| times.back().is_some_and(|last| now.duration_since(*last) < window));
+        let times = requests.entry(client.to_string()).or_default();
+        while times.front().is_some_and(|first| now.duration_since(*first) >= window) {
+            times.pop_front();
+        }
+        if times.len() >= self.max_requests {
+            let retry_after = times.front().map_or(window, |first| window - now.duration_since(*first));
+            return Err(ErrorType::RateLimited { retry_after_millis: retry_after.as_millis() as u64 });
+        }
+        times.push_back(now);
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+use crate::providers::{Clock, SystemClock, Timestamp};
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::request::{self, FromRequest};
+use rocket::{Data, Outcome, Request, Response};
+
+// When the server got the request, in milliseconds on its clock.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ReceivedAt(pub Timestamp);
+
+// Stamps every request as it comes in and answers with the stamp in X-Received-At, so bots can tell
+// how far their clock is off and how long their requests take to arrive.
+pub struct ReceiveTimestamps;
+
+impl Fairing for ReceiveTimestamps {
+    fn info(&self) -> Info {
+        Info {
+            name: "receive timestamps",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    fn on_request(&self, request: &mut Request, _: &Data) {
+        request.local_cache(|| ReceivedAt(SystemClock.now()));
+    }
+
+    fn on_response(&self, request: &Request, response: &mut Response) {
+        let received_at = request.local_cache(|| ReceivedAt(SystemClock.now()));
+        response.set_raw_header("X-Received-At", received_at.0.to_string());
+    }
+}
+
+impl<'a, 'r> FromRequest<'a, 'r> for ReceivedAt {
+    type Error = ();
+
+    fn from_request(request: &'a Request<'r>) -> request::Outcome<ReceivedAt, ()> {
+        Outcome::Success(*request.local_cache(|| ReceivedAt(SystemClock.now())))
+    }
+}
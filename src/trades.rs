@@ -0,0 +1,67 @@
+use crate::order::{OrderId, OrderSide};
+use crate::account::PortfolioId;
+use crate::market::AssetId;
+use crate::providers::Timestamp;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Trade {
+    pub id: u64,
+    pub tick: u64,
+    pub timestamp: Timestamp,
+    pub asset: AssetId,
+    pub price: usize,
+    pub quantity: usize,
+    pub buyer: PortfolioId,
+    pub seller: PortfolioId,
+    pub buy_order: OrderId,
+    pub sell_order: OrderId,
+    // side of the incoming order that took liquidity
+    pub aggressor: OrderSide,
+    #[serde(default)]
+    pub busted: bool,
+}
+
+// One resting order an incoming order traded against.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionLeg {
+    pub trade_id: u64,
+    pub price: usize,
+    pub quantity: usize,
+}
+
+// All an incoming order took from the book, however many resting orders it crossed.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    pub order_id: OrderId,
+    pub asset: AssetId,
+    pub side: OrderSide,
+    pub quantity: usize,
+    // volume weighted, rounded to the nearest tick
+    pub average_price: usize,
+    pub legs: Vec<ExecutionLeg>,
+}
+
+impl ExecutionReport {
+    // The trades among `trades` in which the order was the aggressor, none if it took nothing.
+    // Busted trades don't count.
+    pub fn for_order(order_id: OrderId, trades: &[Trade]) -> Option<ExecutionReport> {
+        let taken: Vec<&Trade> = trades.iter()
+            .filter(|t| !t.busted && match t.aggressor {
+                OrderSide::Buy => t.buy_order == order_id,
+                OrderSide::Sell => t.sell_order == order_id,
+            })
+            .collect();
+        let first = taken.first()?;
+        let quantity: usize = taken.iter().map(|t| t.quantity).sum();
+        let volume: u128 = taken.iter().map(|t| t.price as u128 * t.quantity as u128).sum();
+        let average_price = if quantity == 0 { 0 } else { ((volume + quantity as u128 / 2) / quantity as u128) as usize };
+        Some(ExecutionReport {
+            order_id,
+            asset: first.asset,
+            side: first.aggressor,
+            quantity,
+            average_price,
+            legs: taken.iter().map(|t| ExecutionLeg { trade_id: t.id, price: t.price, quantity: t.quantity }).collect(),
+        })
+    }
+}
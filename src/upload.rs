@@ -0,0 +1,147 @@
+use crate::market::AssetId;
+use crate::order::{OrderMode, OrderSide};
+use crate::validation::FieldError;
+
+// of the rows of one upload, no more are taken
+pub const MAX_UPLOAD_ROWS: usize = 100;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct UploadRow {
+    // in the file, counting from 1
+    pub line: usize,
+    pub asset: AssetId,
+    pub side: OrderSide,
+    pub quantity: usize,
+    pub mode: OrderMode,
+}
+
+fn row_error(line: usize, column: &str, problem: &str) -> FieldError {
+    FieldError::new(&format!("lines[{}].{}", line, column), problem)
+}
+
+// the columns a header row names, in this order
+const HEADER: [&str; 4] = ["asset", "side", "quantity", "limit"];
+
+fn is_header(fields: &[&str]) -> bool {
+    (fields.len() == 3 || fields.len() == 4)
+        && fields.iter().zip(HEADER.iter()).all(|(field, column)| field.eq_ignore_ascii_case(column))
+}
+
+// Rows of `asset,side,quantity,limit` as spreadsheets save them, side being buy or sell and an empty
+// limit making a Best order. Semicolons separate the columns too, quotes around a field are dropped,
+// and blank lines and a first row naming exactly those columns are skipped. `asset` looks the names up.
+// Every row is checked, the problems of all of them come back together.
+pub fn parse_order_csv(csv: &str, asset: impl Fn(&str) -> Option<AssetId>) -> Result<Vec<UploadRow>, Vec<FieldError>> {
+    let mut rows = vec![];
+    let mut errors = vec![];
+    let mut first = true;
+    for (i, line) in csv.trim_start_matches('\u{feff}').lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let separator = if line.contains(',') { ',' } else { ';' };
+        let fields: Vec<&str> = line.split(separator).map(|f| f.trim().trim_matches('"').trim()).collect();
+        if std::mem::replace(&mut first, false) && is_header(&fields) {
+            continue;
+        }
+        let (name, side, quantity, limit) = match fields.as_slice() {
+            [name, side, quantity] => (*name, *side, *quantity, ""),
+            [name, side, quantity, limit] => (*name, *side, *quantity, *limit),
+            _ => {
+                errors.push(FieldError::new(&format!("lines[{}]", number), "needs asset, side, quantity and limit"));
+                continue;
+            },
+        };
+        let asset_id = asset(name);
+        if asset_id.is_none() {
+            errors.push(row_error(number, "asset", "no asset goes by that name"));
+        }
+        let side = match side.to_lowercase().as_str() {
+            "buy" => Some(OrderSide::Buy),
+            "sell" => Some(OrderSide::Sell),
+            _ => None,
+        };
+        if side.is_none() {
+            errors.push(row_error(number, "side", "must be buy or sell"));
+        }
+        let quantity = quantity.parse::<usize>().ok().filter(|q| *q > 0);
+        if quantity.is_none() {
+            errors.push(row_error(number, "quantity", "must be a whole number above 0"));
+        }
+        let mode = match limit {
+            "" => Some(OrderMode::Best),
+            limit => limit.parse::<usize>().ok().filter(|l| *l > 0).map(OrderMode::Limit),
+        };
+        if mode.is_none() {
+            errors.push(row_error(number, "limit", "must be a whole number above 0, or empty for a Best order"));
+        }
+        if let (Some(asset), Some(side), Some(quantity), Some(mode)) = (asset_id, side, quantity, mode) {
+            rows.push(UploadRow { line: number, asset, side, quantity, mode });
+        }
+    }
+    if rows.is_empty() && errors.is_empty() {
+        errors.push(FieldError::new("lines", "needs at least one order"));
+    }
+    if rows.len() > MAX_UPLOAD_ROWS {
+        errors.push(FieldError::new("lines", &format!("can't be more than {} orders", MAX_UPLOAD_ROWS)));
+    }
+    match errors.is_empty() {
+        true => Ok(rows),
+        false => Err(errors),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    fn asset_id() -> AssetId {
+        Uuid::nil()
+    }
+
+    fn parse(csv: &str) -> Result<Vec<UploadRow>, Vec<FieldError>> {
+        parse_order_csv(csv, |name| if name == "gold" { Some(asset_id()) } else { None })
+    }
+
+    fn fields(errors: Vec<FieldError>) -> Vec<String> {
+        errors.into_iter().filter_map(|e| e.field).collect()
+    }
+
+    #[test]
+    fn skips_only_an_exact_header() {
+        let rows = parse("Asset,Side,Quantity,Limit\ngold,buy,2,10").unwrap();
+        assert_eq!(rows, vec![UploadRow { line: 2, asset: asset_id(), side: OrderSide::Buy, quantity: 2, mode: OrderMode::Limit(10) }]);
+        assert!(parse("asset;side;quantity\ngold;sell;1").is_ok());
+        // an asset named like a column is an order, not a header
+        let errors = parse_order_csv("assets,buy,2,10", |_| None).unwrap_err();
+        assert_eq!(fields(errors), vec!["lines[1].asset"]);
+        // only the first row can be one
+        assert_eq!(fields(parse("gold,buy,1,5\nasset,side,quantity,limit").unwrap_err()),
+                   vec!["lines[2].asset", "lines[2].side", "lines[2].quantity", "lines[2].limit"]);
+    }
+
+    #[test]
+    fn takes_semicolons_and_quotes() {
+        let rows = parse("\u{feff}\"gold\";\"sell\";\"3\";\n\n gold , BUY , 4 , 7 ").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!((rows[0].line, rows[0].side, rows[0].mode), (1, OrderSide::Sell, OrderMode::Best));
+        assert_eq!((rows[1].line, rows[1].quantity, rows[1].mode), (3, 4, OrderMode::Limit(7)));
+    }
+
+    #[test]
+    fn reports_every_row() {
+        let errors = parse("gold,hold,0,5\nsilver,buy,1,-1\ngold,buy").unwrap_err();
+        assert_eq!(fields(errors), vec!["lines[1].side", "lines[1].quantity", "lines[2].asset", "lines[2].limit", "lines[3]"]);
+        assert_eq!(fields(parse("asset,side,quantity,limit\n\n").unwrap_err()), vec!["lines"]);
+    }
+
+    #[test]
+    fn caps_the_rows() {
+        let csv = |count: usize| vec!["gold,buy,1,5"; count].join("\n");
+        assert_eq!(parse(&csv(MAX_UPLOAD_ROWS)).unwrap().len(), MAX_UPLOAD_ROWS);
+        assert_eq!(fields(parse(&csv(MAX_UPLOAD_ROWS + 1)).unwrap_err()), vec!["lines"]);
+    }
+}
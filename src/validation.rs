@@ -0,0 +1,65 @@
+use crate::order::OrderMode;
+
+// One thing wrong with a request body. `field` is its path in the body, none where serde couldn't
+// tell which field it choked on.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct FieldError {
+    pub field: Option<String>,
+    pub problem: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, problem: &str) -> FieldError {
+        FieldError {
+            field: Some(field.to_string()),
+            problem: problem.to_string(),
+        }
+    }
+
+    // serde stops at the first problem and only names the field of unknown and missing ones
+    pub fn from_serde(error: &serde_json::Error) -> FieldError {
+        let problem = error.to_string();
+        let field = ["unknown field `", "missing field `"].iter()
+            .find_map(|prefix| problem.strip_prefix(prefix))
+            .and_then(|rest| rest.split('`').next())
+            .map(str::to_string);
+        FieldError { field, problem }
+    }
+}
+
+// Range checks of a body that parsed, all that's wrong with it at once.
+pub trait Validate {
+    fn invalid_fields(&self) -> Vec<FieldError>;
+}
+
+// an optional body that was left out is fine
+impl<T: Validate> Validate for Option<T> {
+    fn invalid_fields(&self) -> Vec<FieldError> {
+        self.as_ref().map_or(vec![], T::invalid_fields)
+    }
+}
+
+pub fn check(ok: bool, field: &str, problem: &str) -> Option<FieldError> {
+    match ok {
+        true => None,
+        false => Some(FieldError::new(field, problem)),
+    }
+}
+
+pub fn check_positive(value: usize, field: &str) -> Option<FieldError> {
+    check(value > 0, field, "must be above 0")
+}
+
+pub fn check_mode(mode: OrderMode, field: &str) -> Option<FieldError> {
+    check(mode != OrderMode::Limit(0), field, "the limit must be above 0")
+}
+
+// the problems of a part of the body, e.g. "legs[1]", with their fields under it
+pub fn nested(prefix: &str, errors: Vec<FieldError>) -> Vec<FieldError> {
+    errors.into_iter()
+        .map(|error| FieldError {
+            field: Some(error.field.map_or(prefix.to_string(), |field| format!("{}.{}", prefix, field))),
+            problem: error.problem,
+        })
+        .collect()
+}
@@ -0,0 +1,79 @@
+use crate::dto::DTO_VERSION;
+use crate::error::ErrorType;
+use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::{ContentType, Status};
+use rocket::http::uri::Origin;
+use rocket::{Data, Request, Response};
+use std::io::Cursor;
+
+// versions of the wire format the server speaks, the newest last
+pub const SUPPORTED_VERSIONS: [u32; 1] = [DTO_VERSION];
+
+// The version a request is served in. `/v<n>/...` picks it by path; the unprefixed routes are the
+// deprecated aliases of the newest version and pick it with the X-Api-Version header instead.
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Negotiated {
+    version: u32,
+    legacy: bool,
+}
+
+// `/v1/book` and `/v1` are version 1, anything else has no prefix
+fn prefix_version(path: &str) -> Option<u32> {
+    let segment = path.trim_start_matches('/').split('/').next()?;
+    segment.strip_prefix('v')?.parse().ok()
+}
+
+fn latest() -> u32 {
+    SUPPORTED_VERSIONS[SUPPORTED_VERSIONS.len() - 1]
+}
+
+// Answers with the negotiated version in X-Api-Version and marks the unprefixed routes deprecated,
+// pointing at their successor. Unsupported versions get 406 without the request reaching a route.
+pub struct ApiVersioning;
+
+impl Fairing for ApiVersioning {
+    fn info(&self) -> Info {
+        Info {
+            name: "API versioning",
+            kind: Kind::Request | Kind::Response,
+        }
+    }
+
+    fn on_request(&self, request: &mut Request, _: &Data) {
+        let path = request.uri().path().to_string();
+        let negotiated = match prefix_version(&path) {
+            Some(version) => Negotiated { version, legacy: false },
+            None => {
+                // a header that doesn't parse asks for version 0, which nobody speaks
+                let version = request.headers().get_one("X-Api-Version")
+                    .map_or(latest(), |v| v.trim().trim_start_matches('v').parse().unwrap_or(0));
+                Negotiated { version, legacy: true }
+            },
+        };
+        request.local_cache(|| negotiated);
+        if negotiated.legacy && !SUPPORTED_VERSIONS.contains(&negotiated.version) {
+            // send it where no route is mounted, `on_response` turns the 404 into a 406
+            let query = request.uri().query().map(|q| format!("?{}", q)).unwrap_or_default();
+            if let Ok(uri) = Origin::parse_owned(format!("/v{}{}{}", negotiated.version, path, query)) {
+                request.set_uri(uri);
+            }
+        }
+    }
+
+    fn on_response(&self, request: &Request, response: &mut Response) {
+        let negotiated = request.local_cache(|| Negotiated { version: latest(), legacy: true });
+        if !SUPPORTED_VERSIONS.contains(&negotiated.version) {
+            let error = ErrorType::UnsupportedApiVersion { requested: negotiated.version, supported: SUPPORTED_VERSIONS.to_vec() };
+            response.set_status(Status::NotAcceptable);
+            response.set_header(ContentType::new("text", "text"));
+            response.set_sized_body(Cursor::new(format!("{:?}", error)));
+            return;
+        }
+        response.set_raw_header("X-Api-Version", negotiated.version.to_string());
+        if negotiated.legacy {
+            response.set_raw_header("Deprecation", "true");
+            let successor = format!("</v{}{}>; rel=\"successor-version\"", negotiated.version, request.uri().path());
+            response.set_raw_header("Link", successor);
+        }
+    }
+}
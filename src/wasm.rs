@@ -0,0 +1,97 @@
+use crate::game::Game;
+use crate::marketdata::{Level, MarketData};
+use crate::dto::PortfolioDto;
+use crate::error::ErrorType;
+use crate::order::{Event, OrderMode, OrderSide};
+use crate::market::Market;
+use crate::engine::Engine;
+use crate::providers::{ManualClock, SequentialIds};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+use wasm_bindgen::prelude::*;
+
+fn to_js(error: ErrorType) -> JsValue {
+    JsValue::from_str(&format!("{:?}", error))
+}
+
+fn parse_id(id: &str) -> Result<Uuid, JsValue> {
+    Uuid::parse_str(id).map_err(|_| JsValue::from_str("InvalidId"))
+}
+
+// Single-player game running entirely in the browser; the page drives the clock.
+#[wasm_bindgen]
+pub struct WasmGame {
+    game: Game,
+    clock: Arc<ManualClock>,
+}
+
+#[wasm_bindgen]
+impl WasmGame {
+    #[wasm_bindgen(constructor)]
+    pub fn new(now: f64) -> WasmGame {
+        let clock = Arc::new(ManualClock::new(now as u64));
+        let engine = Engine::with_providers(Market::new(), Box::new(clock.clone()), Box::new(SequentialIds::new()));
+        WasmGame {
+            game: Game::from_engine(engine),
+            clock,
+        }
+    }
+
+    pub fn set_time(&self, now: f64) {
+        self.clock.set(now as u64);
+    }
+
+    pub fn create_portfolio(&mut self, initial_coins: u32) -> String {
+        self.game.create_portfolio(initial_coins as usize).to_string()
+    }
+
+    pub fn create_asset(&mut self, name: String) -> Result<String, JsValue> {
+        self.game.create_asset(name).map(|id| id.to_string()).map_err(to_js)
+    }
+
+    pub fn set_asset_amount(&mut self, portfolio: &str, asset: &str, amount: u32) -> Result<(), JsValue> {
+        let (portfolio, asset) = (parse_id(portfolio)?, parse_id(asset)?);
+        self.game.set_asset_amount(portfolio, asset, amount as usize);
+        Ok(())
+    }
+
+    // Places an order and returns its id; a missing limit places a Best order.
+    pub fn place_order(&self, portfolio: &str, asset: &str, buy: bool, quantity: u32, limit: Option<u32>) -> Result<String, JsValue> {
+        let side = if buy { OrderSide::Buy } else { OrderSide::Sell };
+        let mode = match limit {
+            Some(limit) => OrderMode::Limit(limit as usize),
+            None => OrderMode::Best,
+        };
+        let order = self.game.new_order(
+            parse_id(portfolio)?,
+            parse_id(asset)?,
+            side,
+            quantity as usize,
+            mode,
+            Duration::from_secs(24*60*60),
+        ).map_err(to_js)?;
+        self.game.process(Event::Order(order)).map_err(to_js)?;
+        Ok(order.get_id().to_string())
+    }
+
+    pub fn cancel_order(&self, portfolio: &str, asset: &str, order: &str) -> Result<(), JsValue> {
+        let event = Event::CancelOrder(parse_id(portfolio)?, parse_id(order)?, parse_id(asset)?);
+        self.game.process(event).map_err(to_js)
+    }
+
+    // JSON of the aggregated (L2) book
+    pub fn get_book(&self, asset: &str) -> Result<String, JsValue> {
+        let data: MarketData = self.game.get_market_data(parse_id(asset)?, Level::L2).map_err(to_js)?.data;
+        serde_json::to_string(&data).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+
+    // JSON of the portfolio with its accounts and reservations
+    pub fn get_portfolio(&self, portfolio: &str) -> Result<String, JsValue> {
+        let engine = self.game.read_engine().map_err(to_js)?;
+        let id = parse_id(portfolio)?;
+        let portfolio = engine.market.get_portfolio(id).map_err(to_js)?;
+        let portfolio = PortfolioDto::new(portfolio, engine.market.unrealized_pnl(id).map_err(to_js)?);
+        serde_json::to_string(&portfolio).map_err(|e| JsValue::from_str(&e.to_string()))
+    }
+}
@@ -0,0 +1,83 @@
+use crate::account::PortfolioId;
+use crate::market::{AssetId, Market, Notification};
+use crate::marketdata;
+use std::collections::HashMap;
+
+// the top of the book and the last trade, all a thin client shows per asset
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchQuote {
+    pub asset: AssetId,
+    pub name: String,
+    pub best_bid: Option<usize>,
+    pub best_ask: Option<usize>,
+    pub last_price: Option<usize>,
+}
+
+// none for assets that are gone, like delisted ones
+pub fn quote(market: &Market, asset_id: AssetId) -> Option<WatchQuote> {
+    let name = market.get_asset(&asset_id).ok()?.name.clone();
+    let l1 = marketdata::l1(market.get_order_book(asset_id).ok()?);
+    Some(WatchQuote {
+        asset: asset_id,
+        name,
+        best_bid: l1.best_bid.map(|level| level.price),
+        best_ask: l1.best_ask.map(|level| level.price),
+        last_price: market.last_trade_prices.get(&asset_id).cloned(),
+    })
+}
+
+// Assets per portfolio whose quotes go out on the portfolio's private channel.
+#[derive(Clone, Debug, Default)]
+pub struct Watchlists {
+    portfolios: HashMap<PortfolioId, Vec<AssetId>>,
+}
+
+impl Watchlists {
+    pub fn new() -> Watchlists {
+        Watchlists {
+            portfolios: HashMap::new(),
+        }
+    }
+
+    // replaces the whole list in the given order, an empty one stops the quotes
+    pub fn set(&mut self, portfolio_id: PortfolioId, assets: Vec<AssetId>) {
+        let mut unique = vec![];
+        for asset in assets {
+            if !unique.contains(&asset) {
+                unique.push(asset);
+            }
+        }
+        if unique.is_empty() {
+            self.portfolios.remove(&portfolio_id);
+        } else {
+            self.portfolios.insert(portfolio_id, unique);
+        }
+    }
+
+    pub fn get(&self, portfolio_id: PortfolioId) -> Vec<AssetId> {
+        self.portfolios.get(&portfolio_id).cloned().unwrap_or_default()
+    }
+
+    pub fn quotes(&self, market: &Market, portfolio_id: PortfolioId) -> Vec<WatchQuote> {
+        self.get(portfolio_id).into_iter()
+            .filter_map(|asset| quote(market, asset))
+            .collect()
+    }
+
+    // a quote for every portfolio watching one of the changed assets
+    pub fn notifications(&self, market: &Market, changed: &[AssetId]) -> Vec<(PortfolioId, Notification)> {
+        let mut notifications = vec![];
+        for asset in changed {
+            let quote = match quote(market, *asset) {
+                Some(quote) => quote,
+                None => continue,
+            };
+            for (portfolio, assets) in &self.portfolios {
+                if assets.contains(asset) {
+                    notifications.push((*portfolio, Notification::Quote(quote.clone())));
+                }
+            }
+        }
+        notifications
+    }
+}
@@ -0,0 +1,238 @@
+use crate::actor::Actor;
+use crate::error::{EngineResult, ErrorType};
+use crate::game::Game;
+use crate::bus::{BusConsumer, DomainEvent, DomainEventKind, EventBus};
+use crate::stream::GameEvent;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const FIRST_RETRY_DELAY: Duration = Duration::from_millis(500);
+const SETTLEMENT_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+fn default_max_per_minute() -> usize {
+    30
+}
+
+fn default_retries() -> usize {
+    3
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    // plain http only, https endpoints like Discord need a relay that terminates TLS
+    pub url: String,
+    // trades below this many coins aren't posted, no trades at all without it
+    #[serde(default)]
+    pub big_trade_notional: Option<usize>,
+    // messages beyond this are dropped, chat services throttle anything faster
+    #[serde(default = "default_max_per_minute")]
+    pub max_per_minute: usize,
+    #[serde(default = "default_retries")]
+    pub retries: usize,
+}
+
+impl WebhookConfig {
+    pub fn new(url: String) -> WebhookConfig {
+        WebhookConfig {
+            url,
+            big_trade_notional: None,
+            max_per_minute: default_max_per_minute(),
+            retries: default_retries(),
+        }
+    }
+}
+
+// `content` is what Discord shows, the event is there for everyone else
+#[derive(Serialize)]
+struct Payload<'a> {
+    content: String,
+    event: &'a GameEvent,
+}
+
+struct Target {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl Target {
+    fn parse(url: &str) -> EngineResult<Target> {
+        let invalid = || ErrorType::InvalidWebhookUrl(url.to_string());
+        let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rfind(':') {
+            Some(i) => (&authority[..i], authority[i + 1..].parse().map_err(|_| invalid())?),
+            None => (authority, 80),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        Ok(Target { host: host.to_string(), port, path: path.to_string() })
+    }
+
+    // true for a 2xx answer
+    fn post(&self, body: &str) -> bool {
+        self.status(body).map_or(false, |status| (200..300).contains(&status))
+    }
+
+    // none without an answer
+    fn status(&self, body: &str) -> Option<u16> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).ok()?;
+        let _ = stream.set_read_timeout(Some(TIMEOUT));
+        let _ = stream.set_write_timeout(Some(TIMEOUT));
+        let request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.path, self.host, body.len(), body);
+        stream.write_all(request.as_bytes()).ok()?;
+        let mut status_line = [0; 12];
+        stream.read_exact(&mut status_line).ok()?;
+        // "HTTP/1.1 204"
+        std::str::from_utf8(&status_line[9..]).ok()?.parse().ok()
+    }
+}
+
+struct Webhook {
+    config: WebhookConfig,
+    events: Sender<GameEvent>,
+}
+
+impl BusConsumer for Webhook {
+    fn wants(&self, kind: DomainEventKind) -> bool {
+        match kind {
+            DomainEventKind::TradeExecuted => self.config.big_trade_notional.is_some(),
+            DomainEventKind::Game => true,
+            _ => false,
+        }
+    }
+
+    fn consume(&self, event: &DomainEvent) {
+        let event = match event {
+            DomainEvent::TradeExecuted { trade, notional } if self.config.big_trade_notional.map_or(false, |min| *notional >= min) =>
+                GameEvent::Trade { asset: trade.asset, price: trade.price, quantity: trade.quantity, notional: *notional },
+            DomainEvent::Game(event) => event.clone(),
+            _ => return,
+        };
+        let _ = self.events.send(event);
+    }
+}
+
+// Posts off the engine's threads, so a slow endpoint only ever delays its own messages.
+// Stops once the webhook is removed and its sender dropped.
+fn dispatch(target: Target, config: WebhookConfig, events: Receiver<GameEvent>) {
+    let mut sent: VecDeque<Instant> = VecDeque::new();
+    while let Ok(event) = events.recv() {
+        while sent.front().map_or(false, |t| t.elapsed() >= Duration::from_secs(60)) {
+            sent.pop_front();
+        }
+        if sent.len() >= config.max_per_minute {
+            continue;
+        }
+        sent.push_back(Instant::now());
+        let body = match serde_json::to_string(&Payload { content: event.summary(), event: &event }) {
+            Ok(body) => body,
+            Err(_) => continue,
+        };
+        let mut delay = FIRST_RETRY_DELAY;
+        for attempt in 0..=config.retries {
+            if target.post(&body) {
+                break;
+            }
+            if attempt < config.retries {
+                thread::sleep(delay);
+                delay *= 2;
+            }
+        }
+    }
+}
+
+pub fn check_url(url: &str) -> EngineResult<()> {
+    Target::parse(url).map(|_| ())
+}
+
+// Posts every pending trade to the settlement policy's url once, a 2xx answer confirms it and a 4xx
+// rejects it. Anything else leaves it pending until it's confirmed through the API or times out.
+pub fn dispatch_settlements(game: Game) {
+    let game = game.acting_as(Actor::bot("settlement"));
+    thread::spawn(move || {
+        let mut posted: HashSet<u64> = HashSet::new();
+        loop {
+            thread::sleep(SETTLEMENT_POLL_INTERVAL);
+            let (policy, pending) = match (game.get_settlement_policy(), game.get_pending_settlements()) {
+                (Ok(Some(policy)), Ok(pending)) => (policy, pending),
+                _ => continue,
+            };
+            posted.retain(|trade| pending.iter().any(|p| p.trade.id == *trade));
+            let target = match policy.url.as_deref().map(Target::parse) {
+                Some(Ok(target)) => target,
+                _ => continue,
+            };
+            for settlement in pending {
+                let trade = settlement.trade.id;
+                if !posted.insert(trade) {
+                    continue;
+                }
+                let body = match serde_json::to_string(&settlement) {
+                    Ok(body) => body,
+                    Err(_) => continue,
+                };
+                let _ = match target.status(&body) {
+                    Some(200..=299) => game.confirm_settlement(trade),
+                    Some(400..=499) => game.reject_settlement(trade),
+                    _ => Ok(()),
+                };
+            }
+        }
+    });
+}
+
+pub struct Webhooks {
+    bus: Arc<EventBus>,
+    // by webhook id, with the id it has on the bus
+    configs: Mutex<HashMap<usize, (WebhookConfig, usize)>>,
+    next_id: AtomicUsize,
+}
+
+impl Webhooks {
+    pub fn new(bus: Arc<EventBus>) -> Webhooks {
+        Webhooks {
+            bus,
+            configs: Mutex::new(HashMap::new()),
+            next_id: AtomicUsize::new(1),
+        }
+    }
+
+    pub fn add(&self, config: WebhookConfig) -> EngineResult<usize> {
+        let target = Target::parse(&config.url)?;
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (sender, receiver) = mpsc::channel();
+        let dispatcher_config = config.clone();
+        thread::spawn(move || dispatch(target, dispatcher_config, receiver));
+        let consumer = self.bus.subscribe(Box::new(Webhook { config: config.clone(), events: sender }))?;
+        self.configs.lock().map_err(|_| ErrorType::InvalidState)?.insert(id, (config, consumer));
+        Ok(id)
+    }
+
+    pub fn remove(&self, id: usize) -> EngineResult<()> {
+        let (_, consumer) = self.configs.lock().map_err(|_| ErrorType::InvalidState)?
+            .remove(&id)
+            .ok_or(ErrorType::WebhookNotFound(id))?;
+        self.bus.unsubscribe(consumer)
+    }
+
+    pub fn get_all(&self) -> EngineResult<Vec<(usize, WebhookConfig)>> {
+        let configs = self.configs.lock().map_err(|_| ErrorType::InvalidState)?;
+        let mut all: Vec<(usize, WebhookConfig)> = configs.iter().map(|(id, (c, _))| (*id, c.clone())).collect();
+        all.sort_by_key(|(id, _)| *id);
+        Ok(all)
+    }
+}
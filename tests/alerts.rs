@@ -0,0 +1,24 @@
+use market_game::alerts::AlertRule;
+use market_game::error::ErrorType;
+use market_game::game::Game;
+use market_game::journal::JournalRecord;
+
+#[test]
+fn alerts_are_journaled_and_restored() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    let kept = game.add_alert(portfolio, AlertRule::PortfolioValueBelow { value: 500 }).unwrap();
+    let removed = game.add_alert(portfolio, AlertRule::OrderRejections { count: 3 }).unwrap();
+    game.remove_alert(portfolio, removed).unwrap();
+    assert!(matches!(game.remove_alert(portfolio, removed), Err(ErrorType::AlertNotFound(id)) if id == removed));
+
+    let mut engine = game.write_engine().unwrap();
+    let journaled = engine.journal.iter()
+        .filter(|e| matches!(e.record, JournalRecord::AddAlert { .. } | JournalRecord::RemoveAlert { .. }))
+        .count();
+    // the rejected removal too, replaying rejects it again
+    assert_eq!(journaled, 4);
+    engine.restore_from_journal().unwrap();
+    let rules: Vec<_> = engine.alerts.get_rules(portfolio).into_iter().map(|a| a.id).collect();
+    assert_eq!(rules, vec![kept]);
+}
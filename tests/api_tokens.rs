@@ -0,0 +1,65 @@
+use market_game::game::Game;
+use market_game::account::PortfolioId;
+use market_game::actor::{self, Actor};
+use market_game::error::{EngineResult, ErrorType};
+use market_game::market::AssetId;
+use market_game::order::{Event, OrderMode, OrderSide};
+use market_game::suspension::Suspension;
+use std::time::Duration;
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId) -> EngineResult<()> {
+    let order = game.new_order(portfolio, asset, OrderSide::Buy, 1, OrderMode::Limit(10), Duration::from_secs(60))?;
+    game.process(Event::Order(order))
+}
+
+#[test]
+fn fingerprint_is_stable() {
+    assert_eq!(actor::fingerprint(""), "cbf29ce484222325");
+    assert_eq!(actor::fingerprint("a"), "af63dc4c8601ec8c");
+}
+
+#[test]
+fn portfolio_with_a_token_only_takes_its_token() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    let other = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let issued = game.issue_token(portfolio).unwrap();
+    let foreign = game.issue_token(other).unwrap();
+    assert!(game.is_token_issued(&issued.token).unwrap());
+    assert!(!game.is_token_issued("made-up").unwrap());
+
+    let anonymous = game.acting_as(Actor::Anonymous);
+    assert!(matches!(place(&anonymous, portfolio, asset), Err(ErrorType::TokenRequired)));
+    let swapped = game.acting_as(Actor::token(&foreign.token));
+    assert!(matches!(place(&swapped, portfolio, asset), Err(ErrorType::TokenRequired)));
+    let player = game.acting_as(Actor::token(&issued.token));
+    place(&player, portfolio, asset).unwrap();
+
+    // omitting the header doesn't get around the suspension anymore
+    game.suspend(Suspension::Token(issued.fingerprint.clone())).unwrap();
+    assert!(matches!(place(&player, portfolio, asset), Err(ErrorType::Suspended)));
+    assert!(matches!(place(&anonymous, portfolio, asset), Err(ErrorType::TokenRequired)));
+
+    game.revoke_token(foreign.fingerprint).unwrap();
+    place(&anonymous, other, asset).unwrap();
+}
+
+#[test]
+fn portfolio_stream_takes_only_its_own_token() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    let other = game.create_portfolio(1000);
+    // the id alone doesn't do, even before the portfolio has a token
+    assert!(matches!(game.check_owner(portfolio, None), Err(ErrorType::TokenRequired)));
+
+    let issued = game.issue_token(portfolio).unwrap();
+    let foreign = game.issue_token(other).unwrap();
+    assert!(matches!(game.check_owner(portfolio, None), Err(ErrorType::TokenRequired)));
+    assert!(matches!(game.check_owner(portfolio, Some("made-up")), Err(ErrorType::TokenRequired)));
+    assert!(matches!(game.check_owner(portfolio, Some(&foreign.token)), Err(ErrorType::TokenRequired)));
+    game.check_owner(portfolio, Some(&issued.token)).unwrap();
+
+    game.suspend(Suspension::Token(issued.fingerprint)).unwrap();
+    assert!(matches!(game.check_owner(portfolio, Some(&issued.token)), Err(ErrorType::Suspended)));
+}
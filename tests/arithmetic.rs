@@ -0,0 +1,146 @@
+use market_game::game::Game;
+use market_game::account::{Account, PortfolioId};
+use market_game::error::{EngineResult, ErrorType};
+use market_game::market::AssetId;
+use market_game::order::{Event, OrderMode, OrderSide};
+use market_game::rebalance::{RebalancePricing, TargetWeights};
+use market_game::dutch::DutchAuctionTerms;
+use market_game::fees::FeeSchedule;
+use market_game::seed::BookSeed;
+use std::time::Duration;
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId, side: OrderSide, quantity: usize, limit: usize) -> EngineResult<()> {
+    let order = game.new_order(
+        portfolio,
+        asset,
+        side,
+        quantity,
+        OrderMode::Limit(limit),
+        Duration::from_secs(60),
+    )?;
+    game.process(Event::Order(order))
+}
+
+fn coins(game: &Game, portfolio: PortfolioId) -> Account {
+    game.read_engine().unwrap().market.get_portfolio(portfolio).unwrap().coins.clone()
+}
+
+#[test]
+fn add_up_to_the_maximum_and_not_beyond() {
+    let mut account = Account::new(usize::MAX - 1);
+    assert!(account.add(1).is_ok());
+    assert_eq!(account.get_total_amount(), usize::MAX);
+    assert!(matches!(account.add(1), Err(ErrorType::ArithmeticOverflow)));
+    assert_eq!(account.get_total_amount(), usize::MAX);
+    assert_eq!(account.get_free_amount(), usize::MAX);
+}
+
+#[test]
+fn buy_order_with_overflowing_volume_is_rejected() {
+    let mut game = Game::new();
+    let buyer = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let result = place(&game, buyer, asset, OrderSide::Buy, 2, usize::MAX);
+    assert!(matches!(result, Err(ErrorType::ArithmeticOverflow)));
+    let account = coins(&game, buyer);
+    assert_eq!(account.get_locked_amount(), 0);
+    assert_eq!(account.get_total_amount(), 999);
+}
+
+#[test]
+fn whole_free_amount_can_be_locked() {
+    let mut game = Game::new();
+    let buyer = game.create_portfolio(11);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    place(&game, buyer, asset, OrderSide::Buy, 1, 10).unwrap();
+    let account = coins(&game, buyer);
+    assert_eq!(account.get_free_amount(), 0);
+    assert_eq!(account.get_locked_amount(), 10);
+    let result = place(&game, buyer, asset, OrderSide::Buy, 1, 1);
+    assert!(matches!(result, Err(ErrorType::InsufficientFreeAmount)));
+}
+
+#[test]
+fn overflowing_credit_rolls_the_trade_back() {
+    let mut game = Game::new();
+    let buyer = game.create_portfolio(1000);
+    let seller = game.create_portfolio(usize::MAX - 1);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 10);
+    place(&game, buyer, asset, OrderSide::Buy, 1, 5).unwrap();
+
+    let result = place(&game, seller, asset, OrderSide::Sell, 1, 5);
+    assert!(matches!(result, Err(ErrorType::ArithmeticOverflow)));
+
+    let engine = game.read_engine().unwrap();
+    assert_eq!(engine.market.get_order_book(asset).unwrap().buy_orders().count(), 1);
+    let seller_portfolio = engine.market.get_portfolio(seller).unwrap();
+    assert_eq!(seller_portfolio.assets[&asset].get_total_amount(), 10);
+    assert_eq!(seller_portfolio.coins.get_total_amount(), usize::MAX - 2);
+    assert_eq!(engine.market.get_portfolio(buyer).unwrap().coins.get_locked_amount(), 5);
+}
+
+#[test]
+fn sell_order_with_overflowing_open_interest_is_rejected() {
+    let mut game = Game::new();
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 10);
+
+    let result = place(&game, seller, asset, OrderSide::Sell, 4, usize::MAX / 2);
+    assert!(matches!(result, Err(ErrorType::ArithmeticOverflow)));
+
+    let engine = game.read_engine().unwrap();
+    let book = engine.market.get_order_book(asset).unwrap();
+    assert_eq!(book.sell_orders().count(), 0);
+    assert!(book.index_report().problems.is_empty());
+    assert_eq!(engine.market.get_portfolio(seller).unwrap().assets[&asset].get_locked_amount(), 0);
+}
+
+#[test]
+fn rebalance_with_overflowing_weights_or_mid_is_rejected() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let other = game.create_asset("other".to_string()).unwrap();
+    let weights = |weights: Vec<(AssetId, usize)>| TargetWeights { weights: weights.into_iter().collect(), pricing: RebalancePricing::Market };
+    let result = game.plan_rebalance(portfolio, &weights(vec![(asset, usize::MAX), (other, 2)]), Duration::from_secs(60));
+    assert!(matches!(result, Err(ErrorType::ArithmeticOverflow)));
+
+    // both quotes above half the range, their sum doesn't fit
+    let buyer = game.create_portfolio(usize::MAX - 1);
+    let seller = game.create_portfolio(1000);
+    game.set_asset_amount(seller, asset, 1);
+    place(&game, buyer, asset, OrderSide::Buy, 1, usize::MAX / 2 + 1).unwrap();
+    place(&game, seller, asset, OrderSide::Sell, 1, usize::MAX / 2 + 2).unwrap();
+    let result = game.plan_rebalance(portfolio, &weights(vec![(asset, 50)]), Duration::from_secs(60));
+    assert!(matches!(result, Err(ErrorType::ArithmeticOverflow)));
+}
+
+#[test]
+fn dutch_auction_with_overflowing_fees_is_rejected() {
+    let mut game = Game::new();
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_fee_schedule(FeeSchedule { event_fee: usize::MAX / 2, ..FeeSchedule::default() }).unwrap();
+    let terms = DutchAuctionTerms { quantity: 1, start_price: 100, floor: 0, decay_per_tick: 1 };
+    let result = game.start_dutch_auction(asset, None, terms);
+    assert!(matches!(result, Err(ErrorType::ArithmeticOverflow)));
+}
+
+#[test]
+fn batch_with_overflowing_fee_is_rejected() {
+    let mut game = Game::new();
+    let buyer = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_fee_schedule(FeeSchedule { event_fee: usize::MAX / 2 + 1, ..FeeSchedule::default() }).unwrap();
+    let order = |limit| game.new_order(buyer, asset, OrderSide::Buy, 1, OrderMode::Limit(limit), Duration::from_secs(60)).unwrap();
+    let result = game.process(Event::AtomicBatch(vec![order(1), order(2)]));
+    assert!(matches!(result, Err(ErrorType::ArithmeticOverflow)));
+    assert_eq!(coins(&game, buyer).get_total_amount(), 1000);
+}
+
+#[test]
+fn seed_ladder_above_the_range_is_rejected() {
+    let seed = BookSeed { price: usize::MAX - 1, step: 1, levels: 2, quantity: 1 };
+    assert!(matches!(seed.ladder(), Err(ErrorType::ArithmeticOverflow)));
+}
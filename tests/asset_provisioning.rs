@@ -0,0 +1,84 @@
+use market_game::game::Game;
+use market_game::account::{Portfolio, PortfolioId};
+use market_game::error::ErrorType;
+use market_game::market::{Asset, AssetId};
+use market_game::book::Book;
+use market_game::order::{Event, OrderMode, OrderSide};
+use market_game::journal::JournalRecord;
+use std::time::Duration;
+use uuid::Uuid;
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId, side: OrderSide, quantity: usize, limit: usize) -> Result<(), ErrorType> {
+    let order = game.new_order(
+        portfolio,
+        asset,
+        side,
+        quantity,
+        OrderMode::Limit(limit),
+        Duration::from_secs(60),
+    )?;
+    game.process(Event::Order(order))
+}
+
+// adds the asset the way a loaded or hand-edited market would have it, without
+// giving the existing portfolios an account for it
+fn add_asset_behind_the_portfolios(game: &Game, name: &str) -> AssetId {
+    let id = Uuid::new_v4();
+    let mut engine = game.write_engine().unwrap();
+    engine.market.assets.insert(id, Asset::with_id(id, name.to_string()));
+    engine.market.asset_names.insert(name.to_string(), id);
+    engine.market.books.insert(id, Book::new(id));
+    id
+}
+
+#[test]
+fn buying_an_asset_added_after_the_portfolio() {
+    let mut game = Game::new();
+    let buyer = game.create_portfolio(1000);
+    let seller = game.create_portfolio(1000);
+    let asset = add_asset_behind_the_portfolios(&game, "late");
+    game.set_asset_amount(seller, asset, 10);
+
+    place(&game, seller, asset, OrderSide::Sell, 4, 5).unwrap();
+    place(&game, buyer, asset, OrderSide::Buy, 4, 5).unwrap();
+
+    let engine = game.read_engine().unwrap();
+    let account = &engine.market.get_portfolio(buyer).unwrap().assets[&asset];
+    assert_eq!(account.get_total_amount(), 4);
+    assert_eq!(account.get_locked_amount(), 0);
+}
+
+#[test]
+fn buy_order_resting_for_a_never_held_asset_gets_filled() {
+    let mut game = Game::new();
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 10);
+    let buyer = Uuid::new_v4();
+    game.write_engine().unwrap().market.portfolios.insert(buyer, Portfolio::with_id(buyer, 1000));
+
+    place(&game, buyer, asset, OrderSide::Buy, 3, 5).unwrap();
+    place(&game, seller, asset, OrderSide::Sell, 3, 5).unwrap();
+
+    let engine = game.read_engine().unwrap();
+    assert_eq!(engine.market.get_portfolio(buyer).unwrap().assets[&asset].get_total_amount(), 3);
+}
+
+#[test]
+fn selling_a_never_held_asset_is_short_of_funds() {
+    let mut game = Game::new();
+    let seller = game.create_portfolio(1000);
+    let asset = add_asset_behind_the_portfolios(&game, "late");
+    let result = place(&game, seller, asset, OrderSide::Sell, 1, 5);
+    assert!(matches!(result, Err(ErrorType::InsufficientFreeAmount)));
+}
+
+#[test]
+fn setting_an_amount_of_an_unknown_asset_fails() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    let unknown = Uuid::new_v4();
+    let result = game.write_engine().unwrap()
+        .apply(JournalRecord::SetAssetAmount { portfolio, asset: unknown, amount: 1 });
+    assert!(matches!(result, Err(ErrorType::AssetNotFound(_))));
+}
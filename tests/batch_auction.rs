@@ -0,0 +1,71 @@
+use market_game::account::PortfolioId;
+use market_game::auction::{Clearing, MarketStructure};
+use market_game::game::Game;
+use market_game::market::AssetId;
+use market_game::order::{Event, OrderMode, OrderSide};
+use std::time::Duration;
+
+struct Auction {
+    game: Game,
+    asset: AssetId,
+    big_buyer: PortfolioId,
+    small_buyer: PortfolioId,
+    low_seller: PortfolioId,
+    high_seller: PortfolioId,
+}
+
+// buys of 10 at 12 and 5 at 10 against sells of 8 at 9 and 4 at 11, collected during the tick
+fn collected() -> Auction {
+    let mut game = Game::new();
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let big_buyer = game.create_portfolio(1000);
+    let small_buyer = game.create_portfolio(1000);
+    let low_seller = game.create_portfolio(1000);
+    let high_seller = game.create_portfolio(1000);
+    game.set_asset_amount(low_seller, asset, 8);
+    game.set_asset_amount(high_seller, asset, 4);
+    game.set_market_structure(MarketStructure::BatchAuction).unwrap();
+    for (portfolio, side, quantity, limit) in [
+        (big_buyer, OrderSide::Buy, 10, 12),
+        (small_buyer, OrderSide::Buy, 5, 10),
+        (low_seller, OrderSide::Sell, 8, 9),
+        (high_seller, OrderSide::Sell, 4, 11),
+    ] {
+        let order = game.new_order(portfolio, asset, side, quantity, OrderMode::Limit(limit), Duration::from_secs(3600)).unwrap();
+        game.process(Event::Order(order)).unwrap();
+    }
+    Auction { game, asset, big_buyer, small_buyer, low_seller, high_seller }
+}
+
+#[test]
+fn imbalanced_book_clears_where_the_most_crosses() {
+    let Auction { game, asset, .. } = collected();
+    assert!(game.read_engine().unwrap().market.trades.is_empty());
+    // 10 cross at 11 and at 12, with 2 left over either way, the lower price wins
+    let clearing = game.get_indicative_clearing(asset).unwrap();
+    assert_eq!(clearing, Some(Clearing { price: 11, volume: 10, imbalance: 2 }));
+
+    game.advance_tick().unwrap();
+    let engine = game.read_engine().unwrap();
+    let trades: Vec<(usize, usize)> = engine.market.trades.iter().map(|t| (t.price, t.quantity)).collect();
+    assert_eq!(trades, vec![(11, 8), (11, 2)]);
+    assert_eq!(engine.market.last_trade_prices.get(&asset), Some(&11));
+}
+
+#[test]
+fn what_doesnt_cross_rests_after_the_auction() {
+    let Auction { game, asset, big_buyer, small_buyer, low_seller, high_seller } = collected();
+    game.advance_tick().unwrap();
+    let engine = game.read_engine().unwrap();
+    let book = engine.market.get_order_book(asset).unwrap();
+    let buys: Vec<(PortfolioId, usize)> = book.buy_orders().map(|o| (o.get_portfolio(), o.quantity)).collect();
+    let sells: Vec<(PortfolioId, usize)> = book.sell_orders().map(|o| (o.get_portfolio(), o.quantity)).collect();
+    assert_eq!(buys, vec![(small_buyer, 5)]);
+    assert_eq!(sells, vec![(high_seller, 2)]);
+    assert_eq!(engine.market.get_portfolio(big_buyer).unwrap().assets[&asset].get_total_amount(), 10);
+    // the buyer at 12 paid the clearing price, each side paid the event fee of 1
+    assert_eq!(engine.market.get_portfolio(big_buyer).unwrap().coins.get_total_amount(), 1000 - 1 - 110);
+    assert_eq!(engine.market.get_portfolio(low_seller).unwrap().coins.get_total_amount(), 1000 - 1 + 88);
+    // what rests is still reserved
+    assert_eq!(engine.market.get_portfolio(high_seller).unwrap().assets[&asset].get_locked_amount(), 2);
+}
@@ -0,0 +1,28 @@
+use market_game::engine::Engine;
+use market_game::game::Game;
+use market_game::market::Market;
+use market_game::order::{Event, OrderMode, OrderSide};
+use market_game::providers::{default_ids, ManualClock};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[test]
+fn ticks_capture_quiet_books() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = Game::from_engine(Engine::with_providers(Market::new(), Box::new(clock.clone()), default_ids()));
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 100);
+    let order = game.new_order(seller, asset, OrderSide::Sell, 10, OrderMode::Limit(5), Duration::from_secs(3600)).unwrap();
+    game.process(Event::Order(order)).unwrap();
+    let captured = game.get_book_history(asset, None, None).unwrap().len();
+
+    // no events, only the clock and the ticks go on
+    for _ in 0..3 {
+        clock.advance(1000);
+        game.advance_tick().unwrap();
+    }
+    let history = game.get_book_history(asset, None, None).unwrap();
+    assert_eq!(history.len(), captured + 3);
+    assert_eq!(history.last().unwrap().sell, vec![(5, 10)]);
+}
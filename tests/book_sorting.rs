@@ -0,0 +1,79 @@
+use market_game::book::DarkPriority;
+use market_game::engine::Engine;
+use market_game::game::Game;
+use market_game::order::{Event, OrderId, OrderMode, OrderSide};
+use market_game::account::PortfolioId;
+use market_game::market::{AssetId, Market};
+use market_game::providers::{default_ids, ManualClock};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId, side: OrderSide, quantity: usize, limit: usize) {
+    let order = game.new_order(portfolio, asset, side, quantity, OrderMode::Limit(limit), Duration::from_secs(60)).unwrap();
+    game.process(Event::Order(order)).unwrap();
+}
+
+fn limits(game: &Game, asset: AssetId, side: OrderSide) -> Vec<usize> {
+    let engine = game.read_engine().unwrap();
+    let book = engine.market.get_order_book(asset).unwrap();
+    let orders: Vec<_> = match side {
+        OrderSide::Buy => book.buy_orders().collect(),
+        OrderSide::Sell => book.sell_orders().collect(),
+    };
+    orders.iter().map(|o| match o.get_mode() {
+        OrderMode::Limit(limit) => limit,
+        OrderMode::Best => 0,
+    }).collect()
+}
+
+// Sells used to be sorted into the buy side and buys lowest first, so the best price wasn't first.
+#[test]
+fn each_side_has_its_best_price_first() {
+    let mut game = Game::new();
+    let seller = game.create_portfolio(1000);
+    let buyer = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 100);
+    for limit in [30, 10, 20].iter() {
+        place(&game, seller, asset, OrderSide::Sell, 1, *limit);
+    }
+    for limit in [5, 9, 7].iter() {
+        place(&game, buyer, asset, OrderSide::Buy, 1, *limit);
+    }
+    assert_eq!(limits(&game, asset, OrderSide::Sell), vec![10, 20, 30]);
+    assert_eq!(limits(&game, asset, OrderSide::Buy), vec![9, 7, 5]);
+
+    // a buy takes the cheapest sells first
+    place(&game, buyer, asset, OrderSide::Buy, 2, 25);
+    assert_eq!(limits(&game, asset, OrderSide::Sell), vec![30]);
+}
+
+// lit and hidden orders rest apart and are merged into one queue as it's walked
+#[test]
+fn hidden_orders_merge_into_the_queue_by_dark_priority() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = Game::from_engine(Engine::with_providers(Market::new(), Box::new(clock.clone()), default_ids()));
+    let buyer = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let rest = |limit: usize, hidden: bool| -> OrderId {
+        clock.advance(1);
+        let mut order = game.new_order(buyer, asset, OrderSide::Buy, 1, OrderMode::Limit(limit), Duration::from_secs(60)).unwrap();
+        if hidden {
+            order = order.hide();
+        }
+        game.process(Event::Order(order)).unwrap();
+        order.get_id()
+    };
+    let dark_at_9 = rest(9, true);
+    let lit_at_8 = rest(8, false);
+    let lit_at_9 = rest(9, false);
+    let dark_at_10 = rest(10, true);
+    let dark_at_7 = rest(7, true);
+    let queue = |priority: DarkPriority| -> Vec<OrderId> {
+        let engine = game.read_engine().unwrap();
+        let book = engine.market.get_order_book(asset).unwrap();
+        book.queue(OrderSide::Buy, priority).map(|o| o.get_id()).collect()
+    };
+    assert_eq!(queue(DarkPriority::BehindLit), vec![dark_at_10, lit_at_9, dark_at_9, lit_at_8, dark_at_7]);
+    assert_eq!(queue(DarkPriority::Time), vec![dark_at_10, dark_at_9, lit_at_9, lit_at_8, dark_at_7]);
+}
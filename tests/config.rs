@@ -0,0 +1,47 @@
+use market_game::game::Game;
+use market_game::config::GameConfig;
+use market_game::error::ErrorType;
+use market_game::order::{Event, OrderMode, OrderSide};
+use std::time::Duration;
+
+fn config(json: &str) -> GameConfig {
+    serde_json::from_str(json).unwrap()
+}
+
+#[test]
+fn fields_left_out_keep_their_values() {
+    let game = Game::new();
+    game.queue.set_max_depth(Some(1000)).unwrap();
+    game.set_config(config(r#"{"price_band_percent":20,"margin_warning_threshold":5}"#)).unwrap();
+    game.set_config(config(r#"{"starting_coins":500}"#)).unwrap();
+
+    let current = game.get_config().unwrap();
+    assert_eq!(current.price_band_percent, Some(Some(20)));
+    assert_eq!(current.margin_warning_threshold, Some(5));
+    assert_eq!(current.starting_coins, Some(500));
+    assert_eq!(current.queue_max_depth, Some(Some(1000)));
+
+    // null switches it off
+    game.set_config(config(r#"{"price_band_percent":null}"#)).unwrap();
+    assert_eq!(game.get_config().unwrap().price_band_percent, Some(None));
+    assert_eq!(game.get_config().unwrap().queue_max_depth, Some(Some(1000)));
+}
+
+#[test]
+fn orders_outside_the_session_are_rejected() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let rejected = game.set_config(config(r#"{"session_times":{"opens_at":5,"closes_at":5}}"#));
+    assert!(matches!(rejected, Err(ErrorType::InvalidSessionTimes)));
+    game.set_config(config(r#"{"session_times":{"opens_at":2,"closes_at":4}}"#)).unwrap();
+
+    let place = |game: &Game| {
+        let order = game.new_order(portfolio, asset, OrderSide::Buy, 1, OrderMode::Limit(10), Duration::from_secs(60))?;
+        game.process(Event::Order(order))
+    };
+    assert!(matches!(place(&game), Err(ErrorType::MarketClosed)));
+    game.advance_tick().unwrap();
+    game.advance_tick().unwrap();
+    place(&game).unwrap();
+}
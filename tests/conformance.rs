@@ -0,0 +1,273 @@
+// Runs the matching vectors in tests/conformance. Every vector sets up a book, sends one incoming
+// order and states the outcome it expects: ok or the error's name, the trades of the incoming order
+// and the book afterwards, sells then buys, best first. Portfolios are named by labels and start
+// with plenty of coins and holdings, an order without a limit is a Best order.
+
+use market_game::account::PortfolioId;
+use market_game::game::Game;
+use market_game::market::{AssetId, ReferencePricePolicy};
+use market_game::order::{Event, Order, OrderMode, OrderSide};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const COINS: usize = 1_000_000;
+const HOLDINGS: usize = 1_000_000;
+
+#[derive(Deserialize)]
+struct Vector {
+    description: String,
+    #[serde(default)]
+    reference_price_policy: Option<ReferencePricePolicy>,
+    #[serde(default)]
+    book: Vec<OrderSpec>,
+    incoming: OrderSpec,
+    expect: Expectation,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+struct OrderSpec {
+    portfolio: String,
+    side: Side,
+    quantity: usize,
+    #[serde(default)]
+    limit: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Deserialize)]
+struct TradeSpec {
+    buyer: String,
+    seller: String,
+    price: usize,
+    quantity: usize,
+}
+
+fn ok() -> String {
+    "ok".to_string()
+}
+
+#[derive(Deserialize)]
+struct Expectation {
+    #[serde(default = "ok")]
+    result: String,
+    #[serde(default)]
+    trades: Vec<TradeSpec>,
+    #[serde(default)]
+    book: Vec<OrderSpec>,
+}
+
+// Just enough YAML for the vectors: block mappings, block sequences, flow mappings like
+// `{side: buy, quantity: 5}`, plain or quoted scalars and `#` comments.
+fn parse_yaml(text: &str) -> Result<Value, String> {
+    let lines: Vec<(usize, &str)> = text.lines()
+        .map(strip_comment)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| (line.len() - line.trim_start().len(), line.trim()))
+        .collect();
+    let mut pos = 0;
+    let value = match lines.first() {
+        Some((indent, _)) => parse_block(&lines, &mut pos, *indent)?,
+        None => Value::Null,
+    };
+    match lines.get(pos) {
+        Some((_, line)) => Err(format!("unexpected `{}`", line)),
+        None => Ok(value),
+    }
+}
+
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with('#') {
+        return "";
+    }
+    match line.find(" #") {
+        Some(i) => &line[..i],
+        None => line,
+    }
+}
+
+fn parse_block(lines: &[(usize, &str)], pos: &mut usize, indent: usize) -> Result<Value, String> {
+    if lines[*pos].1.starts_with('-') {
+        let mut items = vec![];
+        while let Some((i, line)) = lines.get(*pos) {
+            if *i != indent || !line.starts_with('-') {
+                break;
+            }
+            *pos += 1;
+            let item = line[1..].trim();
+            items.push(if item.is_empty() { parse_nested(lines, pos, indent)? } else { parse_value(item)? });
+        }
+        return Ok(Value::Array(items));
+    }
+    let mut map = Map::new();
+    while let Some((i, line)) = lines.get(*pos) {
+        if *i != indent {
+            break;
+        }
+        let (key, rest) = split_key(line)?;
+        *pos += 1;
+        let value = if rest.is_empty() { parse_nested(lines, pos, indent)? } else { parse_value(rest)? };
+        map.insert(key, value);
+    }
+    Ok(Value::Object(map))
+}
+
+fn parse_nested(lines: &[(usize, &str)], pos: &mut usize, indent: usize) -> Result<Value, String> {
+    match lines.get(*pos) {
+        Some((i, _)) if *i > indent => parse_block(lines, pos, *i),
+        _ => Ok(Value::Null),
+    }
+}
+
+fn split_key(line: &str) -> Result<(String, &str), String> {
+    let i = line.find(':').ok_or_else(|| format!("expected `key: value`, got `{}`", line))?;
+    Ok((line[..i].trim().to_string(), line[i + 1..].trim()))
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(inner) = text.strip_prefix('{') {
+        let inner = inner.strip_suffix('}').ok_or_else(|| format!("unclosed `{}`", text))?;
+        let mut map = Map::new();
+        for entry in inner.split(',').filter(|e| !e.trim().is_empty()) {
+            let (key, value) = split_key(entry)?;
+            map.insert(key, parse_scalar(value));
+        }
+        return Ok(Value::Object(map));
+    }
+    if let Some(inner) = text.strip_prefix('[') {
+        let inner = inner.strip_suffix(']').ok_or_else(|| format!("unclosed `{}`", text))?;
+        return Ok(Value::Array(inner.split(',')
+            .filter(|e| !e.trim().is_empty())
+            .map(|e| parse_scalar(e.trim()))
+            .collect()));
+    }
+    Ok(parse_scalar(text))
+}
+
+fn parse_scalar(text: &str) -> Value {
+    let quoted = text.len() >= 2
+        && ((text.starts_with('"') && text.ends_with('"')) || (text.starts_with('\'') && text.ends_with('\'')));
+    if quoted {
+        return Value::String(text[1..text.len() - 1].to_string());
+    }
+    match text {
+        "null" | "~" => Value::Null,
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => match text.parse::<u64>() {
+            Ok(n) => Value::from(n),
+            Err(_) => Value::String(text.to_string()),
+        },
+    }
+}
+
+fn load(path: &Path) -> Result<Vector, String> {
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_value(parse_yaml(&text)?).map_err(|e| e.to_string())
+}
+
+fn place(game: &Game, portfolios: &HashMap<String, PortfolioId>, asset: AssetId, spec: &OrderSpec) -> Result<(), String> {
+    let side = match spec.side {
+        Side::Buy => OrderSide::Buy,
+        Side::Sell => OrderSide::Sell,
+    };
+    let mode = spec.limit.map_or(OrderMode::Best, OrderMode::Limit);
+    let order = game.new_order(portfolios[&spec.portfolio], asset, side, spec.quantity, mode, Duration::from_secs(60))
+        .map_err(|e| format!("{:?}", e))?;
+    game.process(Event::Order(order)).map_err(|e| format!("{:?}", e))
+}
+
+// `PriceOutOfBounds { .. }` and `OrderNotFound(..)` are compared by their name
+fn error_name(debug: &str) -> String {
+    debug.split([' ', '(']).next().unwrap_or("").to_string()
+}
+
+fn run(vector: &Vector) -> Result<(), String> {
+    let mut game = Game::new();
+    let asset = game.create_asset("asset".to_string()).map_err(|e| format!("{:?}", e))?;
+    if let Some(policy) = vector.reference_price_policy {
+        game.set_reference_price_policy(policy).map_err(|e| format!("{:?}", e))?;
+    }
+    let mut portfolios: HashMap<String, PortfolioId> = HashMap::new();
+    for spec in vector.book.iter().chain(std::iter::once(&vector.incoming)) {
+        if !portfolios.contains_key(&spec.portfolio) {
+            let id = game.create_portfolio(COINS);
+            game.set_asset_amount(id, asset, HOLDINGS);
+            portfolios.insert(spec.portfolio.clone(), id);
+        }
+    }
+    let labels: HashMap<PortfolioId, String> = portfolios.iter().map(|(label, id)| (*id, label.clone())).collect();
+
+    for spec in &vector.book {
+        place(&game, &portfolios, asset, spec).map_err(|e| format!("setting up the book: {}", e))?;
+    }
+    let trade_count = game.read_engine().unwrap().market.get_trades_for(asset).len();
+    let result = match place(&game, &portfolios, asset, &vector.incoming) {
+        Ok(()) => ok(),
+        Err(e) => error_name(&e),
+    };
+
+    let engine = game.read_engine().unwrap();
+    let trades: Vec<TradeSpec> = engine.market.get_trades_for(asset).into_iter()
+        .skip(trade_count)
+        .map(|t| TradeSpec {
+            buyer: labels[&t.buyer].clone(),
+            seller: labels[&t.seller].clone(),
+            price: t.price,
+            quantity: t.quantity,
+        })
+        .collect();
+    let book = engine.market.get_order_book(asset).map_err(|e| format!("{:?}", e))?;
+    let spec = |o: &Order| OrderSpec {
+        portfolio: labels[&o.get_portfolio()].clone(),
+        side: if o.get_side() == OrderSide::Buy { Side::Buy } else { Side::Sell },
+        quantity: o.quantity,
+        limit: match o.get_mode() {
+            OrderMode::Limit(limit) => Some(limit),
+            OrderMode::Best => None,
+        },
+    };
+    let resting: Vec<OrderSpec> = book.sell_orders().chain(book.buy_orders()).map(spec).collect();
+
+    let mut mismatches = vec![];
+    if result != vector.expect.result {
+        mismatches.push(format!("result {} instead of {}", result, vector.expect.result));
+    }
+    if trades != vector.expect.trades {
+        mismatches.push(format!("trades {:?} instead of {:?}", trades, vector.expect.trades));
+    }
+    if resting != vector.expect.book {
+        mismatches.push(format!("book {:?} instead of {:?}", resting, vector.expect.book));
+    }
+    if mismatches.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{}: {}", vector.description, mismatches.join(", ")))
+    }
+}
+
+#[test]
+fn matching_conforms_to_vectors() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("conformance");
+    let mut paths: Vec<PathBuf> = fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|e| e == "yaml"))
+        .collect();
+    paths.sort();
+    assert!(!paths.is_empty(), "no vectors in {}", dir.display());
+
+    let failures: Vec<String> = paths.iter()
+        .filter_map(|path| load(path).and_then(|vector| run(&vector)).err()
+            .map(|e| format!("{}: {}", path.file_name().unwrap().to_string_lossy(), e)))
+        .collect();
+    assert!(failures.is_empty(), "\n{}", failures.join("\n"));
+}
@@ -0,0 +1,59 @@
+use market_game::engine::Engine;
+use market_game::error::ErrorType;
+use market_game::game::Game;
+use market_game::actor::Actor;
+use market_game::market::Market;
+use market_game::order::{Event, OrderMode, OrderSide};
+use market_game::providers::{default_ids, ManualClock};
+use market_game::stream::{Subscriber, Topic};
+use std::sync::Arc;
+use std::time::Duration;
+
+struct Listener;
+
+impl Subscriber for Listener {
+    fn id(&self) -> usize {
+        1
+    }
+
+    fn send(&self, _: String) {}
+}
+
+fn open_orders(game: &Game) -> usize {
+    let engine = game.read_engine().unwrap();
+    engine.market.books.values().map(|b| b.all_orders().count()).sum()
+}
+
+#[test]
+fn only_the_owners_stream_keeps_the_heartbeat_alive() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = Game::from_engine(Engine::with_providers(Market::new(), Box::new(clock.clone()), default_ids()));
+    let portfolio = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let order = game.new_order(portfolio, asset, OrderSide::Buy, 1, OrderMode::Limit(10), Duration::from_secs(3600)).unwrap();
+    game.process(Event::Order(order)).unwrap();
+    game.heartbeat(portfolio, Some(Duration::from_millis(100))).unwrap();
+
+    game.stream.owner_connected(portfolio);
+    clock.advance(1000);
+    game.advance_tick().unwrap();
+    assert_eq!(open_orders(&game), 1);
+
+    // anyone may listen to the topic, it doesn't count as the owner being there
+    game.stream.owner_disconnected(portfolio);
+    game.stream.subscribe(Topic::Portfolio(portfolio), Box::new(Listener));
+    clock.advance(1000);
+    game.advance_tick().unwrap();
+    assert_eq!(open_orders(&game), 0);
+}
+
+#[test]
+fn heartbeats_take_the_portfolios_token() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    let issued = game.issue_token(portfolio).unwrap();
+    let anonymous = game.acting_as(Actor::Anonymous);
+    assert!(matches!(anonymous.heartbeat(portfolio, None), Err(ErrorType::TokenRequired)));
+    assert!(matches!(anonymous.disarm_heartbeat(portfolio), Err(ErrorType::TokenRequired)));
+    game.acting_as(Actor::token(&issued.token)).heartbeat(portfolio, None).unwrap();
+}
@@ -0,0 +1,17 @@
+use market_game::game::Game;
+
+#[test]
+fn market_makers_survive_a_rebuild_from_the_journal() {
+    let mut game = Game::new();
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let portfolio = game.add_market_maker(asset, 100, 10, 5, 10_000, 100).unwrap();
+    game.advance_tick().unwrap();
+    let quotes = game.read_engine().unwrap().market_makers[0].quotes.clone();
+    assert_eq!(quotes.len(), 2);
+
+    let mut engine = game.write_engine().unwrap();
+    engine.restore_from_journal().unwrap();
+    let mm = &engine.market_makers[0];
+    assert_eq!((engine.market_makers.len(), mm.portfolio, mm.asset, mm.fair_value), (1, portfolio, asset, 100));
+    assert_eq!(mm.quotes, quotes);
+}
@@ -0,0 +1,74 @@
+use market_game::account::PortfolioId;
+use market_game::engine::Engine;
+use market_game::error::ErrorType;
+use market_game::game::Game;
+use market_game::lifecycle::OrderState;
+use market_game::market::{AssetId, Market};
+use market_game::order::{Event, MinimumRestingTime, OrderId, OrderMode, OrderSide};
+use market_game::providers::{default_ids, ManualClock};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn setup(clock: &Arc<ManualClock>, minimum: MinimumRestingTime) -> (Game, AssetId, PortfolioId) {
+    let mut game = Game::from_engine(Engine::with_providers(Market::new(), Box::new(clock.clone()), default_ids()));
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 100);
+    game.set_minimum_resting_time(Some(minimum)).unwrap();
+    (game, asset, seller)
+}
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId) -> OrderId {
+    let order = game.new_order(portfolio, asset, OrderSide::Sell, 10, OrderMode::Limit(5), Duration::from_secs(60)).unwrap();
+    game.process(Event::Order(order)).unwrap();
+    order.get_id()
+}
+
+#[test]
+fn cancel_is_turned_down_until_the_order_rested_long_enough() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let (game, asset, seller) = setup(&clock, MinimumRestingTime::Millis(500));
+    let order = place(&game, seller, asset);
+    clock.advance(200);
+    let early = game.process(Event::CancelOrder(seller, order, asset));
+    assert!(matches!(early, Err(ErrorType::CancelTooEarly { remaining: MinimumRestingTime::Millis(300) })));
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Resting);
+
+    clock.advance(300);
+    game.process(Event::CancelOrder(seller, order, asset)).unwrap();
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Cancelled);
+}
+
+#[test]
+fn minimum_in_ticks_counts_ticks_not_time() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let (game, asset, seller) = setup(&clock, MinimumRestingTime::Ticks(2));
+    let order = place(&game, seller, asset);
+    clock.advance(30_000);
+    game.advance_tick().unwrap();
+    let early = game.process(Event::CancelOrder(seller, order, asset));
+    assert!(matches!(early, Err(ErrorType::CancelTooEarly { remaining: MinimumRestingTime::Ticks(1) })));
+
+    game.advance_tick().unwrap();
+    game.process(Event::CancelOrder(seller, order, asset)).unwrap();
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Cancelled);
+}
+
+#[test]
+fn admin_cancels_are_not_held_to_the_minimum() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let (game, asset, seller) = setup(&clock, MinimumRestingTime::Millis(500));
+    let order = place(&game, seller, asset);
+    game.force_cancel_order(asset, order).unwrap();
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Cancelled);
+}
+
+#[test]
+fn turning_the_rule_off_allows_immediate_cancels() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let (game, asset, seller) = setup(&clock, MinimumRestingTime::Millis(500));
+    game.set_minimum_resting_time(None).unwrap();
+    let order = place(&game, seller, asset);
+    game.process(Event::CancelOrder(seller, order, asset)).unwrap();
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Cancelled);
+}
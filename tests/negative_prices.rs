@@ -0,0 +1,61 @@
+use market_game::game::Game;
+use market_game::account::PortfolioId;
+use market_game::error::{EngineResult, ErrorType};
+use market_game::market::AssetId;
+use market_game::order::{Event, OrderMode, OrderSide};
+use market_game::pricing::PriceMode;
+use std::time::Duration;
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId, side: OrderSide, quantity: usize, mode: OrderMode) -> EngineResult<()> {
+    let order = game.new_order(portfolio, asset, side, quantity, mode, Duration::from_secs(60))?;
+    game.process(Event::Order(order))
+}
+
+#[test]
+fn resting_sell_below_zero_keeps_the_coins_it_owes_locked() {
+    let mut game = Game::new();
+    game.set_price_mode(PriceMode::Signed { zero: 1000 }).unwrap();
+    let buyer = game.create_portfolio(1000);
+    let seller = game.create_portfolio(101);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 10);
+
+    // 10 at -10 owes 100, all of the seller's coins after the event fee
+    place(&game, seller, asset, OrderSide::Sell, 10, OrderMode::Limit(990)).unwrap();
+    let coins = game.get_portfolio(seller).unwrap().0.coins.clone();
+    assert_eq!(coins.get_locked_amount(), 100);
+    let result = place(&game, seller, asset, OrderSide::Buy, 1, OrderMode::Limit(1001));
+    assert!(matches!(result, Err(ErrorType::InsufficientFreeAmount)));
+
+    // filled at the buyer's -5 for 4 of them, the rest at -10
+    place(&game, buyer, asset, OrderSide::Buy, 4, OrderMode::Limit(995)).unwrap();
+    place(&game, buyer, asset, OrderSide::Buy, 6, OrderMode::Best).unwrap();
+
+    let seller_portfolio = game.get_portfolio(seller).unwrap().0;
+    assert!(seller_portfolio.reservations.is_empty());
+    assert_eq!(seller_portfolio.coins.get_locked_amount(), 0);
+    assert_eq!(seller_portfolio.coins.get_total_amount(), 20);
+    assert!(game.get_debug_state().unwrap().issues.is_empty());
+}
+
+#[test]
+fn selling_at_a_negative_price_pays_the_buyer() {
+    let mut game = Game::new();
+    game.set_price_mode(PriceMode::Signed { zero: 1000 }).unwrap();
+    let buyer = game.create_portfolio(1000);
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 10);
+
+    // 10 at -10, each side paid the event fee of 1
+    place(&game, buyer, asset, OrderSide::Buy, 10, OrderMode::Limit(990)).unwrap();
+    place(&game, seller, asset, OrderSide::Sell, 10, OrderMode::Limit(990)).unwrap();
+
+    let buyer_portfolio = game.get_portfolio(buyer).unwrap().0;
+    let seller_portfolio = game.get_portfolio(seller).unwrap().0;
+    assert_eq!(buyer_portfolio.coins.get_total_amount(), 999 + 100);
+    assert_eq!(seller_portfolio.coins.get_total_amount(), 999 - 100);
+    assert_eq!(buyer_portfolio.assets.get(&asset).unwrap().get_total_amount(), 10);
+    assert_eq!(seller_portfolio.assets.get(&asset).map_or(0, |a| a.get_total_amount()), 0);
+    assert_eq!(game.read_engine().unwrap().market.trades[0].price, 990);
+}
@@ -0,0 +1,75 @@
+use market_game::game::Game;
+use market_game::lifecycle::OrderState;
+use market_game::market::AssetId;
+use market_game::order::{Event, Order, OrderMode, OrderSide};
+use std::time::Duration;
+
+#[test]
+fn second_leg_stays_out_when_the_first_fills_on_entry() {
+    let mut game = Game::new();
+    let buyer = game.create_portfolio(1000);
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 10);
+    let sell = game.new_order(seller, asset, OrderSide::Sell, 5, OrderMode::Limit(10), Duration::from_secs(60)).unwrap();
+    game.process(Event::Order(sell)).unwrap();
+
+    let first = game.new_order(buyer, asset, OrderSide::Buy, 5, OrderMode::Limit(10), Duration::from_secs(60)).unwrap();
+    let second = game.new_order(buyer, asset, OrderSide::Buy, 5, OrderMode::Limit(8), Duration::from_secs(60)).unwrap();
+    game.process(Event::Oco { first, second, trigger_quantity: None }).unwrap();
+
+    let engine = game.read_engine().unwrap();
+    assert_eq!(engine.market.get_order_book(asset).unwrap().buy_orders().count(), 0);
+    assert!(engine.market.oco_groups.is_empty());
+    assert_eq!(engine.orders.get(second.get_id()).unwrap().state, OrderState::Cancelled);
+    assert_eq!(engine.market.get_portfolio(buyer).unwrap().coins.get_locked_amount(), 0);
+}
+
+// a buy at 8 and a sell at 12 of the same portfolio resting as a group, a seller comes in at 8
+fn bracket(trigger_quantity: Option<usize>, sold: usize) -> (Game, AssetId, Order, Order) {
+    let mut game = Game::new();
+    let trader = game.create_portfolio(1000);
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(trader, asset, 10);
+    game.set_asset_amount(seller, asset, 10);
+    let first = game.new_order(trader, asset, OrderSide::Buy, 5, OrderMode::Limit(8), Duration::from_secs(60)).unwrap();
+    let second = game.new_order(trader, asset, OrderSide::Sell, 5, OrderMode::Limit(12), Duration::from_secs(60)).unwrap();
+    game.process(Event::Oco { first, second, trigger_quantity }).unwrap();
+    {
+        let engine = game.read_engine().unwrap();
+        assert_eq!(engine.market.get_portfolio(trader).unwrap().reservations.len(), 2);
+        assert_eq!(engine.market.get_portfolio(trader).unwrap().assets[&asset].get_locked_amount(), 5);
+    }
+    let sell = game.new_order(seller, asset, OrderSide::Sell, sold, OrderMode::Limit(8), Duration::from_secs(60)).unwrap();
+    game.process(Event::Order(sell)).unwrap();
+    (game, asset, first, second)
+}
+
+#[test]
+fn filling_one_leg_cancels_the_other_and_releases_its_reservation() {
+    let (game, asset, first, second) = bracket(None, 5);
+    let engine = game.read_engine().unwrap();
+    let trader = engine.market.get_portfolio(first.get_portfolio()).unwrap();
+    assert!(engine.market.oco_groups.is_empty());
+    assert_eq!(engine.orders.get(first.get_id()).unwrap().state, OrderState::Filled);
+    assert_eq!(engine.orders.get(second.get_id()).unwrap().state, OrderState::Cancelled);
+    assert!(trader.reservations.is_empty());
+    assert_eq!(trader.assets[&asset].get_locked_amount(), 0);
+    assert_eq!(trader.assets[&asset].get_total_amount(), 15);
+    assert_eq!(engine.market.get_order_book(asset).unwrap().sell_orders().count(), 0);
+}
+
+#[test]
+fn reaching_the_trigger_quantity_cancels_the_other_leg() {
+    let (game, asset, first, second) = bracket(Some(2), 2);
+    let engine = game.read_engine().unwrap();
+    let trader = engine.market.get_portfolio(first.get_portfolio()).unwrap();
+    assert!(engine.market.oco_groups.is_empty());
+    assert_eq!(engine.orders.get(second.get_id()).unwrap().state, OrderState::Cancelled);
+    assert_eq!(trader.assets[&asset].get_locked_amount(), 0);
+    // the rest of the first leg keeps resting with what it reserved for the 3 left
+    assert_eq!(trader.reservations.len(), 1);
+    assert_eq!(trader.reservations[&first.get_id()].amount, 24);
+    assert_eq!(trader.coins.get_locked_amount(), 24);
+}
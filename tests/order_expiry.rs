@@ -0,0 +1,137 @@
+use market_game::account::PortfolioId;
+use market_game::engine::Engine;
+use market_game::error::ErrorType;
+use market_game::game::Game;
+use market_game::lifecycle::{ExpiryReason, OrderState};
+use market_game::market::{AssetId, Market};
+use market_game::order::{Event, OrderId, OrderMode, OrderSide};
+use market_game::providers::{default_ids, ManualClock};
+use std::sync::Arc;
+use std::time::Duration;
+
+const HOUR: u64 = 60*60*1000;
+
+fn setup(clock: &Arc<ManualClock>, market: Market) -> Game {
+    Game::from_engine(Engine::with_providers(market, Box::new(clock.clone()), default_ids()))
+}
+
+fn seed(game: &mut Game) -> (AssetId, PortfolioId) {
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 100);
+    (asset, seller)
+}
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId, lifetime: Duration) -> OrderId {
+    let order = game.new_order(portfolio, asset, OrderSide::Sell, 10, OrderMode::Limit(5), lifetime).unwrap();
+    game.process(Event::Order(order)).unwrap();
+    order.get_id()
+}
+
+fn resting(game: &Game, asset: AssetId) -> usize {
+    game.read_engine().unwrap().market.get_order_book(asset).unwrap().sell_orders().count()
+}
+
+#[test]
+fn order_expires_once_its_lifetime_has_passed() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = setup(&clock, Market::new());
+    let (asset, seller) = seed(&mut game);
+    let order = place(&game, seller, asset, Duration::from_secs(1));
+    clock.advance(999);
+    game.advance_tick().unwrap();
+    assert_eq!(resting(&game, asset), 1);
+    clock.advance(1);
+    game.advance_tick().unwrap();
+    assert_eq!(resting(&game, asset), 0);
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Expired { reason: ExpiryReason::GoodTillDate });
+    let engine = game.read_engine().unwrap();
+    assert_eq!(engine.market.get_portfolio(seller).unwrap().assets[&asset].get_locked_amount(), 0);
+}
+
+#[test]
+fn pause_and_resume_keeps_orders_that_are_still_valid() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = setup(&clock, Market::new());
+    let (asset, seller) = seed(&mut game);
+    let order = place(&game, seller, asset, Duration::from_secs(1));
+    clock.advance(400);
+    game.set_paused(true).unwrap();
+    clock.advance(HOUR);
+    game.advance_tick().unwrap();
+    game.set_paused(false).unwrap();
+    game.advance_tick().unwrap();
+    assert_eq!(resting(&game, asset), 1);
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Resting);
+    assert_eq!(game.get_game_clock().unwrap().game_time, 400);
+
+    // the 600 milliseconds it had left when the game was paused
+    clock.advance(599);
+    game.advance_tick().unwrap();
+    assert_eq!(resting(&game, asset), 1);
+    clock.advance(1);
+    game.advance_tick().unwrap();
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Expired { reason: ExpiryReason::GoodTillDate });
+}
+
+#[test]
+fn paused_game_turns_down_events_and_does_not_tick() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = setup(&clock, Market::new());
+    let (asset, seller) = seed(&mut game);
+    game.set_paused(true).unwrap();
+    let tick = game.get_game_clock().unwrap().tick;
+    let order = game.new_order(seller, asset, OrderSide::Sell, 10, OrderMode::Limit(5), Duration::from_secs(1)).unwrap();
+    assert!(matches!(game.process(Event::Order(order)), Err(ErrorType::GamePaused)));
+    game.advance_tick().unwrap();
+    let clock_view = game.get_game_clock().unwrap();
+    assert_eq!(clock_view.tick, tick);
+    assert!(clock_view.paused);
+}
+
+#[test]
+fn restored_snapshot_does_not_count_the_time_it_was_not_loaded() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = setup(&clock, Market::new());
+    let (asset, seller) = seed(&mut game);
+    place(&game, seller, asset, Duration::from_secs(1));
+    clock.advance(500);
+    game.advance_tick().unwrap();
+    let snapshot = serde_json::to_string(&game.read_engine().unwrap().market).unwrap();
+
+    let later = Arc::new(ManualClock::new(1_000_000 + 24*HOUR));
+    let restored = setup(&later, serde_json::from_str(&snapshot).unwrap());
+    restored.advance_tick().unwrap();
+    assert_eq!(resting(&restored, asset), 1);
+    later.advance(500);
+    restored.advance_tick().unwrap();
+    assert_eq!(resting(&restored, asset), 0);
+}
+
+#[test]
+fn ending_the_game_expires_every_order_and_turns_down_events() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = setup(&clock, Market::new());
+    let (asset, seller) = seed(&mut game);
+    let order = place(&game, seller, asset, Duration::from_secs(60));
+    game.end_game().unwrap();
+    assert_eq!(resting(&game, asset), 0);
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Expired { reason: ExpiryReason::GameEnded });
+    let late = game.new_order(seller, asset, OrderSide::Sell, 10, OrderMode::Limit(5), Duration::from_secs(60)).unwrap();
+    assert!(matches!(game.process(Event::Order(late)), Err(ErrorType::GameEnded)));
+    clock.advance(HOUR);
+    game.advance_tick().unwrap();
+    let clock_view = game.get_game_clock().unwrap();
+    assert!(clock_view.ended);
+    assert_eq!(clock_view.game_time, 0);
+}
+
+#[test]
+fn delisting_expires_the_orders_of_the_asset() {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = setup(&clock, Market::new());
+    let (asset, seller) = seed(&mut game);
+    let order = place(&game, seller, asset, Duration::from_secs(60));
+    game.process(Event::DelistAsset(asset, 0)).unwrap();
+    assert_eq!(game.get_order(order).unwrap().state, OrderState::Expired { reason: ExpiryReason::AssetDelisted });
+}
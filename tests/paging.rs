@@ -0,0 +1,17 @@
+use market_game::game::Game;
+use market_game::page::PageQuery;
+
+#[test]
+fn a_zero_limit_still_shows_the_item_its_cursor_points_at() {
+    let mut game = Game::new();
+    for name in &["a", "b", "c"] {
+        game.create_asset(name.to_string()).unwrap();
+    }
+    let (page, next) = game.get_assets(&PageQuery { limit: Some(0), ..PageQuery::default() }).unwrap();
+    assert_eq!(page.len(), 1);
+    assert_eq!(next, Some(page[0].id.to_string()));
+
+    let (rest, next) = game.get_assets(&PageQuery { cursor: next, ..PageQuery::default() }).unwrap();
+    assert_eq!(rest.len(), 2);
+    assert_eq!(next, None);
+}
@@ -0,0 +1,126 @@
+use market_game::game::Game;
+use market_game::order::{Event, OrderId, OrderMode, OrderSide};
+use market_game::account::PortfolioId;
+use market_game::market::AssetId;
+use std::time::Duration;
+
+fn setup() -> (Game, AssetId, PortfolioId, PortfolioId) {
+    let mut game = Game::new();
+    let seller = game.create_portfolio(1000);
+    let buyer = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 100);
+    (game, asset, seller, buyer)
+}
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId, side: OrderSide, quantity: usize, limit: usize) {
+    let order = game.new_order(
+        portfolio,
+        asset,
+        side,
+        quantity,
+        OrderMode::Limit(limit),
+        Duration::from_secs(60),
+    ).unwrap();
+    game.process(Event::Order(order)).unwrap();
+}
+
+fn coins_of(game: &Game, portfolio: PortfolioId) -> usize {
+    game.read_engine().unwrap().market.get_portfolio(portfolio).unwrap().coins.get_total_amount()
+}
+
+fn sell_quantities(game: &Game, asset: AssetId) -> Vec<usize> {
+    game.read_engine().unwrap().market.get_order_book(asset).unwrap()
+        .sell_orders().map(|o| o.quantity).collect()
+}
+
+#[test]
+fn repeated_partial_fills_reduce_resting_order_in_place() {
+    let (game, asset, seller, buyer) = setup();
+    place(&game, seller, asset, OrderSide::Sell, 10, 5);
+    place(&game, buyer, asset, OrderSide::Buy, 3, 5);
+    assert_eq!(sell_quantities(&game, asset), vec![7]);
+    place(&game, buyer, asset, OrderSide::Buy, 3, 5);
+    assert_eq!(sell_quantities(&game, asset), vec![4]);
+
+    let engine = game.read_engine().unwrap();
+    let seller_assets = &engine.market.get_portfolio(seller).unwrap().assets[&asset];
+    assert_eq!(seller_assets.get_total_amount(), 94);
+    assert_eq!(seller_assets.get_locked_amount(), 4);
+    let buyer_portfolio = engine.market.get_portfolio(buyer).unwrap();
+    assert_eq!(buyer_portfolio.assets[&asset].get_total_amount(), 6);
+    assert_eq!(buyer_portfolio.coins.get_total_amount(), 1000 - 2 - 30);
+}
+
+#[test]
+fn partially_filled_order_keeps_queue_priority() {
+    let (mut game, asset, seller, buyer) = setup();
+    let second_seller = game.create_portfolio(1000);
+    game.set_asset_amount(second_seller, asset, 100);
+    place(&game, seller, asset, OrderSide::Sell, 10, 5);
+    place(&game, second_seller, asset, OrderSide::Sell, 5, 5);
+    place(&game, buyer, asset, OrderSide::Buy, 8, 5);
+    assert_eq!(sell_quantities(&game, asset), vec![2, 5]);
+    place(&game, buyer, asset, OrderSide::Buy, 4, 5);
+    assert_eq!(sell_quantities(&game, asset), vec![3]);
+
+    let engine = game.read_engine().unwrap();
+    let first = &engine.market.get_portfolio(seller).unwrap().assets[&asset];
+    assert_eq!(first.get_locked_amount(), 0);
+    let second = &engine.market.get_portfolio(second_seller).unwrap().assets[&asset];
+    assert_eq!(second.get_locked_amount(), 3);
+}
+
+#[test]
+fn remainder_of_incoming_order_is_locked_when_resting() {
+    let (game, asset, seller, buyer) = setup();
+    place(&game, seller, asset, OrderSide::Sell, 3, 5);
+    place(&game, buyer, asset, OrderSide::Buy, 10, 5);
+    assert!(sell_quantities(&game, asset).is_empty());
+
+    let engine = game.read_engine().unwrap();
+    let book = engine.market.get_order_book(asset).unwrap();
+    assert_eq!(book.buy_orders().map(|o| o.quantity).collect::<Vec<_>>(), vec![7]);
+    let coins = &engine.market.get_portfolio(buyer).unwrap().coins;
+    assert_eq!(coins.get_total_amount(), 1000 - 1 - 15);
+    assert_eq!(coins.get_locked_amount(), 35);
+}
+
+// (the resting buy's reservation, the buyer's locked coins)
+fn reserved(game: &Game, buyer: PortfolioId, order: OrderId) -> (Option<usize>, usize) {
+    let engine = game.read_engine().unwrap();
+    let portfolio = engine.market.get_portfolio(buyer).unwrap();
+    (portfolio.reservations.get(&order).map(|r| r.amount), portfolio.coins.get_locked_amount())
+}
+
+fn rest_buy(game: &Game, asset: AssetId, buyer: PortfolioId) -> OrderId {
+    let buy = game.new_order(buyer, asset, OrderSide::Buy, 10, OrderMode::Limit(5), Duration::from_secs(60)).unwrap();
+    game.process(Event::Order(buy)).unwrap();
+    buy.get_id()
+}
+
+#[test]
+fn partial_fill_pays_from_the_resting_reservation() {
+    let (game, asset, seller, buyer) = setup();
+    let buy = rest_buy(&game, asset, buyer);
+    assert_eq!(reserved(&game, buyer, buy), (Some(50), 50));
+    place(&game, seller, asset, OrderSide::Sell, 4, 5);
+    assert_eq!(reserved(&game, buyer, buy), (Some(30), 30));
+    assert_eq!(coins_of(&game, buyer), 1000 - 1 - 20);
+}
+
+#[test]
+fn full_fill_releases_what_is_left_of_the_reservation() {
+    let (game, asset, seller, buyer) = setup();
+    let buy = rest_buy(&game, asset, buyer);
+    place(&game, seller, asset, OrderSide::Sell, 4, 5);
+    // at the seller's lower limit, the 12 of the 30 left the last 6 don't need go with the reservation
+    place(&game, seller, asset, OrderSide::Sell, 6, 3);
+    assert_eq!(reserved(&game, buyer, buy), (None, 0));
+    let engine = game.read_engine().unwrap();
+    let buyer_portfolio = engine.market.get_portfolio(buyer).unwrap();
+    assert_eq!(buyer_portfolio.coins.get_total_amount(), 1000 - 1 - 20 - 18);
+    assert_eq!(buyer_portfolio.assets[&asset].get_total_amount(), 10);
+    let seller_assets = &engine.market.get_portfolio(seller).unwrap().assets[&asset];
+    assert_eq!((seller_assets.get_total_amount(), seller_assets.get_locked_amount()), (90, 0));
+}
@@ -0,0 +1,16 @@
+use market_game::game::Game;
+
+#[test]
+fn long_games_thin_out_the_history() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    for _ in 0..5000 {
+        game.advance_tick().unwrap();
+    }
+    let engine = game.read_engine().unwrap();
+    let samples = engine.portfolio_history.get_samples(portfolio);
+    assert!(samples.len() <= 4096);
+    assert_eq!(samples.first(), Some(&(0, 1000)));
+    assert_eq!(samples.last().map(|(tick, _)| *tick), Some(engine.market.tick));
+    assert!(samples.windows(2).all(|w| w[0].0 < w[1].0));
+}
@@ -0,0 +1,66 @@
+use market_game::engine::Engine;
+use market_game::game::Game;
+use market_game::journal::{JournalRecord, ReplayFile};
+use market_game::market::Market;
+use market_game::order::{Event, OrderMode, OrderSide};
+use std::time::Duration;
+
+fn recorded() -> (Game, u64) {
+    let mut game = Game::new();
+    let seller = game.create_portfolio(1000);
+    let buyer = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 100);
+    for price in 5..8 {
+        let sell = game.new_order(seller, asset, OrderSide::Sell, 10, OrderMode::Limit(price), Duration::from_secs(3600)).unwrap();
+        game.process(Event::Order(sell)).unwrap();
+        let buy = game.new_order(buyer, asset, OrderSide::Buy, 5, OrderMode::Limit(price), Duration::from_secs(3600)).unwrap();
+        game.process(Event::Order(buy)).unwrap();
+    }
+    let sequence = game.read_engine().unwrap().journal.iter()
+        .find(|e| matches!(e.record, JournalRecord::SetAssetAmount { .. }))
+        .unwrap()
+        .sequence;
+    (game, sequence)
+}
+
+// every entry after the changed one diverges as well, only the changed one is reported
+fn tampered(game: &Game, sequence: u64) -> (ReplayFile, Market) {
+    let engine = game.read_engine().unwrap();
+    let mut file = engine.export_replay();
+    for entry in file.entries.iter_mut().filter(|e| e.sequence == sequence) {
+        if let JournalRecord::SetAssetAmount { amount, .. } = &mut entry.record {
+            *amount = 50;
+        }
+    }
+    (file, engine.market.clone())
+}
+
+#[test]
+fn untouched_journal_matches() {
+    let (game, _) = recorded();
+    let engine = game.read_engine().unwrap();
+    assert!(engine.journal.iter().all(|e| e.state_hash.is_some()));
+    assert!(Engine::verify_replay(engine.export_replay(), &engine.market).unwrap().is_none());
+}
+
+#[test]
+fn first_diverging_entry_is_reported() {
+    let (game, sequence) = recorded();
+    let (file, snapshot) = tampered(&game, sequence);
+    let divergence = Engine::verify_replay(file, &snapshot).unwrap().unwrap();
+    assert_eq!(divergence.sequence, Some(sequence));
+    assert!(matches!(divergence.record, Some(JournalRecord::SetAssetAmount { amount: 50, .. })));
+}
+
+#[test]
+fn without_hashes_only_the_end_is_compared() {
+    let (game, sequence) = recorded();
+    let (mut file, snapshot) = tampered(&game, sequence);
+    for entry in &mut file.entries {
+        entry.state_hash = None;
+    }
+    let divergence = Engine::verify_replay(file, &snapshot).unwrap().unwrap();
+    assert_eq!(divergence.sequence, None);
+    assert!(divergence.paths.iter().any(|p| p.contains("/portfolios/")));
+}
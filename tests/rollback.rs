@@ -0,0 +1,49 @@
+use market_game::audit;
+use market_game::error::ErrorType;
+use market_game::fees::FeeSchedule;
+use market_game::game::Game;
+use market_game::order::{Event, OrderMode, OrderSide};
+use std::time::Duration;
+
+#[test]
+fn failing_batch_leaves_the_market_as_it_was() {
+    let mut game = Game::new();
+    game.set_fee_schedule(FeeSchedule { event_fee: 0, ..FeeSchedule::default() }).unwrap();
+    let buyer = game.create_portfolio(100);
+    let seller = game.create_portfolio(1000);
+    let bystander = game.create_portfolio(1000);
+    let first = game.create_asset("first".to_string()).unwrap();
+    let second = game.create_asset("second".to_string()).unwrap();
+    let unrelated = game.create_asset("unrelated".to_string()).unwrap();
+    game.set_asset_amount(seller, first, 100);
+    game.set_asset_amount(bystander, unrelated, 100);
+    let order = |portfolio, asset, side, quantity, price| game.new_order(portfolio, asset, side, quantity, OrderMode::Limit(price), Duration::from_secs(3600)).unwrap();
+    game.process(Event::Order(order(seller, first, OrderSide::Sell, 20, 5))).unwrap();
+    game.process(Event::Order(order(bystander, unrelated, OrderSide::Sell, 10, 7))).unwrap();
+    game.process(Event::Order(order(seller, first, OrderSide::Buy, 1, 4))).unwrap();
+
+    let before = game.read_engine().unwrap().market.clone();
+    // the first leg trades 10 for 50 coins, the second needs 80 of the 50 left
+    let batch = vec![order(buyer, first, OrderSide::Buy, 10, 5), order(buyer, second, OrderSide::Buy, 10, 8)];
+    let result = game.process(Event::AtomicBatch(batch));
+    assert!(matches!(result, Err(ErrorType::InsufficientFreeAmount)));
+
+    {
+        let after = &game.read_engine().unwrap().market;
+        assert_eq!(after.trades.len(), before.trades.len());
+        assert_eq!(after.ledger.len(), before.ledger.len());
+        assert_eq!(after.bank_account, before.bank_account);
+        assert_eq!(after.last_trade_prices, before.last_trade_prices);
+        let value = |market| audit::to_value(market).unwrap();
+        let (seller_before, seller_after) = (&value(&before)["portfolios"][seller.to_string()], &value(after)["portfolios"][seller.to_string()]);
+        assert_eq!(seller_before["reservations"], seller_after["reservations"]);
+        assert_eq!(value(&before)["books"][first.to_string()], value(after)["books"][first.to_string()]);
+        // books, reservations and holdings of everyone, the unrelated asset and its holder included
+        assert!(audit::diff(&value(&before), &value(after)).is_empty());
+    }
+
+    // the first leg alone would have traded
+    let batch = vec![order(buyer, first, OrderSide::Buy, 10, 5), order(buyer, second, OrderSide::Buy, 10, 4)];
+    game.process(Event::AtomicBatch(batch)).unwrap();
+    assert_eq!(game.read_engine().unwrap().market.trades.len(), before.trades.len() + 1);
+}
@@ -0,0 +1,25 @@
+use market_game::game::Game;
+use market_game::order::{Event, OrderMode, OrderSide};
+
+#[test]
+fn schedules_replay_and_go_with_their_asset() {
+    let mut game = Game::new();
+    let portfolio = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    let schedule = game.schedule_order(portfolio, asset, OrderSide::Buy, 1, OrderMode::Limit(5), 2).unwrap();
+    for _ in 0..4 {
+        game.advance_tick().unwrap();
+    }
+    let before = game.get_schedules(portfolio).unwrap();
+    assert_eq!((before[0].id, before[0].placed, before[0].next_tick), (schedule.id, 2, 6));
+
+    {
+        let mut engine = game.write_engine().unwrap();
+        engine.restore_from_journal().unwrap();
+    }
+    let after = game.get_schedules(portfolio).unwrap();
+    assert_eq!((after[0].placed, after[0].next_tick, after[0].last_order), (2, 6, before[0].last_order));
+
+    game.process(Event::DelistAsset(asset, 5)).unwrap();
+    assert!(game.get_schedules(portfolio).unwrap().is_empty());
+}
@@ -0,0 +1,76 @@
+use market_game::account::PortfolioId;
+use market_game::engine::Engine;
+use market_game::game::Game;
+use market_game::market::{AssetId, Market};
+use market_game::order::{Event, OrderMode, OrderSide};
+use market_game::providers::{default_ids, ManualClock};
+use market_game::settlement::SettlementPolicy;
+use std::sync::Arc;
+use std::time::Duration;
+
+struct Traded {
+    game: Game,
+    clock: Arc<ManualClock>,
+    buyer: PortfolioId,
+    seller: PortfolioId,
+    asset: AssetId,
+}
+
+// (coins total, coins free, assets total, assets free)
+fn balances(game: &Game, portfolio: PortfolioId, asset: AssetId) -> (usize, usize, usize, usize) {
+    let engine = game.read_engine().unwrap();
+    let portfolio = engine.market.get_portfolio(portfolio).unwrap();
+    let assets = portfolio.assets.get(&asset);
+    (
+        portfolio.coins.get_total_amount(),
+        portfolio.coins.get_free_amount(),
+        assets.map_or(0, |a| a.get_total_amount()),
+        assets.map_or(0, |a| a.get_free_amount()),
+    )
+}
+
+// 10 at 5 between them, each paid the event fee of 1
+fn traded() -> Traded {
+    let clock = Arc::new(ManualClock::new(1_000_000));
+    let mut game = Game::from_engine(Engine::with_providers(Market::new(), Box::new(clock.clone()), default_ids()));
+    let seller = game.create_portfolio(1000);
+    let buyer = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 100);
+    game.set_settlement_policy(Some(SettlementPolicy { timeout_millis: 1000, url: None })).unwrap();
+    let sell = game.new_order(seller, asset, OrderSide::Sell, 10, OrderMode::Limit(5), Duration::from_secs(3600)).unwrap();
+    game.process(Event::Order(sell)).unwrap();
+    let buy = game.new_order(buyer, asset, OrderSide::Buy, 10, OrderMode::Limit(5), Duration::from_secs(3600)).unwrap();
+    game.process(Event::Order(buy)).unwrap();
+    Traded { game, clock, buyer, seller, asset }
+}
+
+#[test]
+fn received_amounts_are_held_until_confirmed() {
+    let Traded { game, buyer, seller, asset, .. } = traded();
+    let pending = game.get_pending_settlements().unwrap();
+    assert_eq!(pending.len(), 1);
+    assert_eq!(balances(&game, buyer, asset), (949, 949, 10, 0));
+    assert_eq!(balances(&game, seller, asset), (1049, 999, 90, 90));
+
+    game.confirm_settlement(pending[0].trade.id).unwrap();
+    assert!(game.get_pending_settlements().unwrap().is_empty());
+    assert_eq!(balances(&game, buyer, asset), (949, 949, 10, 10));
+    assert_eq!(balances(&game, seller, asset), (1049, 1049, 90, 90));
+}
+
+#[test]
+fn unconfirmed_trades_are_busted_once_the_timeout_passes() {
+    let Traded { game, clock, buyer, seller, asset } = traded();
+    let trade = game.get_pending_settlements().unwrap()[0].trade.id;
+    clock.advance(500);
+    game.advance_tick().unwrap();
+    assert_eq!(game.get_pending_settlements().unwrap().len(), 1);
+
+    clock.advance(1000);
+    game.advance_tick().unwrap();
+    assert!(game.get_pending_settlements().unwrap().is_empty());
+    assert!(game.read_engine().unwrap().market.get_trade(trade).unwrap().busted);
+    assert_eq!(balances(&game, buyer, asset), (999, 999, 0, 0));
+    assert_eq!(balances(&game, seller, asset), (999, 999, 100, 100));
+}
@@ -0,0 +1,37 @@
+use market_game::game::Game;
+use market_game::account::PortfolioId;
+use market_game::error::{EngineResult, ErrorType};
+use market_game::ledger::LedgerKind;
+use market_game::market::AssetId;
+use market_game::order::{Event, OrderMode, OrderSide};
+use std::time::Duration;
+
+fn place(game: &Game, portfolio: PortfolioId, asset: AssetId, side: OrderSide, quantity: usize, limit: usize) -> EngineResult<()> {
+    let order = game.new_order(portfolio, asset, side, quantity, OrderMode::Limit(limit), Duration::from_secs(60))?;
+    game.process(Event::Order(order))
+}
+
+#[test]
+fn bust_books_the_reversal_and_happens_once() {
+    let mut game = Game::new();
+    let buyer = game.create_portfolio(1000);
+    let seller = game.create_portfolio(1000);
+    let asset = game.create_asset("asset".to_string()).unwrap();
+    game.set_asset_amount(seller, asset, 10);
+    place(&game, seller, asset, OrderSide::Sell, 4, 25).unwrap();
+    place(&game, buyer, asset, OrderSide::Buy, 4, 25).unwrap();
+    let trade = game.read_engine().unwrap().market.trades[0].id;
+
+    game.bust_trade(trade).unwrap();
+    let result = game.bust_trade(trade);
+    assert!(matches!(result, Err(ErrorType::TradeAlreadyBusted(id)) if id == trade));
+
+    let engine = game.read_engine().unwrap();
+    let booked = |portfolio: PortfolioId| engine.market.get_ledger_for(portfolio).into_iter()
+        .filter(|e| e.trade == Some(trade))
+        .map(|e| (e.kind, e.amount))
+        .collect::<Vec<_>>();
+    assert_eq!(booked(seller), vec![(LedgerKind::BustCharge, 100)]);
+    assert_eq!(booked(buyer), vec![(LedgerKind::BustRefund, 100)]);
+    assert_eq!(engine.market.get_portfolio(seller).unwrap().assets[&asset].get_total_amount(), 10);
+}